@@ -0,0 +1,295 @@
+//! Bump (arena) allocators.
+//!
+//! An arena hands out memory from a single buffer by advancing an offset. Freeing
+//! memory only reclaims it if it was the most recent allocation; everything else is
+//! reclaimed at once by [`Arena::reset`]. Allocation is fast and never fragments the
+//! firmware heap.
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::LazyBlock;
+
+/// Bump allocation state, shared by the arena types.
+struct Bump {
+    /// Offset of the first unused byte.
+    next: AtomicUsize,
+}
+
+impl Bump {
+    const fn new() -> Self {
+        Self {
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    fn alloc(&self, base: *mut u8, capacity: usize, layout: Layout) -> *mut u8 {
+        let mut next = self.next.load(Ordering::Relaxed);
+        loop {
+            let padding = (base as usize).wrapping_add(next).wrapping_neg() & (layout.align() - 1);
+            let start = next + padding;
+            let end = match start.checked_add(layout.size()) {
+                Some(end) if end <= capacity => end,
+                _ => return ptr::null_mut(),
+            };
+
+            match self
+                .next
+                .compare_exchange_weak(next, end, Ordering::AcqRel, Ordering::Relaxed)
+            {
+                Ok(_) => return unsafe { base.add(start) },
+                Err(current) => next = current,
+            }
+        }
+    }
+
+    /// Reclaims the memory of `ptr` if it was the most recent allocation.
+    fn dealloc(&self, base: *mut u8, ptr: *mut u8, layout: Layout) {
+        let start = ptr as usize - base as usize;
+        let _ = self.next.compare_exchange(
+            start + layout.size(),
+            start,
+            Ordering::AcqRel,
+            Ordering::Relaxed,
+        );
+    }
+
+    fn used(&self) -> usize {
+        self.next.load(Ordering::Relaxed)
+    }
+
+    fn reset(&mut self) {
+        *self.next.get_mut() = 0;
+    }
+}
+
+#[repr(C, align(8))]
+struct Storage<const N: usize>([u8; N]);
+
+/// Arena allocator with an inline buffer of `N` bytes.
+///
+/// Arenas can be used as a `#[global_allocator]` (with the default `global-allocator`
+/// feature disabled), or for individual collections with the `allocator-api` feature.
+/// Only `&Arena` implements `Allocator`, so that the arena can't be moved while
+/// allocations are live.
+///
+/// # Examples
+///
+/// ```
+/// use flipperzero_alloc::arena::Arena;
+///
+/// #[global_allocator]
+/// static ALLOCATOR: Arena<{ 16 * 1024 }> = Arena::new();
+/// ```
+pub struct Arena<const N: usize> {
+    storage: UnsafeCell<MaybeUninit<Storage<N>>>,
+    bump: Bump,
+}
+
+// SAFETY: Allocations are handed out atomically and never overlap.
+unsafe impl<const N: usize> Sync for Arena<N> {}
+
+impl<const N: usize> Arena<N> {
+    /// Creates an empty arena.
+    pub const fn new() -> Self {
+        Self {
+            storage: UnsafeCell::new(MaybeUninit::uninit()),
+            bump: Bump::new(),
+        }
+    }
+
+    fn base(&self) -> *mut u8 {
+        self.storage.get() as *mut u8
+    }
+
+    /// Returns the size of the buffer in bytes.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns the number of bytes in use, including alignment padding.
+    pub fn used(&self) -> usize {
+        self.bump.used()
+    }
+
+    /// Frees all allocations.
+    pub fn reset(&mut self) {
+        self.bump.reset();
+    }
+}
+
+impl<const N: usize> Default for Arena<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl<const N: usize> GlobalAlloc for Arena<N> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.bump.alloc(self.base(), N, layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.bump.dealloc(self.base(), ptr, layout);
+    }
+}
+
+/// Arena allocator whose buffer is allocated from the firmware heap, in a single
+/// block, on first use.
+///
+/// # Examples
+///
+/// ```
+/// use flipperzero_alloc::arena::HeapArena;
+///
+/// #[global_allocator]
+/// static ALLOCATOR: HeapArena = HeapArena::new(16 * 1024);
+/// ```
+pub struct HeapArena {
+    block: LazyBlock,
+    bump: Bump,
+}
+
+impl HeapArena {
+    /// Creates an empty arena of `capacity` bytes.
+    ///
+    /// Panics if `capacity` is zero.
+    pub const fn new(capacity: usize) -> Self {
+        Self {
+            block: LazyBlock::new(capacity),
+            bump: Bump::new(),
+        }
+    }
+
+    /// Returns the size of the buffer in bytes.
+    pub const fn capacity(&self) -> usize {
+        self.block.size()
+    }
+
+    /// Returns the number of bytes in use, including alignment padding.
+    pub fn used(&self) -> usize {
+        self.bump.used()
+    }
+
+    /// Frees all allocations. The buffer itself is kept.
+    pub fn reset(&mut self) {
+        self.bump.reset();
+    }
+}
+
+unsafe impl GlobalAlloc for HeapArena {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let base = self.block.get();
+        if base.is_null() {
+            return ptr::null_mut();
+        }
+        self.bump.alloc(base, self.block.size(), layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.bump.dealloc(self.block.get(), ptr, layout);
+    }
+}
+
+#[cfg(feature = "allocator-api")]
+impl_allocator!(&Arena<N>, const N: usize);
+#[cfg(feature = "allocator-api")]
+impl_allocator!(HeapArena);
+
+#[cfg(test)]
+mod tests {
+    use core::alloc::{GlobalAlloc, Layout};
+
+    use super::{Arena, HeapArena};
+    use crate::sys::HEAP_SIZE;
+
+    fn layout(size: usize, align: usize) -> Layout {
+        Layout::from_size_align(size, align).unwrap()
+    }
+
+    #[test]
+    fn alignment() {
+        let arena = Arena::<64>::new();
+        unsafe {
+            let a = arena.alloc(layout(1, 1));
+            let b = arena.alloc(layout(8, 8));
+            let c = arena.alloc(layout(4, 16));
+            assert_eq!(b as usize % 8, 0);
+            assert_eq!(c as usize % 16, 0);
+            assert!(a < b && b < c);
+            assert_eq!(arena.used(), c as usize + 4 - a as usize);
+        }
+    }
+
+    #[test]
+    fn exhaustion() {
+        let arena = Arena::<32>::new();
+        assert_eq!(arena.capacity(), 32);
+        unsafe {
+            assert!(!arena.alloc(layout(30, 1)).is_null());
+            assert!(arena.alloc(layout(4, 1)).is_null());
+            assert!(!arena.alloc(layout(2, 1)).is_null());
+            assert!(arena.alloc(layout(1, 1)).is_null());
+        }
+        assert_eq!(arena.used(), 32);
+    }
+
+    #[test]
+    fn reuse_last_allocation() {
+        let arena = Arena::<64>::new();
+        unsafe {
+            let a = arena.alloc(layout(8, 8));
+            let b = arena.alloc(layout(8, 8));
+
+            // Only the most recent allocation is reclaimed.
+            arena.dealloc(a, layout(8, 8));
+            assert_eq!(arena.used(), 16);
+            arena.dealloc(b, layout(8, 8));
+            assert_eq!(arena.used(), 8);
+            assert_eq!(arena.alloc(layout(8, 8)), b);
+        }
+    }
+
+    #[test]
+    fn reset() {
+        let mut arena = Arena::<16>::new();
+        let first = unsafe { arena.alloc(layout(16, 8)) };
+        assert!(unsafe { arena.alloc(layout(1, 1)) }.is_null());
+
+        arena.reset();
+        assert_eq!(arena.used(), 0);
+        assert_eq!(unsafe { arena.alloc(layout(16, 8)) }, first);
+    }
+
+    #[test]
+    fn heap_arena() {
+        let mut arena = HeapArena::new(32);
+        assert_eq!(arena.capacity(), 32);
+        unsafe {
+            let a = arena.alloc(layout(32, 8));
+            assert!(!a.is_null());
+            assert!(arena.alloc(layout(1, 1)).is_null());
+
+            // The buffer is kept when reset.
+            arena.reset();
+            assert_eq!(arena.alloc(layout(8, 8)), a);
+        }
+
+        // The buffer can't be allocated from the heap.
+        let arena = HeapArena::new(2 * HEAP_SIZE);
+        assert!(unsafe { arena.alloc(layout(1, 1)) }.is_null());
+    }
+
+    #[cfg(feature = "allocator-api")]
+    #[test]
+    fn allocator() {
+        let arena = Arena::<64>::new();
+        let mut values = Vec::new_in(&arena);
+        values.extend([1u32, 2, 3]);
+        assert_eq!(values, [1, 2, 3]);
+        assert!(arena.used() >= 12);
+    }
+}
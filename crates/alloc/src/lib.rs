@@ -1,32 +1,284 @@
 //! Alloc support for the Flipper Zero.
 //! *Note:* This currently requires using nightly.
+//!
+//! Allocations that the heap can't satisfy are reported to the alloc error hook (see
+//! [`set_alloc_error_hook`]) before failing, instead of crashing the device from
+//! inside the firmware's allocator.
+//!
+//! With the `tracking` feature, [`FuriAlloc`] also records how much memory the
+//! application uses, see [`tracking`].
+//!
+//! For deterministic memory usage, [`arena`] and [`pool`] provide allocators that
+//! carve allocations out of a single buffer. They can replace [`FuriAlloc`] as the
+//! global allocator by disabling the default `global-allocator` feature, or be used
+//! for individual collections with the `allocator-api` feature (which requires
+//! nightly).
+//!
+//! The allocators don't depend on the firmware beyond its heap, so they are tested on
+//! the host, where the heap is simulated. The test harness itself mustn't allocate
+//! through the tracked allocator, so `global-allocator` is disabled:
+//!
+//! ```text
+//! cargo test -p flipperzero-alloc --lib --no-default-features --features tracking \
+//!     --target x86_64-unknown-linux-gnu
+//! ```
 
-#![no_std]
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(feature = "allocator-api", feature(allocator_api))]
 
 use core::alloc::{GlobalAlloc, Layout};
 use core::ffi::c_void;
+use core::mem;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
 
+#[cfg(not(test))]
 use flipperzero_sys as sys;
 
+/// Implements `Allocator` in terms of `GlobalAlloc`.
+///
+/// Allocators with an inline buffer only implement it by reference (`&$type`), as
+/// moving them would invalidate the memory they handed out.
+#[cfg(feature = "allocator-api")]
+macro_rules! impl_allocator {
+    (&$type:ty $(, $($generics:tt)*)?) => {
+        unsafe impl<'a $(, $($generics)*)?> core::alloc::Allocator for &'a $type {
+            fn allocate(
+                &self,
+                layout: core::alloc::Layout,
+            ) -> Result<core::ptr::NonNull<[u8]>, core::alloc::AllocError> {
+                crate::allocate::<$type>(self, layout)
+            }
+
+            unsafe fn deallocate(&self, ptr: core::ptr::NonNull<u8>, layout: core::alloc::Layout) {
+                crate::deallocate::<$type>(self, ptr, layout)
+            }
+        }
+    };
+    ($type:ty $(, $($generics:tt)*)?) => {
+        unsafe impl$(<$($generics)*>)? core::alloc::Allocator for $type {
+            fn allocate(
+                &self,
+                layout: core::alloc::Layout,
+            ) -> Result<core::ptr::NonNull<[u8]>, core::alloc::AllocError> {
+                crate::allocate::<$type>(self, layout)
+            }
+
+            unsafe fn deallocate(&self, ptr: core::ptr::NonNull<u8>, layout: core::alloc::Layout) {
+                crate::deallocate::<$type>(self, ptr, layout)
+            }
+        }
+    };
+}
+
+pub mod arena;
+pub mod pool;
+mod stats;
+#[cfg(test)]
+mod sys;
+#[cfg(feature = "tracking")]
+pub mod tracking;
+
+pub use stats::{heap_stats, HeapStats};
+
+/// Allocations of at least this size are checked against the largest free block.
+///
+/// Finding the largest free block walks the heap, so it is only done for allocations
+/// that are likely to fail because of fragmentation.
+const LARGE_ALLOCATION: usize = 1024;
+
+static ALLOC_ERROR_HOOK: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+/// Sets the hook that is called when an allocation fails.
+///
+/// The allocation then returns NULL, which makes the allocating code call its alloc
+/// error handler (usually a panic).
+pub fn set_alloc_error_hook(hook: fn(Layout)) {
+    ALLOC_ERROR_HOOK.store(hook as *mut (), Ordering::Release);
+}
+
+/// Prints the failed allocation and the state of the heap.
+pub fn default_alloc_error_hook(layout: Layout) {
+    let stats = heap_stats();
+    unsafe {
+        sys::__wrap_printf(
+            sys::c_string!(
+                "\x1b[0;31mmemory allocation of %u bytes (align %u) failed: %u of %u bytes free, largest free block %u bytes\x1b[0m\r\n"
+            ),
+            layout.size(),
+            layout.align(),
+            stats.free,
+            stats.total,
+            stats.max_free_block,
+        );
+        sys::furi_thread_stdout_flush();
+    }
+}
+
+fn alloc_error(layout: Layout) {
+    let hook = ALLOC_ERROR_HOOK.load(Ordering::Acquire);
+    if hook.is_null() {
+        default_alloc_error_hook(layout);
+    } else {
+        // SAFETY: The hook is only ever set from a `fn(Layout)`.
+        let hook: fn(Layout) = unsafe { mem::transmute(hook) };
+        hook(layout);
+    }
+}
+
+/// Returns `true` if the firmware allocator would run out of memory allocating `size`
+/// bytes with `align`, which crashes the device.
+fn exceeds_heap(size: usize, align: usize) -> bool {
+    // `aligned_malloc` over-allocates to align the block and store the original pointer.
+    let needed = match size.checked_add(align + mem::size_of::<*mut c_void>()) {
+        Some(needed) => needed,
+        None => return true,
+    };
+
+    unsafe {
+        needed > sys::memmgr_get_free_heap()
+            || (needed >= LARGE_ALLOCATION && needed > sys::memmgr_heap_get_max_free_block())
+    }
+}
+
+/// Allocates from the firmware heap, reporting failures to the alloc error hook.
+unsafe fn furi_alloc(layout: Layout) -> *mut u8 {
+    if exceeds_heap(layout.size(), layout.align()) {
+        alloc_error(layout);
+        return ptr::null_mut();
+    }
+
+    let ptr = sys::aligned_malloc(layout.size(), layout.align()) as *mut u8;
+    if ptr.is_null() {
+        alloc_error(layout);
+    }
+    ptr
+}
+
+unsafe fn furi_free(ptr: *mut u8) {
+    sys::aligned_free(ptr as *mut c_void);
+}
+
+/// Pass-through used when allocations aren't tracked.
+#[cfg(not(feature = "tracking"))]
+mod tracking {
+    use core::alloc::Layout;
+
+    pub(crate) unsafe fn alloc(layout: Layout, inner: unsafe fn(Layout) -> *mut u8) -> *mut u8 {
+        inner(layout)
+    }
+
+    pub(crate) unsafe fn dealloc(ptr: *mut u8, _layout: Layout, inner: unsafe fn(*mut u8)) {
+        inner(ptr)
+    }
+}
+
 pub struct FuriAlloc;
 
 unsafe impl GlobalAlloc for FuriAlloc {
     #[inline]
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        sys::aligned_malloc(layout.size(), layout.align()) as *mut u8
+        tracking::alloc(layout, furi_alloc)
     }
 
     #[inline]
-    unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
-        sys::aligned_free(ptr as *mut c_void);
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        tracking::dealloc(ptr, layout, furi_free)
     }
 
     #[inline]
     unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
-        // https://github.com/flipperdevices/flipperzero-firmware/issues/1747#issuecomment-1253636552
-        self.alloc(layout)
+        let ptr = self.alloc(layout);
+        if !ptr.is_null() {
+            ptr::write_bytes(ptr, 0, layout.size());
+        }
+        ptr
     }
 }
 
+#[cfg(feature = "global-allocator")]
 #[global_allocator]
 static ALLOCATOR: FuriAlloc = FuriAlloc;
+
+/// A block of the firmware heap that is allocated on first use, and freed when
+/// dropped.
+struct LazyBlock {
+    ptr: AtomicPtr<u8>,
+    size: usize,
+}
+
+impl LazyBlock {
+    /// Alignment of the block.
+    const ALIGN: usize = 8;
+
+    const fn new(size: usize) -> Self {
+        assert!(size > 0, "size must not be zero");
+        Self {
+            ptr: AtomicPtr::new(ptr::null_mut()),
+            size,
+        }
+    }
+
+    const fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the block, allocating it if needed. Returns NULL if out of memory.
+    fn get(&self) -> *mut u8 {
+        let ptr = self.ptr.load(Ordering::Acquire);
+        if !ptr.is_null() {
+            return ptr;
+        }
+
+        let layout = unsafe { Layout::from_size_align_unchecked(self.size, Self::ALIGN) };
+        let new = unsafe { furi_alloc(layout) };
+        if new.is_null() {
+            return new;
+        }
+
+        match self
+            .ptr
+            .compare_exchange(ptr::null_mut(), new, Ordering::AcqRel, Ordering::Acquire)
+        {
+            Ok(_) => new,
+            Err(current) => {
+                // Another thread allocated the block first.
+                unsafe { furi_free(new) };
+                current
+            }
+        }
+    }
+}
+
+impl Drop for LazyBlock {
+    fn drop(&mut self) {
+        let ptr = *self.ptr.get_mut();
+        if !ptr.is_null() {
+            unsafe { furi_free(ptr) };
+        }
+    }
+}
+
+/// `Allocator::allocate` in terms of `GlobalAlloc`.
+#[cfg(feature = "allocator-api")]
+fn allocate<A: GlobalAlloc>(
+    alloc: &A,
+    layout: Layout,
+) -> Result<ptr::NonNull<[u8]>, core::alloc::AllocError> {
+    let ptr = if layout.size() == 0 {
+        // Zero-sized allocations only need to be aligned.
+        layout.align() as *mut u8
+    } else {
+        unsafe { alloc.alloc(layout) }
+    };
+    let ptr = ptr::slice_from_raw_parts_mut(ptr, layout.size());
+    ptr::NonNull::new(ptr).ok_or(core::alloc::AllocError)
+}
+
+/// `Allocator::deallocate` in terms of `GlobalAlloc`.
+#[cfg(feature = "allocator-api")]
+unsafe fn deallocate<A: GlobalAlloc>(alloc: &A, ptr: ptr::NonNull<u8>, layout: Layout) {
+    if layout.size() != 0 {
+        alloc.dealloc(ptr.as_ptr(), layout);
+    }
+}
@@ -0,0 +1,403 @@
+//! Fixed-size block (pool) allocators.
+//!
+//! A pool hands out blocks of a single size. Any allocation that fits in a block
+//! takes a whole block, and freed blocks are reused in constant time, so a pool never
+//! fragments.
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::cell::UnsafeCell;
+use core::mem::{self, MaybeUninit};
+use core::ptr;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::LazyBlock;
+
+/// Alignment of every block.
+pub const BLOCK_ALIGN: usize = 8;
+
+/// Largest number of blocks in a pool.
+pub const MAX_BLOCKS: usize = u16::MAX as usize;
+
+/// Block allocation state, shared by the pool types.
+///
+/// Blocks that were never used are handed out in order, and freed blocks are kept in a
+/// linked list threaded through the blocks themselves.
+struct Blocks {
+    /// Head of the free list: a tag in the upper 16 bits, to detect concurrent
+    /// modification, and the block index plus one (or zero if empty) in the lower 16.
+    free: AtomicU32,
+    /// Index of the first block that was never used.
+    unused: AtomicU32,
+    /// Number of blocks in use.
+    used: AtomicU32,
+}
+
+impl Blocks {
+    const fn new() -> Self {
+        Self {
+            free: AtomicU32::new(0),
+            unused: AtomicU32::new(0),
+            used: AtomicU32::new(0),
+        }
+    }
+
+    /// Returns the link to the next free block, stored in the first word of a block.
+    unsafe fn link<'a>(base: *mut u8, stride: usize, index: u32) -> &'a AtomicU32 {
+        &*(base.add(index as usize * stride) as *const AtomicU32)
+    }
+
+    fn alloc(&self, base: *mut u8, stride: usize, count: usize, layout: Layout) -> *mut u8 {
+        if layout.align() > BLOCK_ALIGN {
+            return ptr::null_mut();
+        }
+
+        let index = match self.pop(base, stride).or_else(|| self.take_unused(count)) {
+            Some(index) => index,
+            None => return ptr::null_mut(),
+        };
+
+        self.used.fetch_add(1, Ordering::Relaxed);
+        unsafe { base.add(index as usize * stride) }
+    }
+
+    fn dealloc(&self, base: *mut u8, stride: usize, ptr: *mut u8) {
+        let index = ((ptr as usize - base as usize) / stride) as u32;
+        self.used.fetch_sub(1, Ordering::Relaxed);
+
+        let mut head = self.free.load(Ordering::Relaxed);
+        loop {
+            unsafe { Self::link(base, stride, index) }.store(head & 0xffff, Ordering::Relaxed);
+            let new = (head & 0xffff_0000).wrapping_add(1 << 16) | (index + 1);
+            match self
+                .free
+                .compare_exchange_weak(head, new, Ordering::AcqRel, Ordering::Relaxed)
+            {
+                Ok(_) => return,
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    fn pop(&self, base: *mut u8, stride: usize) -> Option<u32> {
+        let mut head = self.free.load(Ordering::Acquire);
+        loop {
+            let index = (head & 0xffff).checked_sub(1)?;
+            let next = unsafe { Self::link(base, stride, index) }.load(Ordering::Relaxed);
+            let new = (head & 0xffff_0000).wrapping_add(1 << 16) | next;
+            match self
+                .free
+                .compare_exchange_weak(head, new, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) => return Some(index),
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    fn take_unused(&self, count: usize) -> Option<u32> {
+        self.unused
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |unused| {
+                ((unused as usize) < count).then_some(unused + 1)
+            })
+            .ok()
+    }
+
+    fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed) as usize
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+#[repr(C, align(8))]
+struct Block<const SIZE: usize>([u8; SIZE]);
+
+/// Pool allocator with `COUNT` inline blocks of `SIZE` bytes.
+///
+/// Allocations larger than `SIZE`, or aligned to more than [`BLOCK_ALIGN`], fail.
+///
+/// Pools can be used as a `#[global_allocator]` (with the default `global-allocator`
+/// feature disabled), or for individual collections with the `allocator-api` feature.
+/// Only `&Pool` implements `Allocator`, so that the pool can't be moved while blocks
+/// are in use.
+///
+/// # Examples
+///
+/// ```
+/// use flipperzero_alloc::pool::Pool;
+///
+/// // 64 packets of up to 128 bytes each.
+/// static PACKETS: Pool<128, 64> = Pool::new();
+/// ```
+pub struct Pool<const SIZE: usize, const COUNT: usize> {
+    storage: UnsafeCell<MaybeUninit<[Block<SIZE>; COUNT]>>,
+    blocks: Blocks,
+}
+
+// SAFETY: Blocks are handed out atomically and never overlap.
+unsafe impl<const SIZE: usize, const COUNT: usize> Sync for Pool<SIZE, COUNT> {}
+
+impl<const SIZE: usize, const COUNT: usize> Pool<SIZE, COUNT> {
+    const STRIDE: usize = mem::size_of::<Block<SIZE>>();
+
+    /// Creates an empty pool.
+    ///
+    /// Panics if `SIZE` is smaller than 4 bytes, or `COUNT` is larger than
+    /// [`MAX_BLOCKS`].
+    pub const fn new() -> Self {
+        assert!(
+            SIZE >= mem::size_of::<u32>(),
+            "blocks must be at least 4 bytes"
+        );
+        assert!(COUNT <= MAX_BLOCKS, "too many blocks");
+
+        Self {
+            storage: UnsafeCell::new(MaybeUninit::uninit()),
+            blocks: Blocks::new(),
+        }
+    }
+
+    fn base(&self) -> *mut u8 {
+        self.storage.get() as *mut u8
+    }
+
+    /// Returns the size of a block in bytes.
+    pub const fn block_size(&self) -> usize {
+        SIZE
+    }
+
+    /// Returns the number of blocks.
+    pub const fn capacity(&self) -> usize {
+        COUNT
+    }
+
+    /// Returns the number of blocks in use.
+    pub fn used(&self) -> usize {
+        self.blocks.used()
+    }
+
+    /// Frees all blocks.
+    pub fn reset(&mut self) {
+        self.blocks.reset();
+    }
+}
+
+impl<const SIZE: usize, const COUNT: usize> Default for Pool<SIZE, COUNT> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl<const SIZE: usize, const COUNT: usize> GlobalAlloc for Pool<SIZE, COUNT> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if layout.size() > SIZE {
+            return ptr::null_mut();
+        }
+        self.blocks.alloc(self.base(), Self::STRIDE, COUNT, layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
+        self.blocks.dealloc(self.base(), Self::STRIDE, ptr);
+    }
+}
+
+/// Pool allocator whose blocks are allocated from the firmware heap, in a single
+/// block, on first use.
+///
+/// # Examples
+///
+/// ```
+/// use flipperzero_alloc::pool::HeapPool;
+///
+/// #[global_allocator]
+/// static ALLOCATOR: HeapPool = HeapPool::new(256, 32);
+/// ```
+pub struct HeapPool {
+    block: LazyBlock,
+    blocks: Blocks,
+    block_size: usize,
+    stride: usize,
+    count: usize,
+}
+
+impl HeapPool {
+    /// Creates an empty pool of `count` blocks of `block_size` bytes.
+    ///
+    /// Panics if `block_size` is smaller than 4 bytes, or `count` is zero or larger
+    /// than [`MAX_BLOCKS`].
+    pub const fn new(block_size: usize, count: usize) -> Self {
+        assert!(
+            block_size >= mem::size_of::<u32>(),
+            "blocks must be at least 4 bytes"
+        );
+        assert!(count <= MAX_BLOCKS, "too many blocks");
+
+        let stride = (block_size + BLOCK_ALIGN - 1) & !(BLOCK_ALIGN - 1);
+        Self {
+            block: LazyBlock::new(stride * count),
+            blocks: Blocks::new(),
+            block_size,
+            stride,
+            count,
+        }
+    }
+
+    /// Returns the size of a block in bytes.
+    pub const fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Returns the number of blocks.
+    pub const fn capacity(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the number of blocks in use.
+    pub fn used(&self) -> usize {
+        self.blocks.used()
+    }
+
+    /// Frees all blocks. The memory backing them is kept.
+    pub fn reset(&mut self) {
+        self.blocks.reset();
+    }
+}
+
+unsafe impl GlobalAlloc for HeapPool {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if layout.size() > self.block_size {
+            return ptr::null_mut();
+        }
+
+        let base = self.block.get();
+        if base.is_null() {
+            return ptr::null_mut();
+        }
+        self.blocks.alloc(base, self.stride, self.count, layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
+        self.blocks.dealloc(self.block.get(), self.stride, ptr);
+    }
+}
+
+#[cfg(feature = "allocator-api")]
+impl_allocator!(&Pool<SIZE, COUNT>, const SIZE: usize, const COUNT: usize);
+#[cfg(feature = "allocator-api")]
+impl_allocator!(HeapPool);
+
+#[cfg(test)]
+mod tests {
+    use core::alloc::{GlobalAlloc, Layout};
+
+    use super::{HeapPool, Pool, BLOCK_ALIGN};
+
+    fn layout(size: usize, align: usize) -> Layout {
+        Layout::from_size_align(size, align).unwrap()
+    }
+
+    #[test]
+    fn exhaustion() {
+        let pool = Pool::<12, 3>::new();
+        assert_eq!(pool.block_size(), 12);
+        assert_eq!(pool.capacity(), 3);
+        unsafe {
+            let blocks = [
+                pool.alloc(layout(12, 4)),
+                pool.alloc(layout(1, 1)),
+                pool.alloc(layout(8, 8)),
+            ];
+            for (i, block) in blocks.iter().enumerate() {
+                assert_eq!(*block as usize % BLOCK_ALIGN, 0);
+                assert_eq!(*block as usize - blocks[0] as usize, i * 16);
+            }
+            assert!(pool.alloc(layout(1, 1)).is_null());
+        }
+        assert_eq!(pool.used(), 3);
+    }
+
+    #[test]
+    fn unsupported_layouts() {
+        let pool = Pool::<16, 2>::new();
+        unsafe {
+            assert!(pool.alloc(layout(17, 1)).is_null());
+            assert!(pool.alloc(layout(8, 16)).is_null());
+        }
+        assert_eq!(pool.used(), 0);
+    }
+
+    #[test]
+    fn reuse_after_free() {
+        let pool = Pool::<8, 3>::new();
+        unsafe {
+            let a = pool.alloc(layout(8, 8));
+            let b = pool.alloc(layout(8, 8));
+            let c = pool.alloc(layout(8, 8));
+
+            // Freed blocks are reused, most recently freed first.
+            pool.dealloc(a, layout(8, 8));
+            pool.dealloc(c, layout(8, 8));
+            assert_eq!(pool.used(), 1);
+            assert_eq!(pool.alloc(layout(8, 8)), c);
+            assert_eq!(pool.alloc(layout(8, 8)), a);
+            assert!(pool.alloc(layout(8, 8)).is_null());
+
+            pool.dealloc(b, layout(8, 8));
+            assert_eq!(pool.alloc(layout(8, 8)), b);
+        }
+    }
+
+    #[test]
+    fn reset() {
+        let mut pool = Pool::<8, 2>::new();
+        unsafe {
+            let a = pool.alloc(layout(8, 8));
+            let b = pool.alloc(layout(8, 8));
+            pool.dealloc(a, layout(8, 8));
+
+            pool.reset();
+            assert_eq!(pool.used(), 0);
+            assert_eq!(pool.alloc(layout(8, 8)), a);
+            assert_eq!(pool.alloc(layout(8, 8)), b);
+            assert!(pool.alloc(layout(8, 8)).is_null());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "blocks must be at least 4 bytes")]
+    fn small_blocks() {
+        let _ = Pool::<2, 1>::new();
+    }
+
+    #[test]
+    fn heap_pool() {
+        let pool = HeapPool::new(12, 2);
+        assert_eq!(pool.block_size(), 12);
+        assert_eq!(pool.capacity(), 2);
+        unsafe {
+            let a = pool.alloc(layout(12, 4));
+            let b = pool.alloc(layout(4, 4));
+            assert_eq!(b as usize - a as usize, 16);
+            assert!(pool.alloc(layout(4, 4)).is_null());
+            assert!(pool.alloc(layout(13, 1)).is_null());
+
+            pool.dealloc(a, layout(12, 4));
+            assert_eq!(pool.alloc(layout(4, 4)), a);
+        }
+    }
+
+    #[cfg(feature = "allocator-api")]
+    #[test]
+    fn allocator() {
+        let pool = Pool::<16, 2>::new();
+        let a = Box::new_in(1u64, &pool);
+        let b = Box::new_in(2u64, &pool);
+        assert_eq!(pool.used(), 2);
+        assert!(Box::try_new_in(3u64, &pool).is_err());
+        drop(a);
+        assert_eq!(*b, 2);
+        assert_eq!(pool.used(), 1);
+    }
+}
@@ -0,0 +1,73 @@
+//! Heap statistics.
+
+use crate::sys;
+
+/// State of the firmware heap, shared by all applications and services.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeapStats {
+    /// Size of the heap in bytes.
+    pub total: usize,
+    /// Free bytes.
+    pub free: usize,
+    /// Lowest number of free bytes since boot.
+    pub minimum_free: usize,
+    /// Size of the largest free block in bytes, which bounds the largest possible
+    /// allocation.
+    pub max_free_block: usize,
+}
+
+impl HeapStats {
+    /// Returns the fraction of free memory that is not in the largest free block,
+    /// from `0.0` (not fragmented) to `1.0`.
+    pub fn fragmentation(&self) -> f32 {
+        if self.free == 0 {
+            0.0
+        } else {
+            1.0 - self.max_free_block as f32 / self.free as f32
+        }
+    }
+}
+
+/// Returns the current state of the firmware heap.
+///
+/// Finding the largest free block walks the heap, so avoid calling this in hot paths.
+pub fn heap_stats() -> HeapStats {
+    unsafe {
+        HeapStats {
+            total: sys::memmgr_get_total_heap(),
+            free: sys::memmgr_get_free_heap(),
+            minimum_free: sys::memmgr_get_minimum_free_heap(),
+            max_free_block: sys::memmgr_heap_get_max_free_block(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{heap_stats, HeapStats};
+    use crate::sys::HEAP_SIZE;
+
+    fn stats(free: usize, max_free_block: usize) -> HeapStats {
+        HeapStats {
+            total: 1000,
+            free,
+            minimum_free: free,
+            max_free_block,
+        }
+    }
+
+    #[test]
+    fn fragmentation() {
+        assert_eq!(stats(0, 0).fragmentation(), 0.0);
+        assert_eq!(stats(100, 100).fragmentation(), 0.0);
+        assert_eq!(stats(100, 25).fragmentation(), 0.75);
+    }
+
+    #[test]
+    fn simulated_heap() {
+        let stats = heap_stats();
+        assert_eq!(stats.total, HEAP_SIZE);
+        assert_eq!(stats.free, HEAP_SIZE);
+        assert_eq!(stats.fragmentation(), 0.0);
+    }
+}
@@ -0,0 +1,79 @@
+//! Host stand-ins for the firmware functions used by this crate, so that it can be
+//! tested on the host.
+//!
+//! The simulated heap never runs low: it always has [`HEAP_SIZE`] bytes free, in a
+//! single block.
+
+use core::ffi::{c_char, c_int, c_void};
+use core::mem;
+use std::alloc::{GlobalAlloc, Layout, System};
+
+/// Size of the simulated heap.
+pub(crate) const HEAP_SIZE: usize = 64 * 1024;
+
+macro_rules! c_string {
+    ($str:expr $(,)?) => {{
+        concat!($str, "\0").as_ptr() as *const core::ffi::c_char
+    }};
+}
+pub(crate) use c_string;
+
+/// Takes the arguments of the only `printf` in this crate, as Rust functions can't be
+/// variadic.
+pub(crate) unsafe fn __wrap_printf(
+    _format: *const c_char,
+    _size: usize,
+    _align: usize,
+    _free: usize,
+    _total: usize,
+    _max_free_block: usize,
+) -> c_int {
+    0
+}
+
+pub(crate) unsafe fn furi_thread_stdout_flush() -> i32 {
+    0
+}
+
+pub(crate) unsafe fn memmgr_get_free_heap() -> usize {
+    HEAP_SIZE
+}
+
+pub(crate) unsafe fn memmgr_get_total_heap() -> usize {
+    HEAP_SIZE
+}
+
+pub(crate) unsafe fn memmgr_get_minimum_free_heap() -> usize {
+    HEAP_SIZE
+}
+
+pub(crate) unsafe fn memmgr_heap_get_max_free_block() -> usize {
+    HEAP_SIZE
+}
+
+/// Header in front of each block, holding the layout of the whole allocation.
+const HEADER: usize = 2 * mem::size_of::<usize>();
+
+pub(crate) unsafe fn aligned_malloc(size: usize, alignment: usize) -> *mut c_void {
+    let offset = alignment.max(HEADER);
+    let layout = Layout::from_size_align(size + offset, offset).unwrap();
+    let base = System.alloc(layout);
+    if base.is_null() {
+        return base as *mut c_void;
+    }
+
+    let ptr = base.add(offset);
+    (ptr as *mut [usize; 2])
+        .sub(1)
+        .write([layout.size(), layout.align()]);
+    ptr as *mut c_void
+}
+
+pub(crate) unsafe fn aligned_free(p: *mut c_void) {
+    let ptr = p as *mut u8;
+    let [size, align] = (ptr as *const [usize; 2]).sub(1).read();
+    System.dealloc(
+        ptr.sub(align),
+        Layout::from_size_align_unchecked(size, align),
+    );
+}
@@ -0,0 +1,332 @@
+//! Allocation tracking.
+//!
+//! Records the memory currently allocated by the application, its peak, and the
+//! memory allocated while an [`AllocSite`] is active. Each allocation carries a small
+//! header, so tracking increases memory usage.
+//!
+//! # Examples
+//!
+//! ```
+//! use flipperzero_alloc::tracking::{self, AllocSite};
+//!
+//! static RENDERING: AllocSite = AllocSite::new("rendering");
+//!
+//! {
+//!     let _site = RENDERING.enter();
+//!     // Allocations here are attributed to `RENDERING`.
+//! }
+//!
+//! let stats = RENDERING.stats();
+//! let total = tracking::alloc_stats();
+//! ```
+
+use core::alloc::Layout;
+use core::mem;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+/// Memory usage recorded by the tracking allocator.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllocStats {
+    /// Bytes currently allocated.
+    pub current: usize,
+    /// Highest number of bytes allocated at once.
+    pub peak: usize,
+    /// Number of live allocations.
+    pub allocations: usize,
+    /// Number of allocations made since start.
+    pub total_allocations: usize,
+    /// Number of allocations that failed.
+    pub failures: usize,
+}
+
+/// Counters behind [`AllocStats`].
+struct Counters {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+    allocations: AtomicUsize,
+    total_allocations: AtomicUsize,
+    failures: AtomicUsize,
+}
+
+impl Counters {
+    const fn new() -> Self {
+        Self {
+            current: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            allocations: AtomicUsize::new(0),
+            total_allocations: AtomicUsize::new(0),
+            failures: AtomicUsize::new(0),
+        }
+    }
+
+    fn record_alloc(&self, size: usize) {
+        let current = self.current.fetch_add(size, Ordering::Relaxed) + size;
+        self.peak.fetch_max(current, Ordering::Relaxed);
+        self.allocations.fetch_add(1, Ordering::Relaxed);
+        self.total_allocations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_dealloc(&self, size: usize) {
+        self.current.fetch_sub(size, Ordering::Relaxed);
+        self.allocations.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn record_failure(&self) {
+        self.failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn stats(&self) -> AllocStats {
+        AllocStats {
+            current: self.current.load(Ordering::Relaxed),
+            peak: self.peak.load(Ordering::Relaxed),
+            allocations: self.allocations.load(Ordering::Relaxed),
+            total_allocations: self.total_allocations.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+        }
+    }
+
+    fn reset_peak(&self) {
+        self.peak
+            .store(self.current.load(Ordering::Relaxed), Ordering::Relaxed);
+    }
+}
+
+static TOTAL: Counters = Counters::new();
+static CURRENT_SITE: AtomicPtr<AllocSite> = AtomicPtr::new(ptr::null_mut());
+
+/// Returns the memory usage of the whole application.
+///
+/// Sizes are those requested by the application, without the allocator's overhead.
+pub fn alloc_stats() -> AllocStats {
+    TOTAL.stats()
+}
+
+/// Resets the peak of the whole application to its current usage.
+pub fn reset_peak() {
+    TOTAL.reset_peak();
+}
+
+/// A named part of the application whose allocations are tracked separately.
+///
+/// Sites are global rather than per thread: allocations made by any thread while a
+/// site is active are attributed to it.
+pub struct AllocSite {
+    name: &'static str,
+    counters: Counters,
+}
+
+impl AllocSite {
+    /// Creates a site.
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            counters: Counters::new(),
+        }
+    }
+
+    /// Returns the name of the site.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Attributes allocations to this site until the returned guard is dropped.
+    ///
+    /// Sites can be nested; allocations are attributed to the innermost one.
+    pub fn enter(&'static self) -> SiteGuard {
+        let previous = CURRENT_SITE.swap(self as *const Self as *mut Self, Ordering::AcqRel);
+        SiteGuard { previous }
+    }
+
+    /// Returns the memory usage attributed to this site.
+    ///
+    /// Memory is attributed to the site that allocated it, even if it is freed after
+    /// the site is exited.
+    pub fn stats(&self) -> AllocStats {
+        self.counters.stats()
+    }
+
+    /// Resets the peak of this site to its current usage.
+    pub fn reset_peak(&self) {
+        self.counters.reset_peak();
+    }
+}
+
+/// Restores the previously active site when dropped.
+#[must_use = "the site is exited when the guard is dropped"]
+pub struct SiteGuard {
+    previous: *mut AllocSite,
+}
+
+impl Drop for SiteGuard {
+    fn drop(&mut self) {
+        CURRENT_SITE.store(self.previous, Ordering::Release);
+    }
+}
+
+/// Header stored in front of each allocation: the site that made it, if any.
+type Header = *const AllocSite;
+
+/// Returns the layout of an allocation including its header, and the offset of the
+/// application's data.
+fn with_header(layout: Layout) -> Option<(Layout, usize)> {
+    let offset = layout.align().max(mem::size_of::<Header>());
+    let size = layout.size().checked_add(offset)?;
+    let layout = Layout::from_size_align(size, layout.align().max(mem::align_of::<Header>()));
+    Some((layout.ok()?, offset))
+}
+
+pub(crate) unsafe fn alloc(layout: Layout, inner: unsafe fn(Layout) -> *mut u8) -> *mut u8 {
+    let (outer, offset) = match with_header(layout) {
+        Some(outer) => outer,
+        None => {
+            TOTAL.record_failure();
+            return ptr::null_mut();
+        }
+    };
+
+    let site = CURRENT_SITE.load(Ordering::Acquire) as *const AllocSite;
+    let base = inner(outer);
+    if base.is_null() {
+        TOTAL.record_failure();
+        if let Some(site) = site.as_ref() {
+            site.counters.record_failure();
+        }
+        return ptr::null_mut();
+    }
+
+    let ptr = base.add(offset);
+    (ptr as *mut Header).sub(1).write(site);
+
+    TOTAL.record_alloc(layout.size());
+    if let Some(site) = site.as_ref() {
+        site.counters.record_alloc(layout.size());
+    }
+    ptr
+}
+
+pub(crate) unsafe fn dealloc(ptr: *mut u8, layout: Layout, inner: unsafe fn(*mut u8)) {
+    // The layout was valid when allocating.
+    let (_, offset) = with_header(layout).unwrap_unchecked();
+
+    let site = (ptr as *const Header).sub(1).read();
+    TOTAL.record_dealloc(layout.size());
+    if let Some(site) = site.as_ref() {
+        site.counters.record_dealloc(layout.size());
+    }
+
+    inner(ptr.sub(offset));
+}
+
+#[cfg(test)]
+mod tests {
+    use core::alloc::Layout;
+    use core::mem;
+    use core::ptr;
+
+    use super::{alloc, alloc_stats, dealloc, reset_peak, with_header, AllocSite, AllocStats};
+    use super::{Counters, Header};
+    use crate::{furi_alloc, furi_free};
+
+    unsafe fn fail(_layout: Layout) -> *mut u8 {
+        ptr::null_mut()
+    }
+
+    #[test]
+    fn counters() {
+        let counters = Counters::new();
+        counters.record_alloc(10);
+        counters.record_alloc(20);
+        counters.record_dealloc(10);
+        counters.record_failure();
+        assert_eq!(
+            counters.stats(),
+            AllocStats {
+                current: 20,
+                peak: 30,
+                allocations: 1,
+                total_allocations: 2,
+                failures: 1,
+            }
+        );
+
+        counters.reset_peak();
+        assert_eq!(counters.stats().peak, 20);
+        counters.record_alloc(5);
+        assert_eq!(counters.stats().peak, 25);
+        counters.record_dealloc(25);
+        assert_eq!(counters.stats().current, 0);
+        assert_eq!(counters.stats().peak, 25);
+    }
+
+    #[test]
+    fn header_keeps_alignment() {
+        for align in [1, 2, 4, 8, 16, 64] {
+            let layout = Layout::from_size_align(3, align).unwrap();
+            let (outer, offset) = with_header(layout).unwrap();
+            assert!(offset >= mem::size_of::<Header>());
+            assert_eq!(offset % align, 0);
+            assert!(outer.align() >= align);
+            assert_eq!(outer.size(), 3 + offset);
+        }
+
+        let layout = Layout::from_size_align(isize::MAX as usize - 7, 8).unwrap();
+        assert!(with_header(layout).is_none());
+    }
+
+    // The totals and the current site are global, so they are only used by this test.
+    #[test]
+    fn sites() {
+        static OUTER: AllocSite = AllocSite::new("outer");
+        static INNER: AllocSite = AllocSite::new("inner");
+        assert_eq!(OUTER.name(), "outer");
+
+        let before = alloc_stats();
+        let large = Layout::from_size_align(24, 16).unwrap();
+        let small = Layout::from_size_align(8, 8).unwrap();
+
+        unsafe {
+            let a = alloc(large, furi_alloc);
+            assert_eq!(a as usize % 16, 0);
+
+            let outer = OUTER.enter();
+            let b = alloc(large, furi_alloc);
+            let inner = INNER.enter();
+            let c = alloc(small, furi_alloc);
+            assert!(alloc(small, fail).is_null());
+            drop(inner);
+            let d = alloc(small, furi_alloc);
+            drop(outer);
+
+            assert_eq!(OUTER.stats().current, 32);
+            assert_eq!(OUTER.stats().allocations, 2);
+            assert_eq!(INNER.stats().current, 8);
+            assert_eq!(INNER.stats().failures, 1);
+
+            let total = alloc_stats();
+            assert_eq!(total.current, before.current + 64);
+            assert_eq!(total.allocations, before.allocations + 4);
+            assert_eq!(total.failures, before.failures + 1);
+
+            // Memory is attributed to the site that allocated it, even once exited.
+            dealloc(c, small, furi_free);
+            assert_eq!(INNER.stats().current, 0);
+            assert_eq!(INNER.stats().peak, 8);
+            dealloc(b, large, furi_free);
+            dealloc(d, small, furi_free);
+            assert_eq!(OUTER.stats().current, 0);
+            assert_eq!(OUTER.stats().peak, 32);
+            OUTER.reset_peak();
+            assert_eq!(OUTER.stats().peak, 0);
+
+            dealloc(a, large, furi_free);
+        }
+
+        let after = alloc_stats();
+        assert_eq!(after.current, before.current);
+        assert_eq!(after.peak, before.peak.max(before.current + 64));
+        assert_eq!(after.total_allocations, before.total_allocations + 4);
+        reset_peak();
+        assert_eq!(alloc_stats().peak, before.current);
+    }
+}
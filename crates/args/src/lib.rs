@@ -0,0 +1,117 @@
+//! Launch argument splitting for `flipperzero-rt`.
+//!
+//! This has no dependency on the firmware, so that it can be tested on the host:
+//!
+//! ```text
+//! cargo test -p flipperzero-args --lib --target x86_64-unknown-linux-gnu
+//! ```
+
+#![cfg_attr(not(test), no_std)]
+
+/// Words of an argument string.
+///
+/// Words are separated by ASCII whitespace. A word can contain whitespace by
+/// surrounding it with double quotes (`"like this"`), which are removed. An
+/// unterminated quote extends to the end of the string. There are no escape
+/// sequences: backslashes are kept as they are.
+#[derive(Debug, Clone)]
+pub struct Words<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Words<'a> {
+    /// Splits `args` into words.
+    pub fn new(args: &'a [u8]) -> Self {
+        Words { rest: args }
+    }
+
+    /// Returns `true` if there are no more words.
+    pub fn is_empty(&self) -> bool {
+        self.rest.iter().all(u8::is_ascii_whitespace)
+    }
+}
+
+impl<'a> Iterator for Words<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.rest.iter().position(|b| !b.is_ascii_whitespace())?;
+        let rest = &self.rest[start..];
+
+        let (word, consumed) = if rest[0] == b'"' {
+            match rest[1..].iter().position(|&b| b == b'"') {
+                Some(end) => (&rest[1..end + 1], end + 2),
+                None => (&rest[1..], rest.len()),
+            }
+        } else {
+            let end = rest
+                .iter()
+                .position(u8::is_ascii_whitespace)
+                .unwrap_or(rest.len());
+            (&rest[..end], end)
+        };
+
+        self.rest = &rest[consumed..];
+        Some(word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Words;
+
+    fn split(args: &str) -> Vec<&str> {
+        Words::new(args.as_bytes())
+            .map(|word| std::str::from_utf8(word).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(split(""), Vec::<&str>::new());
+        assert_eq!(split(" \t\r\n"), Vec::<&str>::new());
+        assert!(Words::new(b"").is_empty());
+        assert!(Words::new(b"  ").is_empty());
+        assert!(!Words::new(b" a ").is_empty());
+    }
+
+    #[test]
+    fn whitespace() {
+        assert_eq!(split("one"), ["one"]);
+        assert_eq!(split("  one two\tthree\r\n"), ["one", "two", "three"]);
+
+        let mut words = Words::new(b"one two ");
+        assert_eq!(words.next(), Some(&b"one"[..]));
+        assert!(!words.is_empty());
+        assert_eq!(words.next(), Some(&b"two"[..]));
+        assert!(words.is_empty());
+        assert_eq!(words.next(), None);
+    }
+
+    #[test]
+    fn quotes() {
+        assert_eq!(
+            split(r#"/ext/a.txt "/ext/my file.txt""#),
+            ["/ext/a.txt", "/ext/my file.txt"]
+        );
+        assert_eq!(split(r#""""#), [""]);
+        assert_eq!(split(r#""" b"#), ["", "b"]);
+        assert_eq!(split(r#"" padded ""#), [" padded "]);
+        // Quotes only group whole words.
+        assert_eq!(split(r#""a"b"#), ["a", "b"]);
+        assert_eq!(split(r#"a"b c"#), [r#"a"b"#, "c"]);
+    }
+
+    #[test]
+    fn unterminated_quote() {
+        assert_eq!(split(r#"a "b c"#), ["a", "b c"]);
+        assert_eq!(split(r#"""#), [""]);
+    }
+
+    #[test]
+    fn no_escapes() {
+        assert_eq!(split(r"C:\dir\file"), [r"C:\dir\file"]);
+        assert_eq!(split(r#""a\" b""#), [r"a\", r#"b""#]);
+        assert_eq!(split(r"a\ b"), [r"a\", "b"]);
+    }
+}
@@ -7,6 +7,7 @@
 use core::ffi::{c_char, c_void};
 use core::mem::{self, MaybeUninit};
 
+use flipperzero::gui::Icon;
 use flipperzero_rt as rt;
 use flipperzero_sys as sys;
 
@@ -19,14 +20,7 @@ rt::entry!(main);
 
 const RECORD_GUI: *const c_char = sys::c_string!("gui");
 
-static mut TARGET_ICON: Icon = Icon {
-    width: 48,
-    height: 32,
-    frame_count: 1,
-    frame_rate: 0,
-    frames: unsafe { TARGET_FRAMES.as_ptr() },
-};
-static mut TARGET_FRAMES: [*const u8; 1] = [ include_bytes!("icons/rustacean-48x32.icon").as_ptr() ];
+static TARGET_ICON: &Icon = flipperzero::include_icon!("examples/icons/rustacean-48x32.png");
 
 static mut IMAGE_POSITION: ImagePosition = ImagePosition { x: 0, y: 0 };
 
@@ -36,21 +30,11 @@ struct ImagePosition {
     pub y: u8,
 }
 
-/// Internal icon representation.
-#[repr(C)]
-struct Icon {
-    width: u8,
-    height: u8,
-    frame_count: u8,
-    frame_rate: u8,
-    frames: *const *const u8,
-}
-
 // Screen is 128x64 px
 extern "C" fn app_draw_callback(canvas: *mut sys::Canvas, _ctx: *mut c_void) {
     unsafe {
         sys::canvas_clear(canvas);
-        sys::canvas_draw_icon(canvas, IMAGE_POSITION.x % 128, IMAGE_POSITION.y % 128, &TARGET_ICON as *const Icon as *const c_void as *const sys::Icon);
+        sys::canvas_draw_icon(canvas, IMAGE_POSITION.x % 128, IMAGE_POSITION.y % 128, TARGET_ICON.as_ptr());
     }
 }
 
@@ -0,0 +1,33 @@
+//! Demonstrates use of the Flipper Zero file browser dialog.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+use core::ffi::CStr;
+
+use flipperzero::dialogs::{self, DialogsApp, FileBrowserOptions};
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust file browser example");
+entry!(main);
+
+fn main(_args: *mut u8) -> i32 {
+    let mut dialogs = DialogsApp::open();
+
+    let start_path = CStr::from_bytes_with_nul(b"/ext/subghz\0").unwrap();
+    let extension = CStr::from_bytes_with_nul(b".sub\0").unwrap();
+    let options = FileBrowserOptions::new().base_path(start_path);
+
+    match dialogs.file_browser(start_path, extension, None, options) {
+        Some(path) => dialogs::alert(path.to_str().unwrap_or("(invalid path)")),
+        None => dialogs::alert("No file selected"),
+    }
+
+    0
+}
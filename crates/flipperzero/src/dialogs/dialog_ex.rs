@@ -0,0 +1,207 @@
+//! Dialog view for embedding in a view dispatcher.
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+
+#[cfg(feature = "alloc")]
+use core::ffi::c_void;
+use core::ffi::CStr;
+use core::marker::PhantomData;
+use core::ptr;
+
+use flipperzero_sys as sys;
+
+use crate::gui::canvas::Align;
+use crate::gui::icon::Icon;
+
+/// Result reported by a [`DialogEx`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DialogExResult {
+    Left,
+    Center,
+    Right,
+    /// A button was pressed (only reported with extended events).
+    PressLeft,
+    PressCenter,
+    PressRight,
+    /// A button was released (only reported with extended events).
+    ReleaseLeft,
+    ReleaseCenter,
+    ReleaseRight,
+}
+
+impl DialogExResult {
+    pub fn from_sys(sys: sys::DialogExResult) -> Option<Self> {
+        match sys {
+            sys::DialogExResult_DialogExResultLeft => Some(Self::Left),
+            sys::DialogExResult_DialogExResultCenter => Some(Self::Center),
+            sys::DialogExResult_DialogExResultRight => Some(Self::Right),
+            sys::DialogExResult_DialogExPressLeft => Some(Self::PressLeft),
+            sys::DialogExResult_DialogExPressCenter => Some(Self::PressCenter),
+            sys::DialogExResult_DialogExPressRight => Some(Self::PressRight),
+            sys::DialogExResult_DialogExReleaseLeft => Some(Self::ReleaseLeft),
+            sys::DialogExResult_DialogExReleaseCenter => Some(Self::ReleaseCenter),
+            sys::DialogExResult_DialogExReleaseRight => Some(Self::ReleaseRight),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+type ResultCallback = Box<dyn FnMut(DialogExResult) + Send>;
+
+/// A dialog view with a header, text, icon and up to three buttons.
+///
+/// Unlike [`DialogMessage`](super::DialogMessage), which is shown modally by
+/// [`DialogsApp`](super::DialogsApp), a `DialogEx` is a view that can be added to a
+/// view dispatcher with [`DialogEx::get_view`].
+pub struct DialogEx<'a> {
+    data: *mut sys::DialogEx,
+    #[cfg(feature = "alloc")]
+    callback: Option<Box<ResultCallback>>,
+    _phantom: PhantomData<&'a CStr>,
+}
+
+impl<'a> DialogEx<'a> {
+    /// Allocates a new dialog.
+    pub fn new() -> Self {
+        let data = unsafe { sys::dialog_ex_alloc() };
+        assert!(!data.is_null());
+
+        Self {
+            data,
+            #[cfg(feature = "alloc")]
+            callback: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the view, for adding the dialog to a view dispatcher.
+    ///
+    /// The view is owned by the dialog and must not be used after it is dropped.
+    pub fn get_view(&self) -> *mut sys::View {
+        unsafe { sys::dialog_ex_get_view(self.data) }
+    }
+
+    /// Sets the callback that is called with the button pressed by the user.
+    ///
+    /// The callback is called from the GUI thread.
+    #[cfg(feature = "alloc")]
+    pub fn set_result_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(DialogExResult) + Send + 'static,
+    {
+        unsafe extern "C" fn result_callback(result: sys::DialogExResult, context: *mut c_void) {
+            let callback = &mut *(context as *mut ResultCallback);
+            if let Some(result) = DialogExResult::from_sys(result) {
+                callback(result);
+            }
+        }
+
+        let mut callback: Box<ResultCallback> = Box::new(Box::new(callback));
+        unsafe {
+            sys::dialog_ex_set_context(
+                self.data,
+                &mut *callback as *mut ResultCallback as *mut c_void,
+            );
+            sys::dialog_ex_set_result_callback(self.data, Some(result_callback));
+        }
+        self.callback = Some(callback);
+    }
+
+    /// Sets the header text.
+    pub fn set_header(
+        &mut self,
+        header: &'a CStr,
+        x: u8,
+        y: u8,
+        horizontal: Align,
+        vertical: Align,
+    ) {
+        unsafe {
+            sys::dialog_ex_set_header(
+                self.data,
+                header.as_ptr(),
+                x,
+                y,
+                horizontal.to_sys(),
+                vertical.to_sys(),
+            );
+        }
+    }
+
+    /// Sets the body text.
+    pub fn set_text(&mut self, text: &'a CStr, x: u8, y: u8, horizontal: Align, vertical: Align) {
+        unsafe {
+            sys::dialog_ex_set_text(
+                self.data,
+                text.as_ptr(),
+                x,
+                y,
+                horizontal.to_sys(),
+                vertical.to_sys(),
+            );
+        }
+    }
+
+    /// Sets the icon.
+    pub fn set_icon(&mut self, icon: &'a Icon, x: u8, y: u8) {
+        unsafe {
+            sys::dialog_ex_set_icon(self.data, x, y, icon.as_ptr());
+        }
+    }
+
+    /// Sets the labels of the buttons. Buttons without a label are hidden.
+    pub fn set_buttons(
+        &mut self,
+        left: Option<&'a CStr>,
+        center: Option<&'a CStr>,
+        right: Option<&'a CStr>,
+    ) {
+        let left = left.map_or(ptr::null(), |l| l.as_ptr());
+        let center = center.map_or(ptr::null(), |l| l.as_ptr());
+        let right = right.map_or(ptr::null(), |l| l.as_ptr());
+
+        unsafe {
+            sys::dialog_ex_set_left_button_text(self.data, left);
+            sys::dialog_ex_set_center_button_text(self.data, center);
+            sys::dialog_ex_set_right_button_text(self.data, right);
+        }
+    }
+
+    /// Enables reporting button presses and releases, in addition to clicks.
+    pub fn set_extended_events(&mut self, enabled: bool) {
+        unsafe {
+            if enabled {
+                sys::dialog_ex_enable_extended_events(self.data);
+            } else {
+                sys::dialog_ex_disable_extended_events(self.data);
+            }
+        }
+    }
+
+    /// Clears the header, text, icon, buttons and result callback.
+    pub fn reset(&mut self) {
+        unsafe {
+            sys::dialog_ex_reset(self.data);
+        }
+        #[cfg(feature = "alloc")]
+        {
+            self.callback = None;
+        }
+    }
+}
+
+impl Default for DialogEx<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Drop for DialogEx<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            sys::dialog_ex_free(self.data);
+        }
+    }
+}
@@ -3,19 +3,29 @@
 #[cfg(feature = "alloc")]
 use alloc::ffi::CString;
 
-use core::ffi::{c_char, CStr};
+use core::ffi::{c_char, c_void, CStr};
 use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
 use core::ptr;
 
 use flipperzero_sys as sys;
 use flipperzero_sys::furi::UnsafeRecord;
 
+use crate::furi::string::FuriString;
 use crate::gui::canvas::Align;
+use crate::gui::icon::Icon;
+
+mod dialog_ex;
+pub use self::dialog_ex::{DialogEx, DialogExResult};
 
 const RECORD_DIALOGS: *const c_char = sys::c_string!("dialogs");
 
 #[cfg(feature = "alloc")]
-const BUTTON_OK: &'static CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"OK\0") };
+const BUTTON_OK: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"OK\0") };
+#[cfg(feature = "alloc")]
+const BUTTON_YES: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"Yes\0") };
+#[cfg(feature = "alloc")]
+const BUTTON_NO: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"No\0") };
 
 /// A handle to the Dialogs app.
 pub struct DialogsApp {
@@ -25,10 +35,34 @@ pub struct DialogsApp {
 /// A dialog message.
 pub struct DialogMessage<'a> {
     data: *mut sys::DialogMessage,
+    #[cfg(feature = "alloc")]
+    strings: OwnedStrings,
     _phantom: PhantomData<&'a CStr>,
 }
 
+/// Strings passed to a [`DialogMessage`] as `&str`, which must outlive the message.
+#[cfg(feature = "alloc")]
+#[derive(Default)]
+struct OwnedStrings {
+    header: Option<CString>,
+    text: Option<CString>,
+    buttons: [Option<CString>; 3],
+}
+
+/// Callback that may change the display name of a file browser item.
+type DisplayName<'a> = &'a mut dyn FnMut(&CStr, &mut FuriString);
+
+/// Options for [`DialogsApp::file_browser`].
+pub struct FileBrowserOptions<'a> {
+    base_path: Option<&'a CStr>,
+    skip_assets: bool,
+    hide_dot_files: bool,
+    hide_ext: bool,
+    display_name: Option<DisplayName<'a>>,
+}
+
 /// Button pressed on a dialog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DialogMessageButton {
     Back,
     Left,
@@ -50,6 +84,128 @@ impl DialogsApp {
 
         DialogMessageButton::from_sys(button_sys).expect("Invalid button")
     }
+
+    /// Lets the user pick a file with the given `extension` (for example, `".sub"`, or
+    /// `"*"` for any file).
+    ///
+    /// The browser starts at `start_path`, which may be a directory or a file to
+    /// preselect. `icon` replaces the default icon shown next to each file.
+    ///
+    /// Returns the path of the selected file, or `None` if the user cancelled.
+    pub fn file_browser(
+        &mut self,
+        start_path: &CStr,
+        extension: &CStr,
+        icon: Option<&Icon>,
+        options: FileBrowserOptions<'_>,
+    ) -> Option<FuriString> {
+        unsafe extern "C" fn item_loader(
+            path: *mut sys::FuriString,
+            context: *mut c_void,
+            _icon: *mut *mut u8,
+            item_name: *mut sys::FuriString,
+        ) -> bool {
+            let f = &mut *(context as *mut DisplayName);
+            // Both strings are owned by the file browser.
+            let path = ManuallyDrop::new(FuriString::from_raw(path));
+            let mut item_name = ManuallyDrop::new(FuriString::from_raw(item_name));
+            f(path.as_c_str(), &mut item_name);
+
+            // No custom icon was loaded.
+            false
+        }
+
+        let FileBrowserOptions {
+            base_path,
+            skip_assets,
+            hide_dot_files,
+            hide_ext,
+            mut display_name,
+        } = options;
+
+        let options = sys::DialogsFileBrowserOptions {
+            extension: extension.as_ptr(),
+            base_path: base_path.map_or(ptr::null(), |p| p.as_ptr()),
+            skip_assets,
+            hide_dot_files,
+            icon: icon.map_or(ptr::null(), |i| i.as_ptr()),
+            hide_ext,
+            item_loader_callback: display_name.as_ref().map(|_| item_loader as _),
+            item_loader_context: display_name
+                .as_mut()
+                .map_or(ptr::null_mut(), |f| f as *mut DisplayName)
+                as *mut c_void,
+        };
+
+        let mut start_path = FuriString::from_c_str(start_path);
+        let mut result_path = FuriString::new();
+        let selected = unsafe {
+            sys::dialog_file_browser_show(
+                self.data.as_ptr(),
+                result_path.as_mut_ptr(),
+                start_path.as_mut_ptr(),
+                &options,
+            )
+        };
+
+        selected.then_some(result_path)
+    }
+}
+
+impl<'a> FileBrowserOptions<'a> {
+    /// Creates the default options: assets and dot files are hidden, and extensions are
+    /// shown.
+    pub fn new() -> Self {
+        Self {
+            base_path: None,
+            skip_assets: true,
+            hide_dot_files: true,
+            hide_ext: false,
+            display_name: None,
+        }
+    }
+
+    /// Sets the root directory, above which the user cannot navigate with the back
+    /// button.
+    pub fn base_path(mut self, path: &'a CStr) -> Self {
+        self.base_path = Some(path);
+        self
+    }
+
+    /// Hides assets directories.
+    pub fn skip_assets(mut self, set: bool) -> Self {
+        self.skip_assets = set;
+        self
+    }
+
+    /// Hides files and directories whose names start with a dot.
+    pub fn hide_dot_files(mut self, set: bool) -> Self {
+        self.hide_dot_files = set;
+        self
+    }
+
+    /// Hides the extensions of files.
+    pub fn hide_ext(mut self, set: bool) -> Self {
+        self.hide_ext = set;
+        self
+    }
+
+    /// Sets a callback that is called with the path and display name of each file,
+    /// and may change the display name.
+    ///
+    /// The callback cannot hide files. The firmware only filters entries by the
+    /// extension passed to [`DialogsApp::file_browser`], and with
+    /// [`hide_dot_files`](Self::hide_dot_files) and [`skip_assets`](Self::skip_assets).
+    pub fn display_name(mut self, f: &'a mut dyn FnMut(&CStr, &mut FuriString)) -> Self {
+        self.display_name = Some(f);
+        self
+    }
+}
+
+impl Default for FileBrowserOptions<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<'a> DialogMessage<'a> {
@@ -60,6 +216,8 @@ impl<'a> DialogMessage<'a> {
 
         Self {
             data,
+            #[cfg(feature = "alloc")]
+            strings: OwnedStrings::default(),
             _phantom: PhantomData,
         }
     }
@@ -115,6 +273,83 @@ impl<'a> DialogMessage<'a> {
         }
     }
 
+    /// Sets the icon.
+    pub fn set_icon(&mut self, icon: &'a Icon, x: u8, y: u8) {
+        unsafe {
+            sys::dialog_message_set_icon(self.data, icon.as_ptr(), x, y);
+        }
+    }
+
+    /// Sets the labels of the buttons, copying them into the message.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a label contains a NUL byte.
+    #[cfg(feature = "alloc")]
+    pub fn set_buttons_str(
+        &mut self,
+        left: Option<&str>,
+        center: Option<&str>,
+        right: Option<&str>,
+    ) {
+        let buttons = [left, center, right].map(|b| b.map(|b| CString::new(b).unwrap()));
+        let label = |i: usize| buttons[i].as_ref().map_or(ptr::null(), |b| b.as_ptr());
+
+        unsafe {
+            sys::dialog_message_set_buttons(self.data, label(0), label(1), label(2));
+        }
+        self.strings.buttons = buttons;
+    }
+
+    /// Sets the header text, copying it into the message.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `header` contains a NUL byte.
+    #[cfg(feature = "alloc")]
+    pub fn set_header_str(
+        &mut self,
+        header: &str,
+        x: u8,
+        y: u8,
+        horizontal: Align,
+        vertical: Align,
+    ) {
+        let header = CString::new(header).unwrap();
+        unsafe {
+            sys::dialog_message_set_header(
+                self.data,
+                header.as_ptr(),
+                x,
+                y,
+                horizontal.to_sys(),
+                vertical.to_sys(),
+            );
+        }
+        self.strings.header = Some(header);
+    }
+
+    /// Sets the body text, copying it into the message.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `text` contains a NUL byte.
+    #[cfg(feature = "alloc")]
+    pub fn set_text_str(&mut self, text: &str, x: u8, y: u8, horizontal: Align, vertical: Align) {
+        let text = CString::new(text).unwrap();
+        unsafe {
+            sys::dialog_message_set_text(
+                self.data,
+                text.as_ptr(),
+                x,
+                y,
+                horizontal.to_sys(),
+                vertical.to_sys(),
+            );
+        }
+        self.strings.text = Some(text);
+    }
+
     /// Clears the header text.
     pub fn clear_header(&mut self) {
         unsafe {
@@ -129,6 +364,13 @@ impl<'a> DialogMessage<'a> {
         }
     }
 
+    /// Clears the icon.
+    pub fn clear_icon(&mut self) {
+        unsafe {
+            sys::dialog_message_set_icon(self.data, ptr::null(), 0, 0);
+        }
+    }
+
     /// Clears the body text.
     pub fn clear_text(&mut self) {
         unsafe {
@@ -144,6 +386,12 @@ impl<'a> DialogMessage<'a> {
     }
 }
 
+impl Default for DialogMessage<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'a> Drop for DialogMessage<'a> {
     fn drop(&mut self) {
         unsafe {
@@ -167,13 +415,37 @@ impl DialogMessageButton {
 /// Displays a simple dialog.
 #[cfg(feature = "alloc")]
 pub fn alert(text: &str) {
-    let text = CString::new(text.as_bytes()).unwrap();
-
     let mut dialogs = DialogsApp::open();
     let mut message = DialogMessage::new();
 
-    message.set_text(&text, 0, 0, Align::Left, Align::Top);
+    message.set_text_str(text, 0, 0, Align::Left, Align::Top);
     message.set_buttons(None, Some(BUTTON_OK), None);
 
     dialogs.show(&message);
 }
+
+/// Asks a yes/no question, returning `true` if the user answered yes.
+#[cfg(feature = "alloc")]
+pub fn confirm(header: &str, text: &str) -> bool {
+    let mut dialogs = DialogsApp::open();
+    let mut message = DialogMessage::new();
+
+    message.set_header_str(header, 64, 0, Align::Center, Align::Top);
+    message.set_text_str(text, 64, 26, Align::Center, Align::Center);
+    message.set_buttons(Some(BUTTON_NO), None, Some(BUTTON_YES));
+
+    dialogs.show(&message) == DialogMessageButton::Right
+}
+
+/// Lets the user choose between three options, shown as the button labels.
+///
+/// Returns [`DialogMessageButton::Back`] if the user cancelled.
+#[cfg(feature = "alloc")]
+pub fn choose3(left: &str, center: &str, right: &str) -> DialogMessageButton {
+    let mut dialogs = DialogsApp::open();
+    let mut message = DialogMessage::new();
+
+    message.set_buttons_str(Some(left), Some(center), Some(right));
+
+    dialogs.show(&message)
+}
@@ -0,0 +1,588 @@
+//! Serde deserializer for Flipper Format files.
+
+use alloc::ffi::CString;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use core::ffi::CStr;
+use core::fmt::Display;
+use core::slice;
+
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use super::ser::{to_c_string, Array};
+use super::{Error, FlipperFormat, Result};
+use crate::furi::string::FuriString;
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+}
+
+/// Reads a struct from a Flipper Format file.
+///
+/// Each field is read from the key with the field's name. Fields are looked up from the
+/// start of the file, so their order does not need to match the struct. Missing keys are
+/// deserialized as `None`, and strings can be deserialized into unit enum variants.
+pub fn from_flipper_format<T>(ff: &mut FlipperFormat) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(Deserializer::new(ff))
+}
+
+/// A deserializer that reads a struct from the keys of a Flipper Format file.
+pub struct Deserializer<'a> {
+    ff: &'a mut FlipperFormat,
+}
+
+impl<'a> Deserializer<'a> {
+    /// Creates a deserializer that reads from `ff`.
+    pub fn new(ff: &'a mut FlipperFormat) -> Self {
+        Self { ff }
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for Deserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::UnsupportedType("only structs can be deserialized"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_map(StructAccess {
+            ff: self.ff,
+            fields: fields.iter(),
+            key: None,
+        })
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct seq tuple tuple_struct map enum identifier ignored_any
+    }
+}
+
+/// Yields the struct fields that are present in the file.
+struct StructAccess<'a> {
+    ff: &'a mut FlipperFormat,
+    fields: slice::Iter<'static, &'static str>,
+    key: Option<CString>,
+}
+
+impl<'de, 'a> MapAccess<'de> for StructAccess<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        for &field in self.fields.by_ref() {
+            let key = to_c_string(field)?;
+
+            // Missing keys are skipped, so that optional fields are left as `None`.
+            if self.ff.key_exists(&key) {
+                self.key = Some(key);
+                return seed.deserialize(field.into_deserializer()).map(Some);
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let key = self.key.take().ok_or(Error::Read)?;
+
+        // Reads only search forwards, so start from the top to find keys in any order.
+        self.ff.rewind()?;
+        seed.deserialize(ValueDeserializer {
+            ff: &mut *self.ff,
+            key: &key,
+        })
+    }
+}
+
+/// Deserializes the value of a single key.
+struct ValueDeserializer<'a> {
+    ff: &'a mut FlipperFormat,
+    key: &'a CStr,
+}
+
+impl<'a> ValueDeserializer<'a> {
+    fn read_string(&mut self) -> Result<FuriString> {
+        let mut value = FuriString::new();
+        self.ff.read_string(self.key, &mut value)?;
+        Ok(value)
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for ValueDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        // Values are untyped, so fall back to the most general representation.
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut value = [false];
+        self.ff.read_bool(self.key, &mut value)?;
+        visitor.visit_bool(value[0])
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i32(visitor)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i32(visitor)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut value = [0];
+        self.ff.read_i32(self.key, &mut value)?;
+        visitor.visit_i32(value[0])
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i32(visitor)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_u32(visitor)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_u32(visitor)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut value = [0];
+        self.ff.read_u32(self.key, &mut value)?;
+        visitor.visit_u32(value[0])
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut value = [0];
+        self.ff.read_hex_u64(self.key, &mut value)?;
+        visitor.visit_u64(value[0])
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut value = [0.0];
+        self.ff.read_f32(self.key, &mut value)?;
+        visitor.visit_f32(value[0])
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_f32(visitor)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value> {
+        let value = self.read_string()?;
+        visitor.visit_str(value.to_str().map_err(|_| Error::Read)?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut value = vec![0; self.ff.value_count(self.key)?];
+        self.ff.read_hex(self.key, &mut value)?;
+        visitor.visit_byte_buf(value)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        // The key is known to exist, otherwise it would have been skipped.
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let count = self.ff.value_count(self.key)?;
+        visitor.visit_seq(ArrayAccess {
+            ff: self.ff,
+            key: self.key,
+            count,
+            index: 0,
+            array: None,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::UnsupportedType("cannot deserialize nested map"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value> {
+        Err(Error::UnsupportedType("cannot deserialize nested struct"))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        mut self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let value = self.read_string()?;
+        let variant = String::from(value.to_str().map_err(|_| Error::Read)?);
+        visitor.visit_enum(variant.into_deserializer())
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+}
+
+/// Yields the values of an array key.
+///
+/// The SDK can only read all values of a key at once, so the whole array is read
+/// when the first element is requested, using that element's type.
+struct ArrayAccess<'a> {
+    ff: &'a mut FlipperFormat,
+    key: &'a CStr,
+    count: usize,
+    index: usize,
+    array: Option<Array>,
+}
+
+impl<'de, 'a> SeqAccess<'de> for ArrayAccess<'a> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.index >= self.count {
+            return Ok(None);
+        }
+
+        let value = seed.deserialize(ElementDeserializer { access: self })?;
+        self.index += 1;
+
+        Ok(Some(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.count - self.index)
+    }
+}
+
+/// Deserializes the current element of an [`ArrayAccess`].
+struct ElementDeserializer<'b, 'a> {
+    access: &'b mut ArrayAccess<'a>,
+}
+
+macro_rules! element {
+    ($access:expr, $variant:ident, $read:ident, $zero:expr) => {{
+        let access = $access;
+        if access.array.is_none() {
+            let mut values = vec![$zero; access.count];
+            access.ff.$read(access.key, &mut values)?;
+            access.array = Some(Array::$variant(values));
+        }
+
+        match &access.array {
+            Some(Array::$variant(values)) => values[access.index],
+            _ => {
+                return Err(Error::UnsupportedType(
+                    "array elements must have the same type",
+                ))
+            }
+        }
+    }};
+}
+
+impl<'de, 'b, 'a> de::Deserializer<'de> for ElementDeserializer<'b, 'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::UnsupportedType(
+            "array elements must be numbers or booleans",
+        ))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_bool(element!(self.access, Bool, read_bool, false))
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i32(visitor)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i32(visitor)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i32(element!(self.access, I32, read_i32, 0))
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i32(visitor)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        // Byte arrays are stored as hex, matching the serializer.
+        visitor.visit_u8(element!(self.access, Hex, read_hex, 0))
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_u32(visitor)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u32(element!(self.access, U32, read_u32, 0))
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        // Each value is written as 8 hex bytes, which are counted as separate values.
+        if self.access.array.is_none() {
+            self.access.count /= 8;
+        }
+        visitor.visit_u64(element!(self.access, HexU64, read_hex_u64, 0))
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_f32(element!(self.access, F32, read_f32, 0.0))
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_f32(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf option unit unit_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use core::ffi::CStr;
+
+    use serde::Deserialize;
+
+    use super::from_flipper_format;
+    use crate::format::{Error, FlipperFormat};
+
+    fn key(name: &[u8]) -> &CStr {
+        CStr::from_bytes_with_nul(name).unwrap()
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Mode {
+        Manual,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Fields {
+        filetype: String,
+        version: u32,
+        offset: i16,
+        ratio: f32,
+        enabled: bool,
+        mode: Mode,
+        key: u64,
+        channel: Option<u8>,
+        label: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Arrays {
+        data: Vec<u8>,
+        counts: Vec<u32>,
+        offsets: [i32; 2],
+        flags: (bool, bool),
+        keys: Vec<u64>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Nested {
+        #[allow(dead_code)]
+        version: Version,
+    }
+
+    #[derive(Deserialize)]
+    struct Version {}
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Names {
+        #[allow(dead_code)]
+        counts: Vec<String>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Required {
+        #[allow(dead_code)]
+        missing: u32,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Mistyped {
+        #[allow(dead_code)]
+        filetype: u32,
+    }
+
+    #[test]
+    fn struct_fields_in_any_order() {
+        let mut ff = FlipperFormat::new_string();
+        ff.write_u32(key(b"Channel\0"), &[7]).unwrap();
+        ff.write_hex_u64(key(b"Key\0"), &[0x0123_4567_89AB_CDEF])
+            .unwrap();
+        ff.write_string(key(b"Mode\0"), key(b"Manual\0")).unwrap();
+        ff.write_bool(key(b"Enabled\0"), &[true]).unwrap();
+        ff.write_f32(key(b"Ratio\0"), &[0.5]).unwrap();
+        ff.write_i32(key(b"Offset\0"), &[-3]).unwrap();
+        ff.write_header(key(b"Test File\0"), 2).unwrap();
+        ff.rewind().unwrap();
+
+        let value: Fields = from_flipper_format(&mut ff).unwrap();
+        assert_eq!(value.filetype, "Test File");
+        assert_eq!(value.version, 2);
+        assert_eq!(value.offset, -3);
+        assert_eq!(value.ratio, 0.5);
+        assert!(value.enabled);
+        assert_eq!(value.mode, Mode::Manual);
+        assert_eq!(value.key, 0x0123_4567_89AB_CDEF);
+        assert_eq!(value.channel, Some(7));
+        // Missing keys are `None`.
+        assert_eq!(value.label, None);
+    }
+
+    #[test]
+    fn sequences() {
+        let mut ff = FlipperFormat::new_string();
+        ff.write_hex(key(b"Data\0"), &[0xDE, 0xAD]).unwrap();
+        ff.write_u32(key(b"Counts\0"), &[1, 2, 3]).unwrap();
+        ff.write_i32(key(b"Offsets\0"), &[-1, 1]).unwrap();
+        ff.write_bool(key(b"Flags\0"), &[true, false]).unwrap();
+        ff.write_hex_u64(key(b"Keys\0"), &[1, u64::MAX]).unwrap();
+        ff.rewind().unwrap();
+
+        let value: Arrays = from_flipper_format(&mut ff).unwrap();
+        assert_eq!(value.data, [0xDE, 0xAD]);
+        assert_eq!(value.counts, [1, 2, 3]);
+        assert_eq!(value.offsets, [-1, 1]);
+        assert_eq!(value.flags, (true, false));
+        assert_eq!(value.keys, [1, u64::MAX]);
+    }
+
+    #[test]
+    fn unsupported_types() {
+        let mut ff = FlipperFormat::new_string();
+        ff.write_header(key(b"Test File\0"), 1).unwrap();
+        ff.write_u32(key(b"Counts\0"), &[1, 2]).unwrap();
+        ff.rewind().unwrap();
+
+        assert_eq!(
+            from_flipper_format::<u32>(&mut ff).err(),
+            Some(Error::UnsupportedType("only structs can be deserialized"))
+        );
+        assert_eq!(
+            from_flipper_format::<Nested>(&mut ff).err(),
+            Some(Error::UnsupportedType("cannot deserialize nested struct"))
+        );
+        assert_eq!(
+            from_flipper_format::<Names>(&mut ff).err(),
+            Some(Error::UnsupportedType(
+                "array elements must be numbers or booleans"
+            ))
+        );
+    }
+
+    #[test]
+    fn invalid_values() {
+        let mut ff = FlipperFormat::new_string();
+        ff.write_header(key(b"Test File\0"), 1).unwrap();
+        ff.rewind().unwrap();
+
+        // Required fields must be present.
+        assert_eq!(
+            from_flipper_format::<Required>(&mut ff).err(),
+            Some(Error::Custom(String::from("missing field `Missing`")))
+        );
+
+        // Values must parse as the field type.
+        assert_eq!(
+            from_flipper_format::<Mistyped>(&mut ff).err(),
+            Some(Error::Read)
+        );
+    }
+}
@@ -0,0 +1,644 @@
+//! Flipper Format files.
+//!
+//! Flipper Format is the line-based `Key: value` text format used by the Flipper Zero for
+//! its key files (`.sub`, `.ir`, `.nfc`, `.rfid`, ...) and for many settings files:
+//!
+//! ```text
+//! Filetype: Flipper SubGhz Key File
+//! Version: 1
+//! # Comments start with a hash
+//! Frequency: 433920000
+//! Key: 00 00 00 00 00 12 34 56
+//! ```
+//!
+//! With the `serde` feature enabled, `to_flipper_format` and `from_flipper_format` map
+//! Rust structs directly to files: each struct field is stored under a key with the
+//! same name.
+//!
+//! ```
+//! #[derive(Serialize, Deserialize)]
+//! struct Key {
+//!     #[serde(rename = "Filetype")]
+//!     filetype: String,
+//!     #[serde(rename = "Version")]
+//!     version: u32,
+//!     #[serde(rename = "Frequency")]
+//!     frequency: u32,
+//!     #[serde(rename = "Key")]
+//!     key: Vec<u8>,
+//! }
+//!
+//! let mut ff = FlipperFormat::new_file();
+//! ff.open_existing(CStr::from_bytes_with_nul(b"/ext/subghz/gate.sub\0").unwrap())?;
+//! let key: Key = from_flipper_format(&mut ff)?;
+//! ```
+
+use core::ffi::{c_char, CStr};
+use core::fmt;
+
+use flipperzero_sys as sys;
+use flipperzero_sys::furi::UnsafeRecord;
+
+use crate::furi::string::FuriString;
+use crate::toolbox::stream::{self, StreamRef};
+
+#[cfg(feature = "serde")]
+pub(crate) mod de;
+#[cfg(feature = "serde")]
+pub(crate) mod ser;
+
+#[cfg(feature = "serde")]
+pub use self::de::{from_flipper_format, Deserializer};
+#[cfg(feature = "serde")]
+pub use self::ser::{to_flipper_format, Serializer};
+
+const RECORD_STORAGE: *const c_char = sys::c_string!("storage");
+
+/// Flipper Format Result type.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Flipper Format error kinds.
+///
+/// The SDK only reports success or failure for most operations, so these errors
+/// describe which kind of operation failed rather than why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The backing file could not be opened, closed or repositioned.
+    Io,
+    /// The key is missing, or its value could not be parsed as the requested type.
+    Read,
+    /// The value could not be written.
+    Write,
+    /// The arguments are not supported by the SDK API (for example, too many values).
+    InvalidParameter,
+    /// The Rust type cannot be represented in a Flipper Format file.
+    #[cfg(feature = "serde")]
+    UnsupportedType(&'static str),
+    /// A custom error raised by a `Serialize` or `Deserialize` implementation.
+    #[cfg(feature = "serde")]
+    Custom(alloc::string::String),
+}
+
+impl Error {
+    /// Describes the error.
+    pub fn description(&self) -> &str {
+        match self {
+            Self::Io => "Flipper Format I/O error",
+            Self::Read => "Key is missing or has an invalid value",
+            Self::Write => "Failed to write value",
+            Self::InvalidParameter => "Invalid parameter",
+            #[cfg(feature = "serde")]
+            Self::UnsupportedType(msg) => msg,
+            #[cfg(feature = "serde")]
+            Self::Custom(msg) => msg.as_str(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> core::result::Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+/// The stream that backs a [`FlipperFormat`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    String,
+    File,
+    BufferedFile,
+}
+
+/// A Flipper Format reader and writer.
+///
+/// Reads look for the requested key starting from the current position, so keys are
+/// most efficiently read in the order that they appear in the file. Use
+/// [`FlipperFormat::rewind`] to start over from the beginning.
+pub struct FlipperFormat {
+    data: *mut sys::FlipperFormat,
+    backend: Backend,
+    _storage: Option<UnsafeRecord<sys::Storage>>,
+}
+
+/// Converts an SDK success flag into a `Result`.
+fn check(ok: bool, error: Error) -> Result<()> {
+    if ok {
+        Ok(())
+    } else {
+        Err(error)
+    }
+}
+
+/// Converts a value count into the type expected by the SDK.
+fn count<T>(values: &[T]) -> Result<u16> {
+    values.len().try_into().map_err(|_| Error::InvalidParameter)
+}
+
+impl FlipperFormat {
+    /// Allocates a Flipper Format backed by an in-memory string.
+    pub fn new_string() -> Self {
+        let data = unsafe { sys::flipper_format_string_alloc() };
+        assert!(!data.is_null());
+
+        Self {
+            data,
+            backend: Backend::String,
+            _storage: None,
+        }
+    }
+
+    /// Allocates a Flipper Format backed by an unbuffered file.
+    ///
+    /// A file must be opened with one of the `open_*` methods before use.
+    pub fn new_file() -> Self {
+        unsafe {
+            let storage = UnsafeRecord::open(RECORD_STORAGE);
+            let data = sys::flipper_format_file_alloc(storage.as_ptr());
+            assert!(!data.is_null());
+
+            Self {
+                data,
+                backend: Backend::File,
+                _storage: Some(storage),
+            }
+        }
+    }
+
+    /// Allocates a Flipper Format backed by a buffered file.
+    ///
+    /// Buffered files are faster to read, but can only be opened with
+    /// [`FlipperFormat::open_existing`] or [`FlipperFormat::open_always`].
+    pub fn new_buffered_file() -> Self {
+        unsafe {
+            let storage = UnsafeRecord::open(RECORD_STORAGE);
+            let data = sys::flipper_format_buffered_file_alloc(storage.as_ptr());
+            assert!(!data.is_null());
+
+            Self {
+                data,
+                backend: Backend::BufferedFile,
+                _storage: Some(storage),
+            }
+        }
+    }
+
+    /// Returns the raw pointer to the underlying `FlipperFormat`.
+    pub fn as_ptr(&self) -> *mut sys::FlipperFormat {
+        self.data
+    }
+
+    /// Returns the stream that holds the data, for raw access.
+    ///
+    /// The stream shares its position with this `FlipperFormat`.
+    pub fn stream(&mut self) -> StreamRef<'_> {
+        let backend = match self.backend {
+            Backend::String => stream::Backend::String,
+            Backend::File => stream::Backend::File,
+            Backend::BufferedFile => stream::Backend::BufferedFile,
+        };
+
+        unsafe { StreamRef::new(sys::flipper_format_get_raw_stream(self.data), backend) }
+    }
+
+    /// Opens an existing file, failing if it does not exist.
+    pub fn open_existing(&mut self, path: &CStr) -> Result<()> {
+        let ok = match self.backend {
+            Backend::File => unsafe {
+                sys::flipper_format_file_open_existing(self.data, path.as_ptr())
+            },
+            Backend::BufferedFile => unsafe {
+                sys::flipper_format_buffered_file_open_existing(self.data, path.as_ptr())
+            },
+            Backend::String => return Err(Error::InvalidParameter),
+        };
+
+        check(ok, Error::Io)
+    }
+
+    /// Opens a file, creating it if it does not exist.
+    ///
+    /// An existing file is truncated.
+    pub fn open_always(&mut self, path: &CStr) -> Result<()> {
+        let ok = match self.backend {
+            Backend::File => unsafe {
+                sys::flipper_format_file_open_always(self.data, path.as_ptr())
+            },
+            Backend::BufferedFile => unsafe {
+                sys::flipper_format_buffered_file_open_always(self.data, path.as_ptr())
+            },
+            Backend::String => return Err(Error::InvalidParameter),
+        };
+
+        check(ok, Error::Io)
+    }
+
+    /// Opens a file for appending, creating it if it does not exist.
+    pub fn open_append(&mut self, path: &CStr) -> Result<()> {
+        if self.backend != Backend::File {
+            return Err(Error::InvalidParameter);
+        }
+
+        check(
+            unsafe { sys::flipper_format_file_open_append(self.data, path.as_ptr()) },
+            Error::Io,
+        )
+    }
+
+    /// Creates a new file, failing if it already exists.
+    pub fn open_new(&mut self, path: &CStr) -> Result<()> {
+        if self.backend != Backend::File {
+            return Err(Error::InvalidParameter);
+        }
+
+        check(
+            unsafe { sys::flipper_format_file_open_new(self.data, path.as_ptr()) },
+            Error::Io,
+        )
+    }
+
+    /// Closes the underlying file.
+    ///
+    /// Files are closed automatically on drop; this is only needed to re-use the
+    /// `FlipperFormat` for another file, or to observe errors on close.
+    pub fn close(&mut self) -> Result<()> {
+        let ok = match self.backend {
+            Backend::File => unsafe { sys::flipper_format_file_close(self.data) },
+            Backend::BufferedFile => unsafe { sys::flipper_format_buffered_file_close(self.data) },
+            Backend::String => return Err(Error::InvalidParameter),
+        };
+
+        check(ok, Error::Io)
+    }
+
+    /// Enables or disables strict mode.
+    ///
+    /// In strict mode, reads fail unless the next key in the file is the requested key.
+    pub fn set_strict_mode(&mut self, strict: bool) {
+        unsafe { sys::flipper_format_set_strict_mode(self.data, strict) };
+    }
+
+    /// Moves to the start of the file.
+    pub fn rewind(&mut self) -> Result<()> {
+        check(unsafe { sys::flipper_format_rewind(self.data) }, Error::Io)
+    }
+
+    /// Moves to the end of the file.
+    pub fn seek_to_end(&mut self) -> Result<()> {
+        check(
+            unsafe { sys::flipper_format_seek_to_end(self.data) },
+            Error::Io,
+        )
+    }
+
+    /// Checks whether the key exists anywhere in the file.
+    ///
+    /// The current position is left unchanged.
+    pub fn key_exists(&mut self, key: &CStr) -> bool {
+        unsafe { sys::flipper_format_key_exist(self.data, key.as_ptr()) }
+    }
+
+    /// Returns the number of values stored under the key.
+    pub fn value_count(&mut self, key: &CStr) -> Result<usize> {
+        let mut count = 0;
+        check(
+            unsafe { sys::flipper_format_get_value_count(self.data, key.as_ptr(), &mut count) },
+            Error::Read,
+        )?;
+
+        Ok(count as usize)
+    }
+
+    /// Deletes the key and its values.
+    pub fn delete_key(&mut self, key: &CStr) -> Result<()> {
+        check(
+            unsafe { sys::flipper_format_delete_key(self.data, key.as_ptr()) },
+            Error::Write,
+        )
+    }
+
+    /// Reads the `Filetype` and `Version` header.
+    pub fn read_header(&mut self) -> Result<(FuriString, u32)> {
+        let mut filetype = FuriString::new();
+        let mut version = 0;
+        check(
+            unsafe {
+                sys::flipper_format_read_header(self.data, filetype.as_mut_ptr(), &mut version)
+            },
+            Error::Read,
+        )?;
+
+        Ok((filetype, version))
+    }
+
+    /// Writes the `Filetype` and `Version` header.
+    pub fn write_header(&mut self, filetype: &CStr, version: u32) -> Result<()> {
+        check(
+            unsafe { sys::flipper_format_write_header_cstr(self.data, filetype.as_ptr(), version) },
+            Error::Write,
+        )
+    }
+
+    /// Writes a comment line.
+    pub fn write_comment(&mut self, comment: &CStr) -> Result<()> {
+        check(
+            unsafe { sys::flipper_format_write_comment_cstr(self.data, comment.as_ptr()) },
+            Error::Write,
+        )
+    }
+
+    /// Reads the string value of the key into `value`.
+    pub fn read_string(&mut self, key: &CStr, value: &mut FuriString) -> Result<()> {
+        check(
+            unsafe { sys::flipper_format_read_string(self.data, key.as_ptr(), value.as_mut_ptr()) },
+            Error::Read,
+        )
+    }
+
+    /// Writes a key with a string value.
+    pub fn write_string(&mut self, key: &CStr, value: &CStr) -> Result<()> {
+        check(
+            unsafe {
+                sys::flipper_format_write_string_cstr(self.data, key.as_ptr(), value.as_ptr())
+            },
+            Error::Write,
+        )
+    }
+
+    /// Updates the string value of an existing key.
+    pub fn update_string(&mut self, key: &CStr, value: &CStr) -> Result<()> {
+        check(
+            unsafe {
+                sys::flipper_format_update_string_cstr(self.data, key.as_ptr(), value.as_ptr())
+            },
+            Error::Write,
+        )
+    }
+
+    /// Reads `values.len()` unsigned integers from the key.
+    pub fn read_u32(&mut self, key: &CStr, values: &mut [u32]) -> Result<()> {
+        let n = count(values)?;
+        check(
+            unsafe {
+                sys::flipper_format_read_uint32(self.data, key.as_ptr(), values.as_mut_ptr(), n)
+            },
+            Error::Read,
+        )
+    }
+
+    /// Writes a key with an array of unsigned integers.
+    pub fn write_u32(&mut self, key: &CStr, values: &[u32]) -> Result<()> {
+        let n = count(values)?;
+        check(
+            unsafe {
+                sys::flipper_format_write_uint32(self.data, key.as_ptr(), values.as_ptr(), n)
+            },
+            Error::Write,
+        )
+    }
+
+    /// Updates the unsigned integer values of an existing key.
+    pub fn update_u32(&mut self, key: &CStr, values: &[u32]) -> Result<()> {
+        let n = count(values)?;
+        check(
+            unsafe {
+                sys::flipper_format_update_uint32(self.data, key.as_ptr(), values.as_ptr(), n)
+            },
+            Error::Write,
+        )
+    }
+
+    /// Reads `values.len()` signed integers from the key.
+    pub fn read_i32(&mut self, key: &CStr, values: &mut [i32]) -> Result<()> {
+        let n = count(values)?;
+        check(
+            unsafe {
+                sys::flipper_format_read_int32(self.data, key.as_ptr(), values.as_mut_ptr(), n)
+            },
+            Error::Read,
+        )
+    }
+
+    /// Writes a key with an array of signed integers.
+    pub fn write_i32(&mut self, key: &CStr, values: &[i32]) -> Result<()> {
+        let n = count(values)?;
+        check(
+            unsafe { sys::flipper_format_write_int32(self.data, key.as_ptr(), values.as_ptr(), n) },
+            Error::Write,
+        )
+    }
+
+    /// Updates the signed integer values of an existing key.
+    pub fn update_i32(&mut self, key: &CStr, values: &[i32]) -> Result<()> {
+        let n = count(values)?;
+        check(
+            unsafe {
+                sys::flipper_format_update_int32(self.data, key.as_ptr(), values.as_ptr(), n)
+            },
+            Error::Write,
+        )
+    }
+
+    /// Reads `values.len()` floats from the key.
+    pub fn read_f32(&mut self, key: &CStr, values: &mut [f32]) -> Result<()> {
+        let n = count(values)?;
+        check(
+            unsafe {
+                sys::flipper_format_read_float(self.data, key.as_ptr(), values.as_mut_ptr(), n)
+            },
+            Error::Read,
+        )
+    }
+
+    /// Writes a key with an array of floats.
+    pub fn write_f32(&mut self, key: &CStr, values: &[f32]) -> Result<()> {
+        let n = count(values)?;
+        check(
+            unsafe { sys::flipper_format_write_float(self.data, key.as_ptr(), values.as_ptr(), n) },
+            Error::Write,
+        )
+    }
+
+    /// Updates the float values of an existing key.
+    pub fn update_f32(&mut self, key: &CStr, values: &[f32]) -> Result<()> {
+        let n = count(values)?;
+        check(
+            unsafe {
+                sys::flipper_format_update_float(self.data, key.as_ptr(), values.as_ptr(), n)
+            },
+            Error::Write,
+        )
+    }
+
+    /// Reads `values.len()` booleans from the key.
+    pub fn read_bool(&mut self, key: &CStr, values: &mut [bool]) -> Result<()> {
+        let n = count(values)?;
+        check(
+            unsafe {
+                sys::flipper_format_read_bool(self.data, key.as_ptr(), values.as_mut_ptr(), n)
+            },
+            Error::Read,
+        )
+    }
+
+    /// Writes a key with an array of booleans.
+    pub fn write_bool(&mut self, key: &CStr, values: &[bool]) -> Result<()> {
+        let n = count(values)?;
+        check(
+            unsafe { sys::flipper_format_write_bool(self.data, key.as_ptr(), values.as_ptr(), n) },
+            Error::Write,
+        )
+    }
+
+    /// Updates the boolean values of an existing key.
+    pub fn update_bool(&mut self, key: &CStr, values: &[bool]) -> Result<()> {
+        let n = count(values)?;
+        check(
+            unsafe { sys::flipper_format_update_bool(self.data, key.as_ptr(), values.as_ptr(), n) },
+            Error::Write,
+        )
+    }
+
+    /// Reads `values.len()` hex-encoded bytes from the key.
+    pub fn read_hex(&mut self, key: &CStr, values: &mut [u8]) -> Result<()> {
+        let n = count(values)?;
+        check(
+            unsafe {
+                sys::flipper_format_read_hex(self.data, key.as_ptr(), values.as_mut_ptr(), n)
+            },
+            Error::Read,
+        )
+    }
+
+    /// Writes a key with an array of hex-encoded bytes.
+    pub fn write_hex(&mut self, key: &CStr, values: &[u8]) -> Result<()> {
+        let n = count(values)?;
+        check(
+            unsafe { sys::flipper_format_write_hex(self.data, key.as_ptr(), values.as_ptr(), n) },
+            Error::Write,
+        )
+    }
+
+    /// Updates the hex-encoded bytes of an existing key.
+    pub fn update_hex(&mut self, key: &CStr, values: &[u8]) -> Result<()> {
+        let n = count(values)?;
+        check(
+            unsafe { sys::flipper_format_update_hex(self.data, key.as_ptr(), values.as_ptr(), n) },
+            Error::Write,
+        )
+    }
+
+    /// Reads `values.len()` big-endian, hex-encoded 64-bit integers from the key.
+    pub fn read_hex_u64(&mut self, key: &CStr, values: &mut [u64]) -> Result<()> {
+        let n = count(values)?;
+        check(
+            unsafe {
+                sys::flipper_format_read_hex_uint64(self.data, key.as_ptr(), values.as_mut_ptr(), n)
+            },
+            Error::Read,
+        )
+    }
+
+    /// Writes a key with an array of big-endian, hex-encoded 64-bit integers.
+    pub fn write_hex_u64(&mut self, key: &CStr, values: &[u64]) -> Result<()> {
+        let n = count(values)?;
+        check(
+            unsafe {
+                sys::flipper_format_write_hex_uint64(self.data, key.as_ptr(), values.as_ptr(), n)
+            },
+            Error::Write,
+        )
+    }
+}
+
+impl Drop for FlipperFormat {
+    fn drop(&mut self) {
+        // Freeing the format also closes any open file.
+        unsafe { sys::flipper_format_free(self.data) };
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::ffi::CStr;
+
+    use super::{Error, FlipperFormat};
+
+    const FILETYPE: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"Test File\0") };
+    const NAME: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"Name\0") };
+    const COUNTS: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"Counts\0") };
+    const OFFSET: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"Offset\0") };
+    const KEY: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"Key\0") };
+    const ENABLED: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"Enabled\0") };
+    const MISSING: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"Missing\0") };
+
+    #[test]
+    fn round_trip() {
+        let mut ff = FlipperFormat::new_string();
+        ff.write_header(FILETYPE, 2).unwrap();
+        ff.write_string(NAME, CStr::from_bytes_with_nul(b"dolphin\0").unwrap())
+            .unwrap();
+        ff.write_u32(COUNTS, &[1, 2, 3]).unwrap();
+        ff.write_i32(OFFSET, &[-42]).unwrap();
+        ff.write_hex(KEY, &[0xDE, 0xAD, 0xBE, 0xEF]).unwrap();
+        ff.write_bool(ENABLED, &[true]).unwrap();
+
+        ff.rewind().unwrap();
+
+        let (filetype, version) = ff.read_header().unwrap();
+        assert_eq!(filetype.as_c_str(), FILETYPE);
+        assert_eq!(version, 2);
+
+        let mut name = crate::furi::string::FuriString::new();
+        ff.read_string(NAME, &mut name).unwrap();
+        assert_eq!(name.to_str(), Ok("dolphin"));
+
+        assert_eq!(ff.value_count(COUNTS), Ok(3));
+        let mut counts = [0; 3];
+        ff.read_u32(COUNTS, &mut counts).unwrap();
+        assert_eq!(counts, [1, 2, 3]);
+
+        let mut offset = [0];
+        ff.read_i32(OFFSET, &mut offset).unwrap();
+        assert_eq!(offset, [-42]);
+
+        let mut key = [0; 4];
+        ff.read_hex(KEY, &mut key).unwrap();
+        assert_eq!(key, [0xDE, 0xAD, 0xBE, 0xEF]);
+
+        let mut enabled = [false];
+        ff.read_bool(ENABLED, &mut enabled).unwrap();
+        assert_eq!(enabled, [true]);
+    }
+
+    #[test]
+    fn missing_key() {
+        let mut ff = FlipperFormat::new_string();
+        ff.write_u32(COUNTS, &[7]).unwrap();
+        ff.rewind().unwrap();
+
+        assert!(ff.key_exists(COUNTS));
+        assert!(!ff.key_exists(MISSING));
+
+        let mut value = [0];
+        assert_eq!(ff.read_u32(MISSING, &mut value), Err(Error::Read));
+    }
+
+    #[test]
+    fn string_backend_has_no_file() {
+        let mut ff = FlipperFormat::new_string();
+        assert_eq!(
+            ff.open_existing(CStr::from_bytes_with_nul(b"/ext/test.txt\0").unwrap()),
+            Err(Error::InvalidParameter),
+        );
+    }
+}
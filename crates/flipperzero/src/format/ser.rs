@@ -0,0 +1,773 @@
+//! Serde serializer for Flipper Format files.
+
+use alloc::ffi::CString;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::ffi::CStr;
+use core::fmt::Display;
+
+use serde::ser::{self, Impossible, Serialize};
+
+use super::{Error, FlipperFormat, Result};
+
+impl ser::StdError for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+}
+
+/// Converts a key or string value into a C string.
+pub(super) fn to_c_string(s: &str) -> Result<CString> {
+    CString::new(s).map_err(|_| Error::InvalidParameter)
+}
+
+/// Narrows an `i64` to the `i32` values supported by the format.
+fn to_i32(v: i64) -> Result<i32> {
+    v.try_into()
+        .map_err(|_| Error::UnsupportedType("i64 value does not fit in i32"))
+}
+
+/// Writes a struct to a Flipper Format file.
+///
+/// Each field is written as a key with the field's name. Numbers, booleans, strings,
+/// byte arrays and sequences of numbers or booleans are supported; fields set to
+/// `None` are skipped, and unit enum variants are written as strings.
+///
+/// `u64` values are written as 8-byte hex, like `Key` values in `.sub` files. The
+/// format has no 64-bit integers otherwise, so `i64` values are written as `i32`, and
+/// are rejected if they don't fit.
+///
+/// Use `#[serde(rename = "Filetype")]` and `#[serde(rename = "Version")]` on the first
+/// two fields to write a standard file header.
+pub fn to_flipper_format<T>(value: &T, ff: &mut FlipperFormat) -> Result<()>
+where
+    T: Serialize + ?Sized,
+{
+    value.serialize(Serializer::new(ff))
+}
+
+/// A serializer that writes a struct as the keys of a Flipper Format file.
+pub struct Serializer<'a> {
+    ff: &'a mut FlipperFormat,
+}
+
+impl<'a> Serializer<'a> {
+    /// Creates a serializer that writes at the current position of `ff`.
+    pub fn new(ff: &'a mut FlipperFormat) -> Self {
+        Self { ff }
+    }
+}
+
+macro_rules! unsupported {
+    ($msg:literal; $($method:ident($($arg:ty),*) -> $ret:ty;)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<$ret> {
+                Err(Error::UnsupportedType($msg))
+            }
+        )*
+    };
+}
+
+impl<'a> ser::Serializer for Serializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = StructSerializer<'a>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Ok(StructSerializer { ff: self.ff })
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        Err(Error::UnsupportedType("cannot serialize newtype variant"))
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        Err(Error::UnsupportedType("only structs can be serialized"))
+    }
+
+    unsupported! {
+        "only structs can be serialized";
+        serialize_bool(bool) -> ();
+        serialize_i8(i8) -> ();
+        serialize_i16(i16) -> ();
+        serialize_i32(i32) -> ();
+        serialize_i64(i64) -> ();
+        serialize_u8(u8) -> ();
+        serialize_u16(u16) -> ();
+        serialize_u32(u32) -> ();
+        serialize_u64(u64) -> ();
+        serialize_f32(f32) -> ();
+        serialize_f64(f64) -> ();
+        serialize_char(char) -> ();
+        serialize_str(&str) -> ();
+        serialize_bytes(&[u8]) -> ();
+        serialize_none() -> ();
+        serialize_unit() -> ();
+        serialize_unit_variant(&'static str, u32, &'static str) -> ();
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+}
+
+/// Serializes the fields of a struct.
+#[doc(hidden)]
+pub struct StructSerializer<'a> {
+    ff: &'a mut FlipperFormat,
+}
+
+impl<'a> ser::SerializeStruct for StructSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        let key = to_c_string(key)?;
+        value.serialize(ValueSerializer {
+            ff: self.ff,
+            key: &key,
+        })
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Serializes a single field value under `key`.
+struct ValueSerializer<'a> {
+    ff: &'a mut FlipperFormat,
+    key: &'a CStr,
+}
+
+impl<'a> ser::Serializer for ValueSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = ArraySerializer<'a>;
+    type SerializeTuple = ArraySerializer<'a>;
+    type SerializeTupleStruct = ArraySerializer<'a>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.ff.write_bool(self.key, &[v])
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.serialize_i32(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.serialize_i32(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.ff.write_i32(self.key, &[v])
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.serialize_i32(to_i32(v)?)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.serialize_u32(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.serialize_u32(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.ff.write_u32(self.key, &[v])
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.ff.write_hex_u64(self.key, &[v])
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.ff.write_f32(self.key, &[v])
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.serialize_f32(v as f32)
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.ff.write_string(self.key, &to_c_string(v)?)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.ff.write_hex(self.key, v)
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        // Missing keys are deserialized as `None`.
+        Ok(())
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Err(Error::UnsupportedType("cannot serialize unit value"))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        Err(Error::UnsupportedType("cannot serialize newtype variant"))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(ArraySerializer {
+            ff: self.ff,
+            key: self.key,
+            array: None,
+            len: len.unwrap_or(0),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(Error::UnsupportedType("cannot serialize tuple variant"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(Error::UnsupportedType("cannot serialize nested map"))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(Error::UnsupportedType("cannot serialize nested struct"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(Error::UnsupportedType("cannot serialize struct variant"))
+    }
+}
+
+/// The values of an array field, typed by its first element.
+pub(super) enum Array {
+    Bool(Vec<bool>),
+    U32(Vec<u32>),
+    I32(Vec<i32>),
+    F32(Vec<f32>),
+    Hex(Vec<u8>),
+    HexU64(Vec<u64>),
+}
+
+/// Collects the elements of a sequence so they can be written as a single key.
+struct ArraySerializer<'a> {
+    ff: &'a mut FlipperFormat,
+    key: &'a CStr,
+    array: Option<Array>,
+    len: usize,
+}
+
+impl<'a> ArraySerializer<'a> {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(ElementSerializer {
+            array: &mut self.array,
+            len: self.len,
+        })
+    }
+
+    fn finish(self) -> Result<()> {
+        match self.array {
+            Some(Array::Bool(values)) => self.ff.write_bool(self.key, &values),
+            Some(Array::U32(values)) => self.ff.write_u32(self.key, &values),
+            Some(Array::I32(values)) => self.ff.write_i32(self.key, &values),
+            Some(Array::F32(values)) => self.ff.write_f32(self.key, &values),
+            Some(Array::Hex(values)) => self.ff.write_hex(self.key, &values),
+            Some(Array::HexU64(values)) => self.ff.write_hex_u64(self.key, &values),
+            // The SDK cannot represent a key without values.
+            None => Err(Error::InvalidParameter),
+        }
+    }
+}
+
+impl<'a> ser::SerializeSeq for ArraySerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTuple for ArraySerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for ArraySerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+/// Appends a single array element, checking that all elements have the same type.
+struct ElementSerializer<'a> {
+    array: &'a mut Option<Array>,
+    len: usize,
+}
+
+macro_rules! push_element {
+    ($self:ident, $variant:ident, $value:expr) => {{
+        match $self.array {
+            Some(Array::$variant(values)) => values.push($value),
+            Some(_) => {
+                return Err(Error::UnsupportedType(
+                    "array elements must have the same type",
+                ))
+            }
+            None => {
+                let mut values = Vec::with_capacity($self.len);
+                values.push($value);
+                *$self.array = Some(Array::$variant(values));
+            }
+        }
+        Ok(())
+    }};
+}
+
+impl<'a> ser::Serializer for ElementSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        push_element!(self, Bool, v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.serialize_i32(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.serialize_i32(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        push_element!(self, I32, v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.serialize_i32(to_i32(v)?)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        // Byte arrays are the most common array type, so they are written as hex.
+        push_element!(self, Hex, v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.serialize_u32(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        push_element!(self, U32, v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        push_element!(self, HexU64, v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        push_element!(self, F32, v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.serialize_f32(v as f32)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        Err(Error::UnsupportedType(
+            "array elements must be numbers or booleans",
+        ))
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        Err(Error::UnsupportedType("cannot serialize newtype variant"))
+    }
+
+    unsupported! {
+        "array elements must be numbers or booleans";
+        serialize_char(char) -> ();
+        serialize_str(&str) -> ();
+        serialize_bytes(&[u8]) -> ();
+        serialize_none() -> ();
+        serialize_unit() -> ();
+        serialize_unit_struct(&'static str) -> ();
+        serialize_unit_variant(&'static str, u32, &'static str) -> ();
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::string::String;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::ffi::CStr;
+
+    use serde::Serialize;
+
+    use super::to_flipper_format;
+    use crate::format::{Error, FlipperFormat};
+    use crate::furi::string::FuriString;
+
+    fn key(name: &[u8]) -> &CStr {
+        CStr::from_bytes_with_nul(name).unwrap()
+    }
+
+    #[derive(Serialize)]
+    enum Mode {
+        Auto,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Fields {
+        filetype: String,
+        version: u32,
+        offset: i16,
+        ratio: f32,
+        enabled: bool,
+        mode: Mode,
+        key: u64,
+        channel: Option<u8>,
+        label: Option<String>,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Arrays {
+        data: Vec<u8>,
+        counts: Vec<u32>,
+        offsets: [i32; 2],
+        flags: (bool, bool),
+        keys: Vec<u64>,
+    }
+
+    #[derive(Serialize)]
+    struct Nested {
+        inner: Wide,
+    }
+
+    #[derive(Serialize)]
+    struct Wide {
+        value: i64,
+    }
+
+    #[derive(Serialize)]
+    struct Names {
+        names: Vec<String>,
+    }
+
+    #[derive(Serialize)]
+    struct Empty {
+        values: Vec<u32>,
+    }
+
+    #[test]
+    fn struct_fields() {
+        let value = Fields {
+            filetype: String::from("Test File"),
+            version: 1,
+            offset: -3,
+            ratio: 0.5,
+            enabled: true,
+            mode: Mode::Auto,
+            key: 0x0123_4567_89AB_CDEF,
+            channel: Some(7),
+            label: None,
+        };
+
+        let mut ff = FlipperFormat::new_string();
+        to_flipper_format(&value, &mut ff).unwrap();
+        ff.rewind().unwrap();
+
+        let (filetype, version) = ff.read_header().unwrap();
+        assert_eq!(filetype.to_str(), Ok("Test File"));
+        assert_eq!(version, 1);
+
+        let mut offset = [0];
+        ff.read_i32(key(b"Offset\0"), &mut offset).unwrap();
+        assert_eq!(offset, [-3]);
+
+        let mut ratio = [0.0];
+        ff.read_f32(key(b"Ratio\0"), &mut ratio).unwrap();
+        assert_eq!(ratio, [0.5]);
+
+        let mut enabled = [false];
+        ff.read_bool(key(b"Enabled\0"), &mut enabled).unwrap();
+        assert_eq!(enabled, [true]);
+
+        let mut mode = FuriString::new();
+        ff.read_string(key(b"Mode\0"), &mut mode).unwrap();
+        assert_eq!(mode.to_str(), Ok("Auto"));
+
+        // `u64` values are written as 8 hex bytes.
+        assert_eq!(ff.value_count(key(b"Key\0")), Ok(8));
+        let mut k = [0];
+        ff.read_hex_u64(key(b"Key\0"), &mut k).unwrap();
+        assert_eq!(k, [0x0123_4567_89AB_CDEF]);
+
+        let mut channel = [0];
+        ff.read_u32(key(b"Channel\0"), &mut channel).unwrap();
+        assert_eq!(channel, [7]);
+
+        // `None` fields are skipped.
+        assert!(!ff.key_exists(key(b"Label\0")));
+    }
+
+    #[test]
+    fn sequences() {
+        let value = Arrays {
+            data: vec![0xDE, 0xAD],
+            counts: vec![1, 2, 3],
+            offsets: [-1, 1],
+            flags: (true, false),
+            keys: vec![1, u64::MAX],
+        };
+
+        let mut ff = FlipperFormat::new_string();
+        to_flipper_format(&value, &mut ff).unwrap();
+        ff.rewind().unwrap();
+
+        // Byte arrays are written as hex.
+        let mut data = [0; 2];
+        ff.read_hex(key(b"Data\0"), &mut data).unwrap();
+        assert_eq!(data, [0xDE, 0xAD]);
+
+        let mut counts = [0; 3];
+        ff.read_u32(key(b"Counts\0"), &mut counts).unwrap();
+        assert_eq!(counts, [1, 2, 3]);
+
+        let mut offsets = [0; 2];
+        ff.read_i32(key(b"Offsets\0"), &mut offsets).unwrap();
+        assert_eq!(offsets, [-1, 1]);
+
+        let mut flags = [false; 2];
+        ff.read_bool(key(b"Flags\0"), &mut flags).unwrap();
+        assert_eq!(flags, [true, false]);
+
+        // `u64` elements are written as hex, like single `u64` values.
+        let mut keys = [0; 2];
+        ff.read_hex_u64(key(b"Keys\0"), &mut keys).unwrap();
+        assert_eq!(keys, [1, u64::MAX]);
+    }
+
+    #[test]
+    fn unsupported_types() {
+        let mut ff = FlipperFormat::new_string();
+
+        assert_eq!(
+            to_flipper_format(&7u32, &mut ff),
+            Err(Error::UnsupportedType("only structs can be serialized"))
+        );
+
+        let nested = Nested {
+            inner: Wide { value: 1 },
+        };
+        assert_eq!(
+            to_flipper_format(&nested, &mut ff),
+            Err(Error::UnsupportedType("cannot serialize nested struct"))
+        );
+
+        let names = Names {
+            names: vec![String::from("a")],
+        };
+        assert_eq!(
+            to_flipper_format(&names, &mut ff),
+            Err(Error::UnsupportedType(
+                "array elements must be numbers or booleans"
+            ))
+        );
+
+        // The format has no empty arrays.
+        let empty = Empty { values: Vec::new() };
+        assert_eq!(
+            to_flipper_format(&empty, &mut ff),
+            Err(Error::InvalidParameter)
+        );
+    }
+
+    #[test]
+    fn i64_must_fit_in_i32() {
+        let mut ff = FlipperFormat::new_string();
+        to_flipper_format(&Wide { value: -5 }, &mut ff).unwrap();
+        ff.rewind().unwrap();
+
+        let mut value = [0];
+        ff.read_i32(key(b"value\0"), &mut value).unwrap();
+        assert_eq!(value, [-5]);
+
+        let wide = Wide {
+            value: i64::from(i32::MAX) + 1,
+        };
+        assert_eq!(
+            to_flipper_format(&wide, &mut ff),
+            Err(Error::UnsupportedType("i64 value does not fit in i32"))
+        );
+    }
+}
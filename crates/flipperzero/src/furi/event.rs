@@ -0,0 +1,114 @@
+//! Delivering events from worker threads.
+//!
+//! Workers, such as the [RFID](crate::rfid::worker) and [iButton](crate::ibutton::worker)
+//! workers, deliver their events on the worker thread to an [`EventSink`]: a closure, or
+//! an [`EventQueue`] to receive them from another thread.
+
+use alloc::sync::Arc;
+use core::time::Duration;
+
+use crate::furi::{self, message_queue::MessageQueue};
+
+/// Receives worker events on the worker thread.
+pub trait EventSink<E>: Send + 'static {
+    /// Handles an event. This must not block for long, or the worker may miss parts
+    /// of the signal.
+    fn send(&mut self, event: E);
+}
+
+impl<E, F> EventSink<E> for F
+where
+    F: FnMut(E) + Send + 'static,
+{
+    fn send(&mut self, event: E) {
+        self(event)
+    }
+}
+
+/// A queue of worker events, that can be cloned to be passed to the worker and still
+/// be received from.
+pub struct EventQueue<E> {
+    queue: Arc<MessageQueue<E>>,
+}
+
+// Furi message queues can be used from any thread.
+unsafe impl<E: Send> Send for EventQueue<E> {}
+unsafe impl<E: Send> Sync for EventQueue<E> {}
+
+impl<E> EventQueue<E> {
+    /// Creates a queue holding up to `capacity` events.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            queue: Arc::new(MessageQueue::new(capacity)),
+        }
+    }
+
+    /// Waits up to `timeout` for the next event.
+    pub fn recv(&self, timeout: Duration) -> furi::Result<E> {
+        self.queue.get(timeout)
+    }
+
+    /// Returns the number of events in the queue.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns whether the queue is empty.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+impl<E> Clone for EventQueue<E> {
+    fn clone(&self) -> Self {
+        Self {
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+impl<E: Send + 'static> EventSink<E> for EventQueue<E> {
+    fn send(&mut self, event: E) {
+        // Don't block the worker; the event is dropped if the queue is full.
+        let _ = self.queue.put(event, Duration::ZERO);
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::time::Duration;
+
+    use super::{EventQueue, EventSink};
+
+    #[test]
+    fn event_queue() {
+        let queue = EventQueue::new(2);
+        let mut sink = queue.clone();
+        sink.send(1u32);
+        sink.send(2);
+        // Dropped, as the queue is full.
+        sink.send(3);
+
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.recv(Duration::ZERO), Ok(1));
+        assert_eq!(queue.recv(Duration::ZERO), Ok(2));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn closure_sink() {
+        fn send_all(mut sink: impl EventSink<u32>) {
+            sink.send(1);
+            sink.send(2);
+        }
+
+        let queue = EventQueue::new(2);
+        send_all({
+            let mut queue = queue.clone();
+            move |event| queue.send(event * 10)
+        });
+        assert_eq!(queue.recv(Duration::ZERO), Ok(10));
+        assert_eq!(queue.recv(Duration::ZERO), Ok(20));
+        assert!(queue.is_empty());
+    }
+}
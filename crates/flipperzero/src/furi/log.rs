@@ -0,0 +1,236 @@
+//! Furi Logging API.
+//!
+//! Log records are written to the debug console, prefixed with a timestamp, their
+//! level and a tag, and are only written if their level is enabled in the firmware
+//! settings. Use the [`error!`], [`warn!`], [`info!`], [`debug!`] and [`trace!`]
+//! macros to log records.
+//!
+//! With the `log` feature, [`Logger`] forwards the records of the [`log`] crate to the
+//! firmware log as well.
+//!
+//! [`error!`]: crate::error
+//! [`warn!`]: crate::warn
+//! [`info!`]: crate::info
+//! [`debug!`]: crate::debug
+//! [`trace!`]: crate::trace
+//! [`log`]: https://docs.rs/log
+
+use core::ffi::c_char;
+use core::fmt;
+
+use flipperzero_sys as sys;
+
+use crate::furi::string::FuriString;
+
+/// Longest tag in bytes; longer tags are truncated.
+pub const MAX_TAG_LEN: usize = 31;
+
+/// Log level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    /// Get the level from the corresponding SDK `FuriLogLevel`.
+    ///
+    /// Returns `None` for `FuriLogLevelNone` and `FuriLogLevelDefault`.
+    pub fn from_sys(level: sys::FuriLogLevel) -> Option<Self> {
+        match level {
+            sys::FuriLogLevel_FuriLogLevelError => Some(Level::Error),
+            sys::FuriLogLevel_FuriLogLevelWarn => Some(Level::Warn),
+            sys::FuriLogLevel_FuriLogLevelInfo => Some(Level::Info),
+            sys::FuriLogLevel_FuriLogLevelDebug => Some(Level::Debug),
+            sys::FuriLogLevel_FuriLogLevelTrace => Some(Level::Trace),
+            _ => None,
+        }
+    }
+
+    /// Convert the level into the corresponding SDK `FuriLogLevel`.
+    pub fn to_sys(self) -> sys::FuriLogLevel {
+        match self {
+            Level::Error => sys::FuriLogLevel_FuriLogLevelError,
+            Level::Warn => sys::FuriLogLevel_FuriLogLevelWarn,
+            Level::Info => sys::FuriLogLevel_FuriLogLevelInfo,
+            Level::Debug => sys::FuriLogLevel_FuriLogLevelDebug,
+            Level::Trace => sys::FuriLogLevel_FuriLogLevelTrace,
+        }
+    }
+
+    /// Get the name of the level.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl ufmt::uDisplay for Level {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Returns the most verbose level that is logged, or `None` if logging is disabled.
+pub fn get_level() -> Option<Level> {
+    Level::from_sys(unsafe { sys::furi_log_get_level() })
+}
+
+/// Sets the most verbose level that is logged, or disables logging with `None`.
+///
+/// This changes the level for the whole system until the next reboot.
+pub fn set_level(level: Option<Level>) {
+    let level = level.map_or(sys::FuriLogLevel_FuriLogLevelNone, Level::to_sys);
+    unsafe { sys::furi_log_set_level(level) };
+}
+
+/// Returns `true` if records of `level` are logged.
+pub fn enabled(level: Level) -> bool {
+    matches!(get_level(), Some(max) if level <= max)
+}
+
+/// Writes a formatted record to the log. Used by the logging macros.
+#[doc(hidden)]
+pub fn write_record(level: Level, tag: &str, message: &FuriString) {
+    let mut buf = [0u8; MAX_TAG_LEN + 1];
+    let len = tag.len().min(MAX_TAG_LEN);
+    buf[..len].copy_from_slice(&tag.as_bytes()[..len]);
+
+    unsafe {
+        sys::furi_log_print_format(
+            level.to_sys(),
+            buf.as_ptr() as *const c_char,
+            sys::c_string!("%s"),
+            message.as_c_str().as_ptr(),
+        );
+    }
+}
+
+/// Writes a record to the log, formatted with [`core::fmt`].
+///
+/// Prefer the logging macros, which use the smaller `ufmt` formatting machinery.
+pub fn log_fmt(level: Level, tag: &str, args: fmt::Arguments<'_>) {
+    if !enabled(level) {
+        return;
+    }
+
+    let mut message = FuriString::new();
+    let _ = fmt::write(&mut message, args);
+    write_record(level, tag, &message);
+}
+
+#[cfg(feature = "log")]
+pub use self::logger::{init, Logger};
+
+#[cfg(feature = "log")]
+mod logger {
+    use super::{get_level, log_fmt, Level};
+
+    /// A [`log::Log`] implementation that writes to the firmware log, using the target
+    /// of each record as its tag.
+    ///
+    /// [`log::Log`]: https://docs.rs/log/latest/log/trait.Log.html
+    pub struct Logger;
+
+    impl Logger {
+        fn level(level: ::log::Level) -> Level {
+            match level {
+                ::log::Level::Error => Level::Error,
+                ::log::Level::Warn => Level::Warn,
+                ::log::Level::Info => Level::Info,
+                ::log::Level::Debug => Level::Debug,
+                ::log::Level::Trace => Level::Trace,
+            }
+        }
+    }
+
+    impl ::log::Log for Logger {
+        fn enabled(&self, metadata: &::log::Metadata<'_>) -> bool {
+            super::enabled(Self::level(metadata.level()))
+        }
+
+        fn log(&self, record: &::log::Record<'_>) {
+            log_fmt(Self::level(record.level()), record.target(), *record.args());
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: Logger = Logger;
+
+    /// Installs [`Logger`] as the logger of the [`log`] crate.
+    ///
+    /// The maximum level of the `log` crate is set to the current firmware log level.
+    ///
+    /// [`log`]: https://docs.rs/log
+    pub fn init() -> Result<(), ::log::SetLoggerError> {
+        ::log::set_logger(&LOGGER)?;
+        ::log::set_max_level(match get_level() {
+            None => ::log::LevelFilter::Off,
+            Some(Level::Error) => ::log::LevelFilter::Error,
+            Some(Level::Warn) => ::log::LevelFilter::Warn,
+            Some(Level::Info) => ::log::LevelFilter::Info,
+            Some(Level::Debug) => ::log::LevelFilter::Debug,
+            Some(Level::Trace) => ::log::LevelFilter::Trace,
+        });
+        Ok(())
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::{enabled, get_level, set_level, Level};
+
+    #[test]
+    fn set_level_limits_enabled_levels() {
+        let original = get_level();
+
+        set_level(Some(Level::Warn));
+        assert_eq!(get_level(), Some(Level::Warn));
+        assert!(enabled(Level::Error));
+        assert!(enabled(Level::Warn));
+        assert!(!enabled(Level::Info));
+
+        set_level(None);
+        assert_eq!(get_level(), None);
+        assert!(!enabled(Level::Error));
+
+        set_level(original);
+    }
+
+    #[test]
+    fn disabled_records_are_not_formatted() {
+        struct Panics;
+
+        impl ufmt::uDisplay for Panics {
+            fn fmt<W>(&self, _: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+            where
+                W: ufmt::uWrite + ?Sized,
+            {
+                panic!("formatted a disabled record");
+            }
+        }
+
+        let original = get_level();
+        set_level(Some(Level::Error));
+        crate::debug!("test", "{}", Panics);
+        set_level(original);
+    }
+}
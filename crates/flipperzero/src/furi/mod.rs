@@ -1,8 +1,12 @@
 //! Furi API.
 
+#[cfg(feature = "alloc")]
+pub mod event;
 pub mod io;
+pub mod log;
 pub mod message_queue;
 pub mod rng;
+pub mod string;
 pub mod sync;
 pub mod thread;
 
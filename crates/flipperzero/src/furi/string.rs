@@ -0,0 +1,189 @@
+//! Furi String API.
+
+use core::ffi::{c_char, CStr};
+use core::fmt;
+use core::str::Utf8Error;
+
+use flipperzero_sys as sys;
+
+/// A heap-allocated string owned by the Furi runtime.
+///
+/// This is the string type used throughout the Flipper Zero SDK. Its storage comes
+/// from the firmware heap, so it is available without the `alloc` feature.
+pub struct FuriString(*mut sys::FuriString);
+
+impl FuriString {
+    /// Allocates a new, empty string.
+    pub fn new() -> Self {
+        let data = unsafe { sys::furi_string_alloc() };
+        assert!(!data.is_null());
+
+        FuriString(data)
+    }
+
+    /// Allocates a new string containing a copy of `s`.
+    pub fn from_c_str(s: &CStr) -> Self {
+        let data = unsafe { sys::furi_string_alloc_set_str(s.as_ptr()) };
+        assert!(!data.is_null());
+
+        FuriString(data)
+    }
+
+    /// Takes ownership of a raw `FuriString`.
+    ///
+    /// # Safety
+    ///
+    /// `raw` must be a valid string that is not owned by anything else; it will be
+    /// freed when the returned value is dropped.
+    pub unsafe fn from_raw(raw: *mut sys::FuriString) -> Self {
+        FuriString(raw)
+    }
+
+    /// Returns the raw pointer to the underlying `FuriString`.
+    pub fn as_ptr(&self) -> *const sys::FuriString {
+        self.0
+    }
+
+    /// Returns the raw mutable pointer to the underlying `FuriString`.
+    pub fn as_mut_ptr(&mut self) -> *mut sys::FuriString {
+        self.0
+    }
+
+    /// Returns the length of the string in bytes, excluding the NUL terminator.
+    pub fn len(&self) -> usize {
+        unsafe { sys::furi_string_size(self.0) }
+    }
+
+    /// Is the string empty?
+    pub fn is_empty(&self) -> bool {
+        unsafe { sys::furi_string_empty(self.0) }
+    }
+
+    /// Truncates the string to zero length.
+    pub fn clear(&mut self) {
+        unsafe { sys::furi_string_reset(self.0) };
+    }
+
+    /// Borrows the string as a C string.
+    pub fn as_c_str(&self) -> &CStr {
+        unsafe { CStr::from_ptr(sys::furi_string_get_cstr(self.0)) }
+    }
+
+    /// Borrows the string as a `&str`, if it contains valid UTF-8.
+    pub fn to_str(&self) -> Result<&str, Utf8Error> {
+        self.as_c_str().to_str()
+    }
+
+    /// Appends a string slice to the end of this string.
+    ///
+    /// Note: The SDK uses NUL-terminated strings, so any content after an interior NUL
+    /// byte will not be visible through [`FuriString::as_c_str`].
+    pub fn push_str(&mut self, s: &str) {
+        for b in s.bytes() {
+            unsafe { sys::furi_string_push_back(self.0, b as c_char) };
+        }
+    }
+
+    /// Replaces the contents of this string with a copy of `s`.
+    pub fn set_c_str(&mut self, s: &CStr) {
+        unsafe { sys::furi_string_set_str(self.0, s.as_ptr()) };
+    }
+}
+
+impl Drop for FuriString {
+    fn drop(&mut self) {
+        unsafe { sys::furi_string_free(self.0) };
+    }
+}
+
+impl Default for FuriString {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for FuriString {
+    fn clone(&self) -> Self {
+        let data = unsafe { sys::furi_string_alloc_set(self.0) };
+        assert!(!data.is_null());
+
+        FuriString(data)
+    }
+}
+
+impl PartialEq for FuriString {
+    fn eq(&self, other: &Self) -> bool {
+        unsafe { sys::furi_string_equal(self.0, other.0) }
+    }
+}
+
+impl Eq for FuriString {}
+
+impl fmt::Debug for FuriString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_c_str().fmt(f)
+    }
+}
+
+impl fmt::Write for FuriString {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl ufmt::uWrite for FuriString {
+    type Error = core::convert::Infallible;
+
+    fn write_str(&mut self, s: &str) -> Result<(), Self::Error> {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl ufmt::uDisplay for FuriString {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        let bytes = self.as_c_str().to_bytes();
+        match core::str::from_utf8(bytes) {
+            Ok(s) => f.write_str(s),
+            // Display the valid prefix rather than nothing at all.
+            Err(e) => {
+                f.write_str(unsafe { core::str::from_utf8_unchecked(&bytes[..e.valid_up_to()]) })
+            }
+        }
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::fmt::Write;
+
+    use super::FuriString;
+
+    #[test]
+    fn push_and_read_back() {
+        let mut s = FuriString::new();
+        assert!(s.is_empty());
+
+        s.push_str("Hello");
+        let name = "Rust";
+        write!(s, ", {name}!").unwrap();
+        assert_eq!(s.len(), 12);
+        assert_eq!(s.to_str(), Ok("Hello, Rust!"));
+
+        s.clear();
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    fn clone_is_equal() {
+        let mut s = FuriString::new();
+        s.push_str("dolphin");
+
+        let t = s.clone();
+        assert!(s == t);
+    }
+}
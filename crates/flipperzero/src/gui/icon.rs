@@ -0,0 +1,79 @@
+//! Icons.
+
+use flipperzero_sys as sys;
+
+/// A static, possibly animated, monochrome image.
+///
+/// Icons are usually embedded with [`include_icon!`](crate::include_icon) or
+/// [`include_animation!`](crate::include_animation), which convert PNG images at
+/// compile time.
+#[repr(transparent)]
+pub struct Icon(sys::Icon);
+
+// SAFETY: Icons are immutable.
+unsafe impl Send for Icon {}
+unsafe impl Sync for Icon {}
+
+impl Icon {
+    /// Creates an icon from encoded frames.
+    ///
+    /// # Safety
+    ///
+    /// `frames` must point to `frame_count` frames, each encoded as described in
+    /// `docs/icons.md` for an image of `width` by `height` pixels, and must remain
+    /// valid for the lifetime of the icon.
+    pub const unsafe fn from_raw_parts(
+        width: u8,
+        height: u8,
+        frame_count: u8,
+        frame_rate: u8,
+        frames: *const *const u8,
+    ) -> Self {
+        Icon(sys::Icon {
+            width,
+            height,
+            frame_count,
+            frame_rate,
+            frames,
+        })
+    }
+
+    /// Obtain raw Furi Icon handle.
+    pub fn as_ptr(&self) -> *const sys::Icon {
+        &self.0
+    }
+
+    /// Width in pixels.
+    pub fn width(&self) -> u8 {
+        self.0.width
+    }
+
+    /// Height in pixels.
+    pub fn height(&self) -> u8 {
+        self.0.height
+    }
+
+    /// Number of frames; `1` for icons that are not animated.
+    pub fn frame_count(&self) -> u8 {
+        self.0.frame_count
+    }
+
+    /// Frames per second of an animated icon.
+    pub fn frame_rate(&self) -> u8 {
+        self.0.frame_rate
+    }
+}
+
+/// Frame table of an icon generated by the icon macros.
+#[doc(hidden)]
+#[repr(transparent)]
+pub struct __IconFrames<const N: usize>(pub [*const u8; N]);
+
+// SAFETY: The frames point to immutable static data.
+unsafe impl<const N: usize> Sync for __IconFrames<N> {}
+
+impl<const N: usize> __IconFrames<N> {
+    pub const fn as_ptr(&self) -> *const *const u8 {
+        self.0.as_ptr()
+    }
+}
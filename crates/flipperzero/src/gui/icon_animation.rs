@@ -0,0 +1,107 @@
+//! Animated icons.
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use core::ffi::c_void;
+use core::marker::PhantomData;
+
+use flipperzero_sys as sys;
+
+use super::icon::Icon;
+
+#[cfg(feature = "alloc")]
+type UpdateCallback = Box<dyn FnMut() + Send>;
+
+/// The playback state of an animated [`Icon`].
+pub struct IconAnimation<'a> {
+    data: *mut sys::IconAnimation,
+    #[cfg(feature = "alloc")]
+    callback: Option<Box<UpdateCallback>>,
+    _icon: PhantomData<&'a Icon>,
+}
+
+impl<'a> IconAnimation<'a> {
+    /// Creates a stopped animation of `icon`.
+    pub fn new(icon: &'a Icon) -> Self {
+        let data = unsafe { sys::icon_animation_alloc(icon.as_ptr()) };
+        assert!(!data.is_null());
+
+        Self {
+            data,
+            #[cfg(feature = "alloc")]
+            callback: None,
+            _icon: PhantomData,
+        }
+    }
+
+    /// Obtain raw Furi IconAnimation handle.
+    ///
+    /// This pointer must not be `free`d or otherwise invalidated.
+    /// It must not be referenced after `IconAnimation` has been dropped.
+    pub fn as_ptr(&self) -> *mut sys::IconAnimation {
+        self.data
+    }
+
+    /// Width in pixels.
+    pub fn width(&self) -> u8 {
+        unsafe { sys::icon_animation_get_width(self.data) }
+    }
+
+    /// Height in pixels.
+    pub fn height(&self) -> u8 {
+        unsafe { sys::icon_animation_get_height(self.data) }
+    }
+
+    /// Starts playing the animation, looping until it is stopped.
+    pub fn start(&mut self) {
+        unsafe { sys::icon_animation_start(self.data) };
+    }
+
+    /// Stops the animation.
+    pub fn stop(&mut self) {
+        unsafe { sys::icon_animation_stop(self.data) };
+    }
+
+    /// Returns `true` if the last frame is currently shown.
+    pub fn is_last_frame(&self) -> bool {
+        unsafe { sys::icon_animation_is_last_frame(self.data) }
+    }
+
+    /// Sets a callback that is called whenever the frame changes, for example to
+    /// redraw a view port.
+    ///
+    /// The callback is called from the timer thread.
+    #[cfg(feature = "alloc")]
+    pub fn set_update_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut() + Send + 'static,
+    {
+        unsafe extern "C" fn update_callback(
+            _instance: *mut sys::IconAnimation,
+            context: *mut c_void,
+        ) {
+            let callback = &mut *(context as *mut UpdateCallback);
+            callback();
+        }
+
+        let mut callback: Box<UpdateCallback> = Box::new(Box::new(callback));
+        unsafe {
+            sys::icon_animation_set_update_callback(
+                self.data,
+                Some(update_callback),
+                &mut *callback as *mut UpdateCallback as *mut c_void,
+            );
+        }
+        self.callback = Some(callback);
+    }
+}
+
+impl<'a> Drop for IconAnimation<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            // Stops the animation timer, so the callback is no longer called.
+            sys::icon_animation_free(self.data);
+        }
+    }
+}
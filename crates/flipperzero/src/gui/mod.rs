@@ -1,3 +1,8 @@
 //! GUI service.
 
 pub mod canvas;
+pub mod icon;
+pub mod icon_animation;
+
+pub use self::icon::Icon;
+pub use self::icon_animation::IconAnimation;
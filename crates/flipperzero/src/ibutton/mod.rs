@@ -0,0 +1,441 @@
+//! iButton (1-Wire, Cyfral and Metakom) keys.
+//!
+//! Keys are read, written and emulated with the [`Key`] of a [`worker::Worker`] (with
+//! the `alloc` feature), and saved to and loaded from `.ibtn` files.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use core::ffi::CStr;
+//! use core::time::Duration;
+//!
+//! use flipperzero::furi::event::EventQueue;
+//! use flipperzero::ibutton::worker::Worker;
+//! use flipperzero::ibutton::{Protocols, ReadEvent};
+//!
+//! let protocols = Protocols::new();
+//! let mut worker = Worker::new(&protocols);
+//!
+//! let queue = EventQueue::new(1);
+//! let reading = worker.read(queue.clone());
+//! while queue.recv(Duration::MAX) != Ok(ReadEvent::Done) {}
+//! drop(reading);
+//!
+//! worker.key().save(CStr::from_bytes_with_nul(b"/ext/ibutton/door.ibtn\0").unwrap())?;
+//! ```
+
+use core::ffi::CStr;
+use core::fmt;
+
+use flipperzero_sys as sys;
+
+use crate::furi::string::FuriString;
+
+#[cfg(feature = "alloc")]
+pub mod worker;
+
+/// Folder that `.ibtn` files are saved in by the iButton application.
+pub const IBUTTON_FOLDER: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/ibutton\0") };
+
+/// iButton Result type.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// iButton error kinds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The key has no protocol.
+    NoProtocol,
+    /// The data size doesn't match the key's protocol.
+    InvalidDataSize,
+    /// The `.ibtn` file couldn't be loaded, or has an unknown protocol.
+    Load,
+    /// The `.ibtn` file couldn't be saved.
+    Save,
+}
+
+impl Error {
+    /// Describes the error.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::NoProtocol => "Key has no protocol",
+            Self::InvalidDataSize => "Invalid data size",
+            Self::Load => "Failed to load iButton file",
+            Self::Save => "Failed to save iButton file",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> core::result::Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+/// Progress of reading a key, see [`Worker::read`](worker::Worker::read).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReadEvent {
+    /// A key was read into [`Worker::key`](worker::Worker::key).
+    Done,
+}
+
+/// The result of an attempt to write a key, see
+/// [`Worker::write_blank`](worker::Worker::write_blank).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WriteResult {
+    /// The key was written.
+    Ok,
+    /// The blank already holds the key.
+    SameKey,
+    /// No blank was detected.
+    NoDetect,
+    /// The blank was detected, but couldn't be written.
+    CannotWrite,
+}
+
+impl WriteResult {
+    /// Get the result from the corresponding SDK `iButtonWorkerWriteResult`.
+    pub fn from_sys(result: sys::iButtonWorkerWriteResult) -> Option<Self> {
+        match result {
+            sys::iButtonWorkerWriteResult_iButtonWorkerWriteOK => Some(Self::Ok),
+            sys::iButtonWorkerWriteResult_iButtonWorkerWriteSameKey => Some(Self::SameKey),
+            sys::iButtonWorkerWriteResult_iButtonWorkerWriteNoDetect => Some(Self::NoDetect),
+            sys::iButtonWorkerWriteResult_iButtonWorkerWriteCannotWrite => Some(Self::CannotWrite),
+            _ => None,
+        }
+    }
+
+    /// Convert the result into the corresponding SDK `iButtonWorkerWriteResult`.
+    pub fn to_sys(self) -> sys::iButtonWorkerWriteResult {
+        match self {
+            Self::Ok => sys::iButtonWorkerWriteResult_iButtonWorkerWriteOK,
+            Self::SameKey => sys::iButtonWorkerWriteResult_iButtonWorkerWriteSameKey,
+            Self::NoDetect => sys::iButtonWorkerWriteResult_iButtonWorkerWriteNoDetect,
+            Self::CannotWrite => sys::iButtonWorkerWriteResult_iButtonWorkerWriteCannotWrite,
+        }
+    }
+}
+
+/// Identifies a protocol of [`Protocols`].
+///
+/// The available protocols depend on the firmware, so they are looked up by name with
+/// [`Protocols::find`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProtocolId(sys::iButtonProtocolId);
+
+impl ProtocolId {
+    /// Get the protocol from the corresponding SDK `iButtonProtocolId`. Returns `None`
+    /// for `iButtonProtocolIdInvalid` (-1).
+    pub fn from_sys(id: sys::iButtonProtocolId) -> Option<Self> {
+        (id >= 0).then_some(Self(id))
+    }
+
+    /// Convert the protocol into the corresponding SDK `iButtonProtocolId`.
+    pub fn to_sys(self) -> sys::iButtonProtocolId {
+        self.0
+    }
+}
+
+/// The iButton protocols supported by the firmware.
+pub struct Protocols {
+    raw: *mut sys::iButtonProtocols,
+}
+
+impl Protocols {
+    /// Allocates the firmware's protocols.
+    pub fn new() -> Self {
+        let raw = unsafe { sys::ibutton_protocols_alloc() };
+        assert!(!raw.is_null());
+        Self { raw }
+    }
+
+    /// Returns the raw pointer to the protocols.
+    pub fn as_ptr(&self) -> *mut sys::iButtonProtocols {
+        self.raw
+    }
+
+    /// Returns the number of protocols.
+    pub fn len(&self) -> usize {
+        unsafe { sys::ibutton_protocols_get_protocol_count() as usize }
+    }
+
+    /// Returns `true` if there are no protocols.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator over all protocols.
+    pub fn iter(&self) -> impl Iterator<Item = ProtocolId> {
+        (0..self.len() as sys::iButtonProtocolId).map(ProtocolId)
+    }
+
+    /// Returns the largest data size of all protocols, in bytes.
+    pub fn max_data_size(&self) -> usize {
+        unsafe { sys::ibutton_protocols_get_max_data_size(self.raw) }
+    }
+
+    /// Finds a protocol by its name, e.g. `DS1990` or `Cyfral`.
+    pub fn find(&self, name: &CStr) -> Option<ProtocolId> {
+        ProtocolId::from_sys(unsafe {
+            sys::ibutton_protocols_get_id_by_name(self.raw, name.as_ptr())
+        })
+    }
+
+    /// Returns the name of `protocol`, as saved in `.ibtn` files.
+    pub fn name(&self, protocol: ProtocolId) -> &'static CStr {
+        unsafe { CStr::from_ptr(sys::ibutton_protocols_get_name(self.raw, protocol.0)) }
+    }
+
+    /// Returns the manufacturer of `protocol`'s keys.
+    pub fn manufacturer(&self, protocol: ProtocolId) -> &'static CStr {
+        unsafe {
+            CStr::from_ptr(sys::ibutton_protocols_get_manufacturer(
+                self.raw, protocol.0,
+            ))
+        }
+    }
+}
+
+impl Default for Protocols {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Protocols {
+    fn drop(&mut self) {
+        unsafe { sys::ibutton_protocols_free(self.raw) };
+    }
+}
+
+/// A key: its protocol and data.
+pub struct Key<'a> {
+    raw: *mut sys::iButtonKey,
+    protocols: &'a Protocols,
+}
+
+impl<'a> Key<'a> {
+    /// Creates a key without a protocol, that can hold the data of any of `protocols`.
+    pub fn new(protocols: &'a Protocols) -> Self {
+        let raw = unsafe { sys::ibutton_key_alloc(protocols.max_data_size()) };
+        assert!(!raw.is_null());
+        Self { raw, protocols }
+    }
+
+    /// Returns the raw pointer to the key.
+    pub fn as_ptr(&self) -> *mut sys::iButtonKey {
+        self.raw
+    }
+
+    /// Returns the protocols of the key.
+    pub fn protocols(&self) -> &'a Protocols {
+        self.protocols
+    }
+
+    /// Returns the protocol of the key, if set.
+    pub fn protocol(&self) -> Option<ProtocolId> {
+        ProtocolId::from_sys(unsafe { sys::ibutton_key_get_protocol_id(self.raw) })
+    }
+
+    /// Sets the protocol of the key, clearing its data.
+    pub fn set_protocol(&mut self, protocol: ProtocolId) {
+        unsafe {
+            sys::ibutton_key_reset(self.raw);
+            sys::ibutton_key_set_protocol_id(self.raw, protocol.0);
+        }
+    }
+
+    /// Clears the protocol and data of the key.
+    pub fn reset(&mut self) {
+        unsafe { sys::ibutton_key_reset(self.raw) };
+    }
+
+    fn editable_data(&self) -> Option<sys::iButtonEditableData> {
+        self.protocol()?;
+
+        let mut editable = sys::iButtonEditableData {
+            ptr: core::ptr::null_mut(),
+            size: 0,
+        };
+        unsafe {
+            sys::ibutton_protocols_get_editable_data(self.protocols.raw, self.raw, &mut editable)
+        };
+        (!editable.ptr.is_null()).then_some(editable)
+    }
+
+    /// Returns the editable data of the key, e.g. the ROM of 1-Wire keys. This is
+    /// empty if the key has no protocol.
+    pub fn data(&self) -> &[u8] {
+        match self.editable_data() {
+            Some(editable) => unsafe { core::slice::from_raw_parts(editable.ptr, editable.size) },
+            None => &[],
+        }
+    }
+
+    /// Replaces the editable data of the key, which must be as long as
+    /// [`Key::data`].
+    ///
+    /// Checksums of the data are updated by the protocol, if needed.
+    pub fn set_data(&mut self, data: &[u8]) -> Result<()> {
+        let editable = self.editable_data().ok_or(Error::NoProtocol)?;
+        if data.len() != editable.size {
+            return Err(Error::InvalidDataSize);
+        }
+
+        unsafe {
+            core::ptr::copy_nonoverlapping(data.as_ptr(), editable.ptr, data.len());
+            sys::ibutton_protocols_apply_edits(self.protocols.raw, self.raw);
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the key has a protocol, and its data is valid for it (e.g. its
+    /// checksum matches).
+    pub fn is_valid(&self) -> bool {
+        self.protocol().is_some()
+            && unsafe { sys::ibutton_protocols_is_valid(self.protocols.raw, self.raw) }
+    }
+
+    /// Renders the data of the key as text.
+    pub fn render_data(&self, result: &mut FuriString) -> Result<()> {
+        self.protocol().ok_or(Error::NoProtocol)?;
+        unsafe {
+            sys::ibutton_protocols_render_data(self.protocols.raw, self.raw, result.as_mut_ptr())
+        };
+        Ok(())
+    }
+
+    /// Renders the data of the key as a short text, for small displays.
+    pub fn render_brief_data(&self, result: &mut FuriString) -> Result<()> {
+        self.protocol().ok_or(Error::NoProtocol)?;
+        unsafe {
+            sys::ibutton_protocols_render_brief_data(
+                self.protocols.raw,
+                self.raw,
+                result.as_mut_ptr(),
+            )
+        };
+        Ok(())
+    }
+
+    /// Renders why the data of the key is invalid, see [`Key::is_valid`].
+    pub fn render_error(&self, result: &mut FuriString) -> Result<()> {
+        self.protocol().ok_or(Error::NoProtocol)?;
+        unsafe {
+            sys::ibutton_protocols_render_error(self.protocols.raw, self.raw, result.as_mut_ptr())
+        };
+        Ok(())
+    }
+
+    /// Loads a `.ibtn` file, replacing the protocol and data of the key.
+    pub fn load(&mut self, path: &CStr) -> Result<()> {
+        if unsafe { sys::ibutton_protocols_load(self.protocols.raw, self.raw, path.as_ptr()) } {
+            Ok(())
+        } else {
+            Err(Error::Load)
+        }
+    }
+
+    /// Saves the key to a `.ibtn` file, replacing any existing file.
+    pub fn save(&self, path: &CStr) -> Result<()> {
+        self.protocol().ok_or(Error::NoProtocol)?;
+        if unsafe { sys::ibutton_protocols_save(self.protocols.raw, self.raw, path.as_ptr()) } {
+            Ok(())
+        } else {
+            Err(Error::Save)
+        }
+    }
+}
+
+impl Drop for Key<'_> {
+    fn drop(&mut self) {
+        unsafe { sys::ibutton_key_free(self.raw) };
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::ffi::CStr;
+
+    use super::{Error, Key, Protocols};
+    use crate::furi::string::FuriString;
+    use crate::storage;
+
+    const PATH: &CStr =
+        unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/flipperzero-rs.ibtn\0") };
+    // A DS1990 ROM: family code, serial number and CRC.
+    const ROM: [u8; 8] = [0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xE9];
+
+    #[test]
+    fn protocol_names() {
+        let protocols = Protocols::new();
+        assert!(!protocols.is_empty());
+        for protocol in protocols.iter() {
+            assert_eq!(protocols.find(protocols.name(protocol)), Some(protocol));
+        }
+        assert!(protocols
+            .find(CStr::from_bytes_with_nul(b"DS1990\0").unwrap())
+            .is_some());
+        assert_eq!(
+            protocols.find(CStr::from_bytes_with_nul(b"Unknown\0").unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn edit_key() {
+        let protocols = Protocols::new();
+        let mut key = Key::new(&protocols);
+        assert_eq!(key.protocol(), None);
+        assert!(key.data().is_empty());
+        assert!(!key.is_valid());
+        assert_eq!(key.set_data(&ROM), Err(Error::NoProtocol));
+
+        let ds1990 = protocols
+            .find(CStr::from_bytes_with_nul(b"DS1990\0").unwrap())
+            .unwrap();
+        key.set_protocol(ds1990);
+        assert_eq!(key.protocol(), Some(ds1990));
+        assert_eq!(key.data().len(), ROM.len());
+        assert_eq!(key.set_data(&ROM[..4]), Err(Error::InvalidDataSize));
+
+        key.set_data(&ROM).unwrap();
+        assert_eq!(key.data(), &ROM);
+        assert!(key.is_valid());
+
+        let mut text = FuriString::new();
+        key.render_data(&mut text).unwrap();
+        assert!(!text.as_c_str().to_bytes().is_empty());
+    }
+
+    #[test]
+    fn save_and_load() {
+        let protocols = Protocols::new();
+        let ds1990 = protocols
+            .find(CStr::from_bytes_with_nul(b"DS1990\0").unwrap())
+            .unwrap();
+
+        let mut key = Key::new(&protocols);
+        assert_eq!(key.save(PATH), Err(Error::NoProtocol));
+        key.set_protocol(ds1990);
+        key.set_data(&ROM).unwrap();
+        key.save(PATH).unwrap();
+
+        let mut loaded = Key::new(&protocols);
+        loaded.load(PATH).unwrap();
+        assert_eq!(loaded.protocol(), Some(ds1990));
+        assert_eq!(loaded.data(), &ROM);
+
+        storage::remove(PATH).unwrap();
+        assert_eq!(loaded.load(PATH), Err(Error::Load));
+    }
+}
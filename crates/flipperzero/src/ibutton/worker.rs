@@ -0,0 +1,198 @@
+//! Reading, writing and emulating keys on the iButton worker thread.
+//!
+//! Worker events are delivered from the worker thread to an [`EventSink`]: a closure,
+//! or an [`EventQueue`](crate::furi::event::EventQueue) to receive them from another
+//! thread.
+
+use alloc::boxed::Box;
+use core::ffi::c_void;
+
+use flipperzero_sys as sys;
+
+use super::{Key, Protocols, ReadEvent, WriteResult};
+use crate::furi::event::EventSink;
+
+type Sink<E> = Box<dyn EventSink<E>>;
+
+/// The iButton worker, and the key it reads into and writes and emulates from.
+pub struct Worker<'a> {
+    raw: *mut sys::iButtonWorker,
+    key: Key<'a>,
+    /// The `Box<Sink<E>>` of the running mode, dropped once the mode is stopped.
+    sink: Option<Box<dyn Send>>,
+}
+
+impl<'a> Worker<'a> {
+    /// Starts the worker thread, with an empty key.
+    pub fn new(protocols: &'a Protocols) -> Self {
+        let raw = unsafe { sys::ibutton_worker_alloc(protocols.as_ptr()) };
+        assert!(!raw.is_null());
+        unsafe { sys::ibutton_worker_start_thread(raw) };
+
+        Self {
+            raw,
+            key: Key::new(protocols),
+            sink: None,
+        }
+    }
+
+    /// Returns the key.
+    pub fn key(&self) -> &Key<'a> {
+        &self.key
+    }
+
+    /// Returns the key, e.g. to set the key to write or emulate.
+    pub fn key_mut(&mut self) -> &mut Key<'a> {
+        &mut self.key
+    }
+
+    /// Reads keys until the returned guard is dropped.
+    ///
+    /// `sink` receives [`ReadEvent::Done`] once a key has been read into
+    /// [`Worker::key`].
+    pub fn read(&mut self, sink: impl EventSink<ReadEvent>) -> Running<'_, 'a> {
+        unsafe extern "C" fn read_callback(context: *mut c_void) {
+            let sink = &mut *(context as *mut Sink<ReadEvent>);
+            sink.send(ReadEvent::Done);
+        }
+
+        let context = self.set_sink(sink);
+        unsafe {
+            sys::ibutton_worker_read_set_callback(self.raw, Some(read_callback), context);
+            sys::ibutton_worker_read_start(self.raw, self.key.as_ptr());
+        }
+        Running { worker: self }
+    }
+
+    /// Writes [`Worker::key`] to a blank key of the same protocol, retrying until the
+    /// returned guard is dropped.
+    ///
+    /// `sink` receives the result of each attempt.
+    pub fn write_blank(&mut self, sink: impl EventSink<WriteResult>) -> Running<'_, 'a> {
+        let key = self.set_write_sink(sink);
+        unsafe { sys::ibutton_worker_write_blank_start(self.raw, key) };
+        Running { worker: self }
+    }
+
+    /// Writes [`Worker::key`] to a compatible rewritable key (e.g. a RW1990), retrying
+    /// until the returned guard is dropped.
+    ///
+    /// `sink` receives the result of each attempt.
+    pub fn write_copy(&mut self, sink: impl EventSink<WriteResult>) -> Running<'_, 'a> {
+        let key = self.set_write_sink(sink);
+        unsafe { sys::ibutton_worker_write_copy_start(self.raw, key) };
+        Running { worker: self }
+    }
+
+    /// Emulates [`Worker::key`] until the returned guard is dropped.
+    ///
+    /// `sink` receives `true` each time a reader reads the key.
+    pub fn emulate(&mut self, sink: impl EventSink<bool>) -> Running<'_, 'a> {
+        unsafe extern "C" fn emulate_callback(context: *mut c_void, emulated: bool) {
+            let sink = &mut *(context as *mut Sink<bool>);
+            sink.send(emulated);
+        }
+
+        let context = self.set_sink(sink);
+        unsafe {
+            sys::ibutton_worker_emulate_set_callback(self.raw, Some(emulate_callback), context);
+            sys::ibutton_worker_emulate_start(self.raw, self.key.as_ptr());
+        }
+        Running { worker: self }
+    }
+
+    /// Stops the running mode, if any.
+    ///
+    /// This waits for the worker thread to finish, so that the mode's sink is no longer
+    /// used.
+    pub fn stop(&mut self) {
+        unsafe {
+            sys::ibutton_worker_stop(self.raw);
+            sys::ibutton_worker_stop_thread(self.raw);
+            sys::ibutton_worker_start_thread(self.raw);
+        }
+        self.sink = None;
+    }
+
+    /// Stops the running mode, and sets `sink` as the write callback's sink. Returns the
+    /// key to write.
+    fn set_write_sink(&mut self, sink: impl EventSink<WriteResult>) -> *mut sys::iButtonKey {
+        unsafe extern "C" fn write_callback(
+            context: *mut c_void,
+            result: sys::iButtonWorkerWriteResult,
+        ) {
+            let sink = &mut *(context as *mut Sink<WriteResult>);
+            if let Some(result) = WriteResult::from_sys(result) {
+                sink.send(result);
+            }
+        }
+
+        let context = self.set_sink(sink);
+        unsafe { sys::ibutton_worker_write_set_callback(self.raw, Some(write_callback), context) };
+        self.key.as_ptr()
+    }
+
+    /// Stops the running mode, and stores `sink` for the next one.
+    fn set_sink<E: 'static>(&mut self, sink: impl EventSink<E>) -> *mut c_void {
+        self.stop();
+
+        let mut sink: Box<Sink<E>> = Box::new(Box::new(sink));
+        let context = &mut *sink as *mut Sink<E> as *mut c_void;
+        self.sink = Some(sink);
+        context
+    }
+}
+
+impl Drop for Worker<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            sys::ibutton_worker_stop(self.raw);
+            sys::ibutton_worker_stop_thread(self.raw);
+            sys::ibutton_worker_free(self.raw);
+        }
+    }
+}
+
+/// A running worker mode, stopped when dropped.
+pub struct Running<'w, 'a> {
+    worker: &'w mut Worker<'a>,
+}
+
+impl Drop for Running<'_, '_> {
+    fn drop(&mut self) {
+        self.worker.stop();
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::ffi::CStr;
+
+    use super::Worker;
+    use crate::furi::event::EventQueue;
+    use crate::ibutton::{Protocols, WriteResult};
+
+    #[test]
+    fn start_and_stop() {
+        let protocols = Protocols::new();
+        let mut worker = Worker::new(&protocols);
+        let queue = EventQueue::new(1);
+        drop(worker.read(queue.clone()));
+        assert!(queue.is_empty());
+        assert_eq!(worker.key().protocol(), None);
+
+        let ds1990 = protocols
+            .find(CStr::from_bytes_with_nul(b"DS1990\0").unwrap())
+            .unwrap();
+        let key = worker.key_mut();
+        key.set_protocol(ds1990);
+        key.set_data(&[0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xE9])
+            .unwrap();
+
+        // Closures are sinks too.
+        drop(worker.emulate(|_: bool| {}));
+        drop(worker.write_blank(|_: WriteResult| {}));
+        drop(worker.write_copy(|_: WriteResult| {}));
+        assert_eq!(worker.key().protocol(), Some(ds1990));
+    }
+}
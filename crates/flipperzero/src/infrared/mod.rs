@@ -0,0 +1,426 @@
+//! Infrared transmission and reception.
+//!
+//! Messages of the protocols known to the firmware are sent with [`send`], and
+//! arbitrary signals with [`send_raw`]. With the `alloc` feature, signals are received
+//! with an [`InfraredReceiver`] and remotes are loaded from `.ir` files with
+//! [`remote::Remote`].
+
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, vec::Vec};
+#[cfg(feature = "alloc")]
+use core::ffi::c_void;
+use core::ffi::CStr;
+use core::fmt;
+#[cfg(feature = "alloc")]
+use core::time::Duration;
+
+use flipperzero_sys as sys;
+
+#[cfg(feature = "alloc")]
+use crate::furi::{self, message_queue::MessageQueue};
+
+#[cfg(feature = "alloc")]
+pub mod remote;
+
+/// Lowest carrier frequency of raw signals, in Hz.
+pub const MIN_FREQUENCY: u32 = 10_000;
+/// Highest carrier frequency of raw signals, in Hz.
+pub const MAX_FREQUENCY: u32 = 56_000;
+/// Carrier frequency used by most remotes, in Hz.
+pub const DEFAULT_FREQUENCY: u32 = 38_000;
+/// Carrier duty cycle used by most remotes.
+pub const DEFAULT_DUTY_CYCLE: f32 = 0.33;
+
+/// Infrared Result type.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Infrared error kinds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The address or command doesn't fit in the protocol.
+    InvalidMessage,
+    /// The carrier frequency is outside [`MIN_FREQUENCY`] and [`MAX_FREQUENCY`].
+    InvalidFrequency,
+    /// The duty cycle is not in `(0, 1]`.
+    InvalidDutyCycle,
+    /// A raw signal has no timings.
+    EmptySignal,
+    /// The infrared port is already transmitting or receiving.
+    Busy,
+}
+
+impl Error {
+    /// Describes the error.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::InvalidMessage => "Invalid infrared message",
+            Self::InvalidFrequency => "Invalid carrier frequency",
+            Self::InvalidDutyCycle => "Invalid duty cycle",
+            Self::EmptySignal => "Empty infrared signal",
+            Self::Busy => "Infrared port busy",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> core::result::Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+/// Infrared protocols supported by the firmware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    Nec,
+    NecExt,
+    Nec42,
+    Nec42Ext,
+    Samsung32,
+    Rc6,
+    Rc5,
+    Rc5X,
+    Sirc,
+    Sirc15,
+    Sirc20,
+    Kaseikyo,
+}
+
+impl Protocol {
+    /// All protocols, in firmware order.
+    pub const ALL: [Protocol; 12] = [
+        Protocol::Nec,
+        Protocol::NecExt,
+        Protocol::Nec42,
+        Protocol::Nec42Ext,
+        Protocol::Samsung32,
+        Protocol::Rc6,
+        Protocol::Rc5,
+        Protocol::Rc5X,
+        Protocol::Sirc,
+        Protocol::Sirc15,
+        Protocol::Sirc20,
+        Protocol::Kaseikyo,
+    ];
+
+    /// Get the protocol from the corresponding SDK `InfraredProtocol`.
+    pub fn from_sys(protocol: sys::InfraredProtocol) -> Option<Self> {
+        match protocol {
+            sys::InfraredProtocol_InfraredProtocolNEC => Some(Protocol::Nec),
+            sys::InfraredProtocol_InfraredProtocolNECext => Some(Protocol::NecExt),
+            sys::InfraredProtocol_InfraredProtocolNEC42 => Some(Protocol::Nec42),
+            sys::InfraredProtocol_InfraredProtocolNEC42ext => Some(Protocol::Nec42Ext),
+            sys::InfraredProtocol_InfraredProtocolSamsung32 => Some(Protocol::Samsung32),
+            sys::InfraredProtocol_InfraredProtocolRC6 => Some(Protocol::Rc6),
+            sys::InfraredProtocol_InfraredProtocolRC5 => Some(Protocol::Rc5),
+            sys::InfraredProtocol_InfraredProtocolRC5X => Some(Protocol::Rc5X),
+            sys::InfraredProtocol_InfraredProtocolSIRC => Some(Protocol::Sirc),
+            sys::InfraredProtocol_InfraredProtocolSIRC15 => Some(Protocol::Sirc15),
+            sys::InfraredProtocol_InfraredProtocolSIRC20 => Some(Protocol::Sirc20),
+            sys::InfraredProtocol_InfraredProtocolKaseikyo => Some(Protocol::Kaseikyo),
+            _ => None,
+        }
+    }
+
+    /// Convert the protocol into the corresponding SDK `InfraredProtocol`.
+    pub fn to_sys(self) -> sys::InfraredProtocol {
+        match self {
+            Protocol::Nec => sys::InfraredProtocol_InfraredProtocolNEC,
+            Protocol::NecExt => sys::InfraredProtocol_InfraredProtocolNECext,
+            Protocol::Nec42 => sys::InfraredProtocol_InfraredProtocolNEC42,
+            Protocol::Nec42Ext => sys::InfraredProtocol_InfraredProtocolNEC42ext,
+            Protocol::Samsung32 => sys::InfraredProtocol_InfraredProtocolSamsung32,
+            Protocol::Rc6 => sys::InfraredProtocol_InfraredProtocolRC6,
+            Protocol::Rc5 => sys::InfraredProtocol_InfraredProtocolRC5,
+            Protocol::Rc5X => sys::InfraredProtocol_InfraredProtocolRC5X,
+            Protocol::Sirc => sys::InfraredProtocol_InfraredProtocolSIRC,
+            Protocol::Sirc15 => sys::InfraredProtocol_InfraredProtocolSIRC15,
+            Protocol::Sirc20 => sys::InfraredProtocol_InfraredProtocolSIRC20,
+            Protocol::Kaseikyo => sys::InfraredProtocol_InfraredProtocolKaseikyo,
+        }
+    }
+
+    /// Get the protocol with the given name, as used in `.ir` files (e.g. `NECext`).
+    pub fn from_name(name: &CStr) -> Option<Self> {
+        Self::from_sys(unsafe { sys::infrared_get_protocol_by_name(name.as_ptr()) })
+    }
+
+    /// Returns the name of the protocol, as used in `.ir` files.
+    pub fn name(self) -> &'static CStr {
+        unsafe { CStr::from_ptr(sys::infrared_get_protocol_name(self.to_sys())) }
+    }
+
+    /// Returns the number of address bits.
+    pub fn address_bits(self) -> u8 {
+        unsafe { sys::infrared_get_protocol_address_length(self.to_sys()) }
+    }
+
+    /// Returns the number of command bits.
+    pub fn command_bits(self) -> u8 {
+        unsafe { sys::infrared_get_protocol_command_length(self.to_sys()) }
+    }
+
+    /// Returns the carrier frequency, in Hz.
+    pub fn frequency(self) -> u32 {
+        unsafe { sys::infrared_get_protocol_frequency(self.to_sys()) }
+    }
+
+    /// Returns the carrier duty cycle.
+    pub fn duty_cycle(self) -> f32 {
+        unsafe { sys::infrared_get_protocol_duty_cycle(self.to_sys()) }
+    }
+
+    /// Returns the number of times a message must be sent to be recognized.
+    pub fn min_repeat_count(self) -> usize {
+        unsafe { sys::infrared_get_protocol_min_repeat_count(self.to_sys()) }
+    }
+}
+
+/// A message of a known protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InfraredMessage {
+    pub protocol: Protocol,
+    pub address: u32,
+    pub command: u32,
+    /// Whether this is a repeat of the previous message (a held button).
+    pub repeat: bool,
+}
+
+impl InfraredMessage {
+    /// Creates a message that is not a repeat.
+    pub fn new(protocol: Protocol, address: u32, command: u32) -> Self {
+        Self {
+            protocol,
+            address,
+            command,
+            repeat: false,
+        }
+    }
+
+    /// Convert from a SDK message. Returns `None` if the protocol is unknown.
+    pub fn from_sys(message: &sys::InfraredMessage) -> Option<Self> {
+        Some(Self {
+            protocol: Protocol::from_sys(message.protocol)?,
+            address: message.address,
+            command: message.command,
+            repeat: message.repeat,
+        })
+    }
+
+    /// Convert into a SDK message.
+    pub fn to_sys(&self) -> sys::InfraredMessage {
+        sys::InfraredMessage {
+            protocol: self.protocol.to_sys(),
+            address: self.address,
+            command: self.command,
+            repeat: self.repeat,
+        }
+    }
+
+    /// Returns `true` if the address and command fit in the protocol.
+    pub fn is_valid(&self) -> bool {
+        fn fits(value: u32, bits: u8) -> bool {
+            bits >= 32 || value >> bits == 0
+        }
+
+        fits(self.address, self.protocol.address_bits())
+            && fits(self.command, self.protocol.command_bits())
+    }
+}
+
+fn check_idle() -> Result<()> {
+    if unsafe { sys::furi_hal_infrared_is_busy() } {
+        Err(Error::Busy)
+    } else {
+        Ok(())
+    }
+}
+
+/// Sends a message, repeated as often as its protocol requires to be recognized.
+///
+/// Blocks until the message is sent.
+pub fn send(message: &InfraredMessage) -> Result<()> {
+    if !message.is_valid() {
+        return Err(Error::InvalidMessage);
+    }
+    check_idle()?;
+
+    let times = message.protocol.min_repeat_count().max(1);
+    unsafe { sys::infrared_send(&message.to_sys(), times as i32) };
+    Ok(())
+}
+
+/// Sends a raw signal: alternating mark and space durations in microseconds, starting
+/// with a mark.
+///
+/// Blocks until the signal is sent.
+pub fn send_raw(timings: &[u32], frequency: u32, duty_cycle: f32) -> Result<()> {
+    if timings.is_empty() {
+        return Err(Error::EmptySignal);
+    }
+    if !(MIN_FREQUENCY..=MAX_FREQUENCY).contains(&frequency) {
+        return Err(Error::InvalidFrequency);
+    }
+    if !(duty_cycle > 0.0 && duty_cycle <= 1.0) {
+        return Err(Error::InvalidDutyCycle);
+    }
+    check_idle()?;
+
+    unsafe {
+        sys::infrared_send_raw_ext(
+            timings.as_ptr(),
+            timings.len() as u32,
+            true,
+            frequency,
+            duty_cycle,
+        );
+    }
+    Ok(())
+}
+
+/// A signal received by an [`InfraredReceiver`].
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Signal {
+    /// A message of a known protocol.
+    Decoded(InfraredMessage),
+    /// Alternating mark and space durations in microseconds, starting with a mark.
+    Raw(Vec<u32>),
+}
+
+/// Receives infrared signals on a worker thread.
+///
+/// Signals are queued until they are read with [`InfraredReceiver::recv`]; signals
+/// received while the queue is full are dropped.
+#[cfg(feature = "alloc")]
+pub struct InfraredReceiver {
+    worker: *mut sys::InfraredWorker,
+    queue: Box<MessageQueue<Signal>>,
+}
+
+#[cfg(feature = "alloc")]
+impl InfraredReceiver {
+    /// Starts receiving, queueing up to `capacity` signals.
+    ///
+    /// Signals of known protocols are decoded; see [`InfraredReceiver::set_decoding`].
+    pub fn new(capacity: usize) -> Result<Self> {
+        unsafe extern "C" fn received(
+            context: *mut c_void,
+            signal: *mut sys::InfraredWorkerSignal,
+        ) {
+            let queue = &*(context as *const MessageQueue<Signal>);
+
+            // Don't block the worker; the signal is dropped if the queue is full. A failed
+            // `put` would leak the timings of a raw signal, so check before building it.
+            // This is the only producer, so the space can't be taken in between.
+            if queue.space() == 0 {
+                return;
+            }
+
+            let signal = if sys::infrared_worker_signal_is_decoded(signal) {
+                match InfraredMessage::from_sys(&*sys::infrared_worker_get_decoded_signal(signal)) {
+                    Some(message) => Signal::Decoded(message),
+                    None => return,
+                }
+            } else {
+                let mut timings = core::ptr::null();
+                let mut len = 0;
+                sys::infrared_worker_get_raw_signal(signal, &mut timings, &mut len);
+                Signal::Raw(core::slice::from_raw_parts(timings, len).to_vec())
+            };
+
+            let _ = queue.put(signal, Duration::ZERO);
+        }
+
+        check_idle()?;
+
+        let worker = unsafe { sys::infrared_worker_alloc() };
+        assert!(!worker.is_null());
+
+        let queue = Box::new(MessageQueue::new(capacity));
+        unsafe {
+            sys::infrared_worker_rx_set_received_signal_callback(
+                worker,
+                Some(received),
+                &*queue as *const MessageQueue<Signal> as *mut c_void,
+            );
+            sys::infrared_worker_rx_enable_signal_decoding(worker, true);
+            sys::infrared_worker_rx_start(worker);
+        }
+
+        Ok(Self { worker, queue })
+    }
+
+    /// Sets whether signals of known protocols are decoded. If not, all signals are
+    /// delivered as [`Signal::Raw`].
+    pub fn set_decoding(&mut self, enable: bool) {
+        unsafe { sys::infrared_worker_rx_enable_signal_decoding(self.worker, enable) };
+    }
+
+    /// Sets whether the LED blinks when a signal is received.
+    pub fn set_blink(&mut self, enable: bool) {
+        unsafe { sys::infrared_worker_rx_enable_blink_on_receiving(self.worker, enable) };
+    }
+
+    /// Waits up to `timeout` for the next signal.
+    pub fn recv(&self, timeout: Duration) -> furi::Result<Signal> {
+        self.queue.get(timeout)
+    }
+
+    /// Returns the queue that received signals are put in.
+    pub fn queue(&self) -> &MessageQueue<Signal> {
+        &self.queue
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Drop for InfraredReceiver {
+    fn drop(&mut self) {
+        unsafe {
+            // Stopping joins the worker thread, so the queue is no longer in use.
+            sys::infrared_worker_rx_stop(self.worker);
+            sys::infrared_worker_free(self.worker);
+        }
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::ffi::CStr;
+
+    use super::{send, send_raw, Error, InfraredMessage, Protocol};
+
+    #[test]
+    fn protocol_names_round_trip() {
+        for protocol in Protocol::ALL {
+            assert_eq!(Protocol::from_name(protocol.name()), Some(protocol));
+        }
+        let unknown = CStr::from_bytes_with_nul(b"Unknown\0").unwrap();
+        assert_eq!(Protocol::from_name(unknown), None);
+    }
+
+    #[test]
+    fn invalid_messages_are_rejected() {
+        // NEC has 8-bit addresses and commands.
+        assert!(InfraredMessage::new(Protocol::Nec, 0xff, 0xff).is_valid());
+        let message = InfraredMessage::new(Protocol::Nec, 0x100, 0x01);
+        assert!(!message.is_valid());
+        assert_eq!(send(&message), Err(Error::InvalidMessage));
+    }
+
+    #[test]
+    fn invalid_raw_signals_are_rejected() {
+        assert_eq!(send_raw(&[], 38_000, 0.33), Err(Error::EmptySignal));
+        assert_eq!(send_raw(&[100], 60_000, 0.33), Err(Error::InvalidFrequency));
+        assert_eq!(send_raw(&[100], 38_000, 0.0), Err(Error::InvalidDutyCycle));
+    }
+}
@@ -0,0 +1,404 @@
+//! Infrared remote (`.ir`) files.
+//!
+//! A remote is a list of named buttons, each with either a message of a known
+//! protocol or a raw signal:
+//!
+//! ```text
+//! Filetype: IR signals file
+//! Version: 1
+//! #
+//! name: Power
+//! type: parsed
+//! protocol: NEC
+//! address: 07 00 00 00
+//! command: 02 00 00 00
+//! #
+//! name: Mute
+//! type: raw
+//! frequency: 38000
+//! duty_cycle: 0.330000
+//! data: 9024 4512 579 552 579 1683
+//! ```
+//!
+//! # Examples
+//!
+//! ```
+//! let remote = Remote::load(CStr::from_bytes_with_nul(b"/ext/infrared/tv.ir\0").unwrap())?;
+//! remote.button("Power").unwrap().send()?;
+//! ```
+
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ffi::CStr;
+use core::fmt;
+
+use crate::format::{self, FlipperFormat};
+use crate::furi::string::FuriString;
+
+use super::{send, send_raw, InfraredMessage, Protocol, Signal};
+
+const FILETYPE: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"IR signals file\0") };
+const VERSION: u32 = 1;
+
+const NAME: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"name\0") };
+const TYPE: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"type\0") };
+const PROTOCOL: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"protocol\0") };
+const ADDRESS: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"address\0") };
+const COMMAND: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"command\0") };
+const FREQUENCY: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"frequency\0") };
+const DUTY_CYCLE: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"duty_cycle\0") };
+const DATA: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"data\0") };
+
+const TYPE_PARSED: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"parsed\0") };
+const TYPE_RAW: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"raw\0") };
+const EMPTY: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"\0") };
+
+/// Remote file Result type.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Remote file error kinds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The file could not be read or written.
+    Format(format::Error),
+    /// The file is not an infrared signals file.
+    InvalidHeader,
+    /// A button has an unknown signal type or protocol, or invalid values.
+    InvalidSignal,
+}
+
+impl Error {
+    /// Describes the error.
+    pub fn description(&self) -> &str {
+        match self {
+            Self::Format(err) => err.description(),
+            Self::InvalidHeader => "Not an infrared signals file",
+            Self::InvalidSignal => "Invalid infrared signal",
+        }
+    }
+}
+
+impl From<format::Error> for Error {
+    fn from(value: format::Error) -> Self {
+        Self::Format(value)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> core::result::Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+/// The signal sent by a [`Button`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ButtonSignal {
+    /// A message of a known protocol.
+    Parsed(InfraredMessage),
+    /// Alternating mark and space durations in microseconds, starting with a mark.
+    Raw {
+        frequency: u32,
+        duty_cycle: f32,
+        data: Vec<u32>,
+    },
+}
+
+impl ButtonSignal {
+    /// Sends the signal.
+    pub fn send(&self) -> super::Result<()> {
+        match self {
+            Self::Parsed(message) => send(message),
+            Self::Raw {
+                frequency,
+                duty_cycle,
+                data,
+            } => send_raw(data, *frequency, *duty_cycle),
+        }
+    }
+}
+
+/// Received raw signals use the default carrier.
+impl From<Signal> for ButtonSignal {
+    fn from(value: Signal) -> Self {
+        match value {
+            Signal::Decoded(message) => Self::Parsed(message),
+            Signal::Raw(data) => Self::Raw {
+                frequency: super::DEFAULT_FREQUENCY,
+                duty_cycle: super::DEFAULT_DUTY_CYCLE,
+                data,
+            },
+        }
+    }
+}
+
+/// A named button of a [`Remote`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Button {
+    pub name: String,
+    pub signal: ButtonSignal,
+}
+
+impl Button {
+    /// Creates a button.
+    pub fn new(name: impl Into<String>, signal: impl Into<ButtonSignal>) -> Self {
+        Self {
+            name: name.into(),
+            signal: signal.into(),
+        }
+    }
+
+    /// Sends the signal of the button.
+    pub fn send(&self) -> super::Result<()> {
+        self.signal.send()
+    }
+
+    /// Reads the next button, or returns `None` at the end of the file.
+    fn read(ff: &mut FlipperFormat) -> Result<Option<Self>> {
+        let mut value = FuriString::new();
+        if ff.read_string(NAME, &mut value).is_err() {
+            return Ok(None);
+        }
+        let name = value
+            .to_str()
+            .map_err(|_| Error::InvalidSignal)?
+            .to_string();
+
+        ff.read_string(TYPE, &mut value)?;
+        let signal = if value.as_c_str() == TYPE_PARSED {
+            ff.read_string(PROTOCOL, &mut value)?;
+            let protocol = Protocol::from_name(value.as_c_str()).ok_or(Error::InvalidSignal)?;
+
+            let mut address = [0; 4];
+            ff.read_hex(ADDRESS, &mut address)?;
+            let mut command = [0; 4];
+            ff.read_hex(COMMAND, &mut command)?;
+
+            let message = InfraredMessage::new(
+                protocol,
+                u32::from_le_bytes(address),
+                u32::from_le_bytes(command),
+            );
+            if !message.is_valid() {
+                return Err(Error::InvalidSignal);
+            }
+            ButtonSignal::Parsed(message)
+        } else if value.as_c_str() == TYPE_RAW {
+            let mut frequency = [0];
+            ff.read_u32(FREQUENCY, &mut frequency)?;
+            let mut duty_cycle = [0.0];
+            ff.read_f32(DUTY_CYCLE, &mut duty_cycle)?;
+
+            let mut data = vec![0; ff.value_count(DATA)?];
+            ff.read_u32(DATA, &mut data)?;
+
+            ButtonSignal::Raw {
+                frequency: frequency[0],
+                duty_cycle: duty_cycle[0],
+                data,
+            }
+        } else {
+            return Err(Error::InvalidSignal);
+        };
+
+        Ok(Some(Self { name, signal }))
+    }
+
+    fn write(&self, ff: &mut FlipperFormat) -> Result<()> {
+        let mut name = FuriString::new();
+        name.push_str(&self.name);
+
+        ff.write_comment(EMPTY)?;
+        ff.write_string(NAME, name.as_c_str())?;
+        match &self.signal {
+            ButtonSignal::Parsed(message) => {
+                ff.write_string(TYPE, TYPE_PARSED)?;
+                ff.write_string(PROTOCOL, message.protocol.name())?;
+                ff.write_hex(ADDRESS, &message.address.to_le_bytes())?;
+                ff.write_hex(COMMAND, &message.command.to_le_bytes())?;
+            }
+            ButtonSignal::Raw {
+                frequency,
+                duty_cycle,
+                data,
+            } => {
+                ff.write_string(TYPE, TYPE_RAW)?;
+                ff.write_u32(FREQUENCY, &[*frequency])?;
+                ff.write_f32(DUTY_CYCLE, &[*duty_cycle])?;
+                ff.write_u32(DATA, data)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An infrared remote: a list of named buttons.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Remote {
+    buttons: Vec<Button>,
+}
+
+impl Remote {
+    /// Creates a remote without buttons.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads a remote from a `.ir` file.
+    pub fn load(path: &CStr) -> Result<Self> {
+        let mut ff = FlipperFormat::new_buffered_file();
+        ff.open_existing(path)?;
+        Self::read(&mut ff)
+    }
+
+    /// Saves the remote to a `.ir` file, replacing any existing file.
+    pub fn save(&self, path: &CStr) -> Result<()> {
+        let mut ff = FlipperFormat::new_file();
+        ff.open_always(path)?;
+        self.write(&mut ff)?;
+        ff.close()?;
+        Ok(())
+    }
+
+    /// Reads a remote from the current position of a Flipper Format file.
+    pub fn read(ff: &mut FlipperFormat) -> Result<Self> {
+        let (filetype, version) = ff.read_header()?;
+        if filetype.as_c_str() != FILETYPE || version != VERSION {
+            return Err(Error::InvalidHeader);
+        }
+
+        let mut buttons = Vec::new();
+        while let Some(button) = Button::read(ff)? {
+            buttons.push(button);
+        }
+
+        Ok(Self { buttons })
+    }
+
+    /// Writes the remote to a Flipper Format file.
+    pub fn write(&self, ff: &mut FlipperFormat) -> Result<()> {
+        ff.write_header(FILETYPE, VERSION)?;
+        for button in &self.buttons {
+            button.write(ff)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the buttons, in file order.
+    pub fn buttons(&self) -> &[Button] {
+        &self.buttons
+    }
+
+    /// Returns the first button with the given name.
+    pub fn button(&self, name: &str) -> Option<&Button> {
+        self.buttons.iter().find(|button| button.name == name)
+    }
+
+    /// Appends a button.
+    pub fn push(&mut self, button: Button) {
+        self.buttons.push(button);
+    }
+
+    /// Removes and returns the first button with the given name.
+    pub fn remove(&mut self, name: &str) -> Option<Button> {
+        let index = self.buttons.iter().position(|button| button.name == name)?;
+        Some(self.buttons.remove(index))
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{Button, ButtonSignal, Error, Remote};
+    use crate::format::FlipperFormat;
+    use crate::infrared::{InfraredMessage, Protocol};
+    use crate::io::{Read, Seek, Write};
+
+    const REMOTE: &str = include_str!("../../../../fixtures/infrared/remote.ir");
+    const UNKNOWN_PROTOCOL: &str =
+        include_str!("../../../../fixtures/infrared/unknown_protocol.ir");
+
+    fn string_format(text: &str) -> FlipperFormat {
+        let mut ff = FlipperFormat::new_string();
+        ff.stream().write_all(text.as_bytes()).unwrap();
+        ff.rewind().unwrap();
+        ff
+    }
+
+    fn fixture_remote() -> Remote {
+        let mut remote = Remote::new();
+        remote.push(Button::new(
+            "Power",
+            ButtonSignal::Parsed(InfraredMessage::new(Protocol::Nec, 0x07, 0x02)),
+        ));
+        remote.push(Button::new(
+            "Vol_up",
+            ButtonSignal::Parsed(InfraredMessage::new(Protocol::Samsung32, 0x0E, 0x0C)),
+        ));
+        remote.push(Button::new(
+            "Mute",
+            ButtonSignal::Raw {
+                frequency: 38000,
+                duty_cycle: 0.33,
+                data: [9024, 4512, 579, 552, 579, 1683, 579, 552, 579, 40000].to_vec(),
+            },
+        ));
+        remote
+    }
+
+    #[test]
+    fn read_fixture() {
+        let remote = Remote::read(&mut string_format(REMOTE)).unwrap();
+        assert_eq!(remote, fixture_remote());
+        assert_eq!(
+            remote.button("Vol_up").map(|button| &button.signal),
+            Some(&ButtonSignal::Parsed(InfraredMessage::new(
+                Protocol::Samsung32,
+                0x0E,
+                0x0C
+            ))),
+        );
+        assert!(remote.button("Missing").is_none());
+    }
+
+    #[test]
+    fn write_matches_fixture() {
+        let mut ff = FlipperFormat::new_string();
+        fixture_remote().write(&mut ff).unwrap();
+
+        let mut stream = ff.stream();
+        stream.rewind().unwrap();
+        let mut text = Vec::new();
+        let mut buf = [0; 64];
+        loop {
+            let n = stream.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            text.extend_from_slice(&buf[..n]);
+        }
+
+        assert_eq!(core::str::from_utf8(&text), Ok(REMOTE));
+    }
+
+    #[test]
+    fn unknown_protocol_is_rejected() {
+        assert_eq!(
+            Remote::read(&mut string_format(UNKNOWN_PROTOCOL)),
+            Err(Error::InvalidSignal),
+        );
+    }
+}
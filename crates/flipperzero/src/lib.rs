@@ -8,12 +8,23 @@ extern crate alloc;
 
 pub mod dialogs;
 pub mod dolphin;
+pub mod format;
 pub mod furi;
 pub mod gui;
+pub mod ibutton;
+pub mod infrared;
 pub mod io;
 pub mod macros;
+pub mod nfc;
+pub mod onewire;
+pub mod plugin;
+pub mod rfid;
 pub mod storage;
+pub mod subghz;
 pub mod toolbox;
+pub mod usb;
+
+pub use flipperzero_macros::{include_animation, include_icon};
 
 #[doc(hidden)]
 pub mod __internal {
@@ -24,11 +35,51 @@ pub mod __internal {
 flipperzero_test::tests_runner!(
     name = "flipperzero-rs Unit Tests",
     [
+        crate::format::tests,
+        #[cfg(feature = "serde")]
+        crate::format::de::tests,
+        #[cfg(feature = "serde")]
+        crate::format::ser::tests,
+        #[cfg(feature = "alloc")]
+        crate::furi::event::tests,
+        crate::furi::log::tests,
         crate::furi::message_queue::tests,
         crate::furi::rng::tests,
+        crate::furi::string::tests,
         crate::furi::sync::tests,
+        crate::ibutton::tests,
+        #[cfg(feature = "alloc")]
+        crate::ibutton::worker::tests,
+        crate::infrared::tests,
+        #[cfg(feature = "alloc")]
+        crate::infrared::remote::tests,
+        crate::nfc::tests,
+        crate::nfc::device::tests,
+        crate::onewire::tests,
+        crate::onewire::host::tests,
+        crate::onewire::pin::tests,
+        #[cfg(feature = "alloc")]
+        crate::onewire::slave::tests,
+        crate::plugin::tests,
+        crate::rfid::tests,
+        crate::rfid::dict::tests,
+        crate::rfid::raw::tests,
+        #[cfg(feature = "alloc")]
+        crate::rfid::worker::tests,
+        crate::storage::tests,
+        crate::storage::settings::tests,
+        crate::subghz::tests,
+        #[cfg(feature = "alloc")]
+        crate::subghz::decoder::tests,
+        crate::subghz::encoder::tests,
+        crate::subghz::protocol::tests,
+        crate::subghz::raw::tests,
         crate::toolbox::crc32::tests,
         crate::toolbox::md5::tests,
         crate::toolbox::sha256::tests,
+        crate::toolbox::stream::tests,
+        crate::toolbox::tar::tests,
+        crate::usb::hid::tests,
+        crate::usb::hid::ducky::tests,
     ]
 );
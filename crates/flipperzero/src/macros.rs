@@ -17,3 +17,68 @@ macro_rules! println {
         ufmt::uwriteln!($crate::furi::io::Stdout, $($args)*).ok();
     }};
 }
+
+/// Logs a record at the given [`Level`] with a tag.
+///
+/// The message is only formatted if the level is enabled in the firmware settings.
+///
+/// ```
+/// use flipperzero::furi::log::Level;
+///
+/// flipperzero::log!(Level::Info, "my_app", "started with {} args", 2);
+/// ```
+///
+/// [`Level`]: crate::furi::log::Level
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $tag:expr, $($args:tt)+) => {{
+        let level: $crate::furi::log::Level = $level;
+        if $crate::furi::log::enabled(level) {
+            // The `uwrite!` macro expects `ufmt` in scope
+            use $crate::__internal::ufmt;
+            let mut message = $crate::furi::string::FuriString::new();
+            ufmt::uwrite!(&mut message, $($args)+).ok();
+            $crate::furi::log::write_record(level, $tag, &message);
+        }
+    }};
+}
+
+/// Logs an error with a tag. See [`log!`].
+#[macro_export]
+macro_rules! error {
+    ($tag:expr, $($args:tt)+) => {
+        $crate::log!($crate::furi::log::Level::Error, $tag, $($args)+)
+    };
+}
+
+/// Logs a warning with a tag. See [`log!`].
+#[macro_export]
+macro_rules! warn {
+    ($tag:expr, $($args:tt)+) => {
+        $crate::log!($crate::furi::log::Level::Warn, $tag, $($args)+)
+    };
+}
+
+/// Logs an informational message with a tag. See [`log!`].
+#[macro_export]
+macro_rules! info {
+    ($tag:expr, $($args:tt)+) => {
+        $crate::log!($crate::furi::log::Level::Info, $tag, $($args)+)
+    };
+}
+
+/// Logs a debug message with a tag. See [`log!`].
+#[macro_export]
+macro_rules! debug {
+    ($tag:expr, $($args:tt)+) => {
+        $crate::log!($crate::furi::log::Level::Debug, $tag, $($args)+)
+    };
+}
+
+/// Logs a trace message with a tag. See [`log!`].
+#[macro_export]
+macro_rules! trace {
+    ($tag:expr, $($args:tt)+) => {
+        $crate::log!($crate::furi::log::Level::Trace, $tag, $($args)+)
+    };
+}
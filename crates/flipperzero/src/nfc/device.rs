@@ -0,0 +1,228 @@
+//! Cards saved in `.nfc` files.
+//!
+//! # Examples
+//!
+//! ```
+//! let mut device = NfcDevice::new();
+//! device.set_card(&card);
+//! device.save(CStr::from_bytes_with_nul(b"badge\0").unwrap())?;
+//!
+//! device.load(CStr::from_bytes_with_nul(b"/ext/nfc/badge.nfc\0").unwrap())?;
+//! assert_eq!(device.card(), card);
+//! ```
+
+use core::ffi::CStr;
+
+use flipperzero_sys as sys;
+
+use super::{Card, Error, Result};
+
+/// Folder that `.nfc` files are saved to.
+pub const NFC_FOLDER: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/nfc\0") };
+/// Longest name of a saved card, in bytes.
+pub const MAX_NAME_LEN: usize = 22;
+
+/// Card protocols that the firmware reads and saves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    /// Only the UID, ATQA and SAK of the card are known.
+    Unknown,
+    Emv,
+    MifareUltralight,
+    MifareClassic,
+    MifareDesfire,
+}
+
+impl Protocol {
+    /// Get the protocol from the corresponding SDK `NfcProtocol`.
+    pub fn from_sys(protocol: sys::NfcProtocol) -> Option<Self> {
+        match protocol {
+            sys::NfcProtocol_NfcDeviceProtocolUnknown => Some(Protocol::Unknown),
+            sys::NfcProtocol_NfcDeviceProtocolEMV => Some(Protocol::Emv),
+            sys::NfcProtocol_NfcDeviceProtocolMifareUl => Some(Protocol::MifareUltralight),
+            sys::NfcProtocol_NfcDeviceProtocolMifareClassic => Some(Protocol::MifareClassic),
+            sys::NfcProtocol_NfcDeviceProtocolMifareDesfire => Some(Protocol::MifareDesfire),
+            _ => None,
+        }
+    }
+
+    /// Convert the protocol into the corresponding SDK `NfcProtocol`.
+    pub fn to_sys(self) -> sys::NfcProtocol {
+        match self {
+            Protocol::Unknown => sys::NfcProtocol_NfcDeviceProtocolUnknown,
+            Protocol::Emv => sys::NfcProtocol_NfcDeviceProtocolEMV,
+            Protocol::MifareUltralight => sys::NfcProtocol_NfcDeviceProtocolMifareUl,
+            Protocol::MifareClassic => sys::NfcProtocol_NfcDeviceProtocolMifareClassic,
+            Protocol::MifareDesfire => sys::NfcProtocol_NfcDeviceProtocolMifareDesfire,
+        }
+    }
+}
+
+/// A card and its data, as saved in a `.nfc` file.
+pub struct NfcDevice {
+    raw: *mut sys::NfcDevice,
+}
+
+impl NfcDevice {
+    /// Creates an empty device.
+    pub fn new() -> Self {
+        let raw = unsafe { sys::nfc_device_alloc() };
+        assert!(!raw.is_null());
+        Self { raw }
+    }
+
+    /// Returns the raw pointer to the device.
+    pub fn as_ptr(&self) -> *mut sys::NfcDevice {
+        self.raw
+    }
+
+    /// Loads a `.nfc` file, replacing the device's card.
+    pub fn load(&mut self, path: &CStr) -> Result<()> {
+        if unsafe { sys::nfc_device_load(self.raw, path.as_ptr(), false) } {
+            Ok(())
+        } else {
+            Err(Error::Load)
+        }
+    }
+
+    /// Saves the card as `name` (without the `.nfc` extension), replacing any existing
+    /// file.
+    ///
+    /// The file is saved next to the file the device was loaded from, or in
+    /// [`NFC_FOLDER`].
+    pub fn save(&mut self, name: &CStr) -> Result<()> {
+        let len = name.to_bytes().len();
+        if len == 0 || len > MAX_NAME_LEN {
+            return Err(Error::InvalidName);
+        }
+
+        unsafe { sys::nfc_device_set_name(self.raw, name.as_ptr()) };
+        if unsafe { sys::nfc_device_save(self.raw, name.as_ptr()) } {
+            Ok(())
+        } else {
+            Err(Error::Save)
+        }
+    }
+
+    /// Returns the name of the card, if it has been loaded or saved.
+    pub fn name(&self) -> &CStr {
+        let name = unsafe { &(*self.raw).dev_name };
+        // The firmware keeps the name nul-terminated.
+        unsafe { CStr::from_ptr(name.as_ptr()) }
+    }
+
+    /// Returns the identification of the card.
+    pub fn card(&self) -> Card {
+        Card::from_sys(unsafe { (*self.raw).dev_data.nfc_data })
+    }
+
+    /// Replaces the device's data with `card`, which is saved with only its UID, ATQA
+    /// and SAK.
+    pub fn set_card(&mut self, card: &Card) {
+        unsafe {
+            sys::nfc_device_data_clear(&mut (*self.raw).dev_data);
+            (*self.raw).dev_data.nfc_data = card.to_sys();
+            (*self.raw).dev_data.protocol = Protocol::Unknown.to_sys();
+            (*self.raw).format = sys::NfcDeviceSaveFormat_NfcDeviceSaveFormatUid;
+        }
+    }
+
+    /// Returns the protocol of the card, if known to this crate.
+    pub fn protocol(&self) -> Option<Protocol> {
+        Protocol::from_sys(unsafe { (*self.raw).dev_data.protocol })
+    }
+
+    /// Clears the card and the path it was loaded from.
+    pub fn clear(&mut self) {
+        unsafe { sys::nfc_device_clear(self.raw) };
+    }
+}
+
+impl Default for NfcDevice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for NfcDevice {
+    fn drop(&mut self) {
+        unsafe { sys::nfc_device_free(self.raw) };
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::ffi::CStr;
+
+    use super::{NfcDevice, Protocol};
+    use crate::io::Write;
+    use crate::nfc::{Card, Error, NfcType};
+    use crate::storage::{self, OpenOptions};
+
+    const UID: &str = include_str!("../../../../fixtures/nfc/uid.nfc");
+    const NAME: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"flipperzero-rs\0") };
+    const PATH: &CStr =
+        unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/flipperzero-rs.nfc\0") };
+
+    fn fixture_card() -> Card {
+        Card::new_nfca(
+            &[0x04, 0x85, 0x92, 0x8A, 0xA0, 0x61, 0x81],
+            [0x00, 0x44],
+            0x00,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn load_and_save() {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_always(true)
+            .open(PATH)
+            .unwrap();
+        file.write_all(UID.as_bytes()).unwrap();
+        drop(file);
+
+        let mut device = NfcDevice::new();
+        device.load(PATH).unwrap();
+        assert_eq!(device.protocol(), Some(Protocol::Unknown));
+        assert_eq!(device.card().nfc_type(), Some(NfcType::A));
+        assert_eq!(device.card(), fixture_card());
+
+        // Saved next to the loaded file, replacing it.
+        device.set_card(&Card::new_nfca(&[0x01, 0x02, 0x03, 0x04], [0x00, 0x04], 0x08).unwrap());
+        device.save(NAME).unwrap();
+        assert_eq!(device.name(), NAME);
+
+        let mut saved = NfcDevice::new();
+        saved.load(PATH).unwrap();
+        assert_eq!(saved.card().uid(), &[0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(saved.card().atqa(), [0x00, 0x04]);
+        assert_eq!(saved.card().sak(), 0x08);
+
+        storage::remove(PATH).unwrap();
+    }
+
+    #[test]
+    fn invalid_name() {
+        let mut device = NfcDevice::new();
+        device.set_card(&fixture_card());
+        assert_eq!(
+            device.save(CStr::from_bytes_with_nul(b"\0").unwrap()),
+            Err(Error::InvalidName)
+        );
+        assert_eq!(
+            device.save(CStr::from_bytes_with_nul(b"a-name-that-is-too-long\0").unwrap()),
+            Err(Error::InvalidName)
+        );
+    }
+
+    #[test]
+    fn missing_file() {
+        let mut device = NfcDevice::new();
+        assert_eq!(
+            device.load(CStr::from_bytes_with_nul(b"/ext/flipperzero-rs-missing.nfc\0").unwrap()),
+            Err(Error::Load)
+        );
+    }
+}
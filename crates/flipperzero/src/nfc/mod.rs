@@ -0,0 +1,483 @@
+//! NFC card detection, transceiving and emulation.
+//!
+//! The NFC hardware is used through an [`Nfc`] guard, which wakes the chip when acquired
+//! and puts it back to sleep when dropped. Cards are saved to and loaded from `.nfc`
+//! files with [`device::NfcDevice`].
+//!
+//! # Examples
+//!
+//! ```
+//! let mut nfc = Nfc::acquire()?;
+//! if let Some(card) = nfc.detect(Duration::from_millis(300)) {
+//!     info!("nfc", "UID {:?}, SAK {:#x}", card.uid(), card.sak());
+//!
+//!     // Reads page 0 of a MIFARE Ultralight.
+//!     let response = nfc.transceive(&[0x30, 0x00], TxRxMode::Default, Duration::from_millis(20))?;
+//!     info!("nfc", "{:?}", response.data());
+//! }
+//! ```
+
+use core::ffi::c_void;
+use core::fmt;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use flipperzero_sys as sys;
+
+pub mod device;
+
+/// Longest UID of a card, in bytes.
+pub const MAX_UID_LEN: usize = 10;
+/// Longest frame that can be transceived, in bytes.
+pub const MAX_FRAME_LEN: usize = 512;
+/// Longest response that can be sent while emulating, in bytes.
+pub const MAX_EMULATE_RESPONSE_LEN: usize = 256;
+
+/// Whether an [`Nfc`] guard exists.
+static ACQUIRED: AtomicBool = AtomicBool::new(false);
+
+/// NFC Result type.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// NFC error kinds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The NFC hardware is already in use.
+    Busy,
+    /// The UID is empty or longer than [`MAX_UID_LEN`].
+    InvalidUid,
+    /// The frame is empty or longer than [`MAX_FRAME_LEN`].
+    InvalidFrame,
+    /// No card was activated.
+    Activation,
+    /// The card didn't respond in time, or its response was invalid.
+    Transceive,
+    /// The name is empty or too long to be saved.
+    InvalidName,
+    /// The `.nfc` file couldn't be loaded.
+    Load,
+    /// The `.nfc` file couldn't be saved.
+    Save,
+}
+
+impl Error {
+    /// Describes the error.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Busy => "NFC busy",
+            Self::InvalidUid => "Invalid UID",
+            Self::InvalidFrame => "Invalid frame",
+            Self::Activation => "Card activation failed",
+            Self::Transceive => "Transceive failed",
+            Self::InvalidName => "Invalid name",
+            Self::Load => "Failed to load NFC file",
+            Self::Save => "Failed to save NFC file",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> core::result::Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+/// NFC technologies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NfcType {
+    A,
+    B,
+    F,
+    V,
+}
+
+impl NfcType {
+    /// Get the technology from the corresponding SDK `FuriHalNfcType`.
+    pub fn from_sys(nfc_type: sys::FuriHalNfcType) -> Option<Self> {
+        match nfc_type {
+            sys::FuriHalNfcType_FuriHalNfcTypeA => Some(NfcType::A),
+            sys::FuriHalNfcType_FuriHalNfcTypeB => Some(NfcType::B),
+            sys::FuriHalNfcType_FuriHalNfcTypeF => Some(NfcType::F),
+            sys::FuriHalNfcType_FuriHalNfcTypeV => Some(NfcType::V),
+            _ => None,
+        }
+    }
+
+    /// Convert the technology into the corresponding SDK `FuriHalNfcType`.
+    pub fn to_sys(self) -> sys::FuriHalNfcType {
+        match self {
+            NfcType::A => sys::FuriHalNfcType_FuriHalNfcTypeA,
+            NfcType::B => sys::FuriHalNfcType_FuriHalNfcTypeB,
+            NfcType::F => sys::FuriHalNfcType_FuriHalNfcTypeF,
+            NfcType::V => sys::FuriHalNfcType_FuriHalNfcTypeV,
+        }
+    }
+}
+
+/// Interfaces that a card is activated with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Interface {
+    /// Frames are exchanged as-is.
+    Rf,
+    /// ISO-DEP (ISO/IEC 14443-4).
+    IsoDep,
+    /// NFC-DEP (ISO/IEC 18092).
+    NfcDep,
+}
+
+impl Interface {
+    /// Get the interface from the corresponding SDK `FuriHalNfcInterface`.
+    pub fn from_sys(interface: sys::FuriHalNfcInterface) -> Option<Self> {
+        match interface {
+            sys::FuriHalNfcInterface_FuriHalNfcInterfaceRf => Some(Interface::Rf),
+            sys::FuriHalNfcInterface_FuriHalNfcInterfaceIsoDep => Some(Interface::IsoDep),
+            sys::FuriHalNfcInterface_FuriHalNfcInterfaceNfcDep => Some(Interface::NfcDep),
+            _ => None,
+        }
+    }
+
+    /// Convert the interface into the corresponding SDK `FuriHalNfcInterface`.
+    pub fn to_sys(self) -> sys::FuriHalNfcInterface {
+        match self {
+            Interface::Rf => sys::FuriHalNfcInterface_FuriHalNfcInterfaceRf,
+            Interface::IsoDep => sys::FuriHalNfcInterface_FuriHalNfcInterfaceIsoDep,
+            Interface::NfcDep => sys::FuriHalNfcInterface_FuriHalNfcInterfaceNfcDep,
+        }
+    }
+}
+
+/// The identification of a card: its UID, and for NFC-A cards its ATQA and SAK.
+#[derive(Clone, Copy)]
+pub struct Card {
+    data: sys::FuriHalNfcDevData,
+}
+
+impl Card {
+    /// Creates an NFC-A card, e.g. to be emulated.
+    pub fn new_nfca(uid: &[u8], atqa: [u8; 2], sak: u8) -> Result<Self> {
+        if uid.is_empty() || uid.len() > MAX_UID_LEN {
+            return Err(Error::InvalidUid);
+        }
+
+        let mut data = sys::FuriHalNfcDevData {
+            type_: NfcType::A.to_sys(),
+            interface: Interface::Rf.to_sys(),
+            uid_len: uid.len() as u8,
+            uid: [0; MAX_UID_LEN],
+            cuid: 0,
+            atqa,
+            sak,
+        };
+        data.uid[..uid.len()].copy_from_slice(uid);
+        // The last four bytes of the UID, as the firmware computes it.
+        data.cuid = data.uid[uid.len().saturating_sub(4)..uid.len()]
+            .iter()
+            .fold(0, |cuid, &byte| (cuid << 8) | u32::from(byte));
+
+        Ok(Self { data })
+    }
+
+    /// Get the card from the corresponding SDK `FuriHalNfcDevData`.
+    pub fn from_sys(data: sys::FuriHalNfcDevData) -> Self {
+        Self { data }
+    }
+
+    /// Convert the card into the corresponding SDK `FuriHalNfcDevData`.
+    pub fn to_sys(self) -> sys::FuriHalNfcDevData {
+        self.data
+    }
+
+    /// Returns the technology of the card, if known.
+    pub fn nfc_type(&self) -> Option<NfcType> {
+        NfcType::from_sys(self.data.type_)
+    }
+
+    /// Returns the interface the card was activated with, if known.
+    pub fn interface(&self) -> Option<Interface> {
+        Interface::from_sys(self.data.interface)
+    }
+
+    /// Returns the UID of the card.
+    pub fn uid(&self) -> &[u8] {
+        &self.data.uid[..usize::from(self.data.uid_len).min(MAX_UID_LEN)]
+    }
+
+    /// Returns the last four bytes of the UID, as used by MIFARE Classic
+    /// authentication.
+    pub fn cuid(&self) -> u32 {
+        self.data.cuid
+    }
+
+    /// Returns the ATQA (Answer To Request, type A) of the card.
+    pub fn atqa(&self) -> [u8; 2] {
+        self.data.atqa
+    }
+
+    /// Returns the SAK (Select Acknowledge) of the card.
+    pub fn sak(&self) -> u8 {
+        self.data.sak
+    }
+}
+
+impl fmt::Debug for Card {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Card")
+            .field("nfc_type", &self.nfc_type())
+            .field("uid", &self.uid())
+            .field("atqa", &self.atqa())
+            .field("sak", &self.sak())
+            .finish()
+    }
+}
+
+impl PartialEq for Card {
+    fn eq(&self, other: &Self) -> bool {
+        self.data.type_ == other.data.type_
+            && self.uid() == other.uid()
+            && self.atqa() == other.atqa()
+            && self.sak() == other.sak()
+    }
+}
+
+impl Eq for Card {}
+
+/// How CRCs are handled by [`Nfc::transceive`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TxRxMode {
+    /// The CRC is appended to the request, and checked and removed from the response.
+    Default,
+    /// The CRC is appended to the request, and the response is returned with its CRC,
+    /// unchecked.
+    RxNoCrc,
+}
+
+impl TxRxMode {
+    /// Convert the mode into the corresponding SDK `FuriHalNfcTxRxType`.
+    pub fn to_sys(self) -> sys::FuriHalNfcTxRxType {
+        match self {
+            TxRxMode::Default => sys::FuriHalNfcTxRxType_FuriHalNfcTxRxTypeDefault,
+            TxRxMode::RxNoCrc => sys::FuriHalNfcTxRxType_FuriHalNfcTxRxTypeRxNoCrc,
+        }
+    }
+}
+
+/// A response received by [`Nfc::transceive`].
+#[derive(Debug, Clone, Copy)]
+pub struct Response<'a> {
+    data: &'a [u8],
+    bits: u16,
+}
+
+impl<'a> Response<'a> {
+    /// Returns the received bytes, the last one possibly incomplete.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns the number of bits received.
+    pub fn bits(&self) -> u16 {
+        self.bits
+    }
+}
+
+fn timeout_ms(timeout: Duration) -> u32 {
+    timeout.as_millis().min(u128::from(u32::MAX)) as u32
+}
+
+/// Exclusive access to the NFC hardware.
+///
+/// The NFC chip is woken up when acquired, and its field turned off and the chip put
+/// back to sleep when dropped.
+pub struct Nfc {
+    tx_rx: *mut sys::FuriHalNfcTxRxContext,
+}
+
+impl Nfc {
+    /// Acquires the NFC hardware.
+    ///
+    /// Fails with [`Error::Busy`] if another guard exists, or the hardware is in use
+    /// by the firmware.
+    pub fn acquire() -> Result<Self> {
+        if ACQUIRED
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(Error::Busy);
+        }
+        if unsafe { !sys::furi_hal_nfc_is_init() || sys::furi_hal_nfc_is_busy() } {
+            ACQUIRED.store(false, Ordering::Release);
+            return Err(Error::Busy);
+        }
+
+        // The context is too large for most application stacks.
+        let size = core::mem::size_of::<sys::FuriHalNfcTxRxContext>();
+        let tx_rx = unsafe { sys::malloc(size as u32) } as *mut sys::FuriHalNfcTxRxContext;
+        assert!(!tx_rx.is_null());
+        // All-zero is a valid context: no sniffing callbacks or signal.
+        unsafe { ptr::write_bytes(tx_rx, 0, 1) };
+
+        unsafe { sys::furi_hal_nfc_exit_sleep() };
+
+        Ok(Self { tx_rx })
+    }
+
+    /// Polls for a card in the field for up to `timeout`, activating the first one
+    /// found.
+    pub fn detect(&mut self, timeout: Duration) -> Option<Card> {
+        let mut card = Card::from_sys(unsafe { core::mem::zeroed() });
+        unsafe { sys::furi_hal_nfc_detect(&mut card.data, timeout_ms(timeout)) }.then_some(card)
+    }
+
+    /// Activates an NFC-A card, returning the last four bytes of its UID.
+    pub fn activate_nfca(&mut self, timeout: Duration) -> Result<u32> {
+        let mut cuid = 0;
+        if unsafe { sys::furi_hal_nfc_activate_nfca(timeout_ms(timeout), &mut cuid) } {
+            Ok(cuid)
+        } else {
+            Err(Error::Activation)
+        }
+    }
+
+    /// Sends a frame to the activated card and waits up to `timeout` for its
+    /// response.
+    pub fn transceive(
+        &mut self,
+        request: &[u8],
+        mode: TxRxMode,
+        timeout: Duration,
+    ) -> Result<Response<'_>> {
+        if request.is_empty() || request.len() > MAX_FRAME_LEN {
+            return Err(Error::InvalidFrame);
+        }
+
+        let tx_rx = unsafe { &mut *self.tx_rx };
+        tx_rx.tx_data[..request.len()].copy_from_slice(request);
+        tx_rx.tx_bits = (request.len() * 8) as u16;
+        tx_rx.tx_rx_type = mode.to_sys();
+        tx_rx.rx_bits = 0;
+
+        let timeout = timeout.as_millis().min(u128::from(u16::MAX)) as u16;
+        if !unsafe { sys::furi_hal_nfc_tx_rx(tx_rx, timeout) } {
+            return Err(Error::Transceive);
+        }
+
+        let bits = tx_rx.rx_bits;
+        let len = (usize::from(bits) + 7) / 8;
+        Ok(Response {
+            data: &tx_rx.rx_data[..len.min(MAX_FRAME_LEN)],
+            bits,
+        })
+    }
+
+    /// Emulates an NFC-A card for up to `timeout`.
+    ///
+    /// The card is selected by readers with its UID, ATQA and SAK. Each later request
+    /// is passed to `callback` with its length in bits; the callback writes its
+    /// response to the buffer and returns the response's length in bits, or `None` to
+    /// not respond.
+    ///
+    /// Returns `true` if a reader selected the card.
+    pub fn emulate_nfca<F>(&mut self, card: &Card, timeout: Duration, callback: &mut F) -> bool
+    where
+        F: FnMut(&[u8], u16, &mut [u8; MAX_EMULATE_RESPONSE_LEN]) -> Option<u16>,
+    {
+        unsafe extern "C" fn emulate_callback<F>(
+            buff_rx: *mut u8,
+            buff_rx_len: u16,
+            buff_tx: *mut u8,
+            buff_tx_len: *mut u16,
+            _data_type: *mut u32,
+            context: *mut c_void,
+        ) -> bool
+        where
+            F: FnMut(&[u8], u16, &mut [u8; MAX_EMULATE_RESPONSE_LEN]) -> Option<u16>,
+        {
+            let callback = unsafe { &mut *(context as *mut F) };
+            let request =
+                unsafe { core::slice::from_raw_parts(buff_rx, (usize::from(buff_rx_len) + 7) / 8) };
+            let response = unsafe { &mut *(buff_tx as *mut [u8; MAX_EMULATE_RESPONSE_LEN]) };
+
+            match callback(request, buff_rx_len, response) {
+                Some(bits) => {
+                    let max_bits = (MAX_EMULATE_RESPONSE_LEN * 8) as u16;
+                    unsafe { *buff_tx_len = bits.min(max_bits) };
+                    true
+                }
+                None => false,
+            }
+        }
+
+        let mut data = card.data;
+        unsafe {
+            sys::furi_hal_nfc_emulate_nfca(
+                data.uid.as_mut_ptr(),
+                data.uid_len,
+                data.atqa.as_mut_ptr(),
+                data.sak,
+                Some(emulate_callback::<F>),
+                callback as *mut F as *mut c_void,
+                timeout_ms(timeout),
+            )
+        }
+    }
+
+    /// Turns the reader field on, powering nearby cards.
+    pub fn field_on(&mut self) {
+        unsafe { sys::furi_hal_nfc_field_on() };
+    }
+
+    /// Turns the reader field off.
+    pub fn field_off(&mut self) {
+        unsafe { sys::furi_hal_nfc_field_off() };
+    }
+}
+
+impl Drop for Nfc {
+    fn drop(&mut self) {
+        unsafe {
+            sys::furi_hal_nfc_sleep();
+            sys::free(self.tx_rx as *mut c_void);
+        }
+        ACQUIRED.store(false, Ordering::Release);
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::{Card, Error, Interface, Nfc, NfcType};
+
+    #[test]
+    fn card_identification() {
+        let card =
+            Card::new_nfca(&[0x04, 0x85, 0x92, 0x8A, 0xA0, 0x61, 0x81], [0x00, 0x44], 0).unwrap();
+        assert_eq!(card.nfc_type(), Some(NfcType::A));
+        assert_eq!(card.interface(), Some(Interface::Rf));
+        assert_eq!(card.uid(), &[0x04, 0x85, 0x92, 0x8A, 0xA0, 0x61, 0x81]);
+        assert_eq!(card.cuid(), 0x8AA0_6181);
+        assert_eq!(card.atqa(), [0x00, 0x44]);
+        assert_eq!(card.sak(), 0);
+        assert_eq!(Card::from_sys(card.to_sys()), card);
+
+        assert_eq!(Card::new_nfca(&[], [0, 0], 0), Err(Error::InvalidUid));
+        assert_eq!(Card::new_nfca(&[0; 11], [0, 0], 0), Err(Error::InvalidUid));
+    }
+
+    #[test]
+    fn exclusive_access() {
+        let nfc = Nfc::acquire().unwrap();
+        assert!(matches!(Nfc::acquire(), Err(Error::Busy)));
+        drop(nfc);
+        assert!(Nfc::acquire().is_ok());
+    }
+}
@@ -0,0 +1,189 @@
+use flipperzero_sys as sys;
+
+use super::{Error, Result, Rom, MATCH_ROM, SKIP_ROM};
+
+/// Which devices take part in a [`Search`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SearchMode {
+    /// Every device.
+    Normal,
+    /// Only devices in an alarm state, e.g. temperature sensors over their limits.
+    Conditional,
+}
+
+impl SearchMode {
+    /// Convert the mode into the corresponding SDK `OneWireHostSearchMode`.
+    pub fn to_sys(self) -> sys::OneWireHostSearchMode {
+        match self {
+            Self::Normal => sys::OneWireHostSearchMode_OneWireHostSearchModeNormal,
+            Self::Conditional => sys::OneWireHostSearchMode_OneWireHostSearchModeConditional,
+        }
+    }
+}
+
+/// The host (master) of a 1-Wire bus.
+///
+/// The pin is configured as an open-drain output while the host exists, and needs an
+/// external pull-up resistor unless the device is parasite-powered from the Flipper.
+pub struct OneWireHost {
+    raw: *mut sys::OneWireHost,
+}
+
+impl OneWireHost {
+    /// Takes over `pin` as a 1-Wire bus, e.g. `unsafe { &sys::gpio_ext_pa7 }`.
+    pub fn new(pin: &'static sys::GpioPin) -> Self {
+        let raw = unsafe { sys::onewire_host_alloc(pin) };
+        assert!(!raw.is_null());
+        unsafe { sys::onewire_host_start(raw) };
+        Self { raw }
+    }
+
+    /// Sends a reset pulse, returning `true` if any device answered with a presence
+    /// pulse.
+    pub fn reset(&mut self) -> bool {
+        unsafe { sys::onewire_host_reset(self.raw) }
+    }
+
+    /// Reads a bit.
+    pub fn read_bit(&mut self) -> bool {
+        unsafe { sys::onewire_host_read_bit(self.raw) }
+    }
+
+    /// Reads a byte, least significant bit first.
+    pub fn read_byte(&mut self) -> u8 {
+        unsafe { sys::onewire_host_read(self.raw) }
+    }
+
+    /// Reads bytes into `buf`.
+    pub fn read_bytes(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(u16::MAX.into()) {
+            unsafe {
+                sys::onewire_host_read_bytes(self.raw, chunk.as_mut_ptr(), chunk.len() as u16)
+            };
+        }
+    }
+
+    /// Writes a bit.
+    pub fn write_bit(&mut self, value: bool) {
+        unsafe { sys::onewire_host_write_bit(self.raw, value) };
+    }
+
+    /// Writes a byte, least significant bit first.
+    pub fn write_byte(&mut self, value: u8) {
+        unsafe { sys::onewire_host_write(self.raw, value) };
+    }
+
+    /// Writes the bytes of `buf`.
+    pub fn write_bytes(&mut self, buf: &[u8]) {
+        for chunk in buf.chunks(u16::MAX.into()) {
+            unsafe { sys::onewire_host_write_bytes(self.raw, chunk.as_ptr(), chunk.len() as u16) };
+        }
+    }
+
+    /// Enables or disables overdrive speed, which must be supported by every device on
+    /// the bus.
+    pub fn set_overdrive(&mut self, overdrive: bool) {
+        unsafe { sys::onewire_host_set_overdrive(self.raw, overdrive) };
+    }
+
+    /// Resets the bus and selects the device with `rom`, so that the next function
+    /// command is only received by it.
+    pub fn select(&mut self, rom: &Rom) -> Result<()> {
+        if !self.reset() {
+            return Err(Error::NoPresence);
+        }
+        self.write_byte(MATCH_ROM);
+        self.write_bytes(rom.as_bytes());
+        Ok(())
+    }
+
+    /// Resets the bus and selects every device, so that the next function command is
+    /// received by all of them.
+    pub fn skip(&mut self) -> Result<()> {
+        if !self.reset() {
+            return Err(Error::NoPresence);
+        }
+        self.write_byte(SKIP_ROM);
+        Ok(())
+    }
+
+    /// Searches the ROMs of all devices on the bus.
+    ///
+    /// ROMs are returned as received, and should be checked with [`Rom::is_valid`].
+    pub fn search(&mut self) -> Search<'_> {
+        unsafe { sys::onewire_host_reset_search(self.raw) };
+        Search {
+            host: self,
+            mode: SearchMode::Normal,
+        }
+    }
+
+    /// Searches the ROMs of the devices on the bus, in `mode`, starting from the
+    /// devices of `family_code`.
+    ///
+    /// Devices of other families may be returned after those of `family_code`, so the
+    /// search should be stopped once [`Rom::family_code`] changes.
+    pub fn search_family(&mut self, family_code: u8, mode: SearchMode) -> Search<'_> {
+        unsafe { sys::onewire_host_target_search(self.raw, family_code) };
+        Search { host: self, mode }
+    }
+}
+
+impl Drop for OneWireHost {
+    fn drop(&mut self) {
+        unsafe {
+            sys::onewire_host_stop(self.raw);
+            sys::onewire_host_free(self.raw);
+        }
+    }
+}
+
+/// An iterator over the ROMs of the devices on a bus, see [`OneWireHost::search`].
+pub struct Search<'a> {
+    host: &'a mut OneWireHost,
+    mode: SearchMode,
+}
+
+impl Iterator for Search<'_> {
+    type Item = Rom;
+
+    fn next(&mut self) -> Option<Rom> {
+        let mut rom = [0; 8];
+        unsafe { sys::onewire_host_search(self.host.raw, rom.as_mut_ptr(), self.mode.to_sys()) }
+            .then(|| Rom::from_bytes(rom))
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use flipperzero_sys as sys;
+
+    use super::{OneWireHost, SearchMode};
+    use crate::onewire::Rom;
+
+    #[test]
+    fn bus_operations() {
+        // Nothing is connected to the GPIO header during tests, and without a pull-up the
+        // bus floats, so only check that the bus can be driven.
+        let mut host = OneWireHost::new(unsafe { &sys::gpio_ext_pa7 });
+        if host.skip().is_ok() {
+            host.write_byte(0x44);
+        }
+        let _ = host.select(&Rom::new(0x28, [0; 6]));
+        let _ = host.search().take(4).count();
+        let _ = host.search_family(0x28, SearchMode::Conditional).next();
+
+        host.set_overdrive(true);
+        host.write_bit(true);
+        let _ = host.read_bit();
+        host.set_overdrive(false);
+        host.write_bytes(&[0xBE, 0x00]);
+        let mut buf = [0; 9];
+        host.read_bytes(&mut buf);
+        drop(host);
+
+        // The pin is released, so it can be taken over again.
+        let mut host = OneWireHost::new(unsafe { &sys::gpio_ext_pa7 });
+        let _ = host.reset();
+    }
+}
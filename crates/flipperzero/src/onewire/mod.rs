@@ -0,0 +1,244 @@
+//! 1-Wire bus host and slave, on any GPIO pin.
+//!
+//! [`OneWireHost`] drives a bus of 1-Wire devices, such as DS18B20 temperature sensors
+//! or DS2431 EEPROMs, and [`OneWireSlave`] (with the `alloc` feature) acts as a device
+//! on a bus.
+//!
+//! Drivers that bit-bang the bus themselves can use an [`OpenDrainPin`] instead. With
+//! the `embedded-hal` feature, it implements the `embedded-hal` digital pin traits, and
+//! [`Delay`] implements [`DelayNs`](embedded_hal::delay::DelayNs) for bus timings and
+//! conversions.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use flipperzero::onewire::OneWireHost;
+//! use flipperzero_sys as sys;
+//!
+//! const DS18B20_FAMILY: u8 = 0x28;
+//! const CONVERT_T: u8 = 0x44;
+//!
+//! let mut host = OneWireHost::new(unsafe { &sys::gpio_ext_pa7 });
+//! for rom in host.search() {
+//!     if rom.family_code() == DS18B20_FAMILY && rom.is_valid() {
+//!         host.select(&rom)?;
+//!         host.write_byte(CONVERT_T);
+//!     }
+//! }
+//! ```
+
+use core::fmt;
+
+use flipperzero_sys as sys;
+
+pub mod host;
+pub mod pin;
+#[cfg(feature = "alloc")]
+pub mod slave;
+
+pub use host::{OneWireHost, Search, SearchMode};
+pub use pin::OpenDrainPin;
+#[cfg(feature = "alloc")]
+pub use slave::{OneWireSlave, SlaveBus, SlaveHandler};
+
+/// ROM command selecting a single device by its [`Rom`].
+pub const MATCH_ROM: u8 = 0x55;
+/// ROM command selecting every device, e.g. when there is a single device on the bus.
+pub const SKIP_ROM: u8 = 0xCC;
+/// ROM command reading the [`Rom`] of the only device on the bus.
+pub const READ_ROM: u8 = 0x33;
+/// ROM command searching the [`Rom`]s of the devices on the bus.
+pub const SEARCH_ROM: u8 = 0xF0;
+
+/// 1-Wire Result type.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// 1-Wire error kinds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// No device answered the reset pulse.
+    NoPresence,
+    /// The CRC of a ROM or data doesn't match.
+    InvalidCrc,
+    /// The slave transfer was interrupted by a reset, or timed out.
+    Transfer,
+}
+
+impl Error {
+    /// Describes the error.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::NoPresence => "No device present",
+            Self::InvalidCrc => "Invalid CRC",
+            Self::Transfer => "Transfer failed",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> core::result::Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+/// Computes the Dallas/Maxim CRC8 of `data`, as used for ROMs and scratchpads.
+///
+/// The CRC of data followed by its CRC is `0`.
+pub fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0, |mut crc, &byte| {
+        let mut byte = byte;
+        for _ in 0..8 {
+            let mix = (crc ^ byte) & 0x01;
+            crc >>= 1;
+            if mix != 0 {
+                crc ^= 0x8C;
+            }
+            byte >>= 1;
+        }
+        crc
+    })
+}
+
+/// The 64-bit ROM of a device: its family code, 48-bit serial number and CRC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rom([u8; 8]);
+
+impl Rom {
+    /// Creates a ROM from its bytes, as sent on the bus, without checking its CRC.
+    pub const fn from_bytes(bytes: [u8; 8]) -> Self {
+        Self(bytes)
+    }
+
+    /// Creates a ROM from its family code and serial number, computing its CRC.
+    pub fn new(family_code: u8, serial: [u8; 6]) -> Self {
+        let mut bytes = [family_code, 0, 0, 0, 0, 0, 0, 0];
+        bytes[1..7].copy_from_slice(&serial);
+        bytes[7] = crc8(&bytes[..7]);
+        Self(bytes)
+    }
+
+    /// Returns the bytes of the ROM, as sent on the bus.
+    pub fn as_bytes(&self) -> &[u8; 8] {
+        &self.0
+    }
+
+    /// Returns the family code, identifying the type of device.
+    pub fn family_code(&self) -> u8 {
+        self.0[0]
+    }
+
+    /// Returns the serial number, least significant byte first.
+    pub fn serial(&self) -> [u8; 6] {
+        let mut serial = [0; 6];
+        serial.copy_from_slice(&self.0[1..7]);
+        serial
+    }
+
+    /// Returns the CRC of the ROM.
+    pub fn crc(&self) -> u8 {
+        self.0[7]
+    }
+
+    /// Returns `true` if the CRC of the ROM matches.
+    pub fn is_valid(&self) -> bool {
+        crc8(&self.0) == 0
+    }
+
+    /// Returns the ROM, or [`Error::InvalidCrc`] if its CRC doesn't match.
+    pub fn validate(self) -> Result<Self> {
+        if self.is_valid() {
+            Ok(self)
+        } else {
+            Err(Error::InvalidCrc)
+        }
+    }
+}
+
+impl fmt::Display for Rom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{byte:02X}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Busy-waits for delays, e.g. for 1-Wire device drivers.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Delay;
+
+impl Delay {
+    /// Waits for at least `us` microseconds.
+    pub fn delay_us(&mut self, us: u32) {
+        unsafe { sys::furi_delay_us(us) };
+    }
+
+    /// Waits for at least `ms` milliseconds.
+    pub fn delay_ms(&mut self, ms: u32) {
+        unsafe { sys::furi_delay_ms(ms) };
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::delay::DelayNs for Delay {
+    fn delay_ns(&mut self, ns: u32) {
+        Delay::delay_us(self, ns / 1000 + u32::from(ns % 1000 != 0));
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        Delay::delay_us(self, us);
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        Delay::delay_ms(self, ms);
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::{crc8, Error, Rom};
+
+    #[test]
+    fn crc8_vectors() {
+        assert_eq!(crc8(&[]), 0);
+        // From Maxim application note 27.
+        assert_eq!(crc8(&[0x02, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x00]), 0xA2);
+        assert_eq!(crc8(&[0x02, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x00, 0xA2]), 0);
+
+        let data = [0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD];
+        assert_eq!(crc8(&data), unsafe {
+            flipperzero_sys::maxim_crc8(data.as_ptr(), data.len() as u8, 0)
+        });
+    }
+
+    #[test]
+    fn rom() {
+        let rom = Rom::new(0x01, [0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD]);
+        assert_eq!(
+            rom.as_bytes(),
+            &[0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xE9]
+        );
+        assert_eq!(rom.family_code(), 0x01);
+        assert_eq!(rom.serial(), [0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD]);
+        assert_eq!(rom.crc(), 0xE9);
+        assert!(rom.is_valid());
+        assert_eq!(rom.validate(), Ok(rom));
+
+        let corrupt = Rom::from_bytes([0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0x00]);
+        assert!(!corrupt.is_valid());
+        assert_eq!(corrupt.validate(), Err(Error::InvalidCrc));
+    }
+}
@@ -0,0 +1,127 @@
+//! Open-drain GPIO pin for bit-banged 1-Wire drivers.
+
+use flipperzero_sys as sys;
+
+/// A GPIO pin configured as an open-drain output with the internal pull-up.
+///
+/// This is the pin that bit-banged 1-Wire drivers such as `one-wire-bus` expect: with
+/// the `embedded-hal` feature, it implements
+/// [`InputPin`](embedded_hal::digital::InputPin) and
+/// [`OutputPin`](embedded_hal::digital::OutputPin). Driving it high releases the line,
+/// so that devices can pull it low.
+///
+/// The line is released when the pin is created, and the pin is reset to analog mode
+/// when dropped. It must not be used by a [`OneWireHost`](super::OneWireHost) at the
+/// same time.
+pub struct OpenDrainPin {
+    pin: &'static sys::GpioPin,
+}
+
+impl OpenDrainPin {
+    /// Takes over `pin`, e.g. `unsafe { &sys::gpio_ext_pa7 }`.
+    pub fn new(pin: &'static sys::GpioPin) -> Self {
+        unsafe {
+            sys::furi_hal_gpio_write(pin, true);
+            sys::furi_hal_gpio_init(
+                pin,
+                sys::GpioMode_GpioModeOutputOpenDrain,
+                sys::GpioPull_GpioPullUp,
+                sys::GpioSpeed_GpioSpeedVeryHigh,
+            );
+        }
+
+        Self { pin }
+    }
+
+    /// Releases the line.
+    pub fn set_high(&mut self) {
+        unsafe { sys::furi_hal_gpio_write(self.pin, true) };
+    }
+
+    /// Pulls the line low.
+    pub fn set_low(&mut self) {
+        unsafe { sys::furi_hal_gpio_write(self.pin, false) };
+    }
+
+    /// Returns `true` if the line is high.
+    pub fn is_high(&self) -> bool {
+        unsafe { sys::furi_hal_gpio_read(self.pin) }
+    }
+
+    /// Returns `true` if the line is pulled low, by this pin or by a device.
+    pub fn is_low(&self) -> bool {
+        !self.is_high()
+    }
+}
+
+impl Drop for OpenDrainPin {
+    fn drop(&mut self) {
+        unsafe {
+            sys::furi_hal_gpio_init(
+                self.pin,
+                sys::GpioMode_GpioModeAnalog,
+                sys::GpioPull_GpioPullNo,
+                sys::GpioSpeed_GpioSpeedLow,
+            );
+        }
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::digital::ErrorType for OpenDrainPin {
+    type Error = core::convert::Infallible;
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::digital::InputPin for OpenDrainPin {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(OpenDrainPin::is_high(self))
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(OpenDrainPin::is_low(self))
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::digital::OutputPin for OpenDrainPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        OpenDrainPin::set_low(self);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        OpenDrainPin::set_high(self);
+        Ok(())
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use flipperzero_sys as sys;
+
+    use super::OpenDrainPin;
+
+    #[test]
+    fn drive_line() {
+        // Nothing is connected to the GPIO header during tests, so the internal pull-up
+        // keeps the released line high.
+        let mut pin = OpenDrainPin::new(unsafe { &sys::gpio_ext_pa7 });
+        assert!(pin.is_high());
+
+        pin.set_low();
+        assert!(pin.is_low());
+        pin.set_high();
+        assert!(pin.is_high());
+
+        #[cfg(feature = "embedded-hal")]
+        {
+            use embedded_hal::digital::{InputPin, OutputPin};
+
+            OutputPin::set_low(&mut pin).unwrap();
+            assert_eq!(InputPin::is_low(&mut pin), Ok(true));
+            OutputPin::set_high(&mut pin).unwrap();
+            assert_eq!(InputPin::is_high(&mut pin), Ok(true));
+        }
+    }
+}
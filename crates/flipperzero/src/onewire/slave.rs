@@ -0,0 +1,189 @@
+use alloc::boxed::Box;
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+
+use flipperzero_sys as sys;
+
+use super::{Error, Result};
+
+/// Handles the transactions of a [`OneWireSlave`].
+///
+/// Handlers are called from the GPIO interrupt, and must answer within the bus timings.
+pub trait SlaveHandler: Send {
+    /// Handles a reset pulse, returning `true` to answer it with a presence pulse.
+    ///
+    /// `is_short` is `true` for overdrive reset pulses. By default, only standard speed
+    /// resets are answered.
+    fn reset(&mut self, is_short: bool) -> bool {
+        !is_short
+    }
+
+    /// Handles a command, e.g. [`READ_ROM`](super::READ_ROM), transferring its data over
+    /// `bus`.
+    ///
+    /// Returns `true` to receive another command without a reset.
+    fn command(&mut self, bus: &mut SlaveBus, command: u8) -> bool;
+
+    /// Called once a transaction has ended.
+    fn result(&mut self) {}
+}
+
+/// The bus of a [`OneWireSlave`], during a command.
+pub struct SlaveBus {
+    raw: *mut sys::OneWireSlave,
+    // Only valid in the interrupt.
+    _marker: PhantomData<*mut ()>,
+}
+
+impl SlaveBus {
+    /// Sends a bit.
+    pub fn send_bit(&mut self, value: bool) -> Result<()> {
+        if unsafe { sys::onewire_slave_send_bit(self.raw, value) } {
+            Ok(())
+        } else {
+            Err(Error::Transfer)
+        }
+    }
+
+    /// Receives a bit.
+    pub fn receive_bit(&mut self) -> bool {
+        unsafe { sys::onewire_slave_receive_bit(self.raw) }
+    }
+
+    /// Sends the bytes of `data`, as the host reads them.
+    pub fn send(&mut self, data: &[u8]) -> Result<()> {
+        if unsafe { sys::onewire_slave_send(self.raw, data.as_ptr(), data.len()) } {
+            Ok(())
+        } else {
+            Err(Error::Transfer)
+        }
+    }
+
+    /// Receives bytes into `data`, as the host writes them.
+    pub fn receive(&mut self, data: &mut [u8]) -> Result<()> {
+        if unsafe { sys::onewire_slave_receive(self.raw, data.as_mut_ptr(), data.len()) } {
+            Ok(())
+        } else {
+            Err(Error::Transfer)
+        }
+    }
+
+    /// Enables or disables overdrive speed, e.g. after an overdrive ROM command.
+    pub fn set_overdrive(&mut self, overdrive: bool) {
+        unsafe { sys::onewire_slave_set_overdrive(self.raw, overdrive) };
+    }
+}
+
+struct Context<H> {
+    raw: *mut sys::OneWireSlave,
+    handler: H,
+}
+
+/// A device on a 1-Wire bus, whose transactions are handled by a [`SlaveHandler`].
+pub struct OneWireSlave<H: SlaveHandler> {
+    context: Box<Context<H>>,
+}
+
+impl<H: SlaveHandler> OneWireSlave<H> {
+    /// Takes over `pin` as a 1-Wire bus, and answers the host with `handler` until
+    /// dropped.
+    pub fn new(pin: &'static sys::GpioPin, handler: H) -> Self {
+        unsafe extern "C" fn reset_callback<H: SlaveHandler>(
+            is_short: bool,
+            context: *mut c_void,
+        ) -> bool {
+            let context = &mut *(context as *mut Context<H>);
+            context.handler.reset(is_short)
+        }
+
+        unsafe extern "C" fn command_callback<H: SlaveHandler>(
+            command: u8,
+            context: *mut c_void,
+        ) -> bool {
+            let context = &mut *(context as *mut Context<H>);
+            let mut bus = SlaveBus {
+                raw: context.raw,
+                _marker: PhantomData,
+            };
+            context.handler.command(&mut bus, command)
+        }
+
+        unsafe extern "C" fn result_callback<H: SlaveHandler>(context: *mut c_void) {
+            let context = &mut *(context as *mut Context<H>);
+            context.handler.result();
+        }
+
+        let raw = unsafe { sys::onewire_slave_alloc(pin) };
+        assert!(!raw.is_null());
+
+        let mut context = Box::new(Context { raw, handler });
+        let ctx = &mut *context as *mut Context<H> as *mut c_void;
+        unsafe {
+            sys::onewire_slave_set_reset_callback(raw, Some(reset_callback::<H>), ctx);
+            sys::onewire_slave_set_command_callback(raw, Some(command_callback::<H>), ctx);
+            sys::onewire_slave_set_result_callback(raw, Some(result_callback::<H>), ctx);
+            sys::onewire_slave_start(raw);
+        }
+
+        Self { context }
+    }
+
+    /// Stops answering the host and releases the pin, returning the handler.
+    pub fn into_handler(self) -> H {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never used or dropped again.
+        let context = unsafe { core::ptr::read(&this.context) };
+        unsafe { Self::release(context.raw) };
+        context.handler
+    }
+
+    unsafe fn release(raw: *mut sys::OneWireSlave) {
+        sys::onewire_slave_stop(raw);
+        sys::onewire_slave_free(raw);
+    }
+}
+
+impl<H: SlaveHandler> Drop for OneWireSlave<H> {
+    fn drop(&mut self) {
+        unsafe { Self::release(self.context.raw) };
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use flipperzero_sys as sys;
+
+    use super::{OneWireSlave, SlaveBus, SlaveHandler};
+    use crate::onewire::{Rom, READ_ROM};
+
+    struct Ds1990 {
+        rom: Rom,
+        commands: usize,
+    }
+
+    impl SlaveHandler for Ds1990 {
+        fn command(&mut self, bus: &mut SlaveBus, command: u8) -> bool {
+            self.commands += 1;
+            if command == READ_ROM {
+                let _ = bus.send(self.rom.as_bytes());
+            }
+            false
+        }
+    }
+
+    #[test]
+    fn start_and_stop() {
+        let mut handler = Ds1990 {
+            rom: Rom::new(0x01, [0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD]),
+            commands: 0,
+        };
+        assert!(handler.reset(false));
+        assert!(!handler.reset(true));
+
+        let slave = OneWireSlave::new(unsafe { &sys::gpio_ext_pa7 }, handler);
+        let handler = slave.into_handler();
+        assert_eq!(handler.rom.family_code(), 0x01);
+        drop(OneWireSlave::new(unsafe { &sys::gpio_ext_pa7 }, handler));
+    }
+}
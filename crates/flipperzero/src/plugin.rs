@@ -0,0 +1,238 @@
+//! Loading of application plugins.
+//!
+//! Plugins are FAPs (usually with the `.fal` extension) built with
+//! `flipperzero_rt::plugin!`, which export an entry point of a type shared with the
+//! host application.
+
+use core::ffi::CStr;
+use core::fmt;
+use core::marker::PhantomData;
+
+use flipperzero_sys as sys;
+
+/// Plugin Result type.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Plugin loading error kinds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A plugin could not be loaded, or the plugin directory could not be opened.
+    LoaderError,
+    /// A plugin was built for a different host application.
+    ApplicationIdMismatch,
+    /// A plugin was built for a different API version of the host application.
+    ApiVersionMismatch,
+}
+
+impl Error {
+    /// Convert from a SDK error.
+    pub fn from_sys(err: sys::PluginManagerError) -> Option<Self> {
+        match err {
+            sys::PluginManagerError_PluginManagerErrorLoaderError => Some(Self::LoaderError),
+            sys::PluginManagerError_PluginManagerErrorApplicationIdMismatch => {
+                Some(Self::ApplicationIdMismatch)
+            }
+            sys::PluginManagerError_PluginManagerErrorAPIVersionMismatch => {
+                Some(Self::ApiVersionMismatch)
+            }
+            _ => None,
+        }
+    }
+
+    /// Describes the error.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::LoaderError => "Failed to load plugin",
+            Self::ApplicationIdMismatch => "Plugin application ID mismatch",
+            Self::ApiVersionMismatch => "Plugin API version mismatch",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> core::result::Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+fn check(err: sys::PluginManagerError) -> Result<()> {
+    Error::from_sys(err).map_or(Ok(()), Err)
+}
+
+/// Resolves the symbols imported by plugins from several API interfaces.
+///
+/// Plugins that only use the firmware API can be loaded with [`PluginManager::new`].
+pub struct CompositeApiResolver {
+    raw: *mut sys::CompositeApiResolver,
+}
+
+impl CompositeApiResolver {
+    /// Creates a resolver for the firmware API.
+    pub fn new() -> Self {
+        let raw = unsafe { sys::composite_api_resolver_alloc() };
+        assert!(!raw.is_null());
+
+        let mut resolver = Self { raw };
+        unsafe { resolver.add(sys::firmware_api_interface) };
+        resolver
+    }
+
+    /// Adds an API interface, such as one exported by the host application.
+    ///
+    /// # Safety
+    ///
+    /// `interface` must point to a valid API interface that outlives the resolver.
+    pub unsafe fn add(&mut self, interface: *const sys::ElfApiInterface) {
+        sys::composite_api_resolver_add(self.raw, interface);
+    }
+
+    /// Obtain the combined API interface.
+    pub fn get(&self) -> *const sys::ElfApiInterface {
+        unsafe { sys::composite_api_resolver_get(self.raw) }
+    }
+}
+
+impl Default for CompositeApiResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for CompositeApiResolver {
+    fn drop(&mut self) {
+        unsafe { sys::composite_api_resolver_free(self.raw) };
+    }
+}
+
+/// Loads plugins implementing the API of a host application.
+///
+/// Each plugin exports an entry point of type `T`. Plugins are unloaded when the
+/// manager is dropped, so entry points are borrowed from the manager.
+pub struct PluginManager<T: 'static> {
+    raw: *mut sys::PluginManager,
+    _entry_point: PhantomData<&'static T>,
+}
+
+impl<T: 'static> PluginManager<T> {
+    /// Creates a manager for plugins of the application `app_id` that implement
+    /// `api_version`, and that only use the firmware API.
+    ///
+    /// # Safety
+    ///
+    /// Every plugin with this application ID and API version must export an entry
+    /// point of type `T`.
+    pub unsafe fn new(app_id: &CStr, api_version: u32) -> Self {
+        Self::with_api_interface(app_id, api_version, sys::firmware_api_interface)
+    }
+
+    /// Creates a manager for plugins of the application `app_id` that implement
+    /// `api_version`, resolving their imports with `api_interface`.
+    ///
+    /// # Safety
+    ///
+    /// Every plugin with this application ID and API version must export an entry
+    /// point of type `T`. `api_interface` (for example from
+    /// [`CompositeApiResolver::get`]) must outlive the manager.
+    pub unsafe fn with_api_interface(
+        app_id: &CStr,
+        api_version: u32,
+        api_interface: *const sys::ElfApiInterface,
+    ) -> Self {
+        let raw = sys::plugin_manager_alloc(app_id.as_ptr(), api_version, api_interface);
+        assert!(!raw.is_null());
+
+        Self {
+            raw,
+            _entry_point: PhantomData,
+        }
+    }
+
+    /// Loads the plugin at `path`.
+    pub fn load(&mut self, path: &CStr) -> Result<()> {
+        check(unsafe { sys::plugin_manager_load_single(self.raw, path.as_ptr()) })
+    }
+
+    /// Loads all plugins in the directory `path`.
+    ///
+    /// Loading stops at the first plugin that fails to load; plugins loaded before it
+    /// remain available.
+    pub fn load_all(&mut self, path: &CStr) -> Result<()> {
+        check(unsafe { sys::plugin_manager_load_all(self.raw, path.as_ptr()) })
+    }
+
+    /// Returns the number of loaded plugins.
+    pub fn len(&self) -> usize {
+        unsafe { sys::plugin_manager_get_count(self.raw) as usize }
+    }
+
+    /// Returns `true` if no plugins are loaded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the entry point of the plugin at `index`.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len() {
+            return None;
+        }
+
+        let entry_point = unsafe { sys::plugin_manager_get_ep(self.raw, index as u32) };
+        unsafe { (entry_point as *const T).as_ref() }
+    }
+
+    /// Returns the application ID reported by the plugin at `index`.
+    pub fn app_id(&self, index: usize) -> Option<&CStr> {
+        if index >= self.len() {
+            return None;
+        }
+
+        unsafe {
+            let descriptor = sys::plugin_manager_get(self.raw, index as u32).as_ref()?;
+            Some(CStr::from_ptr(descriptor.appid))
+        }
+    }
+
+    /// Iterates over the entry points of the loaded plugins.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        (0..self.len()).filter_map(|i| self.get(i))
+    }
+}
+
+impl<T: 'static> Drop for PluginManager<T> {
+    fn drop(&mut self) {
+        unsafe { sys::plugin_manager_free(self.raw) };
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::ffi::CStr;
+
+    use super::{Error, PluginManager};
+
+    #[test]
+    fn load_missing_directory() {
+        let mut manager: PluginManager<u32> = unsafe {
+            PluginManager::new(
+                CStr::from_bytes_with_nul(b"flipperzero_rs_test\0").unwrap(),
+                1,
+            )
+        };
+        assert_eq!(
+            manager.load_all(CStr::from_bytes_with_nul(b"/ext/flipperzero-rs-missing\0").unwrap()),
+            Err(Error::LoaderError)
+        );
+        assert!(manager.is_empty());
+        assert!(manager.get(0).is_none());
+    }
+}
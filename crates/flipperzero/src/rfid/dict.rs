@@ -0,0 +1,226 @@
+//! The data of each RFID protocol, and `.rfid` files.
+//!
+//! `.rfid` files hold the data of a single protocol:
+//!
+//! ```text
+//! Filetype: Flipper RFID key
+//! Version: 1
+//! Key type: EM4100
+//! Data: DC 69 66 0F 12
+//! ```
+
+use core::ffi::CStr;
+
+use flipperzero_sys as sys;
+
+use super::{Error, Protocol, Result};
+use crate::furi::string::FuriString;
+
+/// The data of every RFID protocol supported by the firmware.
+pub struct ProtocolDict {
+    raw: *mut sys::ProtocolDict,
+}
+
+impl ProtocolDict {
+    /// Creates a dictionary of the firmware's protocols, with empty data.
+    pub fn new() -> Self {
+        let raw = unsafe {
+            sys::protocol_dict_alloc(
+                core::ptr::addr_of_mut!(sys::lfrfid_protocols) as *mut *const sys::ProtocolBase,
+                sys::LFRFIDProtocol_LFRFIDProtocolMax.into(),
+            )
+        };
+        assert!(!raw.is_null());
+        Self { raw }
+    }
+
+    /// Returns the raw pointer to the dictionary.
+    pub fn as_ptr(&self) -> *mut sys::ProtocolDict {
+        self.raw
+    }
+
+    /// Returns the name of `protocol`, as saved in `.rfid` files.
+    pub fn name(&self, protocol: Protocol) -> &'static CStr {
+        unsafe { CStr::from_ptr(sys::protocol_dict_get_name(self.raw, protocol.index())) }
+    }
+
+    /// Returns the manufacturer of `protocol`'s cards.
+    pub fn manufacturer(&self, protocol: Protocol) -> &'static CStr {
+        unsafe {
+            CStr::from_ptr(sys::protocol_dict_get_manufacturer(
+                self.raw,
+                protocol.index(),
+            ))
+        }
+    }
+
+    /// Finds a protocol by its name.
+    pub fn find(&self, name: &CStr) -> Option<Protocol> {
+        Protocol::from_id(unsafe {
+            sys::protocol_dict_get_protocol_by_name(self.raw, name.as_ptr())
+        })
+    }
+
+    /// Returns the size of `protocol`'s data, in bytes.
+    pub fn data_size(&self, protocol: Protocol) -> usize {
+        unsafe { sys::protocol_dict_get_data_size(self.raw, protocol.index()) }
+    }
+
+    /// Returns the largest data size of all protocols, in bytes.
+    pub fn max_data_size(&self) -> usize {
+        unsafe { sys::protocol_dict_get_max_data_size(self.raw) }
+    }
+
+    /// Copies `protocol`'s data to `data`, which must be [`ProtocolDict::data_size`]
+    /// bytes long.
+    pub fn data(&self, protocol: Protocol, data: &mut [u8]) -> Result<()> {
+        if data.len() != self.data_size(protocol) {
+            return Err(Error::InvalidDataSize);
+        }
+
+        unsafe {
+            sys::protocol_dict_get_data(self.raw, protocol.index(), data.as_mut_ptr(), data.len())
+        };
+        Ok(())
+    }
+
+    /// Sets `protocol`'s data, which must be [`ProtocolDict::data_size`] bytes long.
+    pub fn set_data(&mut self, protocol: Protocol, data: &[u8]) -> Result<()> {
+        if data.len() != self.data_size(protocol) {
+            return Err(Error::InvalidDataSize);
+        }
+
+        unsafe {
+            sys::protocol_dict_set_data(self.raw, protocol.index(), data.as_ptr(), data.len())
+        };
+        Ok(())
+    }
+
+    /// Renders `protocol`'s data as text, e.g. the facility code and card number.
+    pub fn render_data(&self, protocol: Protocol, result: &mut FuriString) {
+        unsafe { sys::protocol_dict_render_data(self.raw, result.as_mut_ptr(), protocol.index()) };
+    }
+
+    /// Renders `protocol`'s data as a short text, for small displays.
+    pub fn render_brief_data(&self, protocol: Protocol, result: &mut FuriString) {
+        unsafe {
+            sys::protocol_dict_render_brief_data(self.raw, result.as_mut_ptr(), protocol.index())
+        };
+    }
+
+    /// Loads a `.rfid` file, returning its protocol and replacing that protocol's data.
+    pub fn load(&mut self, path: &CStr) -> Result<Protocol> {
+        Protocol::from_id(unsafe { sys::lfrfid_dict_file_load(self.raw, path.as_ptr()) })
+            .ok_or(Error::Load)
+    }
+
+    /// Saves `protocol`'s data to a `.rfid` file, replacing any existing file.
+    pub fn save(&self, protocol: Protocol, path: &CStr) -> Result<()> {
+        if unsafe { sys::lfrfid_dict_file_save(self.raw, protocol.to_id(), path.as_ptr()) } {
+            Ok(())
+        } else {
+            Err(Error::Save)
+        }
+    }
+}
+
+impl Default for ProtocolDict {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ProtocolDict {
+    fn drop(&mut self) {
+        unsafe { sys::protocol_dict_free(self.raw) };
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::ffi::CStr;
+
+    use super::ProtocolDict;
+    use crate::furi::string::FuriString;
+    use crate::io::Write;
+    use crate::rfid::{Error, Protocol};
+    use crate::storage::{self, OpenOptions};
+
+    const EM4100: &str = include_str!("../../../../fixtures/rfid/em4100.rfid");
+    const PATH: &CStr =
+        unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/flipperzero-rs.rfid\0") };
+
+    #[test]
+    fn protocol_names() {
+        let dict = ProtocolDict::new();
+        for protocol in Protocol::ALL {
+            assert_eq!(dict.find(dict.name(protocol)), Some(protocol));
+            assert!(dict.data_size(protocol) <= dict.max_data_size());
+        }
+        assert_eq!(
+            dict.name(Protocol::Em4100),
+            CStr::from_bytes_with_nul(b"EM4100\0").unwrap()
+        );
+        assert_eq!(
+            dict.manufacturer(Protocol::Em4100),
+            CStr::from_bytes_with_nul(b"EM-Micro\0").unwrap()
+        );
+        assert_eq!(dict.data_size(Protocol::Em4100), 5);
+        assert_eq!(
+            dict.find(CStr::from_bytes_with_nul(b"Unknown\0").unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn data_round_trip() {
+        let mut dict = ProtocolDict::new();
+        let data = [0xDC, 0x69, 0x66, 0x0F, 0x12];
+        dict.set_data(Protocol::Em4100, &data).unwrap();
+
+        let mut read = [0; 5];
+        dict.data(Protocol::Em4100, &mut read).unwrap();
+        assert_eq!(read, data);
+
+        let mut text = FuriString::new();
+        dict.render_data(Protocol::Em4100, &mut text);
+        assert!(!text.as_c_str().to_bytes().is_empty());
+
+        assert_eq!(
+            dict.set_data(Protocol::Em4100, &[0; 4]),
+            Err(Error::InvalidDataSize)
+        );
+        assert_eq!(
+            dict.data(Protocol::Em4100, &mut [0; 6]),
+            Err(Error::InvalidDataSize)
+        );
+    }
+
+    #[test]
+    fn load_and_save() {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_always(true)
+            .open(PATH)
+            .unwrap();
+        file.write_all(EM4100.as_bytes()).unwrap();
+        drop(file);
+
+        let mut dict = ProtocolDict::new();
+        assert_eq!(dict.load(PATH), Ok(Protocol::Em4100));
+        let mut data = [0; 5];
+        dict.data(Protocol::Em4100, &mut data).unwrap();
+        assert_eq!(data, [0xDC, 0x69, 0x66, 0x0F, 0x12]);
+
+        dict.set_data(Protocol::Em4100, &[0x01, 0x02, 0x03, 0x04, 0x05])
+            .unwrap();
+        dict.save(Protocol::Em4100, PATH).unwrap();
+
+        let mut saved = ProtocolDict::new();
+        assert_eq!(saved.load(PATH), Ok(Protocol::Em4100));
+        saved.data(Protocol::Em4100, &mut data).unwrap();
+        assert_eq!(data, [0x01, 0x02, 0x03, 0x04, 0x05]);
+
+        storage::remove(PATH).unwrap();
+    }
+}
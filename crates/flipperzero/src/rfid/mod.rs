@@ -0,0 +1,343 @@
+//! 125 kHz RFID reading, writing and emulation.
+//!
+//! Card data is kept in a [`dict::ProtocolDict`], which holds the data of each
+//! [`Protocol`] and saves and loads it as `.rfid` files. With the `alloc` feature, cards
+//! are read, written to T5577 tags and emulated by a [`worker::Worker`]. Raw captures
+//! are read with [`raw::RawFile`].
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use core::time::Duration;
+//!
+//! use flipperzero::furi::event::EventQueue;
+//! use flipperzero::furi::string::FuriString;
+//! use flipperzero::info;
+//! use flipperzero::rfid::dict::ProtocolDict;
+//! use flipperzero::rfid::worker::Worker;
+//! use flipperzero::rfid::{ReadEvent, ReadType};
+//!
+//! let queue = EventQueue::new(8);
+//! let mut worker = Worker::new(ProtocolDict::new());
+//!
+//! let reading = worker.read(ReadType::Auto, queue.clone());
+//! let protocol = loop {
+//!     if let Ok(ReadEvent::Done(protocol)) = queue.recv(Duration::MAX) {
+//!         break protocol;
+//!     }
+//! };
+//! drop(reading);
+//!
+//! let mut text = FuriString::new();
+//! worker.dict().render_data(protocol, &mut text);
+//! info!("rfid", "{}: {}", worker.dict().name(protocol), text.as_c_str());
+//! ```
+
+use core::fmt;
+
+use flipperzero_sys as sys;
+
+pub mod dict;
+pub mod raw;
+#[cfg(feature = "alloc")]
+pub mod worker;
+
+/// RFID Result type.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// RFID error kinds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The data size doesn't match the protocol.
+    InvalidDataSize,
+    /// The `.rfid` file couldn't be loaded, or has an unknown protocol.
+    Load,
+    /// The `.rfid` file couldn't be saved.
+    Save,
+    /// The raw file couldn't be opened, or has an invalid header.
+    RawFile,
+}
+
+impl Error {
+    /// Describes the error.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::InvalidDataSize => "Invalid data size",
+            Self::Load => "Failed to load RFID file",
+            Self::Save => "Failed to save RFID file",
+            Self::RawFile => "Invalid raw RFID file",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> core::result::Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+/// RFID protocols supported by the firmware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    Em4100,
+    H10301,
+    Idteck,
+    Indala26,
+    IoProxXsf,
+    Awid,
+    FdxA,
+    FdxB,
+    HidGeneric,
+    HidExGeneric,
+    Pyramid,
+    Viking,
+    Jablotron,
+    Paradox,
+    PacStanley,
+    Keri,
+    Gallagher,
+}
+
+impl Protocol {
+    /// All protocols, in firmware order.
+    pub const ALL: [Protocol; 17] = [
+        Protocol::Em4100,
+        Protocol::H10301,
+        Protocol::Idteck,
+        Protocol::Indala26,
+        Protocol::IoProxXsf,
+        Protocol::Awid,
+        Protocol::FdxA,
+        Protocol::FdxB,
+        Protocol::HidGeneric,
+        Protocol::HidExGeneric,
+        Protocol::Pyramid,
+        Protocol::Viking,
+        Protocol::Jablotron,
+        Protocol::Paradox,
+        Protocol::PacStanley,
+        Protocol::Keri,
+        Protocol::Gallagher,
+    ];
+
+    /// Get the protocol from the corresponding SDK `LFRFIDProtocol`.
+    pub fn from_sys(protocol: sys::LFRFIDProtocol) -> Option<Self> {
+        Self::ALL.get(usize::from(protocol)).copied()
+    }
+
+    /// Convert the protocol into the corresponding SDK `LFRFIDProtocol`.
+    pub fn to_sys(self) -> sys::LFRFIDProtocol {
+        match self {
+            Protocol::Em4100 => sys::LFRFIDProtocol_LFRFIDProtocolEM4100,
+            Protocol::H10301 => sys::LFRFIDProtocol_LFRFIDProtocolH10301,
+            Protocol::Idteck => sys::LFRFIDProtocol_LFRFIDProtocolIdteck,
+            Protocol::Indala26 => sys::LFRFIDProtocol_LFRFIDProtocolIndala26,
+            Protocol::IoProxXsf => sys::LFRFIDProtocol_LFRFIDProtocolIOProxXSF,
+            Protocol::Awid => sys::LFRFIDProtocol_LFRFIDProtocolAwid,
+            Protocol::FdxA => sys::LFRFIDProtocol_LFRFIDProtocolFDXA,
+            Protocol::FdxB => sys::LFRFIDProtocol_LFRFIDProtocolFDXB,
+            Protocol::HidGeneric => sys::LFRFIDProtocol_LFRFIDProtocolHidGeneric,
+            Protocol::HidExGeneric => sys::LFRFIDProtocol_LFRFIDProtocolHidExGeneric,
+            Protocol::Pyramid => sys::LFRFIDProtocol_LFRFIDProtocolPyramid,
+            Protocol::Viking => sys::LFRFIDProtocol_LFRFIDProtocolViking,
+            Protocol::Jablotron => sys::LFRFIDProtocol_LFRFIDProtocolJablotron,
+            Protocol::Paradox => sys::LFRFIDProtocol_LFRFIDProtocolParadox,
+            Protocol::PacStanley => sys::LFRFIDProtocol_LFRFIDProtocolPACStanley,
+            Protocol::Keri => sys::LFRFIDProtocol_LFRFIDProtocolKeri,
+            Protocol::Gallagher => sys::LFRFIDProtocol_LFRFIDProtocolGallagher,
+        }
+    }
+
+    /// Get the protocol from its SDK `ProtocolId`, as returned by protocol
+    /// dictionaries. Returns `None` for `PROTOCOL_NO` (-1).
+    pub fn from_id(id: sys::ProtocolId) -> Option<Self> {
+        u8::try_from(id).ok().and_then(Self::from_sys)
+    }
+
+    /// Convert the protocol into its SDK `ProtocolId`.
+    pub fn to_id(self) -> sys::ProtocolId {
+        self.to_sys().into()
+    }
+
+    /// Returns the index of the protocol in protocol dictionaries.
+    fn index(self) -> usize {
+        self.to_sys().into()
+    }
+}
+
+/// Modulations to read cards with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReadType {
+    /// Alternates between ASK and PSK.
+    Auto,
+    Ask,
+    Psk,
+}
+
+impl ReadType {
+    /// Convert the read type into the corresponding SDK `LFRFIDWorkerReadType`.
+    pub fn to_sys(self) -> sys::LFRFIDWorkerReadType {
+        match self {
+            ReadType::Auto => sys::LFRFIDWorkerReadType_LFRFIDWorkerReadTypeAuto,
+            ReadType::Ask => sys::LFRFIDWorkerReadType_LFRFIDWorkerReadTypeASKOnly,
+            ReadType::Psk => sys::LFRFIDWorkerReadType_LFRFIDWorkerReadTypePSKOnly,
+        }
+    }
+}
+
+/// Progress of reading a card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReadEvent {
+    /// A signal is being sensed.
+    SenseStart,
+    /// The signal was lost.
+    SenseEnd,
+    /// A card is being sensed.
+    SenseCardStart,
+    /// The card was lost.
+    SenseCardEnd,
+    /// Reading switched to ASK.
+    StartAsk,
+    /// Reading switched to PSK.
+    StartPsk,
+    /// A card was read, and its data stored in the dictionary.
+    Done(Protocol),
+}
+
+impl ReadEvent {
+    /// Get the event from the corresponding SDK `LFRFIDWorkerReadResult` and the
+    /// protocol that was read.
+    pub fn from_sys(
+        result: sys::LFRFIDWorkerReadResult,
+        protocol: sys::ProtocolId,
+    ) -> Option<Self> {
+        match result {
+            sys::LFRFIDWorkerReadResult_LFRFIDWorkerReadSenseStart => Some(ReadEvent::SenseStart),
+            sys::LFRFIDWorkerReadResult_LFRFIDWorkerReadSenseEnd => Some(ReadEvent::SenseEnd),
+            sys::LFRFIDWorkerReadResult_LFRFIDWorkerReadSenseCardStart => {
+                Some(ReadEvent::SenseCardStart)
+            }
+            sys::LFRFIDWorkerReadResult_LFRFIDWorkerReadSenseCardEnd => {
+                Some(ReadEvent::SenseCardEnd)
+            }
+            sys::LFRFIDWorkerReadResult_LFRFIDWorkerReadStartASK => Some(ReadEvent::StartAsk),
+            sys::LFRFIDWorkerReadResult_LFRFIDWorkerReadStartPSK => Some(ReadEvent::StartPsk),
+            sys::LFRFIDWorkerReadResult_LFRFIDWorkerReadDone => {
+                Protocol::from_id(protocol).map(ReadEvent::Done)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of an attempt to write a tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WriteEvent {
+    /// The tag was written and verified.
+    Ok,
+    /// The protocol can't be written to T5577 tags.
+    ProtocolCannotBeWritten,
+    /// The tag in the field can't be written.
+    FobCannotBeWritten,
+    /// Writing is taking too long; the tag is probably not a T5577.
+    TooLongToWrite,
+}
+
+impl WriteEvent {
+    /// Get the event from the corresponding SDK `LFRFIDWorkerWriteResult`.
+    pub fn from_sys(result: sys::LFRFIDWorkerWriteResult) -> Option<Self> {
+        match result {
+            sys::LFRFIDWorkerWriteResult_LFRFIDWorkerWriteOK => Some(WriteEvent::Ok),
+            sys::LFRFIDWorkerWriteResult_LFRFIDWorkerWriteProtocolCannotBeWritten => {
+                Some(WriteEvent::ProtocolCannotBeWritten)
+            }
+            sys::LFRFIDWorkerWriteResult_LFRFIDWorkerWriteFobCannotBeWritten => {
+                Some(WriteEvent::FobCannotBeWritten)
+            }
+            sys::LFRFIDWorkerWriteResult_LFRFIDWorkerWriteTooLongToWrite => {
+                Some(WriteEvent::TooLongToWrite)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Errors of raw reading and emulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RawEvent {
+    /// The raw file couldn't be read or written.
+    FileError,
+    /// Samples were dropped because the file couldn't keep up.
+    Overrun,
+}
+
+impl RawEvent {
+    /// Get the event from the corresponding SDK `LFRFIDWorkerReadRawResult`.
+    pub fn from_read_sys(result: sys::LFRFIDWorkerReadRawResult) -> Option<Self> {
+        match result {
+            sys::LFRFIDWorkerReadRawResult_LFRFIDWorkerReadRawFileError => {
+                Some(RawEvent::FileError)
+            }
+            sys::LFRFIDWorkerReadRawResult_LFRFIDWorkerReadRawOverrun => Some(RawEvent::Overrun),
+            _ => None,
+        }
+    }
+
+    /// Get the event from the corresponding SDK `LFRFIDWorkerEmulateRawResult`.
+    pub fn from_emulate_sys(result: sys::LFRFIDWorkerEmulateRawResult) -> Option<Self> {
+        match result {
+            sys::LFRFIDWorkerEmulateRawResult_LFRFIDWorkerEmulateRawFileError => {
+                Some(RawEvent::FileError)
+            }
+            sys::LFRFIDWorkerEmulateRawResult_LFRFIDWorkerEmulateRawOverrun => {
+                Some(RawEvent::Overrun)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use flipperzero_sys as sys;
+
+    use super::{Protocol, ReadEvent};
+
+    #[test]
+    fn protocol_ids_round_trip() {
+        for protocol in Protocol::ALL {
+            assert_eq!(Protocol::from_sys(protocol.to_sys()), Some(protocol));
+            assert_eq!(Protocol::from_id(protocol.to_id()), Some(protocol));
+        }
+        assert_eq!(Protocol::from_id(-1), None);
+        assert_eq!(
+            Protocol::from_sys(sys::LFRFIDProtocol_LFRFIDProtocolMax),
+            None
+        );
+    }
+
+    #[test]
+    fn read_done_event() {
+        assert_eq!(
+            ReadEvent::from_sys(
+                sys::LFRFIDWorkerReadResult_LFRFIDWorkerReadDone,
+                Protocol::FdxB.to_id()
+            ),
+            Some(ReadEvent::Done(Protocol::FdxB))
+        );
+        assert_eq!(
+            ReadEvent::from_sys(sys::LFRFIDWorkerReadResult_LFRFIDWorkerReadDone, -1),
+            None
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! Raw RFID captures, as recorded by [`Worker::read_raw`](super::worker::Worker::read_raw).
+//!
+//! Raw files store the carrier settings and the signal as pairs of pulse and period
+//! durations, in microseconds.
+
+use core::ffi::{c_char, CStr};
+
+use flipperzero_sys as sys;
+use flipperzero_sys::furi::UnsafeRecord;
+
+use super::{Error, Result};
+
+const RECORD_STORAGE: *const c_char = sys::c_string!("storage");
+
+/// A pulse of the signal: the carrier is on for `pulse` out of `duration`
+/// microseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RawPair {
+    pub duration: u32,
+    pub pulse: u32,
+}
+
+/// Reads a raw capture.
+///
+/// The file is iterated over as [`RawPair`]s, until its end.
+pub struct RawFile {
+    raw: *mut sys::LFRFIDRawFile,
+    frequency: f32,
+    duty_cycle: f32,
+    _storage: UnsafeRecord<sys::Storage>,
+}
+
+impl RawFile {
+    /// Opens a raw capture, reading its header.
+    pub fn open(path: &CStr) -> Result<Self> {
+        let storage = unsafe { UnsafeRecord::open(RECORD_STORAGE) };
+        let raw = unsafe { sys::lfrfid_raw_file_alloc(storage.as_ptr()) };
+        assert!(!raw.is_null());
+
+        let mut file = Self {
+            raw,
+            frequency: 0.0,
+            duty_cycle: 0.0,
+            _storage: storage,
+        };
+        let opened = unsafe {
+            sys::lfrfid_raw_file_open_read(raw, path.as_ptr())
+                && sys::lfrfid_raw_file_read_header(raw, &mut file.frequency, &mut file.duty_cycle)
+        };
+        if !opened {
+            return Err(Error::RawFile);
+        }
+
+        Ok(file)
+    }
+
+    /// Returns the carrier frequency the capture was recorded with, in Hz.
+    pub fn frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    /// Returns the carrier duty cycle the capture was recorded with.
+    pub fn duty_cycle(&self) -> f32 {
+        self.duty_cycle
+    }
+}
+
+impl Iterator for RawFile {
+    type Item = RawPair;
+
+    fn next(&mut self) -> Option<RawPair> {
+        let mut pair = RawPair {
+            duration: 0,
+            pulse: 0,
+        };
+        let mut pass_end = false;
+        let read = unsafe {
+            sys::lfrfid_raw_file_read_pair(
+                self.raw,
+                &mut pair.duration,
+                &mut pair.pulse,
+                &mut pass_end,
+            )
+        };
+
+        // The firmware rewinds at the end of the file, to emulate captures in a loop.
+        (read && !pass_end).then_some(pair)
+    }
+}
+
+impl Drop for RawFile {
+    fn drop(&mut self) {
+        unsafe { sys::lfrfid_raw_file_free(self.raw) };
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::ffi::CStr;
+
+    use super::RawFile;
+    use crate::rfid::Error;
+
+    #[test]
+    fn missing_file() {
+        assert!(matches!(
+            RawFile::open(CStr::from_bytes_with_nul(b"/ext/flipperzero-rs-missing.raw\0").unwrap()),
+            Err(Error::RawFile)
+        ));
+    }
+}
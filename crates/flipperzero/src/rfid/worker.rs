@@ -0,0 +1,217 @@
+//! Reading, writing and emulating cards on the RFID worker thread.
+//!
+//! Worker events are delivered from the worker thread to an [`EventSink`]: a closure,
+//! or an [`EventQueue`](crate::furi::event::EventQueue) to receive them from another
+//! thread.
+
+use alloc::boxed::Box;
+use core::ffi::{c_void, CStr};
+
+use flipperzero_sys as sys;
+
+use super::dict::ProtocolDict;
+use super::{Protocol, RawEvent, ReadEvent, ReadType, WriteEvent};
+use crate::furi::event::EventSink;
+
+type Sink<E> = Box<dyn EventSink<E>>;
+
+/// The RFID worker, and the protocol dictionary it reads cards into and writes and
+/// emulates cards from.
+pub struct Worker {
+    raw: *mut sys::LFRFIDWorker,
+    dict: ProtocolDict,
+    /// The `Box<Sink<E>>` of the running mode, dropped once the mode is stopped.
+    sink: Option<Box<dyn Send>>,
+}
+
+impl Worker {
+    /// Starts the worker thread.
+    pub fn new(dict: ProtocolDict) -> Self {
+        let raw = unsafe { sys::lfrfid_worker_alloc(dict.as_ptr()) };
+        assert!(!raw.is_null());
+        unsafe { sys::lfrfid_worker_start_thread(raw) };
+
+        Self {
+            raw,
+            dict,
+            sink: None,
+        }
+    }
+
+    /// Returns the protocol dictionary.
+    pub fn dict(&self) -> &ProtocolDict {
+        &self.dict
+    }
+
+    /// Returns the protocol dictionary, e.g. to set the data to write or emulate.
+    pub fn dict_mut(&mut self) -> &mut ProtocolDict {
+        &mut self.dict
+    }
+
+    /// Reads cards until the returned guard is dropped.
+    ///
+    /// Each card that is read is stored in the dictionary, and reported with
+    /// [`ReadEvent::Done`].
+    pub fn read(&mut self, read_type: ReadType, sink: impl EventSink<ReadEvent>) -> Running<'_> {
+        unsafe extern "C" fn callback(
+            result: sys::LFRFIDWorkerReadResult,
+            protocol: sys::ProtocolId,
+            context: *mut c_void,
+        ) {
+            let sink = &mut *(context as *mut Sink<ReadEvent>);
+            if let Some(event) = ReadEvent::from_sys(result, protocol) {
+                sink.send(event);
+            }
+        }
+
+        let context = self.set_sink(sink);
+        unsafe {
+            sys::lfrfid_worker_read_start(self.raw, read_type.to_sys(), Some(callback), context)
+        };
+        Running { worker: self }
+    }
+
+    /// Writes `protocol`'s data to a T5577 tag, retrying until the returned guard is
+    /// dropped.
+    pub fn write(&mut self, protocol: Protocol, sink: impl EventSink<WriteEvent>) -> Running<'_> {
+        unsafe extern "C" fn callback(result: sys::LFRFIDWorkerWriteResult, context: *mut c_void) {
+            let sink = &mut *(context as *mut Sink<WriteEvent>);
+            if let Some(event) = WriteEvent::from_sys(result) {
+                sink.send(event);
+            }
+        }
+
+        let context = self.set_sink(sink);
+        unsafe {
+            sys::lfrfid_worker_write_start(self.raw, protocol.to_sys(), Some(callback), context)
+        };
+        Running { worker: self }
+    }
+
+    /// Emulates `protocol`'s data until the returned guard is dropped.
+    pub fn emulate(&mut self, protocol: Protocol) -> Running<'_> {
+        self.stop();
+        unsafe { sys::lfrfid_worker_emulate_start(self.raw, protocol.to_sys()) };
+        Running { worker: self }
+    }
+
+    /// Records the raw signal to the file at `path` until the returned guard is
+    /// dropped.
+    pub fn read_raw(
+        &mut self,
+        path: &CStr,
+        read_type: ReadType,
+        sink: impl EventSink<RawEvent>,
+    ) -> Running<'_> {
+        unsafe extern "C" fn callback(
+            result: sys::LFRFIDWorkerReadRawResult,
+            context: *mut c_void,
+        ) {
+            let sink = &mut *(context as *mut Sink<RawEvent>);
+            if let Some(event) = RawEvent::from_read_sys(result) {
+                sink.send(event);
+            }
+        }
+
+        let context = self.set_sink(sink);
+        // The worker copies the path.
+        unsafe {
+            sys::lfrfid_worker_read_raw_start(
+                self.raw,
+                path.as_ptr(),
+                read_type.to_sys(),
+                Some(callback),
+                context,
+            )
+        };
+        Running { worker: self }
+    }
+
+    /// Emulates the raw signal in the file at `path`, in a loop, until the returned
+    /// guard is dropped.
+    pub fn emulate_raw(&mut self, path: &CStr, sink: impl EventSink<RawEvent>) -> Running<'_> {
+        unsafe extern "C" fn callback(
+            result: sys::LFRFIDWorkerEmulateRawResult,
+            context: *mut c_void,
+        ) {
+            let sink = &mut *(context as *mut Sink<RawEvent>);
+            if let Some(event) = RawEvent::from_emulate_sys(result) {
+                sink.send(event);
+            }
+        }
+
+        let context = self.set_sink(sink);
+        unsafe {
+            sys::lfrfid_worker_emulate_raw_start(self.raw, path.as_ptr(), Some(callback), context)
+        };
+        Running { worker: self }
+    }
+
+    /// Stops the running mode, if any.
+    ///
+    /// This waits for the worker thread to finish, so that the mode's event sink is no
+    /// longer used.
+    pub fn stop(&mut self) {
+        unsafe {
+            sys::lfrfid_worker_stop(self.raw);
+            sys::lfrfid_worker_stop_thread(self.raw);
+            sys::lfrfid_worker_start_thread(self.raw);
+        }
+        self.sink = None;
+    }
+
+    /// Stops the running mode, and stores `sink` for the next one.
+    fn set_sink<E: 'static>(&mut self, sink: impl EventSink<E>) -> *mut c_void {
+        self.stop();
+
+        let mut sink: Box<Sink<E>> = Box::new(Box::new(sink));
+        let context = &mut *sink as *mut Sink<E> as *mut c_void;
+        self.sink = Some(sink);
+        context
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        unsafe {
+            sys::lfrfid_worker_stop(self.raw);
+            sys::lfrfid_worker_stop_thread(self.raw);
+            sys::lfrfid_worker_free(self.raw);
+        }
+    }
+}
+
+/// A running worker mode, stopped when dropped.
+pub struct Running<'a> {
+    worker: &'a mut Worker,
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        self.worker.stop();
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::Worker;
+    use crate::furi::event::EventQueue;
+    use crate::rfid::dict::ProtocolDict;
+    use crate::rfid::{Protocol, ReadEvent, ReadType};
+
+    #[test]
+    fn start_and_stop() {
+        let mut worker = Worker::new(ProtocolDict::new());
+        let queue = EventQueue::new(8);
+        drop(worker.read(ReadType::Auto, queue.clone()));
+
+        worker
+            .dict_mut()
+            .set_data(Protocol::Em4100, &[0xDC, 0x69, 0x66, 0x0F, 0x12])
+            .unwrap();
+        drop(worker.emulate(Protocol::Em4100));
+
+        // Closures are sinks too.
+        drop(worker.read(ReadType::Ask, |_: ReadEvent| {}));
+    }
+}
@@ -0,0 +1,709 @@
+use core::ffi::{c_char, c_void, CStr};
+
+use flipperzero_sys as sys;
+use flipperzero_sys::furi::UnsafeRecord;
+
+use crate::furi::string::FuriString;
+use crate::io::*;
+
+pub(crate) mod settings;
+pub use settings::{Migration, Settings, SettingsData};
+
+const RECORD_STORAGE: *const c_char = sys::c_string!("storage");
+
+/// Path alias for the data directory of the running application.
+///
+/// The firmware maps this to a per-application directory on the SD card, so files
+/// under this path do not collide with other applications.
+pub const APP_DATA_PATH: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"/data\0") };
+
+/// Resolves the data directory of the running application.
+///
+/// This expands the [`APP_DATA_PATH`] alias into its real location on the SD card
+/// (for example, `/ext/apps_data/my_app`), creating the directory if it does not exist.
+pub fn app_data_dir() -> FuriString {
+    let mut path = FuriString::from_c_str(APP_DATA_PATH);
+    unsafe {
+        let record: UnsafeRecord<sys::Storage> = UnsafeRecord::open(RECORD_STORAGE);
+        sys::storage_common_resolve_path_and_ensure_app_directory(
+            record.as_ptr(),
+            path.as_mut_ptr(),
+        );
+    }
+
+    path
+}
+
+/// Flag set in [`sys::FileInfo`] for directories (`FSF_DIRECTORY`).
+const FSF_DIRECTORY: u8 = 1 << 0;
+
+/// Metadata information about a file or directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    len: u64,
+    is_dir: bool,
+}
+
+impl Metadata {
+    /// Size of the file in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if this metadata is for a directory.
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    /// Returns `true` if this metadata is for a regular file.
+    pub fn is_file(&self) -> bool {
+        !self.is_dir
+    }
+}
+
+/// Queries metadata about the file or directory at `path`.
+pub fn metadata(path: &CStr) -> Result<Metadata, Error> {
+    let mut info = sys::FileInfo { flags: 0, size: 0 };
+    let error = unsafe {
+        let record: UnsafeRecord<sys::Storage> = UnsafeRecord::open(RECORD_STORAGE);
+        sys::storage_common_stat(record.as_ptr(), path.as_ptr(), &mut info)
+    };
+
+    check(error)?;
+    Ok(Metadata {
+        len: info.size,
+        is_dir: info.flags & FSF_DIRECTORY != 0,
+    })
+}
+
+/// Removes the file or empty directory at `path`.
+///
+/// Succeeds if nothing exists at `path`.
+pub fn remove(path: &CStr) -> Result<(), Error> {
+    let error = unsafe {
+        let record: UnsafeRecord<sys::Storage> = UnsafeRecord::open(RECORD_STORAGE);
+        sys::storage_common_remove(record.as_ptr(), path.as_ptr())
+    };
+
+    match check(error) {
+        Err(Error::NotExists) => Ok(()),
+        result => result,
+    }
+}
+
+/// Suffix of the file that [`write_atomic`] writes the new contents to.
+const TEMP_SUFFIX: &str = ".tmp";
+/// Suffix the temporary file is renamed to once it is complete.
+const COMPLETE_SUFFIX: &str = ".new";
+
+/// Atomically replaces the contents of the file at `path`.
+///
+/// The contents are written by `f` to a temporary sibling file (`path` with a `.tmp`
+/// suffix), which is closed to commit it to the storage device. It is then renamed to
+/// mark it as complete (`path` with a `.new` suffix), and finally renamed over `path`.
+/// If power is lost at any point, either the old or the new contents are left behind,
+/// once [`recover_atomic_write`] has been called on the next start.
+///
+/// If `f` returns an error, the temporary file is removed and `path` is unchanged.
+pub fn write_atomic<T>(
+    path: &CStr,
+    f: impl FnOnce(&mut File) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let temp = sibling_path(path, TEMP_SUFFIX);
+    let complete = sibling_path(path, COMPLETE_SUFFIX);
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_always(true)
+        .open(temp.as_c_str())?;
+
+    let value = match f(&mut file).and_then(|value| file.close().map(|()| value)) {
+        Ok(value) => value,
+        Err(error) => {
+            drop(file);
+            let _ = remove(temp.as_c_str());
+            return Err(error);
+        }
+    };
+    drop(file);
+
+    replace(temp.as_c_str(), complete.as_c_str())?;
+    replace(complete.as_c_str(), path)?;
+
+    Ok(value)
+}
+
+/// Completes or rolls back a [`write_atomic`] that was interrupted.
+///
+/// This should be called on startup before reading `path`. If the new contents had
+/// been fully written, they are moved into place; otherwise the partially written
+/// temporary file is removed and the old contents are kept.
+pub fn recover_atomic_write(path: &CStr) -> Result<(), Error> {
+    let temp = sibling_path(path, TEMP_SUFFIX);
+    let complete = sibling_path(path, COMPLETE_SUFFIX);
+
+    // The temporary file is only renamed once it has been closed, so it may be
+    // incomplete, even if there is no old file.
+    remove(temp.as_c_str())?;
+
+    if metadata(complete.as_c_str()).is_ok() {
+        replace(complete.as_c_str(), path)?;
+    }
+
+    Ok(())
+}
+
+/// Renames `from` over `to`, removing `to` first.
+fn replace(from: &CStr, to: &CStr) -> Result<(), Error> {
+    // Renaming does not reliably replace an existing file.
+    remove(to)?;
+    rename(from, to)
+}
+
+fn rename(from: &CStr, to: &CStr) -> Result<(), Error> {
+    check(unsafe {
+        let record: UnsafeRecord<sys::Storage> = UnsafeRecord::open(RECORD_STORAGE);
+        sys::storage_common_rename(record.as_ptr(), from.as_ptr(), to.as_ptr())
+    })
+}
+
+fn sibling_path(path: &CStr, suffix: &str) -> FuriString {
+    let mut sibling = FuriString::from_c_str(path);
+    sibling.push_str(suffix);
+    sibling
+}
+
+fn check(error: sys::FS_Error) -> Result<(), Error> {
+    match Error::from_sys(error) {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OpenOptions {
+    access_mode: u8,
+    open_mode: u8,
+}
+
+impl OpenOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn from_parts(access_mode: u8, open_mode: u8) -> Self {
+        OpenOptions {
+            access_mode,
+            open_mode,
+        }
+    }
+
+    /// Read access
+    pub fn read(self, set: bool) -> Self {
+        OpenOptions::from_parts(
+            if set {
+                self.access_mode | sys::FS_AccessMode_FSAM_READ
+            } else {
+                self.access_mode & !sys::FS_AccessMode_FSAM_READ
+            },
+            self.open_mode,
+        )
+    }
+
+    /// Write access
+    pub fn write(self, set: bool) -> Self {
+        OpenOptions::from_parts(
+            if set {
+                self.access_mode | sys::FS_AccessMode_FSAM_WRITE
+            } else {
+                self.access_mode & !sys::FS_AccessMode_FSAM_WRITE
+            },
+            self.open_mode,
+        )
+    }
+
+    /// Open file, fail if file doesn't exist
+    pub fn open_existing(self, set: bool) -> Self {
+        OpenOptions::from_parts(
+            self.access_mode,
+            if set {
+                self.open_mode | sys::FS_OpenMode_FSOM_OPEN_EXISTING
+            } else {
+                self.open_mode & !sys::FS_OpenMode_FSOM_OPEN_EXISTING
+            },
+        )
+    }
+
+    /// Open file. Create new file if not exist
+    pub fn open_always(self, set: bool) -> Self {
+        OpenOptions::from_parts(
+            self.access_mode,
+            if set {
+                self.open_mode | sys::FS_OpenMode_FSOM_OPEN_ALWAYS
+            } else {
+                self.open_mode & !sys::FS_OpenMode_FSOM_OPEN_ALWAYS
+            },
+        )
+    }
+
+    /// Open file. Create new file if not exist. Set R/W pointer to EOF
+    pub fn open_append(self, set: bool) -> Self {
+        OpenOptions::from_parts(
+            self.access_mode,
+            if set {
+                self.open_mode | sys::FS_OpenMode_FSOM_OPEN_APPEND
+            } else {
+                self.open_mode & !sys::FS_OpenMode_FSOM_OPEN_APPEND
+            },
+        )
+    }
+
+    /// Creates a new file. Fails if the file is exist
+    pub fn create_new(self, set: bool) -> Self {
+        OpenOptions::from_parts(
+            self.access_mode,
+            if set {
+                self.open_mode | sys::FS_OpenMode_FSOM_CREATE_NEW
+            } else {
+                self.open_mode & !sys::FS_OpenMode_FSOM_CREATE_NEW
+            },
+        )
+    }
+
+    /// Creates a new file. If file exist, truncate to zero size
+    pub fn create_always(self, set: bool) -> Self {
+        OpenOptions::from_parts(
+            self.access_mode,
+            if set {
+                self.open_mode | sys::FS_OpenMode_FSOM_CREATE_ALWAYS
+            } else {
+                self.open_mode & !sys::FS_OpenMode_FSOM_CREATE_ALWAYS
+            },
+        )
+    }
+
+    /// Returns the access mode and the canonicalized open mode.
+    pub(crate) fn modes(self) -> (u8, u8) {
+        // It's possible to produce a nonsensical `open_mode` using the above
+        // operations, so we have some logic here to drop any extraneous
+        // information. The possible open modes form a partial order (for
+        // example, `create_new` is more specialized than `truncate`) so we
+        // search for the first "on" bit in this sequence, and use that as the
+        // open mode.
+        let canonicalized_open_mode = if self.open_mode & sys::FS_OpenMode_FSOM_CREATE_NEW != 0 {
+            sys::FS_OpenMode_FSOM_CREATE_NEW
+        } else if self.open_mode & sys::FS_OpenMode_FSOM_CREATE_ALWAYS != 0 {
+            sys::FS_OpenMode_FSOM_CREATE_ALWAYS
+        } else if self.open_mode & sys::FS_OpenMode_FSOM_OPEN_APPEND != 0 {
+            sys::FS_OpenMode_FSOM_OPEN_APPEND
+        } else if self.open_mode & sys::FS_OpenMode_FSOM_OPEN_ALWAYS != 0 {
+            sys::FS_OpenMode_FSOM_OPEN_ALWAYS
+        } else {
+            sys::FS_OpenMode_FSOM_OPEN_EXISTING
+        };
+
+        (self.access_mode, canonicalized_open_mode)
+    }
+
+    pub fn open(self, path: &CStr) -> Result<File, Error> {
+        let (access_mode, open_mode) = self.modes();
+
+        let mut f = File::new();
+        if unsafe {
+            sys::storage_file_open(
+                f.raw,
+                path.as_ptr() as *const c_char,
+                access_mode,
+                open_mode,
+            )
+        } {
+            // Remembered so that `File::sync_all` can reopen the file.
+            f.path.set_c_str(path);
+            f.access_mode = access_mode;
+            Ok(f)
+        } else {
+            // Per docs, "you need to close the file even if the open operation
+            // failed," but this is handled by `Drop`.
+            Err(Error::from_sys(unsafe { sys::storage_file_get_error(f.raw) }).unwrap())
+        }
+    }
+}
+
+/// Basic, unbuffered file handle
+pub struct File {
+    raw: *mut sys::File,
+    path: FuriString,
+    access_mode: u8,
+    _storage: UnsafeRecord<sys::Storage>,
+}
+
+impl File {
+    pub fn new() -> Self {
+        unsafe {
+            let record = UnsafeRecord::open(RECORD_STORAGE);
+            File {
+                raw: sys::storage_file_alloc(record.as_ptr()),
+                path: FuriString::new(),
+                access_mode: 0,
+                _storage: record,
+            }
+        }
+    }
+
+    /// Flushes all written data to the storage device.
+    ///
+    /// The storage API only commits file data when a file is closed, so this closes
+    /// the file and reopens it at the current position.
+    pub fn sync_all(&mut self) -> Result<(), Error> {
+        if !unsafe { sys::storage_file_is_open(self.raw) } {
+            return Ok(());
+        }
+
+        let position = unsafe { sys::storage_file_tell(self.raw) };
+        self.close()?;
+
+        unsafe {
+            if !sys::storage_file_open(
+                self.raw,
+                self.path.as_c_str().as_ptr(),
+                self.access_mode,
+                sys::FS_OpenMode_FSOM_OPEN_EXISTING,
+            ) {
+                return Err(self.last_error());
+            }
+
+            // The position always fits, as it was read from the same file.
+            if !sys::storage_file_seek(self.raw, position as u32, true) {
+                return Err(self.last_error());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Truncates or extends the file to `size` bytes.
+    ///
+    /// Extended files are padded with zeros. The current position is not changed.
+    pub fn set_len(&mut self, size: u64) -> Result<(), Error> {
+        let size: usize = size.try_into().map_err(|_| Error::InvalidParameter)?;
+        let position = self.stream_position()?;
+        let len = self.stream_len()?;
+
+        if size < len {
+            self.seek(SeekFrom::Start(size as u64))?;
+            if !unsafe { sys::storage_file_truncate(self.raw) } {
+                return Err(self.last_error());
+            }
+        } else if size > len {
+            const ZEROS: [u8; 64] = [0; 64];
+
+            self.seek(SeekFrom::Start(len as u64))?;
+            let mut remaining = size - len;
+            while remaining > 0 {
+                let n = remaining.min(ZEROS.len());
+                self.write_all(&ZEROS[..n])?;
+                remaining -= n;
+            }
+        }
+
+        self.seek(SeekFrom::Start(position as u64)).map(|_| ())
+    }
+
+    /// Queries metadata about the open file.
+    pub fn metadata(&self) -> Result<Metadata, Error> {
+        let (len, is_dir) = unsafe {
+            (
+                sys::storage_file_size(self.raw),
+                sys::storage_file_is_dir(self.raw),
+            )
+        };
+
+        check(unsafe { sys::storage_file_get_error(self.raw) })?;
+        Ok(Metadata { len, is_dir })
+    }
+
+    /// Closes the file, reporting any error that occurs while committing its data.
+    fn close(&mut self) -> Result<(), Error> {
+        if unsafe { sys::storage_file_close(self.raw) } {
+            Ok(())
+        } else {
+            Err(self.last_error())
+        }
+    }
+
+    fn last_error(&self) -> Error {
+        Error::from_sys(unsafe { sys::storage_file_get_error(self.raw) }).unwrap_or(Error::Internal)
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        unsafe {
+            // `storage_file_close` calls `storage_file_sync`
+            // internally, so it's not necesssary to call it here.
+            sys::storage_file_close(self.raw);
+        }
+    }
+}
+
+impl Read for File {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let to_read = buf.len().try_into().map_err(|_| Error::InvalidParameter)?;
+        let bytes_read =
+            unsafe { sys::storage_file_read(self.raw, buf.as_mut_ptr() as *mut c_void, to_read) };
+        let error = unsafe { sys::storage_file_get_error(self.raw) };
+
+        if error == sys::FS_Error_FSE_OK {
+            Ok(bytes_read as usize)
+        } else {
+            Err(Error::from_sys(error).unwrap())
+        }
+    }
+}
+
+impl Seek for File {
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error> {
+        let (from_start, offset) = match pos {
+            SeekFrom::Start(n) => (true, n.try_into().map_err(|_| Error::InvalidParameter)?),
+            SeekFrom::Current(n) => (false, n.try_into().map_err(|_| Error::InvalidParameter)?),
+            SeekFrom::End(n) => {
+                // TODO: Per str4d, "for SeekFrom::End we will need to measure
+                // the length of the file, and then use from_start = true and
+                // offset = file_length - n."
+                //
+                // How can we perform this subtraction safely?
+                let file_length: i64 = self.stream_len()?.try_into().unwrap();
+                (
+                    true,
+                    (file_length - n)
+                        .try_into()
+                        .map_err(|_| Error::InvalidParameter)?,
+                )
+            }
+        };
+        unsafe {
+            if sys::storage_file_seek(self.raw, offset, from_start) {
+                Ok(sys::storage_file_tell(self.raw)
+                    .try_into()
+                    .map_err(|_| Error::InvalidParameter)?)
+            } else {
+                Err(Error::from_sys(sys::storage_file_get_error(self.raw)).unwrap())
+            }
+        }
+    }
+
+    fn rewind(&mut self) -> Result<(), Error> {
+        self.seek(SeekFrom::Start(0)).map(|_| {})
+    }
+
+    fn stream_len(&mut self) -> Result<usize, Error> {
+        Ok(unsafe {
+            sys::storage_file_size(self.raw)
+                .try_into()
+                .map_err(|_| Error::InvalidParameter)?
+        })
+    }
+
+    fn stream_position(&mut self) -> Result<usize, Error> {
+        Ok(unsafe {
+            sys::storage_file_tell(self.raw)
+                .try_into()
+                .map_err(|_| Error::InvalidParameter)?
+        })
+    }
+}
+
+impl Write for File {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let to_write = buf.len().try_into().map_err(|_| Error::InvalidParameter)?;
+        let bytes_written =
+            unsafe { sys::storage_file_write(self.raw, buf.as_ptr() as *mut c_void, to_write) };
+        let error = unsafe { sys::storage_file_get_error(self.raw) };
+
+        if error == sys::FS_Error_FSE_OK {
+            Ok(bytes_written as usize)
+        } else {
+            Err(Error::from_sys(error).unwrap())
+        }
+    }
+
+    /// Commits all written data to the storage device, like [`File::sync_all`].
+    ///
+    /// The SDK doesn't export `storage_file_sync`, so this closes and reopens the file.
+    fn flush(&mut self) -> Result<(), Error> {
+        self.sync_all()
+    }
+}
+
+impl Default for File {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::ffi::CStr;
+
+    use super::{
+        metadata, recover_atomic_write, remove, sibling_path, write_atomic, OpenOptions,
+        COMPLETE_SUFFIX, TEMP_SUFFIX,
+    };
+    use crate::io::{Error, Read, Seek, Write};
+
+    const PATH: &CStr =
+        unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/flipperzero-rs-storage\0") };
+
+    fn write(path: &CStr, contents: &[u8]) {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_always(true)
+            .open(path)
+            .unwrap();
+        file.write_all(contents).unwrap();
+    }
+
+    fn read_all(path: &CStr, buf: &mut [u8]) -> usize {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .open_existing(true)
+            .open(path)
+            .unwrap();
+        file.read(buf).unwrap()
+    }
+
+    #[test]
+    fn remove_missing_file() {
+        remove(PATH).unwrap();
+        remove(PATH).unwrap();
+        assert!(matches!(metadata(PATH), Err(Error::NotExists)));
+    }
+
+    #[test]
+    fn flush_keeps_position() {
+        remove(PATH).unwrap();
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_always(true)
+            .open(PATH)
+            .unwrap();
+        file.write_all(b"hello").unwrap();
+        file.flush().unwrap();
+        assert_eq!(file.stream_position().unwrap(), 5);
+        file.write_all(b" world").unwrap();
+        drop(file);
+
+        let mut buf = [0; 16];
+        let n = read_all(PATH, &mut buf);
+        assert_eq!(&buf[..n], b"hello world");
+
+        remove(PATH).unwrap();
+    }
+
+    #[test]
+    fn set_len_and_metadata() {
+        remove(PATH).unwrap();
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_always(true)
+            .open(PATH)
+            .unwrap();
+        assert!(file.metadata().unwrap().is_empty());
+        file.write_all(b"hello world").unwrap();
+        assert_eq!(file.metadata().unwrap().len(), 11);
+
+        file.set_len(5).unwrap();
+        assert_eq!(file.metadata().unwrap().len(), 5);
+        assert_eq!(file.stream_position().unwrap(), 5);
+
+        file.set_len(8).unwrap();
+        file.rewind().unwrap();
+        let mut buf = [0xff; 16];
+        assert_eq!(file.read(&mut buf).unwrap(), 8);
+        assert_eq!(&buf[..8], b"hello\0\0\0");
+        drop(file);
+
+        let info = metadata(PATH).unwrap();
+        assert!(info.is_file());
+        assert!(!info.is_empty());
+        assert_eq!(info.len(), 8);
+
+        remove(PATH).unwrap();
+    }
+
+    #[test]
+    fn write_atomic_replaces_contents() {
+        remove(PATH).unwrap();
+
+        write_atomic(PATH, |file| file.write_all(b"first")).unwrap();
+        write_atomic(PATH, |file| file.write_all(b"second")).unwrap();
+
+        let mut buf = [0; 16];
+        let n = read_all(PATH, &mut buf);
+        assert_eq!(&buf[..n], b"second");
+
+        // A failed write leaves the old contents in place.
+        assert!(matches!(
+            write_atomic(PATH, |file| {
+                file.write_all(b"third")?;
+                Err::<(), _>(Error::Internal)
+            }),
+            Err(Error::Internal)
+        ));
+        let n = read_all(PATH, &mut buf);
+        assert_eq!(&buf[..n], b"second");
+        assert!(matches!(
+            metadata(sibling_path(PATH, TEMP_SUFFIX).as_c_str()),
+            Err(Error::NotExists)
+        ));
+
+        remove(PATH).unwrap();
+    }
+
+    #[test]
+    fn recover_interrupted_write() {
+        let temp = sibling_path(PATH, TEMP_SUFFIX);
+        let complete = sibling_path(PATH, COMPLETE_SUFFIX);
+        let mut buf = [0; 16];
+        remove(PATH).unwrap();
+
+        // Interrupted while writing the first contents.
+        write(temp.as_c_str(), b"partial");
+        recover_atomic_write(PATH).unwrap();
+        assert!(matches!(metadata(PATH), Err(Error::NotExists)));
+        assert!(matches!(metadata(temp.as_c_str()), Err(Error::NotExists)));
+
+        // Interrupted after the first contents were complete.
+        write(complete.as_c_str(), b"old");
+        recover_atomic_write(PATH).unwrap();
+        let n = read_all(PATH, &mut buf);
+        assert_eq!(&buf[..n], b"old");
+
+        // Interrupted while writing new contents.
+        write(temp.as_c_str(), b"partial");
+        recover_atomic_write(PATH).unwrap();
+        let n = read_all(PATH, &mut buf);
+        assert_eq!(&buf[..n], b"old");
+        assert!(matches!(metadata(temp.as_c_str()), Err(Error::NotExists)));
+
+        // Interrupted after the new contents were complete.
+        write(complete.as_c_str(), b"new");
+        recover_atomic_write(PATH).unwrap();
+        let n = read_all(PATH, &mut buf);
+        assert_eq!(&buf[..n], b"new");
+        assert!(matches!(
+            metadata(complete.as_c_str()),
+            Err(Error::NotExists)
+        ));
+
+        remove(PATH).unwrap();
+    }
+}
@@ -0,0 +1,315 @@
+//! Persistent application settings.
+
+use core::ffi::{c_void, CStr};
+use core::marker::PhantomData;
+use core::mem::{self, MaybeUninit};
+
+use flipperzero_sys as sys;
+
+use super::OpenOptions;
+use crate::io::{Error, Read};
+
+/// Plain data that can be persisted with [`Settings`].
+///
+/// Settings are stored as a raw copy of the struct, prefixed by a header containing
+/// [`SettingsData::MAGIC`], [`SettingsData::VERSION`] and a checksum. Stored settings
+/// are only loaded if all three match.
+///
+/// # Safety
+///
+/// The implementing type must be `#[repr(C)]` and must only contain fields for which
+/// every bit pattern is valid (integers, floats and arrays of them). In particular, it
+/// must not contain pointers, references, `bool`s or enums.
+///
+/// # Examples
+///
+/// ```
+/// #[repr(C)]
+/// #[derive(Clone, Copy, Default)]
+/// struct ConfigV1 {
+///     volume: u8,
+/// }
+///
+/// unsafe impl SettingsData for ConfigV1 {
+///     const MAGIC: u8 = 0x42;
+///     const VERSION: u8 = 1;
+/// }
+///
+/// #[repr(C)]
+/// #[derive(Clone, Copy, Default)]
+/// struct ConfigV2 {
+///     volume: u8,
+///     brightness: u8,
+/// }
+///
+/// unsafe impl SettingsData for ConfigV2 {
+///     const MAGIC: u8 = 0x42;
+///     const VERSION: u8 = 2;
+///
+///     fn migrate(old: Migration<'_>) -> Option<Self> {
+///         match old.version() {
+///             1 => old.load::<ConfigV1>().map(|v1| ConfigV2 {
+///                 volume: v1.volume,
+///                 ..Default::default()
+///             }),
+///             _ => None,
+///         }
+///     }
+/// }
+/// ```
+pub unsafe trait SettingsData: Copy + Default {
+    /// Identifies the kind of struct stored in the file.
+    const MAGIC: u8;
+
+    /// Layout version of the struct.
+    ///
+    /// This must be changed whenever the fields of the struct change.
+    const VERSION: u8;
+
+    /// Converts settings that were saved with a different [`SettingsData::VERSION`].
+    ///
+    /// Returning `None` discards the stored settings. By default, no migrations are
+    /// supported.
+    fn migrate(old: Migration<'_>) -> Option<Self> {
+        let _ = old;
+        None
+    }
+}
+
+/// Settings saved with an older version of a [`SettingsData`] type.
+pub struct Migration<'a> {
+    path: &'a CStr,
+    magic: u8,
+    version: u8,
+}
+
+impl<'a> Migration<'a> {
+    /// Returns the version that the settings were saved with.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Loads the stored settings as `Old`.
+    ///
+    /// Returns `None` if `Old` does not match the stored magic and version, or if the
+    /// stored data is corrupted.
+    pub fn load<Old: SettingsData>(&self) -> Option<Old> {
+        if Old::MAGIC != self.magic || Old::VERSION != self.version {
+            return None;
+        }
+
+        load_raw(self.path)
+    }
+}
+
+/// A [`SettingsData`] struct persisted at a fixed path.
+///
+/// Paths are usually placed in the application's data directory (see
+/// [`APP_DATA_PATH`](super::APP_DATA_PATH)), for example `/data/settings.bin`.
+pub struct Settings<'a, T: SettingsData> {
+    path: &'a CStr,
+    _marker: PhantomData<T>,
+}
+
+impl<'a, T: SettingsData> Settings<'a, T> {
+    /// Creates a handle for settings stored at `path`.
+    pub fn new(path: &'a CStr) -> Self {
+        Self {
+            path,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Loads the stored settings, falling back to `T::default()` if they are missing or
+    /// cannot be loaded.
+    pub fn load(&self) -> T {
+        self.try_load().unwrap_or_default()
+    }
+
+    /// Loads the stored settings.
+    ///
+    /// Settings saved with a different version are passed to
+    /// [`SettingsData::migrate`]. Successfully migrated settings are saved back
+    /// immediately, so that the migration only happens once.
+    ///
+    /// Returns `None` if the settings are missing, corrupted or could not be migrated.
+    pub fn try_load(&self) -> Option<T> {
+        if let Some(value) = load_raw::<T>(self.path) {
+            return Some(value);
+        }
+
+        let (magic, version) = read_header(self.path)?;
+        if magic != T::MAGIC || version == T::VERSION {
+            // Either another kind of file, or corrupted.
+            return None;
+        }
+
+        let value = T::migrate(Migration {
+            path: self.path,
+            magic,
+            version,
+        })?;
+
+        // A failure here only means that the migration will be repeated next time.
+        let _ = self.save(&value);
+
+        Some(value)
+    }
+
+    /// Saves the settings, replacing any stored settings.
+    pub fn save(&self, value: &T) -> Result<(), Error> {
+        let mut value = *value;
+        let saved = unsafe {
+            sys::saved_struct_save(
+                self.path.as_ptr(),
+                &mut value as *mut T as *mut c_void,
+                mem::size_of::<T>(),
+                T::MAGIC,
+                T::VERSION,
+            )
+        };
+
+        if saved {
+            Ok(())
+        } else {
+            Err(Error::Internal)
+        }
+    }
+}
+
+/// Loads a struct using its own magic and version.
+fn load_raw<T: SettingsData>(path: &CStr) -> Option<T> {
+    let mut value = MaybeUninit::<T>::uninit();
+    let loaded = unsafe {
+        sys::saved_struct_load(
+            path.as_ptr(),
+            value.as_mut_ptr() as *mut c_void,
+            mem::size_of::<T>(),
+            T::MAGIC,
+            T::VERSION,
+        )
+    };
+
+    // SAFETY: `SettingsData` guarantees that any loaded bytes are a valid `T`.
+    loaded.then(|| unsafe { value.assume_init() })
+}
+
+/// Reads the magic and version from the header of a saved struct.
+fn read_header(path: &CStr) -> Option<(u8, u8)> {
+    // The header starts with `uint8_t magic; uint8_t version;`.
+    let mut header = [0; 2];
+    let mut file = OpenOptions::new()
+        .read(true)
+        .open_existing(true)
+        .open(path)
+        .ok()?;
+
+    match file.read(&mut header) {
+        Ok(2) => Some((header[0], header[1])),
+        _ => None,
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::ffi::CStr;
+
+    use super::{Migration, Settings, SettingsData};
+    use crate::storage;
+
+    const PATH: &CStr =
+        unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/flipperzero-rs-settings\0") };
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq)]
+    struct ConfigV1 {
+        volume: u8,
+    }
+
+    unsafe impl SettingsData for ConfigV1 {
+        const MAGIC: u8 = 0x52;
+        const VERSION: u8 = 1;
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq)]
+    struct ConfigV2 {
+        volume: u8,
+        brightness: u8,
+    }
+
+    unsafe impl SettingsData for ConfigV2 {
+        const MAGIC: u8 = 0x52;
+        const VERSION: u8 = 2;
+
+        fn migrate(old: Migration<'_>) -> Option<Self> {
+            match old.version() {
+                1 => old.load::<ConfigV1>().map(|v1| ConfigV2 {
+                    volume: v1.volume,
+                    brightness: 100,
+                }),
+                _ => None,
+            }
+        }
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq)]
+    struct Other {
+        value: u32,
+    }
+
+    unsafe impl SettingsData for Other {
+        const MAGIC: u8 = 0x17;
+        const VERSION: u8 = 1;
+    }
+
+    #[test]
+    fn save_and_load() {
+        storage::remove(PATH).unwrap();
+
+        let settings = Settings::<ConfigV1>::new(PATH);
+        assert_eq!(settings.try_load(), None);
+        assert_eq!(settings.load(), ConfigV1::default());
+
+        let config = ConfigV1 { volume: 7 };
+        settings.save(&config).unwrap();
+        assert_eq!(settings.load(), config);
+
+        storage::remove(PATH).unwrap();
+    }
+
+    #[test]
+    fn mismatched_magic_uses_default() {
+        storage::remove(PATH).unwrap();
+
+        Settings::<ConfigV1>::new(PATH)
+            .save(&ConfigV1 { volume: 7 })
+            .unwrap();
+        assert_eq!(Settings::<Other>::new(PATH).load(), Other::default());
+
+        storage::remove(PATH).unwrap();
+    }
+
+    #[test]
+    fn migrate_from_older_version() {
+        storage::remove(PATH).unwrap();
+
+        Settings::<ConfigV1>::new(PATH)
+            .save(&ConfigV1 { volume: 7 })
+            .unwrap();
+
+        let settings = Settings::<ConfigV2>::new(PATH);
+        let expected = ConfigV2 {
+            volume: 7,
+            brightness: 100,
+        };
+        assert_eq!(settings.load(), expected);
+
+        // The migrated settings have been saved with the new version.
+        assert_eq!(Settings::<ConfigV1>::new(PATH).try_load(), None);
+        assert_eq!(settings.try_load(), Some(expected));
+
+        storage::remove(PATH).unwrap();
+    }
+}
@@ -0,0 +1,468 @@
+//! Decoding received signals with Sub-GHz protocols.
+//!
+//! A [`SubGhzDecoder`] runs the decoders of the protocols in an [`Environment`]. The
+//! SDK exports neither the firmware's protocol registry nor its decodable protocols
+//! (Princeton, CAME, KeeLoq, ...): the only exported protocol is [`Protocol::raw`],
+//! which never decodes a packet. Applications list the protocols they implement
+//! against the SDK's `SubGhzProtocolDecoder` interface in a
+//! [`ProtocolRegistry`](super::protocol::ProtocolRegistry).
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use flipperzero::info;
+//! use flipperzero::subghz::decoder::SubGhzDecoder;
+//! use flipperzero::subghz::protocol::{Environment, ProtocolRegistry};
+//! use flipperzero::subghz::{Preset, Radio};
+//!
+//! static REGISTRY: ProtocolRegistry = ProtocolRegistry::new(&[&MY_PROTOCOL]);
+//!
+//! let environment = Environment::new(&REGISTRY);
+//! let mut decoder = SubGhzDecoder::new(&environment, |packet| {
+//!     info!("scanner", "{}", packet.description());
+//! });
+//!
+//! let mut radio = Radio::new();
+//! radio.load_preset(Preset::Ook650Async);
+//! radio.set_frequency(433_920_000)?;
+//! let _receiving = decoder.start(&mut radio);
+//! ```
+
+use core::ffi::{c_void, CStr};
+use core::marker::PhantomData;
+
+use alloc::boxed::Box;
+
+use flipperzero_sys as sys;
+
+use super::protocol::{Environment, Error, Protocol, ProtocolFlags, RadioPreset, Result};
+use super::{AsyncRx, Radio};
+use crate::format::FlipperFormat;
+use crate::furi::string::FuriString;
+
+const BIT: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"Bit\0") };
+const KEY: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"Key\0") };
+const AM650: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"AM650\0") };
+
+type PacketCallback<'a> = Box<dyn FnMut(&DecodedPacket<'_>) + Send + 'a>;
+
+/// The key of a decoded packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    /// Number of significant bits.
+    pub bits: u32,
+    /// The key, most significant byte first.
+    pub data: [u8; 8],
+}
+
+impl Key {
+    /// Returns the key as an integer.
+    pub fn to_u64(&self) -> u64 {
+        u64::from_be_bytes(self.data)
+    }
+}
+
+/// A packet decoded by a [`SubGhzDecoder`].
+pub struct DecodedPacket<'a> {
+    raw: *mut sys::SubGhzProtocolDecoderBase,
+    _decoder: PhantomData<&'a mut sys::SubGhzProtocolDecoderBase>,
+}
+
+impl DecodedPacket<'_> {
+    /// Returns the protocol that decoded the packet.
+    pub fn protocol(&self) -> Protocol {
+        unsafe { Protocol::from_raw((*self.raw).protocol) }
+    }
+
+    /// Returns the name of the protocol that decoded the packet.
+    pub fn protocol_name(&self) -> &'static CStr {
+        self.protocol().name()
+    }
+
+    /// Returns a human-readable description of the packet, as shown by the Sub-GHz
+    /// application.
+    pub fn description(&self) -> FuriString {
+        let mut description = FuriString::new();
+        unsafe { sys::subghz_protocol_decoder_base_get_string(self.raw, description.as_mut_ptr()) };
+        description
+    }
+
+    /// Returns a hash of the packet, to detect repeated transmissions.
+    pub fn hash(&self) -> u8 {
+        unsafe { sys::subghz_protocol_decoder_base_get_hash_data(self.raw) }
+    }
+
+    /// Returns the key of the packet, for protocols that store a single key.
+    pub fn key(&self) -> Result<Key> {
+        let mut ff = FlipperFormat::new_string();
+        self.serialize(&mut ff, &RadioPreset::new(AM650, 0, &[]))?;
+        ff.rewind()?;
+
+        let mut key = Key {
+            bits: 0,
+            data: [0; 8],
+        };
+        ff.read_u32(BIT, core::slice::from_mut(&mut key.bits))
+            .map_err(|_| Error::InvalidBitCount)?;
+        ff.read_hex(KEY, &mut key.data)
+            .map_err(|_| Error::InvalidKey)?;
+
+        Ok(key)
+    }
+
+    /// Writes the packet in the `.sub` key format, without the header.
+    ///
+    /// `preset` holds the settings the packet was received with, so that it can be
+    /// transmitted again.
+    pub fn serialize(&self, ff: &mut FlipperFormat, preset: &RadioPreset<'_>) -> Result<()> {
+        preset.with_sys(|preset| {
+            Error::from_status(unsafe {
+                sys::subghz_protocol_decoder_base_serialize(self.raw, ff.as_ptr(), preset)
+            })
+        })
+    }
+}
+
+/// Decodes signals with the protocols of an [`Environment`].
+///
+/// Signals are either fed in with [`SubGhzDecoder::feed`], or received from the radio
+/// with [`SubGhzDecoder::start`].
+pub struct SubGhzDecoder<'a> {
+    receiver: *mut sys::SubGhzReceiver,
+    worker: *mut sys::SubGhzWorker,
+    _callback: Box<PacketCallback<'a>>,
+    _environment: PhantomData<&'a Environment>,
+}
+
+impl<'a> SubGhzDecoder<'a> {
+    /// Allocates a decoder for the protocols of `environment`, calling `callback` with
+    /// each decoded packet.
+    ///
+    /// The callback is called from the thread feeding the decoder: the worker thread
+    /// while receiving.
+    pub fn new<F>(environment: &'a Environment, callback: F) -> Self
+    where
+        F: FnMut(&DecodedPacket<'_>) + Send + 'a,
+    {
+        unsafe extern "C" fn decoded(
+            _receiver: *mut sys::SubGhzReceiver,
+            decoder: *mut sys::SubGhzProtocolDecoderBase,
+            context: *mut c_void,
+        ) {
+            let callback = &mut *(context as *mut PacketCallback<'_>);
+            callback(&DecodedPacket {
+                raw: decoder,
+                _decoder: PhantomData,
+            });
+        }
+
+        unsafe extern "C" fn pair(context: *mut c_void, level: bool, duration: u32) {
+            sys::subghz_receiver_decode(context as *mut sys::SubGhzReceiver, level, duration);
+        }
+
+        unsafe extern "C" fn overrun(context: *mut c_void) {
+            sys::subghz_receiver_reset(context as *mut sys::SubGhzReceiver);
+        }
+
+        let receiver = unsafe { sys::subghz_receiver_alloc_init(environment.as_ptr()) };
+        assert!(!receiver.is_null());
+        let worker = unsafe { sys::subghz_worker_alloc() };
+        assert!(!worker.is_null());
+
+        let mut callback: Box<PacketCallback<'a>> = Box::new(Box::new(callback));
+        unsafe {
+            sys::subghz_receiver_set_filter(receiver, ProtocolFlags::DECODABLE.to_sys());
+            sys::subghz_receiver_set_rx_callback(
+                receiver,
+                Some(decoded),
+                &mut *callback as *mut PacketCallback<'a> as *mut c_void,
+            );
+
+            sys::subghz_worker_set_overrun_callback(worker, Some(overrun));
+            sys::subghz_worker_set_pair_callback(worker, Some(pair));
+            sys::subghz_worker_set_context(worker, receiver as *mut c_void);
+        }
+
+        Self {
+            receiver,
+            worker,
+            _callback: callback,
+            _environment: PhantomData,
+        }
+    }
+
+    /// Sets which protocols decode signals: those with any of the `flags`.
+    ///
+    /// By default, all [decodable](ProtocolFlags::DECODABLE) protocols run.
+    pub fn set_filter(&mut self, flags: ProtocolFlags) {
+        unsafe { sys::subghz_receiver_set_filter(self.receiver, flags.to_sys()) };
+    }
+
+    /// Feeds a level and its duration in microseconds to the protocols.
+    pub fn feed(&mut self, level: bool, duration: u32) {
+        unsafe { sys::subghz_receiver_decode(self.receiver, level, duration) };
+    }
+
+    /// Resets the state of the protocols, discarding any partial packet.
+    pub fn reset(&mut self) {
+        unsafe { sys::subghz_receiver_reset(self.receiver) };
+    }
+
+    /// Starts decoding the signal received by `radio`, until the returned guard is
+    /// dropped.
+    ///
+    /// The radio must be tuned and have a preset loaded.
+    pub fn start<'r>(&'r mut self, radio: &'r mut Radio) -> Receiving<'r> {
+        let rx = unsafe {
+            radio.start_async_rx_raw(
+                Some(sys::subghz_worker_rx_callback),
+                self.worker as *mut c_void,
+            )
+        };
+        unsafe {
+            // A leaked `Receiving` guard leaves the worker running.
+            if sys::subghz_worker_is_running(self.worker) {
+                sys::subghz_worker_stop(self.worker);
+            }
+            sys::subghz_worker_start(self.worker);
+        }
+
+        Receiving {
+            worker: self.worker,
+            rx,
+            _decoder: PhantomData,
+        }
+    }
+}
+
+impl Drop for SubGhzDecoder<'_> {
+    fn drop(&mut self) {
+        // The `Receiving` guard may have been leaked, leaving the worker running.
+        unsafe {
+            if sys::subghz_worker_is_running(self.worker) {
+                sys::subghz_worker_stop(self.worker);
+            }
+        }
+        super::stop_async_rx(Some(self.worker as *mut c_void));
+
+        unsafe {
+            sys::subghz_worker_free(self.worker);
+            sys::subghz_receiver_free(self.receiver);
+        }
+    }
+}
+
+/// Decodes the received signal until dropped. See [`SubGhzDecoder::start`].
+pub struct Receiving<'a> {
+    worker: *mut sys::SubGhzWorker,
+    rx: AsyncRx<'a>,
+    _decoder: PhantomData<&'a mut sys::SubGhzWorker>,
+}
+
+impl Receiving<'_> {
+    /// Returns the received signal strength, in dBm.
+    pub fn rssi(&self) -> f32 {
+        self.rx.rssi()
+    }
+}
+
+impl Drop for Receiving<'_> {
+    fn drop(&mut self) {
+        // Stopping joins the worker thread; the radio stops receiving afterwards, when
+        // `rx` is dropped.
+        unsafe { sys::subghz_worker_stop(self.worker) };
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::ffi::{c_void, CStr};
+    use core::ptr;
+
+    use alloc::boxed::Box;
+
+    use flipperzero_sys as sys;
+
+    use super::{Key, SubGhzDecoder, BIT, KEY};
+    use crate::subghz::protocol::{Environment, ProtocolFlags, ProtocolRegistry, RAW_REGISTRY};
+
+    /// A protocol sending 8 bits, most significant first: a high pulse of 300 µs for 0
+    /// or 900 µs for 1, each followed by 300 µs low. A gap of 5 ms ends the packet.
+    #[repr(C)]
+    struct TestDecoder {
+        base: sys::SubGhzProtocolDecoderBase,
+        data: u8,
+        bits: u32,
+    }
+
+    unsafe extern "C" fn alloc(_environment: *mut sys::SubGhzEnvironment) -> *mut c_void {
+        Box::into_raw(Box::new(TestDecoder {
+            base: sys::SubGhzProtocolDecoderBase {
+                protocol: &TEST_PROTOCOL.0,
+                callback: None,
+                context: ptr::null_mut(),
+            },
+            data: 0,
+            bits: 0,
+        })) as *mut c_void
+    }
+
+    unsafe extern "C" fn free(decoder: *mut c_void) {
+        drop(Box::from_raw(decoder as *mut TestDecoder));
+    }
+
+    unsafe extern "C" fn feed(decoder: *mut c_void, level: bool, duration: u32) {
+        let decoder = &mut *(decoder as *mut TestDecoder);
+        match (level, duration) {
+            (true, 200..=400) if decoder.bits < 8 => {
+                decoder.data <<= 1;
+                decoder.bits += 1;
+            }
+            (true, 800..=1000) if decoder.bits < 8 => {
+                decoder.data = (decoder.data << 1) | 1;
+                decoder.bits += 1;
+            }
+            (false, 200..=400) => (),
+            (false, 5000..) if decoder.bits == 8 => {
+                if let Some(callback) = decoder.base.callback {
+                    callback(&mut decoder.base, decoder.base.context);
+                }
+                reset(decoder as *mut TestDecoder as *mut c_void);
+            }
+            _ => reset(decoder as *mut TestDecoder as *mut c_void),
+        }
+    }
+
+    unsafe extern "C" fn reset(decoder: *mut c_void) {
+        let decoder = &mut *(decoder as *mut TestDecoder);
+        decoder.data = 0;
+        decoder.bits = 0;
+    }
+
+    unsafe extern "C" fn get_hash_data(decoder: *mut c_void) -> u8 {
+        (*(decoder as *mut TestDecoder)).data
+    }
+
+    unsafe extern "C" fn get_string(decoder: *mut c_void, output: *mut sys::FuriString) {
+        let data = (*(decoder as *mut TestDecoder)).data;
+        sys::furi_string_printf(output, b"Test %02X\0".as_ptr() as *const _, u32::from(data));
+    }
+
+    unsafe extern "C" fn serialize(
+        decoder: *mut c_void,
+        ff: *mut sys::FlipperFormat,
+        _preset: *mut sys::SubGhzRadioPreset,
+    ) -> sys::SubGhzProtocolStatus {
+        let data = [0, 0, 0, 0, 0, 0, 0, (*(decoder as *mut TestDecoder)).data];
+        if sys::flipper_format_write_uint32(ff, BIT.as_ptr(), &8, 1)
+            && sys::flipper_format_write_hex(ff, KEY.as_ptr(), data.as_ptr(), 8)
+        {
+            sys::SubGhzProtocolStatus_SubGhzProtocolStatusOk
+        } else {
+            sys::SubGhzProtocolStatus_SubGhzProtocolStatusErrorParserKey
+        }
+    }
+
+    static TEST_DECODER: sys::SubGhzProtocolDecoder = sys::SubGhzProtocolDecoder {
+        alloc: Some(alloc),
+        free: Some(free),
+        feed: Some(feed),
+        reset: Some(reset),
+        get_hash_data: Some(get_hash_data),
+        get_string: Some(get_string),
+        serialize: Some(serialize),
+        deserialize: None,
+    };
+
+    struct TestProtocol(sys::SubGhzProtocol);
+
+    // SAFETY: The protocol is never modified.
+    unsafe impl Sync for TestProtocol {}
+
+    static TEST_PROTOCOL: TestProtocol = TestProtocol(sys::SubGhzProtocol {
+        name: b"Test\0".as_ptr() as *const _,
+        type_: sys::SubGhzProtocolType_SubGhzProtocolTypeStatic,
+        flag: sys::SubGhzProtocolFlag_SubGhzProtocolFlag_Decodable
+            | sys::SubGhzProtocolFlag_SubGhzProtocolFlag_AM
+            | sys::SubGhzProtocolFlag_SubGhzProtocolFlag_433,
+        encoder: ptr::null(),
+        decoder: &TEST_DECODER,
+    });
+
+    static TEST_REGISTRY: ProtocolRegistry = ProtocolRegistry::new(&[&TEST_PROTOCOL.0]);
+
+    /// Feeds 0xA5 in the test protocol, after an interrupted packet.
+    fn feed_fixture(decoder: &mut SubGhzDecoder<'_>) {
+        decoder.feed(true, 900);
+        decoder.feed(false, 300);
+        decoder.feed(true, 300);
+        decoder.feed(false, 2000);
+        for i in (0..8).rev() {
+            decoder.feed(true, if (0xA5 >> i) & 1 == 1 { 900 } else { 300 });
+            decoder.feed(false, if i == 0 { 10_000 } else { 300 });
+        }
+    }
+
+    #[test]
+    fn decode_fixture() {
+        let environment = Environment::new(&TEST_REGISTRY);
+        let mut packets = 0;
+        {
+            let mut decoder = SubGhzDecoder::new(&environment, |packet| {
+                packets += 1;
+                assert_eq!(
+                    packet.protocol_name(),
+                    CStr::from_bytes_with_nul(b"Test\0").unwrap()
+                );
+                assert_eq!(
+                    packet.description().as_c_str(),
+                    CStr::from_bytes_with_nul(b"Test A5\0").unwrap()
+                );
+                assert_eq!(packet.hash(), 0xA5);
+                assert_eq!(
+                    packet.key(),
+                    Ok(Key {
+                        bits: 8,
+                        data: [0, 0, 0, 0, 0, 0, 0, 0xA5],
+                    })
+                );
+                assert_eq!(packet.key().unwrap().to_u64(), 0xA5);
+            });
+            feed_fixture(&mut decoder);
+        }
+        assert_eq!(packets, 1);
+    }
+
+    #[test]
+    fn filter() {
+        let environment = Environment::new(&TEST_REGISTRY);
+        let mut packets = 0;
+        {
+            let mut decoder = SubGhzDecoder::new(&environment, |_| packets += 1);
+            decoder.set_filter(ProtocolFlags::RAW);
+            feed_fixture(&mut decoder);
+
+            decoder.set_filter(ProtocolFlags::DECODABLE);
+            decoder.feed(true, 900);
+            decoder.reset();
+            feed_fixture(&mut decoder);
+        }
+        assert_eq!(packets, 1);
+    }
+
+    #[test]
+    fn raw_decodes_nothing() {
+        let environment = Environment::new(&RAW_REGISTRY);
+        let mut packets = 0;
+        {
+            let mut decoder = SubGhzDecoder::new(&environment, |_| packets += 1);
+            decoder.set_filter(ProtocolFlags::DECODABLE);
+            for _ in 0..100 {
+                decoder.feed(true, 500);
+                decoder.feed(false, 500);
+            }
+            decoder.reset();
+        }
+        assert_eq!(packets, 0);
+    }
+}
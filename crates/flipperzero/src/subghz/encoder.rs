@@ -0,0 +1,178 @@
+//! Transmitting saved keys with the firmware's protocols.
+//!
+//! # Examples
+//!
+//! ```
+//! let environment = Environment::with_assets(&REGISTRY)?;
+//! let mut encoder =
+//!     Encoder::load(&environment, CStr::from_bytes_with_nul(b"/ext/subghz/gate.sub\0").unwrap())?;
+//!
+//! let mut radio = Radio::new();
+//! encoder.transmit(&mut radio)?;
+//! ```
+
+use core::ffi::{c_void, CStr};
+use core::marker::PhantomData;
+
+use flipperzero_sys as sys;
+
+use super::protocol::{Environment, Error, FileHeader, Protocol, Result, KEY_FILETYPE};
+use super::{Preset, Radio};
+use crate::format::FlipperFormat;
+
+/// Transmits a key saved in a `.sub` file.
+pub struct Encoder<'a> {
+    transmitter: *mut sys::SubGhzTransmitter,
+    ff: FlipperFormat,
+    protocol: Protocol,
+    frequency: u32,
+    preset: Option<Preset>,
+    _environment: PhantomData<&'a Environment>,
+}
+
+impl<'a> Encoder<'a> {
+    /// Loads a `.sub` key file.
+    pub fn load(environment: &'a Environment, path: &CStr) -> Result<Self> {
+        let mut ff = FlipperFormat::new_buffered_file();
+        ff.open_existing(path)?;
+        Self::from_flipper_format(environment, ff)
+    }
+
+    /// Reads a key in the `.sub` key format, keeping `ff` to read the key from each time
+    /// it is transmitted.
+    pub fn from_flipper_format(
+        environment: &'a Environment,
+        mut ff: FlipperFormat,
+    ) -> Result<Self> {
+        let header = FileHeader::read(&mut ff, KEY_FILETYPE)?;
+        let protocol = environment
+            .registry()
+            .find(header.protocol.as_c_str())
+            .ok_or(Error::UnknownProtocol)?;
+
+        let transmitter = unsafe {
+            sys::subghz_transmitter_alloc_init(environment.as_ptr(), protocol.name().as_ptr())
+        };
+        if transmitter.is_null() {
+            return Err(Error::UnknownProtocol);
+        }
+
+        let mut encoder = Self {
+            transmitter,
+            ff,
+            protocol,
+            frequency: header.frequency,
+            preset: header.preset,
+            _environment: PhantomData,
+        };
+        // Check that the protocol accepts the key before it is transmitted.
+        encoder.deserialize()?;
+
+        Ok(encoder)
+    }
+
+    /// Returns the protocol of the key.
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    /// Returns the frequency the key is transmitted on, in Hz.
+    pub fn frequency(&self) -> u32 {
+        self.frequency
+    }
+
+    /// Returns the preset the key is transmitted with, or `None` for a custom preset.
+    pub fn preset(&self) -> Option<Preset> {
+        self.preset
+    }
+
+    /// Transmits the key, blocking until it is sent.
+    ///
+    /// The radio is tuned to the key's frequency, and its preset is loaded. Keys saved
+    /// with a custom preset are sent with the preset already loaded in the radio.
+    pub fn transmit(&mut self, radio: &mut Radio) -> Result<()> {
+        // The protocol's signal is consumed by a transmission, so it's generated again.
+        self.deserialize()?;
+
+        if let Some(preset) = self.preset {
+            radio.load_preset(preset);
+        }
+        radio.set_frequency(self.frequency)?;
+
+        let result = unsafe {
+            radio.transmit_raw(
+                Some(sys::subghz_transmitter_yield),
+                self.transmitter as *mut c_void,
+            )
+        };
+        unsafe { sys::subghz_transmitter_stop(self.transmitter) };
+
+        result.map_err(Error::from)
+    }
+
+    fn deserialize(&mut self) -> Result<()> {
+        self.ff.rewind()?;
+        Error::from_status(unsafe {
+            sys::subghz_transmitter_deserialize(self.transmitter, self.ff.as_ptr())
+        })
+    }
+}
+
+impl Drop for Encoder<'_> {
+    fn drop(&mut self) {
+        unsafe { sys::subghz_transmitter_free(self.transmitter) };
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::ffi::CStr;
+
+    use super::Encoder;
+    use crate::format::FlipperFormat;
+    use crate::subghz::protocol::{Environment, Error, FILE_VERSION, KEY_FILETYPE, RAW_REGISTRY};
+
+    fn key_file(protocol: &[u8]) -> FlipperFormat {
+        let mut ff = FlipperFormat::new_string();
+        ff.write_header(KEY_FILETYPE, FILE_VERSION).unwrap();
+        ff.write_u32(
+            CStr::from_bytes_with_nul(b"Frequency\0").unwrap(),
+            &[433_920_000],
+        )
+        .unwrap();
+        ff.write_string(
+            CStr::from_bytes_with_nul(b"Preset\0").unwrap(),
+            CStr::from_bytes_with_nul(b"FuriHalSubGhzPresetOok650Async\0").unwrap(),
+        )
+        .unwrap();
+        ff.write_string(
+            CStr::from_bytes_with_nul(b"Protocol\0").unwrap(),
+            CStr::from_bytes_with_nul(protocol).unwrap(),
+        )
+        .unwrap();
+        ff.rewind().unwrap();
+        ff
+    }
+
+    #[test]
+    fn unknown_protocol() {
+        let environment = Environment::new(&RAW_REGISTRY);
+        assert!(matches!(
+            Encoder::from_flipper_format(&environment, key_file(b"Princeton\0")),
+            Err(Error::UnknownProtocol)
+        ));
+    }
+
+    #[test]
+    fn invalid_header() {
+        let environment = Environment::new(&RAW_REGISTRY);
+        let mut ff = FlipperFormat::new_string();
+        ff.write_header(CStr::from_bytes_with_nul(b"IR signals file\0").unwrap(), 1)
+            .unwrap();
+        ff.rewind().unwrap();
+        assert!(matches!(
+            Encoder::from_flipper_format(&environment, ff),
+            Err(Error::InvalidHeader)
+        ));
+    }
+}
@@ -0,0 +1,579 @@
+//! Sub-GHz radio.
+//!
+//! The [`Radio`] drives the CC1101 transceiver directly: it tunes the radio, loads a
+//! modulation preset, reads the RSSI, and receives or transmits signals as
+//! level/duration pairs.
+//!
+//! Transmission is only allowed on frequencies permitted by the region the device is
+//! provisioned for (see [`is_frequency_allowed`]).
+//!
+//! # Examples
+//!
+//! ```
+//! let mut radio = Radio::new();
+//! radio.load_preset(Preset::Ook650Async);
+//! radio.set_frequency(433_920_000)?;
+//!
+//! // 10 pulses of 500us.
+//! radio.transmit(core::iter::repeat(500).take(20))?;
+//! ```
+
+use core::ffi::{c_void, CStr};
+use core::fmt;
+use core::marker::PhantomData;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+use flipperzero_sys as sys;
+
+#[cfg(feature = "alloc")]
+pub mod decoder;
+pub mod encoder;
+pub mod protocol;
+pub mod raw;
+pub mod setting;
+
+/// Size of the radio's packet FIFO in bytes.
+pub const FIFO_SIZE: usize = 64;
+
+/// Longest duration of a level, in microseconds.
+pub const MAX_DURATION: u32 = (1 << 30) - 1;
+
+/// Context of the running async RX, or NULL if it isn't running.
+///
+/// [`AsyncRx`] guards can be leaked, so whatever owns a context also stops the RX that
+/// uses it; see [`stop_async_rx`].
+static ASYNC_RX_CONTEXT: AtomicPtr<c_void> = AtomicPtr::new(ptr::null_mut());
+
+/// Sub-GHz Result type.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Sub-GHz error kinds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The radio can't be tuned to the frequency.
+    InvalidFrequency,
+    /// The region doesn't allow transmitting on the frequency.
+    FrequencyNotAllowed,
+    /// Custom preset data is not a list of register/value pairs terminated by
+    /// `00 00`, followed by the 8 byte PA table.
+    InvalidPreset,
+    /// A packet is larger than the radio's FIFO.
+    PacketTooLarge,
+}
+
+impl Error {
+    /// Describes the error.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::InvalidFrequency => "Invalid frequency",
+            Self::FrequencyNotAllowed => "Frequency not allowed in region",
+            Self::InvalidPreset => "Invalid preset data",
+            Self::PacketTooLarge => "Packet too large",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> core::result::Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+/// Returns `true` if the radio can be tuned to `frequency` (in Hz).
+pub fn is_frequency_valid(frequency: u32) -> bool {
+    unsafe { sys::furi_hal_subghz_is_frequency_valid(frequency) }
+}
+
+/// Returns `true` if the region allows transmitting on `frequency` (in Hz).
+///
+/// Devices that are not provisioned for a region don't allow transmitting at all.
+pub fn is_frequency_allowed(frequency: u32) -> bool {
+    unsafe { sys::furi_hal_region_is_frequency_allowed(frequency) }
+}
+
+/// Returns `true` if the device is provisioned for a region.
+pub fn is_region_provisioned() -> bool {
+    unsafe { sys::furi_hal_region_is_provisioned() }
+}
+
+/// Returns the name of the region the device is provisioned for.
+pub fn region_name() -> &'static CStr {
+    unsafe { CStr::from_ptr(sys::furi_hal_region_get_name()) }
+}
+
+/// Built-in modulation presets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Preset {
+    /// OOK, bandwidth 270kHz.
+    Ook270Async,
+    /// OOK, bandwidth 650kHz.
+    Ook650Async,
+    /// 2-FSK, deviation 2.380371kHz.
+    TwoFskDev238Async,
+    /// 2-FSK, deviation 47.60742kHz.
+    TwoFskDev476Async,
+    /// MSK, deviation 47.60742kHz, 99.97Kb/s.
+    Msk99_97KbAsync,
+    /// GFSK, deviation 19.042969kHz, 9.996Kb/s.
+    Gfsk9_99KbAsync,
+}
+
+impl Preset {
+    /// All built-in presets.
+    pub const ALL: [Self; 6] = [
+        Self::Ook270Async,
+        Self::Ook650Async,
+        Self::TwoFskDev238Async,
+        Self::TwoFskDev476Async,
+        Self::Msk99_97KbAsync,
+        Self::Gfsk9_99KbAsync,
+    ];
+
+    /// Get the preset from the corresponding SDK `FuriHalSubGhzPreset`.
+    pub fn from_sys(preset: sys::FuriHalSubGhzPreset) -> Option<Self> {
+        match preset {
+            sys::FuriHalSubGhzPreset_FuriHalSubGhzPresetOok270Async => Some(Self::Ook270Async),
+            sys::FuriHalSubGhzPreset_FuriHalSubGhzPresetOok650Async => Some(Self::Ook650Async),
+            sys::FuriHalSubGhzPreset_FuriHalSubGhzPreset2FSKDev238Async => {
+                Some(Self::TwoFskDev238Async)
+            }
+            sys::FuriHalSubGhzPreset_FuriHalSubGhzPreset2FSKDev476Async => {
+                Some(Self::TwoFskDev476Async)
+            }
+            sys::FuriHalSubGhzPreset_FuriHalSubGhzPresetMSK99_97KbAsync => {
+                Some(Self::Msk99_97KbAsync)
+            }
+            sys::FuriHalSubGhzPreset_FuriHalSubGhzPresetGFSK9_99KbAsync => {
+                Some(Self::Gfsk9_99KbAsync)
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the preset from its name in `.sub` files (e.g. `FuriHalSubGhzPresetOok650Async`).
+    pub fn from_name(name: &CStr) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.name() == name)
+    }
+
+    /// Returns the name of the preset in `.sub` files.
+    pub fn name(self) -> &'static CStr {
+        let name: &[u8] = match self {
+            Self::Ook270Async => b"FuriHalSubGhzPresetOok270Async\0",
+            Self::Ook650Async => b"FuriHalSubGhzPresetOok650Async\0",
+            Self::TwoFskDev238Async => b"FuriHalSubGhzPreset2FSKDev238Async\0",
+            Self::TwoFskDev476Async => b"FuriHalSubGhzPreset2FSKDev476Async\0",
+            Self::Msk99_97KbAsync => b"FuriHalSubGhzPresetMSK99_97KbAsync\0",
+            Self::Gfsk9_99KbAsync => b"FuriHalSubGhzPresetGFSK9_99KbAsync\0",
+        };
+        unsafe { CStr::from_bytes_with_nul_unchecked(name) }
+    }
+
+    /// Convert the preset into the corresponding SDK `FuriHalSubGhzPreset`.
+    pub fn to_sys(self) -> sys::FuriHalSubGhzPreset {
+        match self {
+            Self::Ook270Async => sys::FuriHalSubGhzPreset_FuriHalSubGhzPresetOok270Async,
+            Self::Ook650Async => sys::FuriHalSubGhzPreset_FuriHalSubGhzPresetOok650Async,
+            Self::TwoFskDev238Async => sys::FuriHalSubGhzPreset_FuriHalSubGhzPreset2FSKDev238Async,
+            Self::TwoFskDev476Async => sys::FuriHalSubGhzPreset_FuriHalSubGhzPreset2FSKDev476Async,
+            Self::Msk99_97KbAsync => sys::FuriHalSubGhzPreset_FuriHalSubGhzPresetMSK99_97KbAsync,
+            Self::Gfsk9_99KbAsync => sys::FuriHalSubGhzPreset_FuriHalSubGhzPresetGFSK9_99KbAsync,
+        }
+    }
+}
+
+/// Checks that `data` is a valid custom preset: CC1101 register/value pairs
+/// terminated by `00 00`, followed by the 8 byte PA table.
+pub fn is_custom_preset_valid(data: &[u8]) -> bool {
+    match data.chunks_exact(2).position(|pair| pair == [0, 0]) {
+        Some(end) => data.len() >= (end + 1) * 2 + 8,
+        None => false,
+    }
+}
+
+/// Makes a firmware level/duration pair.
+pub(crate) fn level_duration(level: bool, duration: u32) -> sys::LevelDuration {
+    sys::LevelDuration {
+        _bitfield_align_1: [],
+        // The firmware encodes low as 1 and high as 2; 0 marks the end of a signal.
+        _bitfield_1: sys::LevelDuration::new_bitfield_1(
+            duration.min(MAX_DURATION),
+            if level { 2 } else { 1 },
+        ),
+    }
+}
+
+/// Makes the firmware level/duration pair that ends a signal.
+pub(crate) fn level_duration_reset() -> sys::LevelDuration {
+    sys::LevelDuration {
+        _bitfield_align_1: [],
+        _bitfield_1: sys::LevelDuration::new_bitfield_1(0, 0),
+    }
+}
+
+/// The Sub-GHz radio.
+///
+/// The radio is reset when created and put to sleep when dropped. Only one radio
+/// should exist at a time, and the Sub-GHz application must not be running.
+pub struct Radio {
+    frequency: Option<u32>,
+}
+
+impl Radio {
+    /// Resets the radio and puts it in the idle state.
+    pub fn new() -> Self {
+        unsafe {
+            sys::furi_hal_subghz_reset();
+            sys::furi_hal_subghz_idle();
+        }
+
+        Self { frequency: None }
+    }
+
+    /// Loads a built-in modulation preset.
+    pub fn load_preset(&mut self, preset: Preset) {
+        unsafe { sys::furi_hal_subghz_load_preset(preset.to_sys()) };
+    }
+
+    /// Loads a custom modulation preset; see [`is_custom_preset_valid`] for its format.
+    ///
+    /// Presets defined in the Sub-GHz settings can be found with
+    /// [`setting::Setting::preset_data`].
+    pub fn load_custom_preset(&mut self, data: &[u8]) -> Result<()> {
+        if !is_custom_preset_valid(data) {
+            return Err(Error::InvalidPreset);
+        }
+
+        // The firmware only reads the data.
+        unsafe { sys::furi_hal_subghz_load_custom_preset(data.as_ptr() as *mut u8) };
+        Ok(())
+    }
+
+    /// Tunes the radio to `frequency` (in Hz), selecting the matching antenna path.
+    ///
+    /// Returns the frequency that was actually set, which can differ slightly.
+    pub fn set_frequency(&mut self, frequency: u32) -> Result<u32> {
+        if !is_frequency_valid(frequency) {
+            return Err(Error::InvalidFrequency);
+        }
+
+        let actual = unsafe { sys::furi_hal_subghz_set_frequency_and_path(frequency) };
+        self.frequency = Some(actual);
+        Ok(actual)
+    }
+
+    /// Returns the frequency the radio is tuned to, if it has been set.
+    pub fn frequency(&self) -> Option<u32> {
+        self.frequency
+    }
+
+    /// Returns the received signal strength, in dBm.
+    ///
+    /// Only meaningful while receiving.
+    pub fn rssi(&self) -> f32 {
+        unsafe { sys::furi_hal_subghz_get_rssi() }
+    }
+
+    /// Returns the link quality indicator of the last received packet.
+    pub fn lqi(&self) -> u8 {
+        unsafe { sys::furi_hal_subghz_get_lqi() }
+    }
+
+    /// Puts the radio in the idle state.
+    pub fn idle(&mut self) {
+        unsafe { sys::furi_hal_subghz_idle() };
+    }
+
+    /// Switches the radio to receive.
+    pub fn rx(&mut self) {
+        unsafe { sys::furi_hal_subghz_rx() };
+    }
+
+    /// Switches the radio to transmit.
+    pub fn tx(&mut self) -> Result<()> {
+        self.check_tx_allowed()?;
+        if unsafe { sys::furi_hal_subghz_tx() } {
+            Ok(())
+        } else {
+            Err(Error::FrequencyNotAllowed)
+        }
+    }
+
+    /// Writes a packet to the transmit FIFO, for presets with packet handling.
+    pub fn write_packet(&mut self, data: &[u8]) -> Result<()> {
+        if data.len() > FIFO_SIZE {
+            return Err(Error::PacketTooLarge);
+        }
+
+        unsafe { sys::furi_hal_subghz_write_packet(data.as_ptr(), data.len() as u8) };
+        Ok(())
+    }
+
+    /// Reads a received packet, if there is one. Returns the packet and whether its CRC
+    /// is valid.
+    pub fn read_packet<'a>(&mut self, buf: &'a mut [u8; FIFO_SIZE]) -> Option<(&'a [u8], bool)> {
+        if !unsafe { sys::furi_hal_subghz_rx_pipe_not_empty() } {
+            return None;
+        }
+
+        let crc_valid = unsafe { sys::furi_hal_subghz_is_rx_data_crc_valid() };
+        let mut len = 0;
+        unsafe { sys::furi_hal_subghz_read_packet(buf.as_mut_ptr(), &mut len) };
+        Some((&buf[..(len as usize).min(FIFO_SIZE)], crc_valid))
+    }
+
+    /// Discards the contents of the receive FIFO.
+    pub fn flush_rx(&mut self) {
+        unsafe { sys::furi_hal_subghz_flush_rx() };
+    }
+
+    /// Discards the contents of the transmit FIFO.
+    pub fn flush_tx(&mut self) {
+        unsafe { sys::furi_hal_subghz_flush_tx() };
+    }
+
+    /// Receives the raw signal while `scope` runs, calling `callback` with each level
+    /// and its duration in microseconds. Returns the result of `scope`.
+    ///
+    /// The callback is called from an interrupt: it must be quick, and must not block
+    /// or allocate. Receiving is stopped before this returns, so the callback can
+    /// borrow from the caller.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut pulses = 0;
+    /// let rssi = radio.async_rx(
+    ///     |level, _duration| pulses += level as u32,
+    ///     |rx| {
+    ///         furi::thread::sleep(Duration::from_secs(1));
+    ///         rx.rssi()
+    ///     },
+    /// );
+    /// ```
+    pub fn async_rx<F, S, R>(&mut self, mut callback: F, scope: S) -> R
+    where
+        F: FnMut(bool, u32) + Send,
+        S: FnOnce(&AsyncRx<'_>) -> R,
+    {
+        unsafe extern "C" fn capture<F: FnMut(bool, u32)>(
+            level: bool,
+            duration: u32,
+            context: *mut c_void,
+        ) {
+            let callback = &mut *(context as *mut F);
+            callback(level, duration);
+        }
+
+        let rx = unsafe {
+            self.start_async_rx_raw(Some(capture::<F>), &mut callback as *mut F as *mut c_void)
+        };
+        let result = scope(&rx);
+        drop(rx);
+        result
+    }
+
+    /// Starts receiving the raw signal with an SDK capture callback.
+    ///
+    /// # Safety
+    ///
+    /// `context` must be valid for `callback` until the returned guard is dropped.
+    pub(crate) unsafe fn start_async_rx_raw(
+        &mut self,
+        callback: sys::FuriHalSubGhzCaptureCallback,
+        context: *mut c_void,
+    ) -> AsyncRx<'_> {
+        // The guard of a previous RX may have been leaked.
+        stop_async_rx(None);
+
+        debug_assert!(!context.is_null());
+        ASYNC_RX_CONTEXT.store(context, Ordering::Release);
+        sys::furi_hal_subghz_start_async_rx(callback, context);
+
+        AsyncRx {
+            context,
+            _radio: PhantomData,
+        }
+    }
+
+    /// Transmits a raw signal, blocking until it is sent.
+    ///
+    /// `durations` are the durations in microseconds of alternating levels, starting
+    /// with high. The iterator is advanced from an interrupt, so it must be quick and
+    /// must not block or allocate.
+    pub fn transmit<I>(&mut self, durations: I) -> Result<()>
+    where
+        I: IntoIterator<Item = u32>,
+        I::IntoIter: Send,
+    {
+        struct Source<I> {
+            durations: I,
+            level: bool,
+        }
+
+        unsafe extern "C" fn next<I: Iterator<Item = u32>>(
+            context: *mut c_void,
+        ) -> sys::LevelDuration {
+            let source = &mut *(context as *mut Source<I>);
+            match source.durations.next() {
+                Some(duration) => {
+                    let level = source.level;
+                    source.level = !level;
+                    level_duration(level, duration)
+                }
+                None => level_duration_reset(),
+            }
+        }
+
+        let mut source = Source {
+            durations: durations.into_iter(),
+            level: true,
+        };
+        unsafe {
+            self.transmit_raw(
+                Some(next::<I::IntoIter>),
+                &mut source as *mut Source<I::IntoIter> as *mut c_void,
+            )
+        }
+    }
+
+    /// Transmits the signal yielded by an SDK transmit callback, blocking until it is
+    /// sent.
+    ///
+    /// # Safety
+    ///
+    /// `context` must be valid for `callback` until this returns.
+    pub(crate) unsafe fn transmit_raw(
+        &mut self,
+        callback: sys::FuriHalSubGhzAsyncTxCallback,
+        context: *mut c_void,
+    ) -> Result<()> {
+        self.check_tx_allowed()?;
+
+        if !sys::furi_hal_subghz_start_async_tx(callback, context) {
+            return Err(Error::FrequencyNotAllowed);
+        }
+
+        while !sys::furi_hal_subghz_is_async_tx_complete() {
+            sys::furi_delay_ms(1);
+        }
+        sys::furi_hal_subghz_stop_async_tx();
+
+        Ok(())
+    }
+
+    fn check_tx_allowed(&self) -> Result<()> {
+        match self.frequency {
+            Some(frequency) if is_frequency_allowed(frequency) => Ok(()),
+            _ => Err(Error::FrequencyNotAllowed),
+        }
+    }
+}
+
+impl Default for Radio {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Radio {
+    fn drop(&mut self) {
+        stop_async_rx(None);
+        unsafe { sys::furi_hal_subghz_sleep() };
+    }
+}
+
+/// Stops the async RX if it is running with `context`, or with any context if `None`.
+pub(crate) fn stop_async_rx(context: Option<*mut c_void>) {
+    let running = ASYNC_RX_CONTEXT.load(Ordering::Acquire);
+    if running.is_null() || context.map_or(false, |context| context != running) {
+        return;
+    }
+
+    ASYNC_RX_CONTEXT.store(ptr::null_mut(), Ordering::Release);
+    unsafe { sys::furi_hal_subghz_stop_async_rx() };
+}
+
+/// Receives a raw signal until dropped. See [`Radio::async_rx`].
+pub struct AsyncRx<'a> {
+    context: *mut c_void,
+    _radio: PhantomData<&'a mut Radio>,
+}
+
+impl AsyncRx<'_> {
+    /// Returns the received signal strength, in dBm.
+    pub fn rssi(&self) -> f32 {
+        unsafe { sys::furi_hal_subghz_get_rssi() }
+    }
+}
+
+impl Drop for AsyncRx<'_> {
+    fn drop(&mut self) {
+        stop_async_rx(Some(self.context));
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::{is_custom_preset_valid, is_frequency_valid, Error, Preset, Radio};
+
+    #[test]
+    fn frequency_validity() {
+        assert!(is_frequency_valid(433_920_000));
+        assert!(is_frequency_valid(315_000_000));
+        assert!(!is_frequency_valid(100_000_000));
+        assert!(!is_frequency_valid(2_400_000_000));
+    }
+
+    #[test]
+    fn custom_preset_validity() {
+        let pa_table = [0xC0, 0, 0, 0, 0, 0, 0, 0];
+
+        let mut preset = [0u8; 12];
+        preset[..2].copy_from_slice(&[0x02, 0x0D]);
+        preset[4..].copy_from_slice(&pa_table);
+        assert!(is_custom_preset_valid(&preset));
+
+        // Missing PA table.
+        assert!(!is_custom_preset_valid(&preset[..6]));
+        // Missing terminator.
+        assert!(!is_custom_preset_valid(&[0x02, 0x0D]));
+    }
+
+    #[test]
+    fn radio_rejects_invalid_settings() {
+        let mut radio = Radio::new();
+        assert_eq!(
+            radio.load_custom_preset(&[1, 2, 3]),
+            Err(Error::InvalidPreset)
+        );
+        assert_eq!(radio.set_frequency(1_000), Err(Error::InvalidFrequency));
+
+        // Transmitting requires a frequency.
+        assert_eq!(radio.transmit([100, 100]), Err(Error::FrequencyNotAllowed));
+
+        radio.load_preset(Preset::Ook650Async);
+        assert_eq!(
+            Preset::from_sys(Preset::Ook650Async.to_sys()),
+            Some(Preset::Ook650Async)
+        );
+    }
+
+    #[test]
+    fn preset_names_round_trip() {
+        for preset in Preset::ALL {
+            assert_eq!(Preset::from_sys(preset.to_sys()), Some(preset));
+            assert_eq!(Preset::from_name(preset.name()), Some(preset));
+        }
+    }
+}
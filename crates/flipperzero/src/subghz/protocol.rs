@@ -0,0 +1,541 @@
+//! Sub-GHz protocols: the registry of protocols known to the firmware, and the
+//! environment shared by decoders and encoders.
+//!
+//! Protocols are decoders and encoders collected in a [`ProtocolRegistry`]. The firmware
+//! implements many (Princeton, CAME, KeeLoq, ...), but the SDK only exports
+//! [`Protocol::raw`]; other protocols are implemented by the application. An
+//! [`Environment`] holds the registry, along with the manufacturer keystore and
+//! rainbow tables that some of the dynamic protocols need.
+
+use core::ffi::CStr;
+use core::fmt;
+use core::ops::BitOr;
+
+use flipperzero_sys as sys;
+
+use super::Preset;
+use crate::format::{self, FlipperFormat};
+use crate::furi::string::FuriString;
+
+/// Filetype of `.sub` files holding a decoded key.
+pub const KEY_FILETYPE: &CStr =
+    unsafe { CStr::from_bytes_with_nul_unchecked(b"Flipper SubGhz Key File\0") };
+
+/// Filetype of `.sub` files holding a raw signal.
+pub const RAW_FILETYPE: &CStr =
+    unsafe { CStr::from_bytes_with_nul_unchecked(b"Flipper SubGhz RAW File\0") };
+
+/// Version of `.sub` files.
+pub const FILE_VERSION: u32 = 1;
+
+const FREQUENCY: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"Frequency\0") };
+const PRESET: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"Preset\0") };
+const PRESET_CUSTOM: &CStr =
+    unsafe { CStr::from_bytes_with_nul_unchecked(b"FuriHalSubGhzPresetCustom\0") };
+const PROTOCOL: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"Protocol\0") };
+
+/// Path of the manufacturer keystore.
+pub const KEYSTORE_PATH: &CStr =
+    unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/subghz/assets/keeloq_mfcodes\0") };
+
+/// Path of the user's manufacturer keystore.
+pub const KEYSTORE_USER_PATH: &CStr =
+    unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/subghz/assets/keeloq_mfcodes_user\0") };
+
+/// Path of the CAME Atomo rainbow table.
+pub const CAME_ATOMO_PATH: &CStr =
+    unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/subghz/assets/came_atomo\0") };
+
+/// Path of the Alutech AT-4N rainbow table.
+pub const ALUTECH_AT_4N_PATH: &CStr =
+    unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/subghz/assets/alutech_at_4n\0") };
+
+/// Path of the Nice FloR-S rainbow table.
+pub const NICE_FLOR_S_PATH: &CStr =
+    unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/subghz/assets/nice_flor_s\0") };
+
+/// Sub-GHz protocol Result type.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Sub-GHz protocol error kinds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The file could not be read or written.
+    Format(format::Error),
+    /// The radio refused the operation.
+    Radio(super::Error),
+    /// The protocol is not in the registry.
+    UnknownProtocol,
+    /// The keystore could not be loaded.
+    Keystore,
+    /// The file has the wrong filetype or version.
+    InvalidHeader,
+    /// The frequency is missing or invalid.
+    InvalidFrequency,
+    /// The preset is missing or invalid.
+    InvalidPreset,
+    /// The custom preset data is missing or invalid.
+    InvalidCustomPreset,
+    /// The protocol name is missing or doesn't match the protocol.
+    InvalidProtocolName,
+    /// The bit count is missing or not supported by the protocol.
+    InvalidBitCount,
+    /// The key is missing or invalid.
+    InvalidKey,
+    /// The timing element is missing or invalid.
+    InvalidTe,
+    /// Other protocol-specific data is missing or invalid.
+    InvalidData,
+    /// The protocol failed to generate the signal.
+    Upload,
+    /// The protocol failed for an unspecified reason.
+    Failed,
+}
+
+impl Error {
+    /// Describes the error.
+    pub fn description(&self) -> &str {
+        match self {
+            Self::Format(error) => error.description(),
+            Self::Radio(error) => error.description(),
+            Self::UnknownProtocol => "Unknown protocol",
+            Self::Keystore => "Keystore could not be loaded",
+            Self::InvalidHeader => "Invalid header",
+            Self::InvalidFrequency => "Invalid frequency",
+            Self::InvalidPreset => "Invalid preset",
+            Self::InvalidCustomPreset => "Invalid custom preset",
+            Self::InvalidProtocolName => "Invalid protocol name",
+            Self::InvalidBitCount => "Invalid bit count",
+            Self::InvalidKey => "Invalid key",
+            Self::InvalidTe => "Invalid timing element",
+            Self::InvalidData => "Invalid protocol data",
+            Self::Upload => "Signal could not be generated",
+            Self::Failed => "Protocol error",
+        }
+    }
+
+    /// Converts an SDK `SubGhzProtocolStatus` into a `Result`.
+    pub fn from_status(status: sys::SubGhzProtocolStatus) -> Result<()> {
+        Err(match status {
+            sys::SubGhzProtocolStatus_SubGhzProtocolStatusOk => return Ok(()),
+            sys::SubGhzProtocolStatus_SubGhzProtocolStatusErrorParserHeader => Self::InvalidHeader,
+            sys::SubGhzProtocolStatus_SubGhzProtocolStatusErrorParserFrequency => {
+                Self::InvalidFrequency
+            }
+            sys::SubGhzProtocolStatus_SubGhzProtocolStatusErrorParserPreset => Self::InvalidPreset,
+            sys::SubGhzProtocolStatus_SubGhzProtocolStatusErrorParserCustomPreset => {
+                Self::InvalidCustomPreset
+            }
+            sys::SubGhzProtocolStatus_SubGhzProtocolStatusErrorParserProtocolName => {
+                Self::InvalidProtocolName
+            }
+            sys::SubGhzProtocolStatus_SubGhzProtocolStatusErrorParserBitCount
+            | sys::SubGhzProtocolStatus_SubGhzProtocolStatusErrorValueBitCount => {
+                Self::InvalidBitCount
+            }
+            sys::SubGhzProtocolStatus_SubGhzProtocolStatusErrorParserKey => Self::InvalidKey,
+            sys::SubGhzProtocolStatus_SubGhzProtocolStatusErrorParserTe => Self::InvalidTe,
+            sys::SubGhzProtocolStatus_SubGhzProtocolStatusErrorParserOthers => Self::InvalidData,
+            sys::SubGhzProtocolStatus_SubGhzProtocolStatusErrorEncoderGetUpload => Self::Upload,
+            _ => Self::Failed,
+        })
+    }
+}
+
+impl From<format::Error> for Error {
+    fn from(value: format::Error) -> Self {
+        Self::Format(value)
+    }
+}
+
+impl From<super::Error> for Error {
+    fn from(value: super::Error) -> Self {
+        Self::Radio(value)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> core::result::Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+/// Protocol capabilities, also used to filter which protocols a decoder runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProtocolFlags(sys::SubGhzProtocolFlag);
+
+impl ProtocolFlags {
+    /// No flags.
+    pub const NONE: Self = Self(0);
+    /// Captures the raw signal.
+    pub const RAW: Self = Self(sys::SubGhzProtocolFlag_SubGhzProtocolFlag_RAW);
+    /// Decodes signals into keys.
+    pub const DECODABLE: Self = Self(sys::SubGhzProtocolFlag_SubGhzProtocolFlag_Decodable);
+    /// Used on the 315 MHz band.
+    pub const BAND_315: Self = Self(sys::SubGhzProtocolFlag_SubGhzProtocolFlag_315);
+    /// Used on the 433 MHz band.
+    pub const BAND_433: Self = Self(sys::SubGhzProtocolFlag_SubGhzProtocolFlag_433);
+    /// Used on the 868 MHz band.
+    pub const BAND_868: Self = Self(sys::SubGhzProtocolFlag_SubGhzProtocolFlag_868);
+    /// Uses amplitude modulation.
+    pub const AM: Self = Self(sys::SubGhzProtocolFlag_SubGhzProtocolFlag_AM);
+    /// Uses frequency modulation.
+    pub const FM: Self = Self(sys::SubGhzProtocolFlag_SubGhzProtocolFlag_FM);
+    /// Keys can be saved.
+    pub const SAVE: Self = Self(sys::SubGhzProtocolFlag_SubGhzProtocolFlag_Save);
+    /// Keys can be loaded.
+    pub const LOAD: Self = Self(sys::SubGhzProtocolFlag_SubGhzProtocolFlag_Load);
+    /// Keys can be transmitted.
+    pub const SEND: Self = Self(sys::SubGhzProtocolFlag_SubGhzProtocolFlag_Send);
+    /// Captures unknown signals as binary data.
+    pub const BIN_RAW: Self = Self(sys::SubGhzProtocolFlag_SubGhzProtocolFlag_BinRAW);
+
+    /// Get the flags from the corresponding SDK `SubGhzProtocolFlag`.
+    pub fn from_sys(flags: sys::SubGhzProtocolFlag) -> Self {
+        Self(flags)
+    }
+
+    /// Convert the flags into the corresponding SDK `SubGhzProtocolFlag`.
+    pub fn to_sys(self) -> sys::SubGhzProtocolFlag {
+        self.0
+    }
+
+    /// Returns `true` if all of `other`'s flags are set.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for ProtocolFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// A protocol implemented by the firmware.
+#[derive(Clone, Copy)]
+pub struct Protocol {
+    raw: &'static sys::SubGhzProtocol,
+}
+
+impl Protocol {
+    /// Wraps an SDK `SubGhzProtocol`.
+    ///
+    /// # Safety
+    ///
+    /// `raw` must point to a valid `SubGhzProtocol` that lives for the rest of the
+    /// program, such as the ones in the firmware's registry.
+    pub unsafe fn from_raw(raw: *const sys::SubGhzProtocol) -> Self {
+        Self { raw: &*raw }
+    }
+
+    /// Returns the raw pointer to the underlying `SubGhzProtocol`.
+    pub fn as_ptr(&self) -> *const sys::SubGhzProtocol {
+        self.raw
+    }
+
+    /// The protocol that captures the raw signal.
+    pub fn raw() -> Self {
+        Self {
+            raw: unsafe { &sys::subghz_protocol_raw },
+        }
+    }
+
+    /// Returns the name of the protocol, as used in `.sub` files.
+    pub fn name(&self) -> &'static CStr {
+        unsafe { CStr::from_ptr(self.raw.name) }
+    }
+
+    /// Returns the capabilities of the protocol.
+    pub fn flags(&self) -> ProtocolFlags {
+        ProtocolFlags::from_sys(self.raw.flag)
+    }
+}
+
+impl fmt::Debug for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Protocol").field(&self.name()).finish()
+    }
+}
+
+impl PartialEq for Protocol {
+    fn eq(&self, other: &Self) -> bool {
+        core::ptr::eq(self.raw, other.raw)
+    }
+}
+
+impl Eq for Protocol {}
+
+/// A list of protocols.
+///
+/// The SDK does not export the firmware's own registry, so applications list the
+/// protocols they use; only [`Protocol::raw`] is exported by the current SDK.
+#[repr(transparent)]
+pub struct ProtocolRegistry(sys::SubGhzProtocolRegistry);
+
+// SAFETY: The registry is never modified after it is created.
+unsafe impl Sync for ProtocolRegistry {}
+
+impl ProtocolRegistry {
+    /// Makes a registry from a list of protocols.
+    pub const fn new(protocols: &'static [&'static sys::SubGhzProtocol]) -> Self {
+        Self(sys::SubGhzProtocolRegistry {
+            // The firmware only reads the list.
+            items: protocols.as_ptr() as *mut *const sys::SubGhzProtocol,
+            size: protocols.len(),
+        })
+    }
+
+    /// Returns the raw pointer to the underlying `SubGhzProtocolRegistry`.
+    pub fn as_ptr(&self) -> *const sys::SubGhzProtocolRegistry {
+        &self.0
+    }
+
+    /// Returns the number of protocols.
+    pub fn len(&self) -> usize {
+        unsafe { sys::subghz_protocol_registry_count(&self.0) }
+    }
+
+    /// Returns `true` if the registry has no protocols.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the protocol at `index`.
+    pub fn get(&self, index: usize) -> Option<Protocol> {
+        if index >= self.len() {
+            return None;
+        }
+
+        Some(unsafe {
+            Protocol::from_raw(sys::subghz_protocol_registry_get_by_index(&self.0, index))
+        })
+    }
+
+    /// Returns the protocol with the given name.
+    pub fn find(&self, name: &CStr) -> Option<Protocol> {
+        let raw = unsafe { sys::subghz_protocol_registry_get_by_name(&self.0, name.as_ptr()) };
+        if raw.is_null() {
+            None
+        } else {
+            Some(unsafe { Protocol::from_raw(raw) })
+        }
+    }
+
+    /// Returns the protocols.
+    pub fn iter(&self) -> impl Iterator<Item = Protocol> + '_ {
+        (0..self.len()).filter_map(|index| self.get(index))
+    }
+}
+
+/// A registry with just the raw protocol.
+pub static RAW_REGISTRY: ProtocolRegistry =
+    ProtocolRegistry::new(&[unsafe { &sys::subghz_protocol_raw }]);
+
+/// The radio settings a signal was received with, as saved in `.sub` files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RadioPreset<'a> {
+    /// Name of the preset in the Sub-GHz settings (e.g. `AM650`); see
+    /// [`Setting::preset_name`](super::setting::Setting::preset_name).
+    pub name: &'a CStr,
+    /// Frequency, in Hz.
+    pub frequency: u32,
+    /// Register data of the preset, which is saved for custom presets; see
+    /// [`Setting::preset_data`](super::setting::Setting::preset_data).
+    pub data: &'a [u8],
+}
+
+impl<'a> RadioPreset<'a> {
+    /// Makes a radio preset.
+    pub fn new(name: &'a CStr, frequency: u32, data: &'a [u8]) -> Self {
+        Self {
+            name,
+            frequency,
+            data,
+        }
+    }
+
+    /// Calls `f` with the corresponding SDK `SubGhzRadioPreset`.
+    pub(crate) fn with_sys<T>(&self, f: impl FnOnce(*mut sys::SubGhzRadioPreset) -> T) -> T {
+        let mut name = FuriString::from_c_str(self.name);
+        let mut preset = sys::SubGhzRadioPreset {
+            name: name.as_mut_ptr(),
+            frequency: self.frequency,
+            // The firmware only reads the data.
+            data: self.data.as_ptr() as *mut u8,
+            data_size: self.data.len(),
+        };
+
+        f(&mut preset)
+    }
+}
+
+/// The settings at the start of a `.sub` file.
+pub(crate) struct FileHeader {
+    pub frequency: u32,
+    /// `None` for a custom preset.
+    pub preset: Option<Preset>,
+    pub protocol: FuriString,
+}
+
+impl FileHeader {
+    /// Reads the header of a `.sub` file with the given filetype.
+    pub fn read(ff: &mut FlipperFormat, filetype: &CStr) -> Result<Self> {
+        let (actual_filetype, version) = ff.read_header()?;
+        if actual_filetype.as_c_str() != filetype || version != FILE_VERSION {
+            return Err(Error::InvalidHeader);
+        }
+
+        let mut frequency = 0;
+        ff.read_u32(FREQUENCY, core::slice::from_mut(&mut frequency))
+            .map_err(|_| Error::InvalidFrequency)?;
+
+        let mut value = FuriString::new();
+        ff.read_string(PRESET, &mut value)
+            .map_err(|_| Error::InvalidPreset)?;
+        let preset = match Preset::from_name(value.as_c_str()) {
+            Some(preset) => Some(preset),
+            None if value.as_c_str() == PRESET_CUSTOM => None,
+            None => return Err(Error::InvalidPreset),
+        };
+
+        ff.read_string(PROTOCOL, &mut value)
+            .map_err(|_| Error::InvalidProtocolName)?;
+
+        Ok(Self {
+            frequency,
+            preset,
+            protocol: value,
+        })
+    }
+}
+
+/// The registry, keystore and rainbow tables used by decoders and encoders.
+pub struct Environment {
+    raw: *mut sys::SubGhzEnvironment,
+    registry: &'static ProtocolRegistry,
+}
+
+impl Environment {
+    /// Allocates an environment for the protocols of `registry`.
+    pub fn new(registry: &'static ProtocolRegistry) -> Self {
+        let raw = unsafe { sys::subghz_environment_alloc() };
+        assert!(!raw.is_null());
+
+        unsafe {
+            sys::subghz_environment_set_protocol_registry(
+                raw,
+                registry.as_ptr() as *mut core::ffi::c_void,
+            );
+        }
+
+        Self { raw, registry }
+    }
+
+    /// Allocates an environment and loads the keystores and rainbow tables of the
+    /// Sub-GHz application, like the application does.
+    ///
+    /// A missing user keystore is not an error.
+    pub fn with_assets(registry: &'static ProtocolRegistry) -> Result<Self> {
+        let mut environment = Self::new(registry);
+        environment.load_keystore(KEYSTORE_PATH)?;
+        let _ = environment.load_keystore(KEYSTORE_USER_PATH);
+        environment.set_rainbow_tables(CAME_ATOMO_PATH, ALUTECH_AT_4N_PATH, NICE_FLOR_S_PATH);
+
+        Ok(environment)
+    }
+
+    /// Returns the raw pointer to the underlying `SubGhzEnvironment`.
+    pub fn as_ptr(&self) -> *mut sys::SubGhzEnvironment {
+        self.raw
+    }
+
+    /// Returns the protocol registry.
+    pub fn registry(&self) -> &'static ProtocolRegistry {
+        self.registry
+    }
+
+    /// Loads manufacturer keys from a keystore file, adding them to the loaded keys.
+    pub fn load_keystore(&mut self, path: &CStr) -> Result<()> {
+        if unsafe { sys::subghz_environment_load_keystore(self.raw, path.as_ptr()) } {
+            Ok(())
+        } else {
+            Err(Error::Keystore)
+        }
+    }
+
+    /// Sets the rainbow tables used by the CAME Atomo, Alutech AT-4N and Nice FloR-S
+    /// protocols. The files are read when the protocols need them.
+    pub fn set_rainbow_tables(
+        &mut self,
+        came_atomo: &'static CStr,
+        alutech_at_4n: &'static CStr,
+        nice_flor_s: &'static CStr,
+    ) {
+        unsafe {
+            sys::subghz_environment_set_came_atomo_rainbow_table_file_name(
+                self.raw,
+                came_atomo.as_ptr(),
+            );
+            sys::subghz_environment_set_alutech_at_4n_rainbow_table_file_name(
+                self.raw,
+                alutech_at_4n.as_ptr(),
+            );
+            sys::subghz_environment_set_nice_flor_s_rainbow_table_file_name(
+                self.raw,
+                nice_flor_s.as_ptr(),
+            );
+        }
+    }
+}
+
+impl Drop for Environment {
+    fn drop(&mut self) {
+        unsafe { sys::subghz_environment_free(self.raw) };
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::ffi::CStr;
+
+    use super::{Environment, Error, Protocol, ProtocolFlags, RAW_REGISTRY};
+
+    #[test]
+    fn raw_registry() {
+        let raw = Protocol::raw();
+        assert_eq!(RAW_REGISTRY.len(), 1);
+        assert_eq!(RAW_REGISTRY.get(0), Some(raw));
+        assert_eq!(RAW_REGISTRY.get(1), None);
+        assert_eq!(RAW_REGISTRY.find(raw.name()), Some(raw));
+        assert_eq!(
+            RAW_REGISTRY.find(CStr::from_bytes_with_nul(b"Unknown\0").unwrap()),
+            None
+        );
+        assert!(raw
+            .flags()
+            .contains(ProtocolFlags::RAW | ProtocolFlags::SAVE));
+        assert!(!raw.flags().contains(ProtocolFlags::DECODABLE));
+    }
+
+    #[test]
+    fn missing_keystore() {
+        let mut environment = Environment::new(&RAW_REGISTRY);
+        assert_eq!(
+            environment.load_keystore(CStr::from_bytes_with_nul(b"/ext/missing\0").unwrap()),
+            Err(Error::Keystore)
+        );
+    }
+}
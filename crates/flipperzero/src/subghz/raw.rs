@@ -0,0 +1,333 @@
+//! Recording and playing raw signals as `.sub` RAW files.
+//!
+//! RAW files store the received signal as-is, as the durations of alternating levels:
+//!
+//! ```text
+//! Filetype: Flipper SubGhz RAW File
+//! Version: 1
+//! Frequency: 433920000
+//! Preset: FuriHalSubGhzPresetOok650Async
+//! Protocol: RAW
+//! RAW_Data: 29262 -362 1403 -1030 ...
+//! ```
+//!
+//! Positive durations are high levels and negative durations low levels, in
+//! microseconds. Files are written and read in chunks, so captures can be longer than
+//! would fit in memory.
+//!
+//! # Examples
+//!
+//! ```
+//! let environment = Environment::new(&RAW_REGISTRY);
+//! let preset = RadioPreset::new(CStr::from_bytes_with_nul(b"AM650\0").unwrap(), 433_920_000, &[]);
+//! let mut recorder =
+//!     RawRecorder::new(&environment, CStr::from_bytes_with_nul(b"capture\0").unwrap(), &preset)?;
+//!
+//! let mut radio = Radio::new();
+//! radio.load_preset(Preset::Ook650Async);
+//! radio.set_frequency(preset.frequency)?;
+//! let recording = recorder.start(&mut radio);
+//! for _ in 0..10 {
+//!     info!("raw", "RSSI {} dBm, {} samples", recording.rssi(), recording.sample_count());
+//!     sleep(Duration::from_millis(100));
+//! }
+//! ```
+
+use core::ffi::{c_void, CStr};
+use core::marker::PhantomData;
+
+use flipperzero_sys as sys;
+
+use super::protocol::{
+    Environment, Error, FileHeader, Protocol, RadioPreset, Result, RAW_FILETYPE,
+};
+use super::{AsyncRx, Preset, Radio};
+use crate::format::{self, FlipperFormat};
+use crate::furi::string::FuriString;
+
+/// Folder that RAW files are recorded to.
+pub const RAW_FOLDER: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/subghz\0") };
+
+/// Records the received signal to a RAW file.
+pub struct RawRecorder<'a> {
+    decoder: *mut sys::SubGhzProtocolDecoderRAW,
+    worker: *mut sys::SubGhzWorker,
+    _environment: PhantomData<&'a Environment>,
+}
+
+impl<'a> RawRecorder<'a> {
+    /// Creates the RAW file `name` in [`RAW_FOLDER`] (without the `.sub` extension),
+    /// replacing any existing file.
+    ///
+    /// `preset` holds the radio settings that are saved in the file; the radio itself
+    /// must be configured to match.
+    pub fn new(
+        environment: &'a Environment,
+        name: &CStr,
+        preset: &RadioPreset<'_>,
+    ) -> Result<Self> {
+        unsafe extern "C" fn pair(context: *mut c_void, level: bool, duration: u32) {
+            sys::subghz_protocol_decoder_raw_feed(context, level, duration);
+        }
+
+        unsafe extern "C" fn overrun(context: *mut c_void) {
+            sys::subghz_protocol_decoder_raw_reset(context);
+        }
+
+        let decoder = unsafe { sys::subghz_protocol_decoder_raw_alloc(environment.as_ptr()) }
+            as *mut sys::SubGhzProtocolDecoderRAW;
+        assert!(!decoder.is_null());
+
+        let opened = preset.with_sys(|preset| unsafe {
+            sys::subghz_protocol_raw_save_to_file_init(decoder, name.as_ptr(), preset)
+        });
+        if !opened {
+            unsafe { sys::subghz_protocol_decoder_raw_free(decoder as *mut c_void) };
+            return Err(Error::Format(format::Error::Io));
+        }
+
+        let worker = unsafe { sys::subghz_worker_alloc() };
+        assert!(!worker.is_null());
+        unsafe {
+            sys::subghz_worker_set_overrun_callback(worker, Some(overrun));
+            sys::subghz_worker_set_pair_callback(worker, Some(pair));
+            sys::subghz_worker_set_context(worker, decoder as *mut c_void);
+        }
+
+        Ok(Self {
+            decoder,
+            worker,
+            _environment: PhantomData,
+        })
+    }
+
+    /// Records a level and its duration in microseconds.
+    ///
+    /// Repeated levels and glitches shorter than the protocol's minimum duration are
+    /// dropped.
+    pub fn feed(&mut self, level: bool, duration: u32) {
+        unsafe {
+            sys::subghz_protocol_decoder_raw_feed(self.decoder as *mut c_void, level, duration)
+        };
+    }
+
+    /// Pauses or resumes recording. The signal received while paused is dropped.
+    pub fn set_paused(&mut self, paused: bool) {
+        unsafe { sys::subghz_protocol_raw_save_to_file_pause(self.decoder, paused) };
+    }
+
+    /// Returns the number of samples recorded so far.
+    pub fn sample_count(&self) -> usize {
+        unsafe { sys::subghz_protocol_raw_get_sample_write(self.decoder) }
+    }
+
+    /// Starts recording the signal received by `radio`, until the returned guard is
+    /// dropped.
+    ///
+    /// Samples are written to the file from the worker thread.
+    pub fn start<'r>(&'r mut self, radio: &'r mut Radio) -> Recording<'r> {
+        let rx = unsafe {
+            radio.start_async_rx_raw(
+                Some(sys::subghz_worker_rx_callback),
+                self.worker as *mut c_void,
+            )
+        };
+        unsafe {
+            // A leaked `Recording` guard leaves the worker running.
+            if sys::subghz_worker_is_running(self.worker) {
+                sys::subghz_worker_stop(self.worker);
+            }
+            sys::subghz_worker_start(self.worker);
+        }
+
+        Recording {
+            decoder: self.decoder,
+            worker: self.worker,
+            rx,
+            _recorder: PhantomData,
+        }
+    }
+}
+
+impl Drop for RawRecorder<'_> {
+    fn drop(&mut self) {
+        // The `Recording` guard may have been leaked, leaving the worker running.
+        unsafe {
+            if sys::subghz_worker_is_running(self.worker) {
+                sys::subghz_worker_stop(self.worker);
+            }
+        }
+        super::stop_async_rx(Some(self.worker as *mut c_void));
+
+        unsafe {
+            // Writes the remaining samples and closes the file.
+            sys::subghz_protocol_raw_save_to_file_stop(self.decoder);
+            sys::subghz_worker_free(self.worker);
+            sys::subghz_protocol_decoder_raw_free(self.decoder as *mut c_void);
+        }
+    }
+}
+
+/// Records the received signal until dropped. See [`RawRecorder::start`].
+pub struct Recording<'a> {
+    decoder: *mut sys::SubGhzProtocolDecoderRAW,
+    worker: *mut sys::SubGhzWorker,
+    rx: AsyncRx<'a>,
+    _recorder: PhantomData<&'a mut sys::SubGhzProtocolDecoderRAW>,
+}
+
+impl Recording<'_> {
+    /// Returns the received signal strength, in dBm.
+    pub fn rssi(&self) -> f32 {
+        self.rx.rssi()
+    }
+
+    /// Returns the number of samples recorded so far.
+    pub fn sample_count(&self) -> usize {
+        unsafe { sys::subghz_protocol_raw_get_sample_write(self.decoder) }
+    }
+}
+
+impl Drop for Recording<'_> {
+    fn drop(&mut self) {
+        // Stopping joins the worker thread; the radio stops receiving afterwards, when
+        // `rx` is dropped.
+        unsafe { sys::subghz_worker_stop(self.worker) };
+    }
+}
+
+/// Plays a RAW file.
+pub struct RawPlayer<'a> {
+    encoder: *mut sys::SubGhzProtocolEncoderRAW,
+    path: FuriString,
+    frequency: u32,
+    preset: Option<Preset>,
+    _environment: PhantomData<&'a Environment>,
+}
+
+impl<'a> RawPlayer<'a> {
+    /// Opens a RAW file, reading its radio settings.
+    pub fn load(environment: &'a Environment, path: &CStr) -> Result<Self> {
+        let mut ff = FlipperFormat::new_buffered_file();
+        ff.open_existing(path)?;
+        let header = FileHeader::read(&mut ff, RAW_FILETYPE)?;
+        if header.protocol.as_c_str() != Protocol::raw().name() {
+            return Err(Error::InvalidProtocolName);
+        }
+
+        let encoder = unsafe { sys::subghz_protocol_encoder_raw_alloc(environment.as_ptr()) }
+            as *mut sys::SubGhzProtocolEncoderRAW;
+        assert!(!encoder.is_null());
+
+        Ok(Self {
+            encoder,
+            path: FuriString::from_c_str(path),
+            frequency: header.frequency,
+            preset: header.preset,
+            _environment: PhantomData,
+        })
+    }
+
+    /// Returns the frequency the signal is played on, in Hz.
+    pub fn frequency(&self) -> u32 {
+        self.frequency
+    }
+
+    /// Returns the preset the signal is played with, or `None` for a custom preset.
+    pub fn preset(&self) -> Option<Preset> {
+        self.preset
+    }
+
+    /// Plays the signal, blocking until the end of the file.
+    ///
+    /// The radio is tuned to the file's frequency, and its preset is loaded. Files
+    /// recorded with a custom preset are played with the preset already loaded in the
+    /// radio.
+    pub fn play(&mut self, radio: &mut Radio) -> Result<()> {
+        if let Some(preset) = self.preset {
+            radio.load_preset(preset);
+        }
+        radio.set_frequency(self.frequency)?;
+
+        // The encoder streams the file from a worker thread, starting when the file
+        // name is deserialized.
+        let mut ff = FlipperFormat::new_string();
+        unsafe {
+            sys::subghz_protocol_raw_gen_fff_data(ff.as_ptr(), self.path.as_c_str().as_ptr())
+        };
+        ff.rewind()?;
+        Error::from_status(unsafe {
+            sys::subghz_protocol_encoder_raw_deserialize(self.encoder as *mut c_void, ff.as_ptr())
+        })?;
+
+        let result = unsafe {
+            radio.transmit_raw(
+                Some(sys::subghz_protocol_encoder_raw_yield),
+                self.encoder as *mut c_void,
+            )
+        };
+        unsafe { sys::subghz_protocol_encoder_raw_stop(self.encoder as *mut c_void) };
+
+        result.map_err(Error::from)
+    }
+}
+
+impl Drop for RawPlayer<'_> {
+    fn drop(&mut self) {
+        unsafe { sys::subghz_protocol_encoder_raw_free(self.encoder as *mut c_void) };
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::ffi::CStr;
+
+    use super::{RawPlayer, RawRecorder};
+    use crate::format::FlipperFormat;
+    use crate::storage;
+    use crate::subghz::protocol::{Environment, RadioPreset, RAW_FILETYPE, RAW_REGISTRY};
+    use crate::subghz::Preset;
+
+    const NAME: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"flipperzero-rs-raw\0") };
+    const PATH: &CStr =
+        unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/subghz/flipperzero-rs-raw.sub\0") };
+
+    #[test]
+    fn record_and_load() {
+        let environment = Environment::new(&RAW_REGISTRY);
+        let preset = RadioPreset::new(
+            CStr::from_bytes_with_nul(b"AM650\0").unwrap(),
+            433_920_000,
+            &[],
+        );
+
+        {
+            let mut recorder = RawRecorder::new(&environment, NAME, &preset).unwrap();
+            for i in 0..10 {
+                recorder.feed(i % 2 == 0, 500);
+            }
+            assert_eq!(recorder.sample_count(), 10);
+        }
+
+        let mut ff = FlipperFormat::new_file();
+        ff.open_existing(PATH).unwrap();
+        let (filetype, _) = ff.read_header().unwrap();
+        assert_eq!(filetype.as_c_str(), RAW_FILETYPE);
+        let raw_data = CStr::from_bytes_with_nul(b"RAW_Data\0").unwrap();
+        let mut samples = [0i32; 10];
+        assert_eq!(ff.value_count(raw_data), Ok(10));
+        ff.read_i32(raw_data, &mut samples).unwrap();
+        assert_eq!(
+            samples,
+            [500, -500, 500, -500, 500, -500, 500, -500, 500, -500]
+        );
+        drop(ff);
+
+        let player = RawPlayer::load(&environment, PATH).unwrap();
+        assert_eq!(player.frequency(), 433_920_000);
+        assert_eq!(player.preset(), Some(Preset::Ook650Async));
+        drop(player);
+
+        storage::remove(PATH).unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+//! Sub-GHz settings: the frequency list and modulation presets of the Sub-GHz
+//! application.
+
+use core::ffi::CStr;
+
+use flipperzero_sys as sys;
+
+use crate::format::FlipperFormat;
+
+/// Path of the user's Sub-GHz settings.
+pub const SETTING_USER_PATH: &CStr =
+    unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/subghz/assets/setting_user\0") };
+
+/// The frequencies and presets offered by the Sub-GHz application.
+pub struct Setting {
+    raw: *mut sys::SubGhzSetting,
+}
+
+impl Setting {
+    /// Loads the settings from a file, falling back to the firmware defaults for
+    /// anything the file doesn't define.
+    pub fn load(path: &CStr) -> Self {
+        let raw = unsafe { sys::subghz_setting_alloc() };
+        assert!(!raw.is_null());
+
+        unsafe { sys::subghz_setting_load(raw, path.as_ptr()) };
+        Self { raw }
+    }
+
+    /// Loads the user's settings (see [`SETTING_USER_PATH`]).
+    pub fn load_user() -> Self {
+        Self::load(SETTING_USER_PATH)
+    }
+
+    /// Returns the raw pointer to the underlying `SubGhzSetting`.
+    pub fn as_ptr(&self) -> *mut sys::SubGhzSetting {
+        self.raw
+    }
+
+    /// Returns the frequencies, in Hz.
+    pub fn frequencies(&self) -> impl Iterator<Item = u32> + '_ {
+        let count = unsafe { sys::subghz_setting_get_frequency_count(self.raw) };
+        (0..count).map(move |i| unsafe { sys::subghz_setting_get_frequency(self.raw, i) })
+    }
+
+    /// Returns the frequencies that are scanned when hopping, in Hz.
+    pub fn hopper_frequencies(&self) -> impl Iterator<Item = u32> + '_ {
+        let count = unsafe { sys::subghz_setting_get_hopper_frequency_count(self.raw) };
+        (0..count).map(move |i| unsafe { sys::subghz_setting_get_hopper_frequency(self.raw, i) })
+    }
+
+    /// Returns the default frequency, in Hz.
+    pub fn default_frequency(&self) -> u32 {
+        unsafe { sys::subghz_setting_get_default_frequency(self.raw) }
+    }
+
+    /// Returns the number of presets.
+    pub fn preset_count(&self) -> usize {
+        unsafe { sys::subghz_setting_get_preset_count(self.raw) }
+    }
+
+    /// Returns the name of the preset at `index` (e.g. `AM650`).
+    pub fn preset_name(&self, index: usize) -> Option<&CStr> {
+        if index >= self.preset_count() {
+            return None;
+        }
+
+        Some(unsafe { CStr::from_ptr(sys::subghz_setting_get_preset_name(self.raw, index)) })
+    }
+
+    /// Returns the index of the preset with the given name.
+    pub fn preset_index(&self, name: &CStr) -> Option<usize> {
+        let index = unsafe { sys::subghz_setting_get_inx_preset_by_name(self.raw, name.as_ptr()) };
+        usize::try_from(index)
+            .ok()
+            .filter(|&index| index < self.preset_count())
+    }
+
+    /// Returns the register data of the preset at `index`, for
+    /// [`Radio::load_custom_preset`](super::Radio::load_custom_preset).
+    pub fn preset_data(&self, index: usize) -> Option<&[u8]> {
+        if index >= self.preset_count() {
+            return None;
+        }
+
+        unsafe {
+            let data = sys::subghz_setting_get_preset_data(self.raw, index);
+            let len = sys::subghz_setting_get_preset_data_size(self.raw, index);
+            Some(core::slice::from_raw_parts(data, len))
+        }
+    }
+
+    /// Returns the register data of the preset with the given name.
+    pub fn preset_data_by_name(&self, name: &CStr) -> Option<&[u8]> {
+        self.preset_data(self.preset_index(name)?)
+    }
+
+    /// Adds a custom preset, reading its registers from the `Custom_preset_data` key
+    /// of `ff`.
+    pub fn load_custom_preset(&mut self, name: &CStr, ff: &mut FlipperFormat) -> bool {
+        unsafe { sys::subghz_setting_load_custom_preset(self.raw, name.as_ptr(), ff.as_ptr()) }
+    }
+
+    /// Removes a custom preset.
+    pub fn delete_custom_preset(&mut self, name: &CStr) -> bool {
+        unsafe { sys::subghz_setting_delete_custom_preset(self.raw, name.as_ptr()) }
+    }
+}
+
+impl Drop for Setting {
+    fn drop(&mut self) {
+        unsafe { sys::subghz_setting_free(self.raw) };
+    }
+}
@@ -19,3 +19,9 @@ pub use self::md5::Md5;
 
 pub(crate) mod sha256;
 pub use sha256::Sha256;
+
+pub mod stream;
+pub use self::stream::Stream;
+
+pub mod tar;
+pub use self::tar::TarArchive;
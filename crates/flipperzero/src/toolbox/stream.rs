@@ -0,0 +1,515 @@
+//! Generic byte streams.
+//!
+//! A [`Stream`] is the firmware's common interface over in-memory strings and files. It
+//! is also the storage layer underneath [`FlipperFormat`](crate::format::FlipperFormat),
+//! whose stream can be accessed with
+//! [`FlipperFormat::stream`](crate::format::FlipperFormat::stream).
+
+use core::ffi::{c_char, c_void, CStr};
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::ops::Deref;
+
+use flipperzero_sys as sys;
+use flipperzero_sys::furi::UnsafeRecord;
+
+use crate::io::{Error, Read, Seek, SeekFrom, Write};
+use crate::storage::OpenOptions;
+
+const RECORD_STORAGE: *const c_char = sys::c_string!("storage");
+
+/// The storage that backs a [`Stream`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Backend {
+    String,
+    File,
+    BufferedFile,
+}
+
+/// A readable, writable and seekable byte stream.
+///
+/// In addition to [`Read`], [`Write`] and [`Seek`], streams support inserting and
+/// deleting data in the middle of the stream, which [`File`](crate::storage::File)
+/// cannot do.
+pub struct Stream {
+    raw: *mut sys::Stream,
+    backend: Backend,
+    _storage: Option<UnsafeRecord<sys::Storage>>,
+}
+
+impl Stream {
+    /// Creates an empty stream backed by memory.
+    pub fn new_string() -> Self {
+        let raw = unsafe { sys::string_stream_alloc() };
+        assert!(!raw.is_null());
+
+        Stream {
+            raw,
+            backend: Backend::String,
+            _storage: None,
+        }
+    }
+
+    /// Opens a stream backed by the file at `path`.
+    pub fn open_file(path: &CStr, options: OpenOptions) -> Result<Self, Error> {
+        let storage = unsafe { UnsafeRecord::open(RECORD_STORAGE) };
+        let raw = unsafe { sys::file_stream_alloc(storage.as_ptr()) };
+        assert!(!raw.is_null());
+
+        let stream = Stream {
+            raw,
+            backend: Backend::File,
+            _storage: Some(storage),
+        };
+        let (access_mode, open_mode) = options.modes();
+        if unsafe { sys::file_stream_open(raw, path.as_ptr(), access_mode, open_mode) } {
+            Ok(stream)
+        } else {
+            Err(stream.last_error())
+        }
+    }
+
+    /// Opens a stream backed by the file at `path`, with buffered reads and writes.
+    ///
+    /// Buffered writes are only committed to the file by [`Write::flush`] or when the
+    /// stream is dropped.
+    pub fn open_buffered_file(path: &CStr, options: OpenOptions) -> Result<Self, Error> {
+        let storage = unsafe { UnsafeRecord::open(RECORD_STORAGE) };
+        let raw = unsafe { sys::buffered_file_stream_alloc(storage.as_ptr()) };
+        assert!(!raw.is_null());
+
+        let stream = Stream {
+            raw,
+            backend: Backend::BufferedFile,
+            _storage: Some(storage),
+        };
+        let (access_mode, open_mode) = options.modes();
+        if unsafe { sys::buffered_file_stream_open(raw, path.as_ptr(), access_mode, open_mode) } {
+            Ok(stream)
+        } else {
+            Err(stream.last_error())
+        }
+    }
+
+    /// Obtain raw Furi Stream handle.
+    ///
+    /// This pointer must not be `free`d or otherwise invalidated.
+    /// It must not be referenced after `Stream` has been dropped.
+    pub fn as_ptr(&self) -> *mut sys::Stream {
+        self.raw
+    }
+
+    /// Returns the size of the stream in bytes.
+    pub fn len(&self) -> usize {
+        unsafe { sys::stream_size(self.raw) }
+    }
+
+    /// Returns `true` if the stream is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if the current position is at the end of the stream.
+    pub fn eof(&self) -> bool {
+        unsafe { sys::stream_eof(self.raw) }
+    }
+
+    /// Removes all data from the stream.
+    pub fn clear(&mut self) {
+        unsafe { sys::stream_clean(self.raw) };
+    }
+
+    /// Inserts `data` at the current position, moving the following data back.
+    ///
+    /// The position is moved to the end of the inserted data.
+    pub fn insert(&mut self, data: &[u8]) -> Result<(), Error> {
+        if unsafe { sys::stream_insert(self.raw, data.as_ptr(), data.len()) } {
+            Ok(())
+        } else {
+            Err(self.last_error())
+        }
+    }
+
+    /// Deletes `len` bytes starting at the current position, moving the following data
+    /// forward.
+    pub fn delete(&mut self, len: usize) -> Result<(), Error> {
+        if unsafe { sys::stream_delete(self.raw, len) } {
+            Ok(())
+        } else {
+            Err(self.last_error())
+        }
+    }
+
+    /// Replaces `len` bytes starting at the current position with `data`.
+    ///
+    /// The position is moved to the end of the inserted data.
+    pub fn replace(&mut self, len: usize, data: &[u8]) -> Result<(), Error> {
+        unsafe extern "C" fn write(stream: *mut sys::Stream, context: *const c_void) -> bool {
+            let data = &*(context as *const &[u8]);
+            sys::stream_write(stream, data.as_ptr(), data.len()) == data.len()
+        }
+
+        let ok = unsafe {
+            sys::stream_delete_and_insert(
+                self.raw,
+                len,
+                Some(write),
+                &data as *const &[u8] as *const c_void,
+            )
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(self.last_error())
+        }
+    }
+
+    /// Copies `len` bytes from the current position of this stream to the current
+    /// position of `to`.
+    ///
+    /// Returns the number of bytes copied.
+    pub fn copy_to(&mut self, to: &mut Stream, len: usize) -> usize {
+        unsafe { sys::stream_copy(self.raw, to.raw, len) }
+    }
+
+    /// Inserts the contents of the file at `path` at the current position.
+    ///
+    /// Returns the number of bytes loaded.
+    pub fn load_from_file(&mut self, path: &CStr) -> usize {
+        let storage: UnsafeRecord<sys::Storage> = unsafe { UnsafeRecord::open(RECORD_STORAGE) };
+        unsafe { sys::stream_load_from_file(self.raw, storage.as_ptr(), path.as_ptr()) }
+    }
+
+    /// Writes the data from the current position to the end of the stream to the file
+    /// at `path`, replacing any existing file.
+    ///
+    /// Returns the number of bytes saved.
+    pub fn save_to_file(&mut self, path: &CStr) -> usize {
+        let storage: UnsafeRecord<sys::Storage> = unsafe { UnsafeRecord::open(RECORD_STORAGE) };
+        unsafe {
+            sys::stream_save_to_file(
+                self.raw,
+                storage.as_ptr(),
+                path.as_ptr(),
+                sys::FS_OpenMode_FSOM_CREATE_ALWAYS,
+            )
+        }
+    }
+
+    fn last_error(&self) -> Error {
+        let error = match self.backend {
+            Backend::String => return Error::Internal,
+            Backend::File => unsafe { sys::file_stream_get_error(self.raw) },
+            Backend::BufferedFile => unsafe { sys::buffered_file_stream_get_error(self.raw) },
+        };
+
+        Error::from_sys(error).unwrap_or(Error::Internal)
+    }
+
+    /// Returns an error if the backing file reports one.
+    fn check_error(&self) -> Result<(), Error> {
+        let error = match self.backend {
+            Backend::String => return Ok(()),
+            Backend::File => unsafe { sys::file_stream_get_error(self.raw) },
+            Backend::BufferedFile => unsafe { sys::buffered_file_stream_get_error(self.raw) },
+        };
+
+        match Error::from_sys(error) {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        unsafe {
+            match self.backend {
+                Backend::String => (),
+                Backend::File => {
+                    sys::file_stream_close(self.raw);
+                }
+                Backend::BufferedFile => {
+                    sys::buffered_file_stream_close(self.raw);
+                }
+            }
+            sys::stream_free(self.raw);
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let bytes_read = unsafe { sys::stream_read(self.raw, buf.as_mut_ptr(), buf.len()) };
+        self.check_error()?;
+
+        Ok(bytes_read)
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let bytes_written = unsafe { sys::stream_write(self.raw, buf.as_ptr(), buf.len()) };
+        self.check_error()?;
+
+        Ok(bytes_written)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        if self.backend == Backend::BufferedFile
+            && !unsafe { sys::buffered_file_stream_sync(self.raw) }
+        {
+            return Err(self.last_error());
+        }
+
+        Ok(())
+    }
+}
+
+impl Seek for Stream {
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error> {
+        let (offset, offset_type) = match pos {
+            SeekFrom::Start(n) => (
+                n.try_into().map_err(|_| Error::InvalidParameter)?,
+                sys::StreamOffset_StreamOffsetFromStart,
+            ),
+            SeekFrom::End(n) => (
+                n.try_into().map_err(|_| Error::InvalidParameter)?,
+                sys::StreamOffset_StreamOffsetFromEnd,
+            ),
+            SeekFrom::Current(n) => (
+                n.try_into().map_err(|_| Error::InvalidParameter)?,
+                sys::StreamOffset_StreamOffsetFromCurrent,
+            ),
+        };
+
+        if unsafe { sys::stream_seek(self.raw, offset, offset_type) } {
+            Ok(self.stream_position()?)
+        } else {
+            Err(Error::InvalidParameter)
+        }
+    }
+
+    fn rewind(&mut self) -> Result<(), Error> {
+        if unsafe { sys::stream_rewind(self.raw) } {
+            Ok(())
+        } else {
+            Err(self.last_error())
+        }
+    }
+
+    fn stream_len(&mut self) -> Result<usize, Error> {
+        Ok(self.len())
+    }
+
+    fn stream_position(&mut self) -> Result<usize, Error> {
+        Ok(unsafe { sys::stream_tell(self.raw) })
+    }
+}
+
+/// A [`Stream`] borrowed from another object, such as a
+/// [`FlipperFormat`](crate::format::FlipperFormat).
+///
+/// The stream can only be modified through the methods of `StreamRef`, as the owner
+/// frees it.
+pub struct StreamRef<'a> {
+    stream: ManuallyDrop<Stream>,
+    _owner: PhantomData<&'a mut ()>,
+}
+
+impl<'a> StreamRef<'a> {
+    /// # Safety
+    ///
+    /// `raw` must be a valid stream of kind `backend` that lives for `'a`.
+    pub(crate) unsafe fn new(raw: *mut sys::Stream, backend: Backend) -> Self {
+        StreamRef {
+            stream: ManuallyDrop::new(Stream {
+                raw,
+                backend,
+                _storage: None,
+            }),
+            _owner: PhantomData,
+        }
+    }
+
+    /// Removes all data from the stream.
+    pub fn clear(&mut self) {
+        self.stream.clear()
+    }
+
+    /// Inserts `data` at the current position, moving the following data back.
+    ///
+    /// The position is moved to the end of the inserted data.
+    pub fn insert(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.stream.insert(data)
+    }
+
+    /// Deletes `len` bytes starting at the current position, moving the following data
+    /// forward.
+    pub fn delete(&mut self, len: usize) -> Result<(), Error> {
+        self.stream.delete(len)
+    }
+
+    /// Replaces `len` bytes starting at the current position with `data`.
+    ///
+    /// The position is moved to the end of the inserted data.
+    pub fn replace(&mut self, len: usize, data: &[u8]) -> Result<(), Error> {
+        self.stream.replace(len, data)
+    }
+
+    /// Copies `len` bytes from the current position of this stream to the current
+    /// position of `to`.
+    ///
+    /// Returns the number of bytes copied.
+    pub fn copy_to(&mut self, to: &mut Stream, len: usize) -> usize {
+        self.stream.copy_to(to, len)
+    }
+
+    /// Inserts the contents of the file at `path` at the current position.
+    ///
+    /// Returns the number of bytes loaded.
+    pub fn load_from_file(&mut self, path: &CStr) -> usize {
+        self.stream.load_from_file(path)
+    }
+
+    /// Writes the data from the current position to the end of the stream to the file
+    /// at `path`, replacing any existing file.
+    ///
+    /// Returns the number of bytes saved.
+    pub fn save_to_file(&mut self, path: &CStr) -> usize {
+        self.stream.save_to_file(path)
+    }
+}
+
+// `DerefMut` is not implemented, as it would allow the borrowed stream to be swapped
+// out and freed while its owner still uses it.
+impl Deref for StreamRef<'_> {
+    type Target = Stream;
+
+    fn deref(&self) -> &Stream {
+        &self.stream
+    }
+}
+
+impl Read for StreamRef<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.stream.read(buf)
+    }
+}
+
+impl Write for StreamRef<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.stream.flush()
+    }
+}
+
+impl Seek for StreamRef<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error> {
+        self.stream.seek(pos)
+    }
+
+    fn rewind(&mut self) -> Result<(), Error> {
+        self.stream.rewind()
+    }
+
+    fn stream_len(&mut self) -> Result<usize, Error> {
+        self.stream.stream_len()
+    }
+
+    fn stream_position(&mut self) -> Result<usize, Error> {
+        self.stream.stream_position()
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::ffi::CStr;
+
+    use super::Stream;
+    use crate::format::FlipperFormat;
+    use crate::io::{Read, Seek, SeekFrom, Write};
+    use crate::storage::{self, OpenOptions};
+
+    const PATH: &CStr =
+        unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/flipperzero-rs-stream\0") };
+
+    fn read_to_end(stream: &mut Stream, buf: &mut [u8]) -> usize {
+        stream.rewind().unwrap();
+        stream.read(buf).unwrap()
+    }
+
+    #[test]
+    fn insert_and_delete() {
+        let mut stream = Stream::new_string();
+        stream.write_all(b"hello world").unwrap();
+        assert_eq!(stream.len(), 11);
+
+        stream.seek(SeekFrom::Start(5)).unwrap();
+        stream.insert(b",").unwrap();
+        assert_eq!(stream.stream_position().unwrap(), 6);
+
+        let mut buf = [0; 32];
+        let n = read_to_end(&mut stream, &mut buf);
+        assert_eq!(&buf[..n], b"hello, world");
+
+        stream.seek(SeekFrom::Start(0)).unwrap();
+        stream.delete(7).unwrap();
+        let n = read_to_end(&mut stream, &mut buf);
+        assert_eq!(&buf[..n], b"world");
+
+        stream.seek(SeekFrom::Start(0)).unwrap();
+        stream.replace(1, b"W").unwrap();
+        let n = read_to_end(&mut stream, &mut buf);
+        assert_eq!(&buf[..n], b"World");
+    }
+
+    #[test]
+    fn file_stream() {
+        let options = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_always(true);
+        let mut stream = Stream::open_buffered_file(PATH, options).unwrap();
+        stream.write_all(b"line 1\nline 3\n").unwrap();
+        stream.seek(SeekFrom::Start(7)).unwrap();
+        stream.insert(b"line 2\n").unwrap();
+        stream.flush().unwrap();
+        drop(stream);
+
+        let mut stream = Stream::new_string();
+        assert_eq!(stream.load_from_file(PATH), 21);
+        let mut buf = [0; 32];
+        let n = read_to_end(&mut stream, &mut buf);
+        assert_eq!(&buf[..n], b"line 1\nline 2\nline 3\n");
+
+        storage::remove(PATH).unwrap();
+    }
+
+    #[test]
+    fn flipper_format_stream() {
+        let mut ff = FlipperFormat::new_string();
+        ff.write_string(
+            CStr::from_bytes_with_nul(b"Key\0").unwrap(),
+            CStr::from_bytes_with_nul(b"Value\0").unwrap(),
+        )
+        .unwrap();
+
+        let mut copy = Stream::new_string();
+        {
+            let mut stream = ff.stream();
+            stream.rewind().unwrap();
+            stream.insert(b"# Comment\n").unwrap();
+            stream.rewind().unwrap();
+            let len = stream.len();
+            assert_eq!(stream.copy_to(&mut copy, len), len);
+        }
+
+        let mut buf = [0; 32];
+        let n = read_to_end(&mut copy, &mut buf);
+        assert_eq!(&buf[..n], b"# Comment\nKey: Value\n");
+    }
+}
@@ -0,0 +1,459 @@
+//! Tar archives.
+//!
+//! Archives are read and written directly on the storage device, using the `tar`
+//! implementation embedded into the Flipper Zero firmware.
+
+use core::ffi::{c_char, c_void, CStr};
+use core::fmt;
+
+use flipperzero_sys as sys;
+use flipperzero_sys::furi::UnsafeRecord;
+
+#[cfg(feature = "alloc")]
+use alloc::{ffi::CString, vec::Vec};
+
+use crate::storage;
+
+const RECORD_STORAGE: *const c_char = sys::c_string!("storage");
+
+/// Tar Result type.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Tar error kinds.
+///
+/// The SDK only reports success or failure, so these errors describe which kind of
+/// operation failed rather than why.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The archive could not be opened.
+    Open,
+    /// The archive could not be read or unpacked.
+    Read,
+    /// An entry could not be added to the archive, or the archive could not be
+    /// finalized.
+    Write,
+    /// The arguments are not supported by the SDK API (for example, a file larger than
+    /// 2 GiB).
+    InvalidParameter,
+}
+
+impl Error {
+    /// Describes the error.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Open => "Failed to open archive",
+            Self::Read => "Failed to read archive",
+            Self::Write => "Failed to write archive",
+            Self::InvalidParameter => "Invalid parameter",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> core::result::Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+/// Converts an SDK success flag into a `Result`.
+fn check(ok: bool, error: Error) -> Result<()> {
+    if ok {
+        Ok(())
+    } else {
+        Err(error)
+    }
+}
+
+/// An entry in a [`TarArchive`].
+#[derive(Debug, Clone, Copy)]
+pub struct Entry<'a> {
+    name: &'a CStr,
+    is_dir: bool,
+}
+
+impl<'a> Entry<'a> {
+    /// Path of the entry within the archive.
+    pub fn name(&self) -> &'a CStr {
+        self.name
+    }
+
+    /// Returns `true` if the entry is a directory.
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    /// Copies the entry so that it can outlive the archive traversal.
+    #[cfg(feature = "alloc")]
+    pub fn to_owned(&self) -> OwnedEntry {
+        OwnedEntry {
+            name: self.name.into(),
+            is_dir: self.is_dir,
+        }
+    }
+}
+
+/// An owned entry in a [`TarArchive`], returned by [`TarArchive::entries`].
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedEntry {
+    name: CString,
+    is_dir: bool,
+}
+
+#[cfg(feature = "alloc")]
+impl OwnedEntry {
+    /// Path of the entry within the archive.
+    pub fn name(&self) -> &CStr {
+        &self.name
+    }
+
+    /// Returns `true` if the entry is a directory.
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+}
+
+/// Iterator over the entries of a [`TarArchive`].
+#[cfg(feature = "alloc")]
+pub struct Entries(alloc::vec::IntoIter<OwnedEntry>);
+
+#[cfg(feature = "alloc")]
+impl Iterator for Entries {
+    type Item = OwnedEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl ExactSizeIterator for Entries {}
+
+/// A tar archive stored on the storage device.
+///
+/// Archives are either opened for reading with [`TarArchive::open`], or created with
+/// [`TarArchive::create`]. Created archives must be completed with
+/// [`TarArchive::finish`], otherwise they will be truncated.
+pub struct TarArchive {
+    raw: *mut sys::TarArchive,
+    _storage: UnsafeRecord<sys::Storage>,
+}
+
+impl TarArchive {
+    fn alloc() -> Self {
+        unsafe {
+            let storage = UnsafeRecord::open(RECORD_STORAGE);
+            let raw = sys::tar_archive_alloc(storage.as_ptr());
+            assert!(!raw.is_null());
+
+            TarArchive {
+                raw,
+                _storage: storage,
+            }
+        }
+    }
+
+    /// Opens an existing archive for reading.
+    pub fn open(path: &CStr) -> Result<Self> {
+        let archive = Self::alloc();
+        check(
+            unsafe {
+                sys::tar_archive_open(
+                    archive.raw,
+                    path.as_ptr(),
+                    sys::TarOpenMode_TAR_OPEN_MODE_READ,
+                )
+            },
+            Error::Open,
+        )?;
+
+        Ok(archive)
+    }
+
+    /// Creates a new archive, replacing any existing file at `path`.
+    pub fn create(path: &CStr) -> Result<Self> {
+        let archive = Self::alloc();
+        check(
+            unsafe {
+                sys::tar_archive_open(
+                    archive.raw,
+                    path.as_ptr(),
+                    sys::TarOpenMode_TAR_OPEN_MODE_WRITE,
+                )
+            },
+            Error::Open,
+        )?;
+
+        Ok(archive)
+    }
+
+    /// Returns the number of entries in the archive.
+    pub fn entries_count(&mut self) -> Result<usize> {
+        let count = unsafe { sys::tar_archive_get_entries_count(self.raw) };
+        count.try_into().map_err(|_| Error::Read)
+    }
+
+    /// Calls `f` with each entry of the archive, without unpacking anything.
+    pub fn for_each_entry<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(Entry<'_>),
+    {
+        // Entries for which the callback returns `false` are skipped, so the
+        // destination is never written to.
+        self.foreach(sys::c_string!("/ext"), |entry| {
+            f(entry);
+            false
+        })
+    }
+
+    /// Returns an iterator over the entries of the archive.
+    #[cfg(feature = "alloc")]
+    pub fn entries(&mut self) -> Result<Entries> {
+        let mut entries = Vec::new();
+        self.for_each_entry(|entry| entries.push(entry.to_owned()))?;
+        Ok(Entries(entries.into_iter()))
+    }
+
+    /// Unpacks all entries into the `destination` directory.
+    pub fn unpack_to(&mut self, destination: &CStr) -> Result<()> {
+        check(
+            unsafe { sys::tar_archive_unpack_to(self.raw, destination.as_ptr(), None) },
+            Error::Read,
+        )
+    }
+
+    /// Unpacks all entries into the `destination` directory, reporting progress.
+    ///
+    /// Before each entry is unpacked, `progress` is called with the entry, the number
+    /// of entries unpacked so far, and the total number of entries.
+    pub fn unpack_to_with_progress<F>(&mut self, destination: &CStr, mut progress: F) -> Result<()>
+    where
+        F: FnMut(Entry<'_>, usize, usize),
+    {
+        let total = self.entries_count()?;
+        let mut done = 0;
+        self.foreach(destination.as_ptr(), |entry| {
+            progress(entry, done, total);
+            done += 1;
+            true
+        })
+    }
+
+    /// Unpacks the single entry `name` to the file `destination`.
+    pub fn unpack_file(&mut self, name: &CStr, destination: &CStr) -> Result<()> {
+        check(
+            unsafe { sys::tar_archive_unpack_file(self.raw, name.as_ptr(), destination.as_ptr()) },
+            Error::Read,
+        )
+    }
+
+    /// Adds the file at `path` to the archive as `name`.
+    pub fn add_file(&mut self, path: &CStr, name: &CStr) -> Result<()> {
+        let size = storage::metadata(path)
+            .map_err(|_| Error::Read)?
+            .len()
+            .try_into()
+            .map_err(|_| Error::InvalidParameter)?;
+
+        check(
+            unsafe { sys::tar_archive_add_file(self.raw, path.as_ptr(), name.as_ptr(), size) },
+            Error::Write,
+        )
+    }
+
+    /// Recursively adds the directory at `path` to the archive, placing its contents
+    /// under `prefix`.
+    pub fn add_dir(&mut self, path: &CStr, prefix: &CStr) -> Result<()> {
+        check(
+            unsafe { sys::tar_archive_add_dir(self.raw, path.as_ptr(), prefix.as_ptr()) },
+            Error::Write,
+        )
+    }
+
+    /// Adds an empty directory entry `name` to the archive.
+    pub fn add_dir_entry(&mut self, name: &CStr) -> Result<()> {
+        check(
+            unsafe { sys::tar_archive_dir_add_element(self.raw, name.as_ptr()) },
+            Error::Write,
+        )
+    }
+
+    /// Adds a file named `name` containing `data` to the archive.
+    pub fn add_data(&mut self, name: &CStr, data: &[u8]) -> Result<()> {
+        let len = data.len().try_into().map_err(|_| Error::InvalidParameter)?;
+        check(
+            unsafe { sys::tar_archive_store_data(self.raw, name.as_ptr(), data.as_ptr(), len) },
+            Error::Write,
+        )
+    }
+
+    /// Completes a created archive, and closes it.
+    pub fn finish(self) -> Result<()> {
+        check(unsafe { sys::tar_archive_finalize(self.raw) }, Error::Write)
+    }
+
+    /// Runs `tar_archive_unpack_to` with `f` as the per-entry callback.
+    ///
+    /// Entries for which `f` returns `false` are not unpacked.
+    fn foreach<F>(&mut self, destination: *const c_char, mut f: F) -> Result<()>
+    where
+        F: FnMut(Entry<'_>) -> bool,
+    {
+        unsafe extern "C" fn callback<F>(
+            name: *const c_char,
+            is_directory: bool,
+            context: *mut c_void,
+        ) -> bool
+        where
+            F: FnMut(Entry<'_>) -> bool,
+        {
+            let f = &mut *(context as *mut F);
+            f(Entry {
+                name: CStr::from_ptr(name),
+                is_dir: is_directory,
+            })
+        }
+
+        unsafe {
+            sys::tar_archive_set_file_callback(
+                self.raw,
+                Some(callback::<F>),
+                &mut f as *mut F as *mut c_void,
+            );
+            let ok = sys::tar_archive_unpack_to(self.raw, destination, None);
+            // `f` goes out of scope when we return, so it must not be called again.
+            sys::tar_archive_set_file_callback(self.raw, None, core::ptr::null_mut());
+
+            check(ok, Error::Read)
+        }
+    }
+}
+
+impl Drop for TarArchive {
+    fn drop(&mut self) {
+        unsafe { sys::tar_archive_free(self.raw) };
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::ffi::CStr;
+
+    use flipperzero_sys as sys;
+    use flipperzero_sys::furi::UnsafeRecord;
+
+    use super::TarArchive;
+    use crate::io::Read;
+    use crate::storage::{self, OpenOptions};
+
+    const ARCHIVE: &CStr =
+        unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/flipperzero-rs-tar.tar\0") };
+    const OUT_DIR: &CStr =
+        unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/flipperzero-rs-tar\0") };
+    const OUT_FILE: &CStr =
+        unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/flipperzero-rs-tar/assets/a.txt\0") };
+
+    fn cleanup() {
+        storage::remove(ARCHIVE).unwrap();
+
+        let record: UnsafeRecord<sys::Storage> =
+            unsafe { UnsafeRecord::open(sys::c_string!("storage")) };
+        unsafe { sys::storage_simply_remove_recursive(record.as_ptr(), OUT_DIR.as_ptr()) };
+    }
+
+    fn create_archive() {
+        let mut archive = TarArchive::create(ARCHIVE).unwrap();
+        archive
+            .add_dir_entry(CStr::from_bytes_with_nul(b"assets\0").unwrap())
+            .unwrap();
+        archive
+            .add_data(
+                CStr::from_bytes_with_nul(b"assets/a.txt\0").unwrap(),
+                b"hello",
+            )
+            .unwrap();
+        archive.finish().unwrap();
+    }
+
+    #[test]
+    fn list_entries() {
+        cleanup();
+        create_archive();
+
+        let mut archive = TarArchive::open(ARCHIVE).unwrap();
+        assert_eq!(archive.entries_count().unwrap(), 2);
+
+        // Assertions inside the callback would panic rather than fail the test.
+        let mut entries = [(false, [0; 16], 0); 2];
+        let mut count = 0;
+        archive
+            .for_each_entry(|entry| {
+                if let Some((is_dir, name, len)) = entries.get_mut(count) {
+                    let bytes = entry.name().to_bytes();
+                    *len = bytes.len().min(name.len());
+                    name[..*len].copy_from_slice(&bytes[..*len]);
+                    *is_dir = entry.is_dir();
+                }
+                count += 1;
+            })
+            .unwrap();
+        assert_eq!(count, 2);
+        let (is_dir, name, len) = entries[0];
+        assert!(is_dir);
+        assert!(name[..len].starts_with(b"assets"));
+        let (is_dir, name, len) = entries[1];
+        assert!(!is_dir);
+        assert_eq!(&name[..len], b"assets/a.txt");
+
+        cleanup();
+    }
+
+    #[test]
+    fn unpack_with_progress() {
+        cleanup();
+        create_archive();
+
+        let storage: UnsafeRecord<sys::Storage> =
+            unsafe { UnsafeRecord::open(sys::c_string!("storage")) };
+        unsafe { sys::storage_simply_mkdir(storage.as_ptr(), OUT_DIR.as_ptr()) };
+
+        let mut archive = TarArchive::open(ARCHIVE).unwrap();
+        let mut progress = [(0, 0); 2];
+        let mut count = 0;
+        archive
+            .unpack_to_with_progress(OUT_DIR, |_, done, total| {
+                if let Some(p) = progress.get_mut(count) {
+                    *p = (done, total);
+                }
+                count += 1;
+            })
+            .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(progress, [(0, 2), (1, 2)]);
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .open_existing(true)
+            .open(OUT_FILE)
+            .unwrap();
+        let mut buf = [0; 16];
+        let n = file.read(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"hello");
+
+        cleanup();
+    }
+}
@@ -0,0 +1,312 @@
+//! DuckyScript interpreter, compatible with the BadUSB application.
+//!
+//! Scripts are parsed with [`parser`], from the `flipperzero-hid` crate, which has no
+//! dependency on the firmware.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use core::ffi::CStr;
+//! use core::time::Duration;
+//!
+//! use flipperzero::usb::hid::ducky::{self, Interpreter};
+//! use flipperzero::usb::hid::{Hid, KeyboardLayout};
+//!
+//! let script = ducky::read_script(CStr::from_bytes_with_nul(b"/ext/badusb/demo.txt\0").unwrap())?;
+//! let mut hid = Hid::acquire()?;
+//! hid.wait_connected(Duration::from_secs(5));
+//! Interpreter::new(KeyboardLayout::EN_US).run(&mut hid, &script, || {})?;
+//! ```
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+#[cfg(feature = "alloc")]
+use core::ffi::CStr;
+use core::fmt;
+use core::time::Duration;
+
+use super::{Hid, Key, KeyboardLayout, Modifiers};
+use crate::furi;
+#[cfg(feature = "alloc")]
+use crate::io::Read;
+#[cfg(feature = "alloc")]
+use crate::storage::OpenOptions;
+
+pub use flipperzero_hid::ducky as parser;
+
+pub use parser::{parse, Combo, ComboKey, Command, ParseError, ParseErrorKind};
+
+/// DuckyScript Result type.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// DuckyScript error kinds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A line of the script is invalid.
+    Parse(ParseError),
+    /// A command couldn't be sent.
+    Hid(super::Error),
+    /// The script couldn't be read, or isn't UTF-8.
+    Load,
+}
+
+impl Error {
+    /// Describes the error.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Parse(err) => err.kind.description(),
+            Self::Hid(err) => err.description(),
+            Self::Load => "Failed to load script",
+        }
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(value: ParseError) -> Self {
+        Self::Parse(value)
+    }
+}
+
+impl From<super::Error> for Error {
+    fn from(value: super::Error) -> Self {
+        Self::Hid(value)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(err) => err.fmt(f),
+            _ => f.write_str(self.description()),
+        }
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> core::result::Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        match self {
+            Self::Parse(err) => {
+                ufmt::uwrite!(f, "line {}: {}", err.line, err.kind.description())
+            }
+            _ => f.write_str(self.description()),
+        }
+    }
+}
+
+/// Reads a script from a file.
+#[cfg(feature = "alloc")]
+pub fn read_script(path: &CStr) -> Result<String> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .open_existing(true)
+        .open(path)
+        .map_err(|_| Error::Load)?;
+
+    let mut script = Vec::new();
+    let mut buf = [0; 256];
+    loop {
+        match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => script.extend_from_slice(&buf[..n]),
+            Err(_) => return Err(Error::Load),
+        }
+    }
+    String::from_utf8(script).map_err(|_| Error::Load)
+}
+
+/// Runs scripts.
+pub struct Interpreter {
+    layout: KeyboardLayout,
+    default_delay: Duration,
+    string_delay: Duration,
+}
+
+impl Interpreter {
+    /// Creates an interpreter typing text in `layout`.
+    pub fn new(layout: KeyboardLayout) -> Self {
+        Self {
+            layout,
+            default_delay: Duration::ZERO,
+            string_delay: Duration::ZERO,
+        }
+    }
+
+    /// Returns the keyboard layout text is typed in.
+    pub fn layout(&self) -> &KeyboardLayout {
+        &self.layout
+    }
+
+    /// Runs `script`.
+    ///
+    /// The whole script is parsed first, so that nothing is typed if it is invalid.
+    /// `wait_for_button` is called for `WAIT_FOR_BUTTON_PRESS`, and should return once
+    /// the user presses a button.
+    pub fn run<W>(&mut self, hid: &mut Hid, script: &str, mut wait_for_button: W) -> Result<()>
+    where
+        W: FnMut(),
+    {
+        if let Some(err) = parse(script).find_map(|command| command.err()) {
+            return Err(err.into());
+        }
+
+        let mut previous = None;
+        for (_, command) in parse(script).flatten() {
+            match command {
+                Command::Repeat(count) => {
+                    if let Some(previous) = previous {
+                        for _ in 0..count {
+                            self.execute(hid, previous, &mut wait_for_button)?;
+                            furi::thread::sleep(self.default_delay);
+                        }
+                    }
+                }
+                Command::Rem(_) | Command::Id(_) => continue,
+                command => {
+                    self.execute(hid, command, &mut wait_for_button)?;
+                    previous = Some(command);
+                }
+            }
+            furi::thread::sleep(self.default_delay);
+        }
+
+        hid.release_all()?;
+        Ok(())
+    }
+
+    fn execute<W>(&mut self, hid: &mut Hid, command: Command, wait_for_button: &mut W) -> Result<()>
+    where
+        W: FnMut(),
+    {
+        match command {
+            Command::Rem(_) | Command::Id(_) | Command::Repeat(_) => {}
+            Command::Delay(ms) => furi::thread::sleep(Duration::from_millis(ms.into())),
+            Command::DefaultDelay(ms) => self.default_delay = Duration::from_millis(ms.into()),
+            Command::StringDelay(ms) => self.string_delay = Duration::from_millis(ms.into()),
+            Command::String(text) => self.type_str(hid, text)?,
+            Command::StringLn(text) => {
+                self.type_str(hid, text)?;
+                hid.tap(Key::ENTER)?;
+            }
+            Command::Press(combo) => hid.tap(self.combo_key(combo)?)?,
+            Command::Hold(combo) => hid.press(self.combo_key(combo)?)?,
+            Command::Release(combo) => hid.release(self.combo_key(combo)?)?,
+            Command::AltChar(code) => self.alt_code(hid, code)?,
+            Command::AltString(text) => {
+                for c in text.chars() {
+                    self.alt_code(hid, c as u16)?;
+                }
+            }
+            Command::SysRq(c) => {
+                hid.press(Key::PRINT_SCREEN.with(Modifiers::LEFT_ALT))?;
+                hid.tap(self.layout.key(c).ok_or(super::Error::Unmappable)?)?;
+                hid.release_all()?;
+            }
+            Command::Media(key) => hid.consumer_tap(key)?,
+            Command::WaitForButtonPress => wait_for_button(),
+        }
+        Ok(())
+    }
+
+    fn type_str(&self, hid: &mut Hid, text: &str) -> Result<()> {
+        if self.string_delay.is_zero() {
+            return Ok(hid.type_str(text, &self.layout)?);
+        }
+
+        for c in text.chars() {
+            hid.type_char(c, &self.layout)?;
+            furi::thread::sleep(self.string_delay);
+        }
+        Ok(())
+    }
+
+    fn combo_key(&self, combo: Combo) -> Result<Key> {
+        let key = match combo.key {
+            Some(ComboKey::Key(key)) => key,
+            Some(ComboKey::Char(c)) => self.layout.key(c).ok_or(super::Error::Unmappable)?,
+            None => Key::NONE,
+        };
+        Ok(key.with(combo.modifiers))
+    }
+
+    /// Types the Windows Alt code `code`, by holding Alt and typing its digits on the
+    /// keypad.
+    fn alt_code(&self, hid: &mut Hid, code: u16) -> Result<()> {
+        let alt = Key::NONE.with(Modifiers::LEFT_ALT);
+        hid.press(alt)?;
+
+        let mut digits = [0; 5];
+        let mut len = 0;
+        let mut code = code;
+        loop {
+            digits[len] = (code % 10) as u8;
+            len += 1;
+            code /= 10;
+            if code == 0 {
+                break;
+            }
+        }
+        for &digit in digits[..len].iter().rev() {
+            // Digits are 0 to 9, so they all have a keypad key.
+            hid.tap(Key::keypad_digit(digit).unwrap())?;
+        }
+
+        hid.release(alt)?;
+        Ok(())
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new(KeyboardLayout::EN_US)
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::{Error, ParseError, ParseErrorKind};
+
+    #[test]
+    fn load_script() {
+        // Scripts are read into a `String`.
+        #[cfg(feature = "alloc")]
+        {
+            use core::ffi::CStr;
+
+            use super::read_script;
+            use crate::io::Write;
+            use crate::storage::{self, OpenOptions};
+
+            const HELLO: &str = include_str!("../../../../../../fixtures/usb/hello.txt");
+            const PATH: &CStr =
+                unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/flipperzero-rs-ducky.txt\0") };
+
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create_always(true)
+                .open(PATH)
+                .unwrap();
+            file.write_all(HELLO.as_bytes()).unwrap();
+            drop(file);
+
+            let script = read_script(PATH).unwrap();
+            assert_eq!(script, HELLO);
+            assert!(super::parse(&script).all(|command| command.is_ok()));
+
+            storage::remove(PATH).unwrap();
+            assert_eq!(read_script(PATH), Err(Error::Load));
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = Error::from(ParseError {
+            line: 3,
+            kind: ParseErrorKind::UnknownCommand,
+        });
+        assert_eq!(err.description(), "Unknown command");
+    }
+}
@@ -0,0 +1,363 @@
+//! USB HID keyboard, mouse and consumer keys.
+//!
+//! [`Hid`] switches the USB interface to HID while it exists, so the Flipper appears as
+//! a keyboard and mouse to the connected computer. Scripts are run with [`ducky`].
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use core::time::Duration;
+//!
+//! use flipperzero::usb::hid::{Hid, Key, KeyboardLayout, Modifiers};
+//!
+//! let mut hid = Hid::acquire()?;
+//! if hid.wait_connected(Duration::from_secs(5)) {
+//!     hid.tap(Key::R.with(Modifiers::LEFT_GUI))?;
+//!     hid.type_str("notepad\n", &KeyboardLayout::EN_US)?;
+//! }
+//! ```
+
+use core::ffi::CStr;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use flipperzero_sys as sys;
+
+use crate::furi;
+use crate::io::Read;
+use crate::storage::OpenOptions;
+
+pub mod ducky;
+
+pub use flipperzero_hid::keys;
+
+pub use keys::{ConsumerKey, Key, KeyboardLayout, Modifiers};
+
+/// Whether a [`Hid`] exists.
+static ACQUIRED: AtomicBool = AtomicBool::new(false);
+
+/// HID Result type.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// HID error kinds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The USB interface is already used as HID.
+    Busy,
+    /// The USB interface is locked, e.g. by an RPC session.
+    Locked,
+    /// The USB interface couldn't be switched.
+    SetConfig,
+    /// The report wasn't sent, e.g. because the host isn't connected or too many keys
+    /// are pressed.
+    Report,
+    /// The character can't be typed in the keyboard layout.
+    Unmappable,
+    /// The keyboard layout file couldn't be loaded.
+    Load,
+}
+
+impl Error {
+    /// Describes the error.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Busy => "HID busy",
+            Self::Locked => "USB locked",
+            Self::SetConfig => "Failed to switch USB interface",
+            Self::Report => "Failed to send HID report",
+            Self::Unmappable => "Character not in keyboard layout",
+            Self::Load => "Failed to load keyboard layout",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> core::result::Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+/// A mouse button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+impl MouseButton {
+    /// Convert the button into the corresponding `furi_hal_hid_mouse_press` value.
+    pub fn to_sys(self) -> u8 {
+        match self {
+            Self::Left => 1 << 0,
+            Self::Right => 1 << 1,
+            Self::Middle => 1 << 2,
+        }
+    }
+}
+
+/// The keyboard LEDs, as set by the host.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LedState(u8);
+
+impl LedState {
+    pub const NUM_LOCK: Self = Self(1 << 0);
+    pub const CAPS_LOCK: Self = Self(1 << 1);
+    pub const SCROLL_LOCK: Self = Self(1 << 2);
+
+    /// Get the state from the corresponding `furi_hal_hid_get_led_state` value.
+    pub fn from_sys(state: u8) -> Self {
+        Self(state)
+    }
+
+    /// Returns `true` if all LEDs of `other` are on.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+fn report(sent: bool) -> Result<()> {
+    if sent {
+        Ok(())
+    } else {
+        Err(Error::Report)
+    }
+}
+
+/// The USB interface, as a HID keyboard and mouse.
+///
+/// The previous USB interface, usually the serial CLI, is restored when dropped.
+pub struct Hid {
+    previous: *mut sys::FuriHalUsbInterface,
+}
+
+impl Hid {
+    /// Switches the USB interface to HID.
+    pub fn acquire() -> Result<Self> {
+        if ACQUIRED
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(Error::Busy);
+        }
+
+        let result = unsafe {
+            if sys::furi_hal_usb_is_locked() {
+                Err(Error::Locked)
+            } else {
+                let previous = sys::furi_hal_usb_get_config();
+                if sys::furi_hal_usb_set_config(
+                    core::ptr::addr_of_mut!(sys::usb_hid),
+                    core::ptr::null_mut(),
+                ) {
+                    Ok(Self { previous })
+                } else {
+                    Err(Error::SetConfig)
+                }
+            }
+        };
+        if result.is_err() {
+            ACQUIRED.store(false, Ordering::Release);
+        }
+        result
+    }
+
+    /// Returns `true` if the host has configured the HID interface.
+    pub fn is_connected(&self) -> bool {
+        unsafe { sys::furi_hal_hid_is_connected() }
+    }
+
+    /// Waits up to `timeout` for the host to configure the HID interface.
+    pub fn wait_connected(&self, timeout: Duration) -> bool {
+        const POLL: Duration = Duration::from_millis(10);
+
+        let mut waited = Duration::ZERO;
+        while !self.is_connected() {
+            if waited >= timeout {
+                return false;
+            }
+            furi::thread::sleep(POLL);
+            waited += POLL;
+        }
+        true
+    }
+
+    /// Returns the keyboard LEDs set by the host.
+    pub fn led_state(&self) -> LedState {
+        LedState::from_sys(unsafe { sys::furi_hal_hid_get_led_state() })
+    }
+
+    /// Presses `key` and its modifiers, until released.
+    pub fn press(&mut self, key: Key) -> Result<()> {
+        report(unsafe { sys::furi_hal_hid_kb_press(key.to_raw()) })
+    }
+
+    /// Releases `key` and its modifiers.
+    pub fn release(&mut self, key: Key) -> Result<()> {
+        report(unsafe { sys::furi_hal_hid_kb_release(key.to_raw()) })
+    }
+
+    /// Releases all keys and modifiers.
+    pub fn release_all(&mut self) -> Result<()> {
+        report(unsafe { sys::furi_hal_hid_kb_release_all() })
+    }
+
+    /// Presses and releases `key`.
+    pub fn tap(&mut self, key: Key) -> Result<()> {
+        self.press(key)?;
+        self.release(key)
+    }
+
+    /// Types `c` with the key that types it in `layout`.
+    pub fn type_char(&mut self, c: char, layout: &KeyboardLayout) -> Result<()> {
+        self.tap(layout.key(c).ok_or(Error::Unmappable)?)
+    }
+
+    /// Types `s` with the keys that type it in `layout`.
+    ///
+    /// Nothing is typed if `s` has characters that are not in the layout.
+    pub fn type_str(&mut self, s: &str, layout: &KeyboardLayout) -> Result<()> {
+        if !s.chars().all(|c| layout.key(c).is_some()) {
+            return Err(Error::Unmappable);
+        }
+        s.chars().try_for_each(|c| self.type_char(c, layout))
+    }
+
+    /// Moves the mouse by `dx` and `dy` pixels.
+    pub fn mouse_move(&mut self, dx: i8, dy: i8) -> Result<()> {
+        report(unsafe { sys::furi_hal_hid_mouse_move(dx, dy) })
+    }
+
+    /// Presses a mouse button, until released.
+    pub fn mouse_press(&mut self, button: MouseButton) -> Result<()> {
+        report(unsafe { sys::furi_hal_hid_mouse_press(button.to_sys()) })
+    }
+
+    /// Releases a mouse button.
+    pub fn mouse_release(&mut self, button: MouseButton) -> Result<()> {
+        report(unsafe { sys::furi_hal_hid_mouse_release(button.to_sys()) })
+    }
+
+    /// Presses and releases a mouse button.
+    pub fn mouse_click(&mut self, button: MouseButton) -> Result<()> {
+        self.mouse_press(button)?;
+        self.mouse_release(button)
+    }
+
+    /// Scrolls the mouse wheel by `delta` steps, positive values scrolling up.
+    pub fn mouse_scroll(&mut self, delta: i8) -> Result<()> {
+        report(unsafe { sys::furi_hal_hid_mouse_scroll(delta) })
+    }
+
+    /// Presses a consumer key, until released.
+    pub fn consumer_press(&mut self, key: ConsumerKey) -> Result<()> {
+        report(unsafe { sys::furi_hal_hid_consumer_key_press(key.to_raw()) })
+    }
+
+    /// Releases a consumer key.
+    pub fn consumer_release(&mut self, key: ConsumerKey) -> Result<()> {
+        report(unsafe { sys::furi_hal_hid_consumer_key_release(key.to_raw()) })
+    }
+
+    /// Presses and releases a consumer key.
+    pub fn consumer_tap(&mut self, key: ConsumerKey) -> Result<()> {
+        self.consumer_press(key)?;
+        self.consumer_release(key)
+    }
+}
+
+impl Drop for Hid {
+    fn drop(&mut self) {
+        unsafe {
+            // Don't leave keys pressed on the host.
+            sys::furi_hal_hid_kb_release_all();
+            sys::furi_hal_usb_set_config(self.previous, core::ptr::null_mut());
+        }
+        ACQUIRED.store(false, Ordering::Release);
+    }
+}
+
+/// Loads a `.kl` keyboard layout file, as used by the BadUSB application.
+pub fn load_layout(path: &CStr) -> Result<KeyboardLayout> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .open_existing(true)
+        .open(path)
+        .map_err(|_| Error::Load)?;
+
+    let mut bytes = [0; 256];
+    let mut len = 0;
+    while len < bytes.len() {
+        match file.read(&mut bytes[len..]) {
+            Ok(0) | Err(_) => return Err(Error::Load),
+            Ok(n) => len += n,
+        }
+    }
+    Ok(KeyboardLayout::from_bytes(&bytes))
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::ffi::CStr;
+
+    use super::{load_layout, Error, Key, KeyboardLayout, LedState, Modifiers};
+    use crate::io::Write;
+    use crate::storage::{self, OpenOptions};
+
+    // Tests don't switch the USB interface, as they are usually run over the serial CLI.
+
+    const PATH: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"/ext/flipperzero-rs.kl\0") };
+
+    #[test]
+    fn layout_file() {
+        // A layout with Y and Z swapped, like German.
+        let mut bytes = KeyboardLayout::EN_US.to_bytes();
+        bytes[usize::from(b'y') * 2] = Key::Z.usage();
+        bytes[usize::from(b'z') * 2] = Key::Y.usage();
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_always(true)
+            .open(PATH)
+            .unwrap();
+        file.write_all(&bytes).unwrap();
+        drop(file);
+
+        let layout = load_layout(PATH).unwrap();
+        assert_eq!(layout.key('y'), Some(Key::Z));
+        assert_eq!(layout.key('z'), Some(Key::Y));
+        assert_eq!(layout.key('Y'), Some(Key::Y.with(Modifiers::LEFT_SHIFT)));
+
+        // Truncated layouts are rejected.
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_always(true)
+            .open(PATH)
+            .unwrap();
+        file.write_all(&bytes[..128]).unwrap();
+        drop(file);
+        assert_eq!(load_layout(PATH), Err(Error::Load));
+
+        storage::remove(PATH).unwrap();
+        assert_eq!(load_layout(PATH), Err(Error::Load));
+    }
+
+    #[test]
+    fn led_state() {
+        let state = LedState::from_sys(0b011);
+        assert!(state.contains(LedState::NUM_LOCK));
+        assert!(state.contains(LedState::CAPS_LOCK));
+        assert!(!state.contains(LedState::SCROLL_LOCK));
+    }
+}
@@ -0,0 +1,6 @@
+//! USB device interfaces.
+//!
+//! The Flipper is a USB serial device by default, used by the CLI and qFlipper. The
+//! interfaces of this module temporarily replace it.
+
+pub mod hid;
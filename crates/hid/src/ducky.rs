@@ -0,0 +1,389 @@
+//! DuckyScript parser.
+//!
+//! Scripts are parsed a line at a time, into [`Command`]s that borrow from the script.
+
+use core::fmt;
+use core::str::FromStr;
+
+use crate::keys::{ConsumerKey, Key, Modifiers};
+
+/// A key combination, e.g. `CTRL ALT DELETE` or `GUI r`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Combo {
+    pub modifiers: Modifiers,
+    pub key: Option<ComboKey>,
+}
+
+/// The key of a [`Combo`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComboKey {
+    /// A named key, e.g. `ENTER`.
+    Key(Key),
+    /// The key typing a character, in the keyboard layout.
+    Char(char),
+}
+
+/// A line of a script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command<'a> {
+    /// `REM text`: a comment.
+    Rem(&'a str),
+    /// `ID vid:pid manufacturer:product`: the USB identity to use, which is ignored.
+    Id(&'a str),
+    /// `DELAY ms`: waits for a number of milliseconds.
+    Delay(u32),
+    /// `DEFAULT_DELAY ms`: sets the delay after each command.
+    DefaultDelay(u32),
+    /// `STRING_DELAY ms`: sets the delay after each character typed.
+    StringDelay(u32),
+    /// `STRING text`: types text.
+    String(&'a str),
+    /// `STRINGLN text`: types text, then presses enter.
+    StringLn(&'a str),
+    /// `REPEAT n`: runs the previous command `n` more times.
+    Repeat(u32),
+    /// A key combination, pressed and released.
+    Press(Combo),
+    /// `HOLD combo`: presses a key combination, until it is released.
+    Hold(Combo),
+    /// `RELEASE combo`: releases a held key combination.
+    Release(Combo),
+    /// `ALTCHAR code`: types a character by its Alt code.
+    AltChar(u16),
+    /// `ALTSTRING text`: types text with Alt codes.
+    AltString(&'a str),
+    /// `SYSRQ c`: sends a magic SysRq key.
+    SysRq(char),
+    /// `MEDIA key`: presses a consumer key.
+    Media(ConsumerKey),
+    /// `WAIT_FOR_BUTTON_PRESS`: waits for the user.
+    WaitForButtonPress,
+}
+
+/// The kind of a [`ParseError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    /// The command is not known.
+    UnknownCommand,
+    /// The command is missing its argument.
+    MissingArgument,
+    /// The argument is not a valid number.
+    InvalidNumber,
+    /// A key of a combination is not known.
+    UnknownKey,
+    /// A combination has more than one key besides its modifiers.
+    TooManyKeys,
+}
+
+impl ParseErrorKind {
+    /// Describes the error.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::UnknownCommand => "Unknown command",
+            Self::MissingArgument => "Missing argument",
+            Self::InvalidNumber => "Invalid number",
+            Self::UnknownKey => "Unknown key",
+            Self::TooManyKeys => "Too many keys",
+        }
+    }
+}
+
+/// An error parsing a line of a script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseError {
+    /// The line number, starting at 1.
+    pub line: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind.description())
+    }
+}
+
+/// Parses a line of a script, returning `None` for blank lines.
+pub fn parse_line(line: &str) -> Result<Option<Command<'_>>, ParseErrorKind> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    if line.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let line = line.trim_start();
+    let (name, arg) = match line.split_once(' ') {
+        Some((name, arg)) => (name, Some(arg)),
+        None => (line, None),
+    };
+    // Text is typed as is, but other arguments ignore surrounding whitespace.
+    let text = || arg.ok_or(ParseErrorKind::MissingArgument);
+    let word = || {
+        arg.map(str::trim)
+            .filter(|arg| !arg.is_empty())
+            .ok_or(ParseErrorKind::MissingArgument)
+    };
+    let number = || word().and_then(parse_number);
+
+    Ok(Some(match name {
+        "REM" => Command::Rem(arg.unwrap_or("")),
+        "ID" => Command::Id(word()?),
+        "DELAY" => Command::Delay(number()?),
+        "DEFAULT_DELAY" | "DEFAULTDELAY" => Command::DefaultDelay(number()?),
+        "STRING_DELAY" | "STRINGDELAY" => Command::StringDelay(number()?),
+        "STRING" => Command::String(text()?),
+        "STRINGLN" => Command::StringLn(text()?),
+        "REPEAT" => Command::Repeat(number()?),
+        "HOLD" => Command::Hold(parse_combo(word()?)?),
+        "RELEASE" => Command::Release(parse_combo(word()?)?),
+        "ALTCHAR" => Command::AltChar(word().and_then(parse_number)?),
+        "ALTSTRING" | "ALTCODE" => Command::AltString(text()?),
+        "SYSRQ" => Command::SysRq(parse_char(word()?).ok_or(ParseErrorKind::UnknownKey)?),
+        "MEDIA" => {
+            Command::Media(ConsumerKey::from_name(word()?).ok_or(ParseErrorKind::UnknownKey)?)
+        }
+        "WAIT_FOR_BUTTON_PRESS" => Command::WaitForButtonPress,
+        _ => match parse_combo(line.trim_end()) {
+            Ok(combo) => Command::Press(combo),
+            // A line that doesn't start with a key isn't a combination.
+            Err(ParseErrorKind::UnknownKey) if parse_combo_part(first_key(line)).is_none() => {
+                return Err(ParseErrorKind::UnknownCommand)
+            }
+            Err(err) => return Err(err),
+        },
+    }))
+}
+
+/// Parses the lines of a script, skipping blank lines.
+pub fn parse(script: &str) -> Parser<'_> {
+    Parser {
+        lines: script.lines().enumerate(),
+    }
+}
+
+/// An iterator over the commands of a script, see [`parse`].
+pub struct Parser<'a> {
+    lines: core::iter::Enumerate<core::str::Lines<'a>>,
+}
+
+impl<'a> Iterator for Parser<'a> {
+    /// The line number, starting at 1, and the command.
+    type Item = Result<(usize, Command<'a>), ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (i, line) in self.lines.by_ref() {
+            let line_number = i + 1;
+            match parse_line(line) {
+                Ok(Some(command)) => return Some(Ok((line_number, command))),
+                Ok(None) => continue,
+                Err(kind) => {
+                    return Some(Err(ParseError {
+                        line: line_number,
+                        kind,
+                    }))
+                }
+            }
+        }
+        None
+    }
+}
+
+fn parse_number<T: FromStr>(arg: &str) -> Result<T, ParseErrorKind> {
+    arg.parse().map_err(|_| ParseErrorKind::InvalidNumber)
+}
+
+fn parse_char(arg: &str) -> Option<char> {
+    let mut chars = arg.chars();
+    let c = chars.next()?;
+    chars.next().is_none().then_some(c)
+}
+
+fn first_key(line: &str) -> &str {
+    line.split([' ', '-']).next().unwrap_or("")
+}
+
+enum ComboPart {
+    Modifiers(Modifiers),
+    Key(ComboKey),
+}
+
+fn parse_combo_part(name: &str) -> Option<ComboPart> {
+    if let Some(modifiers) = Modifiers::from_name(name) {
+        Some(ComboPart::Modifiers(modifiers))
+    } else if let Some(key) = Key::from_name(name) {
+        Some(ComboPart::Key(ComboKey::Key(key)))
+    } else {
+        parse_char(name).map(|c| ComboPart::Key(ComboKey::Char(c)))
+    }
+}
+
+/// Parses a key combination, whose keys are separated by spaces or dashes, e.g.
+/// `CTRL-ALT DELETE`.
+pub fn parse_combo(combo: &str) -> Result<Combo, ParseErrorKind> {
+    let mut result = Combo {
+        modifiers: Modifiers::NONE,
+        key: None,
+    };
+
+    // A lone dash is the key typing it.
+    if combo == "-" {
+        result.key = Some(ComboKey::Char('-'));
+        return Ok(result);
+    }
+
+    for name in combo.split([' ', '-']).filter(|name| !name.is_empty()) {
+        match parse_combo_part(name).ok_or(ParseErrorKind::UnknownKey)? {
+            ComboPart::Modifiers(modifiers) => result.modifiers = result.modifiers | modifiers,
+            ComboPart::Key(_) if result.key.is_some() => return Err(ParseErrorKind::TooManyKeys),
+            ComboPart::Key(key) => result.key = Some(key),
+        }
+    }
+
+    if result.modifiers.is_empty() && result.key.is_none() {
+        return Err(ParseErrorKind::UnknownKey);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse, parse_combo, parse_line, Combo, ComboKey, Command, ConsumerKey, Key, Modifiers,
+        ParseError, ParseErrorKind,
+    };
+
+    fn combo(modifiers: Modifiers, key: Option<ComboKey>) -> Combo {
+        Combo { modifiers, key }
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(parse_line(""), Ok(None));
+        assert_eq!(parse_line("   \r\n"), Ok(None));
+        assert_eq!(parse_line("REM hello"), Ok(Some(Command::Rem("hello"))));
+        assert_eq!(parse_line("REM"), Ok(Some(Command::Rem(""))));
+        assert_eq!(
+            parse_line("ID 1234:abcd Flipper:Keyboard"),
+            Ok(Some(Command::Id("1234:abcd Flipper:Keyboard")))
+        );
+        assert_eq!(parse_line("DELAY 500"), Ok(Some(Command::Delay(500))));
+        assert_eq!(
+            parse_line("DEFAULTDELAY 20\r"),
+            Ok(Some(Command::DefaultDelay(20)))
+        );
+        assert_eq!(
+            parse_line("STRING_DELAY 5"),
+            Ok(Some(Command::StringDelay(5)))
+        );
+        assert_eq!(
+            parse_line("STRING  two spaces "),
+            Ok(Some(Command::String(" two spaces ")))
+        );
+        assert_eq!(
+            parse_line("STRINGLN echo hi"),
+            Ok(Some(Command::StringLn("echo hi")))
+        );
+        assert_eq!(parse_line("REPEAT 3"), Ok(Some(Command::Repeat(3))));
+        assert_eq!(parse_line("ALTCHAR 169"), Ok(Some(Command::AltChar(169))));
+        assert_eq!(
+            parse_line("ALTSTRING abc"),
+            Ok(Some(Command::AltString("abc")))
+        );
+        assert_eq!(parse_line("SYSRQ b"), Ok(Some(Command::SysRq('b'))));
+        assert_eq!(
+            parse_line("MEDIA VOLUME_UP"),
+            Ok(Some(Command::Media(ConsumerKey::VOLUME_UP)))
+        );
+        assert_eq!(
+            parse_line("WAIT_FOR_BUTTON_PRESS"),
+            Ok(Some(Command::WaitForButtonPress))
+        );
+    }
+
+    #[test]
+    fn combos() {
+        assert_eq!(
+            parse_line("ENTER"),
+            Ok(Some(Command::Press(combo(
+                Modifiers::NONE,
+                Some(ComboKey::Key(Key::ENTER))
+            ))))
+        );
+        assert_eq!(
+            parse_line("GUI r"),
+            Ok(Some(Command::Press(combo(
+                Modifiers::LEFT_GUI,
+                Some(ComboKey::Char('r'))
+            ))))
+        );
+        assert_eq!(
+            parse_combo("CTRL-ALT DELETE"),
+            Ok(combo(
+                Modifiers::LEFT_CTRL | Modifiers::LEFT_ALT,
+                Some(ComboKey::Key(Key::DELETE))
+            ))
+        );
+        assert_eq!(
+            parse_line("HOLD SHIFT"),
+            Ok(Some(Command::Hold(combo(Modifiers::LEFT_SHIFT, None))))
+        );
+        assert_eq!(
+            parse_line("RELEASE SHIFT"),
+            Ok(Some(Command::Release(combo(Modifiers::LEFT_SHIFT, None))))
+        );
+        assert_eq!(
+            parse_combo("-"),
+            Ok(combo(Modifiers::NONE, Some(ComboKey::Char('-'))))
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse_line("TYPE hello"),
+            Err(ParseErrorKind::UnknownCommand)
+        );
+        assert_eq!(parse_line("DELAY"), Err(ParseErrorKind::MissingArgument));
+        assert_eq!(parse_line("DELAY  "), Err(ParseErrorKind::MissingArgument));
+        assert_eq!(parse_line("DELAY 1s"), Err(ParseErrorKind::InvalidNumber));
+        assert_eq!(parse_line("STRING"), Err(ParseErrorKind::MissingArgument));
+        assert_eq!(parse_line("CTRL FOO"), Err(ParseErrorKind::UnknownKey));
+        assert_eq!(parse_line("CTRL a b"), Err(ParseErrorKind::TooManyKeys));
+        assert_eq!(parse_line("MEDIA LOUDER"), Err(ParseErrorKind::UnknownKey));
+        assert_eq!(parse_line("SYSRQ ab"), Err(ParseErrorKind::UnknownKey));
+    }
+
+    #[test]
+    fn script() {
+        let script = include_str!("../../../fixtures/usb/hello.txt");
+        let commands: Result<Vec<_>, _> = parse(script).collect();
+        assert_eq!(
+            commands,
+            Ok(vec![
+                (1, Command::Rem("Opens a terminal and greets the world.")),
+                (2, Command::DefaultDelay(50)),
+                (3, Command::Delay(1000)),
+                (
+                    5,
+                    Command::Press(combo(
+                        Modifiers::LEFT_CTRL | Modifiers::LEFT_ALT,
+                        Some(ComboKey::Char('t'))
+                    ))
+                ),
+                (6, Command::Delay(500)),
+                (7, Command::StringLn("echo Hello, World!")),
+                (8, Command::Repeat(2)),
+                (9, Command::Media(ConsumerKey::MUTE)),
+            ])
+        );
+
+        let mut errors = parse("REM ok\n\nBEEP\n").filter_map(Result::err);
+        assert_eq!(
+            errors.next(),
+            Some(ParseError {
+                line: 3,
+                kind: ParseErrorKind::UnknownCommand
+            })
+        );
+        assert_eq!(errors.next(), None);
+    }
+}
@@ -0,0 +1,461 @@
+//! HID keyboard and consumer key codes, and keyboard layouts.
+
+use core::ops::BitOr;
+
+/// Keyboard modifiers, held while a [`Key`] is pressed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const NONE: Self = Self(0);
+    pub const LEFT_CTRL: Self = Self(1 << 0);
+    pub const LEFT_SHIFT: Self = Self(1 << 1);
+    pub const LEFT_ALT: Self = Self(1 << 2);
+    pub const LEFT_GUI: Self = Self(1 << 3);
+    pub const RIGHT_CTRL: Self = Self(1 << 4);
+    pub const RIGHT_SHIFT: Self = Self(1 << 5);
+    pub const RIGHT_ALT: Self = Self(1 << 6);
+    pub const RIGHT_GUI: Self = Self(1 << 7);
+
+    /// Creates modifiers from their bits in the HID report.
+    pub const fn from_bits(bits: u8) -> Self {
+        Self(bits)
+    }
+
+    /// Returns the bits of the modifiers in the HID report.
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Returns `true` if no modifiers are set.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns `true` if all of `other` are set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Finds modifiers by their DuckyScript name, e.g. `CTRL` or `GUI`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "CTRL" | "CONTROL" => Self::LEFT_CTRL,
+            "SHIFT" => Self::LEFT_SHIFT,
+            "ALT" | "OPTION" => Self::LEFT_ALT,
+            "GUI" | "WINDOWS" | "COMMAND" => Self::LEFT_GUI,
+            "RIGHT_CTRL" => Self::RIGHT_CTRL,
+            "RIGHT_SHIFT" => Self::RIGHT_SHIFT,
+            "RIGHT_ALT" | "ALTGR" => Self::RIGHT_ALT,
+            "RIGHT_GUI" => Self::RIGHT_GUI,
+            _ => return None,
+        })
+    }
+}
+
+impl BitOr for Modifiers {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// A keyboard key and the modifiers held with it, as sent to `furi_hal_hid_kb_press`.
+///
+/// Keys are the usage IDs of the HID keyboard page, in the low byte, and [`Modifiers`]
+/// in the high byte. Keys without a usage ID only press their modifiers.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key(u16);
+
+impl Key {
+    pub const NONE: Self = Self(0x00);
+    pub const A: Self = Self(0x04);
+    pub const B: Self = Self(0x05);
+    pub const C: Self = Self(0x06);
+    pub const D: Self = Self(0x07);
+    pub const E: Self = Self(0x08);
+    pub const F: Self = Self(0x09);
+    pub const G: Self = Self(0x0A);
+    pub const H: Self = Self(0x0B);
+    pub const I: Self = Self(0x0C);
+    pub const J: Self = Self(0x0D);
+    pub const K: Self = Self(0x0E);
+    pub const L: Self = Self(0x0F);
+    pub const M: Self = Self(0x10);
+    pub const N: Self = Self(0x11);
+    pub const O: Self = Self(0x12);
+    pub const P: Self = Self(0x13);
+    pub const Q: Self = Self(0x14);
+    pub const R: Self = Self(0x15);
+    pub const S: Self = Self(0x16);
+    pub const T: Self = Self(0x17);
+    pub const U: Self = Self(0x18);
+    pub const V: Self = Self(0x19);
+    pub const W: Self = Self(0x1A);
+    pub const X: Self = Self(0x1B);
+    pub const Y: Self = Self(0x1C);
+    pub const Z: Self = Self(0x1D);
+    pub const DIGIT_1: Self = Self(0x1E);
+    pub const DIGIT_2: Self = Self(0x1F);
+    pub const DIGIT_3: Self = Self(0x20);
+    pub const DIGIT_4: Self = Self(0x21);
+    pub const DIGIT_5: Self = Self(0x22);
+    pub const DIGIT_6: Self = Self(0x23);
+    pub const DIGIT_7: Self = Self(0x24);
+    pub const DIGIT_8: Self = Self(0x25);
+    pub const DIGIT_9: Self = Self(0x26);
+    pub const DIGIT_0: Self = Self(0x27);
+    pub const ENTER: Self = Self(0x28);
+    pub const ESCAPE: Self = Self(0x29);
+    pub const BACKSPACE: Self = Self(0x2A);
+    pub const TAB: Self = Self(0x2B);
+    pub const SPACE: Self = Self(0x2C);
+    pub const MINUS: Self = Self(0x2D);
+    pub const EQUAL: Self = Self(0x2E);
+    pub const LEFT_BRACKET: Self = Self(0x2F);
+    pub const RIGHT_BRACKET: Self = Self(0x30);
+    pub const BACKSLASH: Self = Self(0x31);
+    pub const NON_US_HASH: Self = Self(0x32);
+    pub const SEMICOLON: Self = Self(0x33);
+    pub const APOSTROPHE: Self = Self(0x34);
+    pub const GRAVE: Self = Self(0x35);
+    pub const COMMA: Self = Self(0x36);
+    pub const DOT: Self = Self(0x37);
+    pub const SLASH: Self = Self(0x38);
+    pub const CAPS_LOCK: Self = Self(0x39);
+    pub const F1: Self = Self(0x3A);
+    pub const F2: Self = Self(0x3B);
+    pub const F3: Self = Self(0x3C);
+    pub const F4: Self = Self(0x3D);
+    pub const F5: Self = Self(0x3E);
+    pub const F6: Self = Self(0x3F);
+    pub const F7: Self = Self(0x40);
+    pub const F8: Self = Self(0x41);
+    pub const F9: Self = Self(0x42);
+    pub const F10: Self = Self(0x43);
+    pub const F11: Self = Self(0x44);
+    pub const F12: Self = Self(0x45);
+    pub const PRINT_SCREEN: Self = Self(0x46);
+    pub const SCROLL_LOCK: Self = Self(0x47);
+    pub const PAUSE: Self = Self(0x48);
+    pub const INSERT: Self = Self(0x49);
+    pub const HOME: Self = Self(0x4A);
+    pub const PAGE_UP: Self = Self(0x4B);
+    pub const DELETE: Self = Self(0x4C);
+    pub const END: Self = Self(0x4D);
+    pub const PAGE_DOWN: Self = Self(0x4E);
+    pub const RIGHT: Self = Self(0x4F);
+    pub const LEFT: Self = Self(0x50);
+    pub const DOWN: Self = Self(0x51);
+    pub const UP: Self = Self(0x52);
+    pub const NUM_LOCK: Self = Self(0x53);
+    pub const KEYPAD_SLASH: Self = Self(0x54);
+    pub const KEYPAD_ASTERISK: Self = Self(0x55);
+    pub const KEYPAD_MINUS: Self = Self(0x56);
+    pub const KEYPAD_PLUS: Self = Self(0x57);
+    pub const KEYPAD_ENTER: Self = Self(0x58);
+    pub const KEYPAD_1: Self = Self(0x59);
+    pub const KEYPAD_2: Self = Self(0x5A);
+    pub const KEYPAD_3: Self = Self(0x5B);
+    pub const KEYPAD_4: Self = Self(0x5C);
+    pub const KEYPAD_5: Self = Self(0x5D);
+    pub const KEYPAD_6: Self = Self(0x5E);
+    pub const KEYPAD_7: Self = Self(0x5F);
+    pub const KEYPAD_8: Self = Self(0x60);
+    pub const KEYPAD_9: Self = Self(0x61);
+    pub const KEYPAD_0: Self = Self(0x62);
+    pub const KEYPAD_DOT: Self = Self(0x63);
+    pub const NON_US_BACKSLASH: Self = Self(0x64);
+    pub const APPLICATION: Self = Self(0x65);
+    pub const F13: Self = Self(0x68);
+    pub const F14: Self = Self(0x69);
+    pub const F15: Self = Self(0x6A);
+    pub const F16: Self = Self(0x6B);
+    pub const F17: Self = Self(0x6C);
+    pub const F18: Self = Self(0x6D);
+    pub const F19: Self = Self(0x6E);
+    pub const F20: Self = Self(0x6F);
+    pub const F21: Self = Self(0x70);
+    pub const F22: Self = Self(0x71);
+    pub const F23: Self = Self(0x72);
+    pub const F24: Self = Self(0x73);
+
+    /// Creates a key from its raw `furi_hal_hid_kb_press` value.
+    pub const fn from_raw(raw: u16) -> Self {
+        Self(raw)
+    }
+
+    /// Returns the raw `furi_hal_hid_kb_press` value of the key.
+    pub const fn to_raw(self) -> u16 {
+        self.0
+    }
+
+    /// Returns the usage ID of the key, without its modifiers.
+    pub const fn usage(self) -> u8 {
+        self.0 as u8
+    }
+
+    /// Returns the modifiers held with the key.
+    pub const fn modifiers(self) -> Modifiers {
+        Modifiers((self.0 >> 8) as u8)
+    }
+
+    /// Returns the key with `modifiers` held too.
+    pub const fn with(self, modifiers: Modifiers) -> Self {
+        Self(self.0 | (modifiers.0 as u16) << 8)
+    }
+
+    /// Finds a key by its DuckyScript name, e.g. `ENTER`, `DELETE` or `F5`.
+    ///
+    /// Printable characters aren't named, as the key that types them depends on the
+    /// [`KeyboardLayout`].
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "ENTER" => Self::ENTER,
+            "ESC" | "ESCAPE" => Self::ESCAPE,
+            "BACKSPACE" => Self::BACKSPACE,
+            "TAB" => Self::TAB,
+            "SPACE" => Self::SPACE,
+            "CAPSLOCK" => Self::CAPS_LOCK,
+            "PRINTSCREEN" => Self::PRINT_SCREEN,
+            "SCROLLLOCK" => Self::SCROLL_LOCK,
+            "PAUSE" | "BREAK" => Self::PAUSE,
+            "INSERT" => Self::INSERT,
+            "HOME" => Self::HOME,
+            "PAGEUP" => Self::PAGE_UP,
+            "DELETE" | "DEL" => Self::DELETE,
+            "END" => Self::END,
+            "PAGEDOWN" => Self::PAGE_DOWN,
+            "RIGHT" | "RIGHTARROW" => Self::RIGHT,
+            "LEFT" | "LEFTARROW" => Self::LEFT,
+            "DOWN" | "DOWNARROW" => Self::DOWN,
+            "UP" | "UPARROW" => Self::UP,
+            "NUMLOCK" => Self::NUM_LOCK,
+            "MENU" | "APP" => Self::APPLICATION,
+            "F1" => Self::F1,
+            "F2" => Self::F2,
+            "F3" => Self::F3,
+            "F4" => Self::F4,
+            "F5" => Self::F5,
+            "F6" => Self::F6,
+            "F7" => Self::F7,
+            "F8" => Self::F8,
+            "F9" => Self::F9,
+            "F10" => Self::F10,
+            "F11" => Self::F11,
+            "F12" => Self::F12,
+            "F13" => Self::F13,
+            "F14" => Self::F14,
+            "F15" => Self::F15,
+            "F16" => Self::F16,
+            "F17" => Self::F17,
+            "F18" => Self::F18,
+            "F19" => Self::F19,
+            "F20" => Self::F20,
+            "F21" => Self::F21,
+            "F22" => Self::F22,
+            "F23" => Self::F23,
+            "F24" => Self::F24,
+            _ => return None,
+        })
+    }
+
+    /// Returns the keypad key of a decimal digit, e.g. for Alt codes.
+    pub const fn keypad_digit(digit: u8) -> Option<Self> {
+        match digit {
+            0 => Some(Self::KEYPAD_0),
+            1..=9 => Some(Self(Self::KEYPAD_1.0 + digit as u16 - 1)),
+            _ => None,
+        }
+    }
+}
+
+/// A key of the HID consumer page, e.g. media and volume keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConsumerKey(u16);
+
+impl ConsumerKey {
+    pub const BRIGHTNESS_UP: Self = Self(0x006F);
+    pub const BRIGHTNESS_DOWN: Self = Self(0x0070);
+    pub const NEXT_TRACK: Self = Self(0x00B5);
+    pub const PREVIOUS_TRACK: Self = Self(0x00B6);
+    pub const STOP: Self = Self(0x00B7);
+    pub const EJECT: Self = Self(0x00B8);
+    pub const PLAY_PAUSE: Self = Self(0x00CD);
+    pub const MUTE: Self = Self(0x00E2);
+    pub const VOLUME_UP: Self = Self(0x00E9);
+    pub const VOLUME_DOWN: Self = Self(0x00EA);
+    pub const HOME: Self = Self(0x0223);
+    pub const BACK: Self = Self(0x0224);
+
+    /// Creates a key from its usage ID.
+    pub const fn from_raw(raw: u16) -> Self {
+        Self(raw)
+    }
+
+    /// Returns the usage ID of the key.
+    pub const fn to_raw(self) -> u16 {
+        self.0
+    }
+
+    /// Finds a key by its DuckyScript `MEDIA` name, e.g. `VOLUME_UP`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "BRIGHT_UP" | "BRIGHTNESS_UP" => Self::BRIGHTNESS_UP,
+            "BRIGHT_DOWN" | "BRIGHTNESS_DOWN" => Self::BRIGHTNESS_DOWN,
+            "NEXT_TRACK" => Self::NEXT_TRACK,
+            "PREV_TRACK" | "PREVIOUS_TRACK" => Self::PREVIOUS_TRACK,
+            "STOP" => Self::STOP,
+            "EJECT" => Self::EJECT,
+            "PLAY_PAUSE" | "PLAY" | "PAUSE" => Self::PLAY_PAUSE,
+            "MUTE" => Self::MUTE,
+            "VOLUME_UP" => Self::VOLUME_UP,
+            "VOLUME_DOWN" => Self::VOLUME_DOWN,
+            "HOME" => Self::HOME,
+            "BACK" => Self::BACK,
+            _ => return None,
+        })
+    }
+}
+
+/// Maps ASCII characters to the [`Key`]s that type them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyboardLayout {
+    keys: [u16; 128],
+}
+
+impl KeyboardLayout {
+    /// The US English layout, used when no layout is loaded.
+    pub const EN_US: Self = Self { keys: en_us() };
+
+    /// Creates a layout from the contents of a `.kl` file, as used by the BadUSB
+    /// application: the raw key of each ASCII character, in little-endian order.
+    pub fn from_bytes(bytes: &[u8; 256]) -> Self {
+        let mut keys = [0; 128];
+        for (key, bytes) in keys.iter_mut().zip(bytes.chunks_exact(2)) {
+            *key = u16::from_le_bytes([bytes[0], bytes[1]]);
+        }
+        Self { keys }
+    }
+
+    /// Returns the contents of a `.kl` file for the layout.
+    pub fn to_bytes(&self) -> [u8; 256] {
+        let mut bytes = [0; 256];
+        for (bytes, key) in bytes.chunks_exact_mut(2).zip(self.keys) {
+            bytes.copy_from_slice(&key.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Returns the key that types `c`, if any.
+    pub fn key(&self, c: char) -> Option<Key> {
+        let key = *self.keys.get(c as usize)?;
+        (key != 0).then_some(Key(key))
+    }
+}
+
+impl Default for KeyboardLayout {
+    fn default() -> Self {
+        Self::EN_US
+    }
+}
+
+const fn en_us() -> [u16; 128] {
+    const SHIFT: u16 = (Modifiers::LEFT_SHIFT.0 as u16) << 8;
+    // Characters typed by the keys from `-` to `/`, without and with shift.
+    const SYMBOLS: [(u8, u8, Key); 11] = [
+        (b'-', b'_', Key::MINUS),
+        (b'=', b'+', Key::EQUAL),
+        (b'[', b'{', Key::LEFT_BRACKET),
+        (b']', b'}', Key::RIGHT_BRACKET),
+        (b'\\', b'|', Key::BACKSLASH),
+        (b';', b':', Key::SEMICOLON),
+        (b'\'', b'"', Key::APOSTROPHE),
+        (b'`', b'~', Key::GRAVE),
+        (b',', b'<', Key::COMMA),
+        (b'.', b'>', Key::DOT),
+        (b'/', b'?', Key::SLASH),
+    ];
+    // Characters typed by the digit keys, which go from 1 to 0, without and with shift.
+    const DIGITS: &[u8; 10] = b"1234567890";
+    const SHIFTED_DIGITS: &[u8; 10] = b"!@#$%^&*()";
+
+    let mut keys = [0; 128];
+    keys[0x08] = Key::BACKSPACE.0;
+    keys[b'\t' as usize] = Key::TAB.0;
+    keys[b'\n' as usize] = Key::ENTER.0;
+    keys[b' ' as usize] = Key::SPACE.0;
+
+    let mut i = 0;
+    while i < 26 {
+        keys[(b'a' + i) as usize] = Key::A.0 + i as u16;
+        keys[(b'A' + i) as usize] = SHIFT | (Key::A.0 + i as u16);
+        i += 1;
+    }
+
+    let mut i = 0;
+    while i < 10 {
+        keys[DIGITS[i] as usize] = Key::DIGIT_1.0 + i as u16;
+        keys[SHIFTED_DIGITS[i] as usize] = SHIFT | (Key::DIGIT_1.0 + i as u16);
+        i += 1;
+    }
+
+    let mut i = 0;
+    while i < SYMBOLS.len() {
+        let (c, shifted, key) = SYMBOLS[i];
+        keys[c as usize] = key.0;
+        keys[shifted as usize] = SHIFT | key.0;
+        i += 1;
+    }
+
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConsumerKey, Key, KeyboardLayout, Modifiers};
+
+    #[test]
+    fn key_modifiers() {
+        let key = Key::DELETE.with(Modifiers::LEFT_CTRL | Modifiers::LEFT_ALT);
+        assert_eq!(key.to_raw(), 0x054C);
+        assert_eq!(key.usage(), 0x4C);
+        assert!(key.modifiers().contains(Modifiers::LEFT_ALT));
+        assert!(!key.modifiers().contains(Modifiers::LEFT_SHIFT));
+        assert!(Key::A.modifiers().is_empty());
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(Key::from_name("ENTER"), Some(Key::ENTER));
+        assert_eq!(Key::from_name("F24"), Some(Key::F24));
+        assert_eq!(Key::from_name("enter"), None);
+        assert_eq!(Modifiers::from_name("WINDOWS"), Some(Modifiers::LEFT_GUI));
+        assert_eq!(
+            ConsumerKey::from_name("VOLUME_UP"),
+            Some(ConsumerKey::VOLUME_UP)
+        );
+        assert_eq!(Key::keypad_digit(0), Some(Key::KEYPAD_0));
+        assert_eq!(Key::keypad_digit(9), Some(Key::KEYPAD_9));
+        assert_eq!(Key::keypad_digit(10), None);
+    }
+
+    #[test]
+    fn en_us_layout() {
+        let layout = KeyboardLayout::EN_US;
+        let shift = |key: Key| key.with(Modifiers::LEFT_SHIFT);
+        assert_eq!(layout.key('a'), Some(Key::A));
+        assert_eq!(layout.key('Z'), Some(shift(Key::Z)));
+        assert_eq!(layout.key('1'), Some(Key::DIGIT_1));
+        assert_eq!(layout.key('0'), Some(Key::DIGIT_0));
+        assert_eq!(layout.key('!'), Some(shift(Key::DIGIT_1)));
+        assert_eq!(layout.key(')'), Some(shift(Key::DIGIT_0)));
+        assert_eq!(layout.key('?'), Some(shift(Key::SLASH)));
+        assert_eq!(layout.key('\n'), Some(Key::ENTER));
+        assert_eq!(layout.key('\0'), None);
+        assert_eq!(layout.key('é'), None);
+
+        assert_eq!(KeyboardLayout::from_bytes(&layout.to_bytes()), layout);
+    }
+}
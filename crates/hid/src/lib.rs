@@ -0,0 +1,13 @@
+//! USB HID key codes, keyboard layouts and DuckyScript parser.
+//!
+//! This is the part of `flipperzero::usb::hid` that has no dependency on the firmware,
+//! so that `flipperzero-tools` can use it on the host. It is tested on the host too:
+//!
+//! ```text
+//! cargo test -p flipperzero-hid --lib --target x86_64-unknown-linux-gnu
+//! ```
+
+#![cfg_attr(not(test), no_std)]
+
+pub mod ducky;
+pub mod keys;
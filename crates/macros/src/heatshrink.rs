@@ -0,0 +1,150 @@
+//! Heatshrink compression, as used by the firmware to store icons.
+//!
+//! The firmware decompresses icons with a window size of 2^8 bytes and a lookahead of
+//! 2^4 bytes (`heatshrink -w 8 -l 4`).
+
+/// Base-2 log of the window size.
+const WINDOW_SZ2: u32 = 8;
+/// Base-2 log of the lookahead (maximum match length).
+const LOOKAHEAD_SZ2: u32 = 4;
+
+const WINDOW: usize = 1 << WINDOW_SZ2;
+const LOOKAHEAD: usize = 1 << LOOKAHEAD_SZ2;
+
+/// Writes bits MSB-first.
+struct BitWriter {
+    output: Vec<u8>,
+    current: u8,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn push(&mut self, value: usize, count: u32) {
+        for i in (0..count).rev() {
+            self.current = (self.current << 1) | ((value >> i) & 1) as u8;
+            self.bits += 1;
+            if self.bits == 8 {
+                self.output.push(self.current);
+                self.current = 0;
+                self.bits = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.output.push(self.current << (8 - self.bits));
+        }
+        self.output
+    }
+}
+
+/// Compresses `data`.
+///
+/// Like the reference encoder, back-references may point into the zero-filled window
+/// that precedes the data.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut buf = vec![0; WINDOW];
+    buf.extend_from_slice(data);
+
+    let mut writer = BitWriter {
+        output: Vec::new(),
+        current: 0,
+        bits: 0,
+    };
+
+    let mut pos = WINDOW;
+    while pos < buf.len() {
+        let max_len = LOOKAHEAD.min(buf.len() - pos);
+
+        // Search backwards, so that ties are resolved by the nearest match.
+        let (mut best_len, mut best_pos) = (0, 0);
+        for candidate in (pos - WINDOW..pos).rev() {
+            let len = (0..max_len)
+                .take_while(|&i| buf[candidate + i] == buf[pos + i])
+                .count();
+            if len > best_len {
+                best_len = len;
+                best_pos = candidate;
+                if len == max_len {
+                    break;
+                }
+            }
+        }
+
+        // A back-reference costs 13 bits, a literal 9 bits.
+        if best_len > 1 {
+            writer.push(0, 1);
+            writer.push(pos - best_pos - 1, WINDOW_SZ2);
+            writer.push(best_len - 1, LOOKAHEAD_SZ2);
+            pos += best_len;
+        } else {
+            writer.push(1, 1);
+            writer.push(buf[pos] as usize, 8);
+            pos += 1;
+        }
+    }
+
+    writer.finish()
+}
+
+/// Decompresses `data`, stopping when the input runs out.
+#[cfg(test)]
+pub fn decompress(data: &[u8]) -> Vec<u8> {
+    let total_bits = data.len() * 8;
+    let mut bit = 0;
+    let mut read = |count: u32| -> Option<usize> {
+        if bit + count as usize > total_bits {
+            return None;
+        }
+        let mut value = 0;
+        for _ in 0..count {
+            value = (value << 1) | ((data[bit / 8] >> (7 - bit % 8)) & 1) as usize;
+            bit += 1;
+        }
+        Some(value)
+    };
+
+    let mut output: Vec<u8> = Vec::new();
+    let history = |output: &Vec<u8>, distance: usize| {
+        output.len().checked_sub(distance).map_or(0, |i| output[i])
+    };
+
+    while let Some(tag) = read(1) {
+        if tag == 1 {
+            let Some(byte) = read(8) else { break };
+            output.push(byte as u8);
+        } else {
+            let (Some(index), Some(count)) = (read(WINDOW_SZ2), read(LOOKAHEAD_SZ2)) else {
+                break;
+            };
+            for _ in 0..=count {
+                let byte = history(&output, index + 1);
+                output.push(byte);
+            }
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compress, decompress};
+
+    #[test]
+    fn round_trip() {
+        let data: Vec<u8> = (0..600u32).map(|i| (i * i % 7) as u8).collect();
+        let compressed = compress(&data);
+        assert!(compressed.len() < data.len());
+        assert_eq!(decompress(&compressed), data);
+    }
+
+    #[test]
+    fn leading_zeros_reference_window() {
+        // The zero-filled window allows zeros to be back-referenced immediately.
+        let compressed = compress(&[0; 16]);
+        assert_eq!(compressed.len(), 2);
+        assert_eq!(decompress(&compressed), [0; 16]);
+    }
+}
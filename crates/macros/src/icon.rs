@@ -0,0 +1,124 @@
+//! Conversion of PNG images into the firmware's icon format.
+//!
+//! See `docs/icons.md` for a description of the format.
+
+use std::fs::File;
+use std::path::Path;
+
+use crate::heatshrink;
+
+/// A monochrome image.
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    /// Rows of pixels, each padded to a whole byte, least significant bit first. Set
+    /// bits are drawn in the foreground color.
+    pub bits: Vec<u8>,
+}
+
+impl Image {
+    /// Loads a PNG, treating opaque pixels that are darker than 50% grey as set.
+    pub fn load_png(path: &Path) -> Result<Self, String> {
+        let error = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+
+        let file = File::open(path).map_err(|e| error(&e))?;
+        let mut decoder = png::Decoder::new(file);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info().map_err(|e| error(&e))?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).map_err(|e| error(&e))?;
+
+        let (width, height) = (info.width as usize, info.height as usize);
+        if width > 255 || height > 255 {
+            return Err(error(&"icons must be at most 255x255 pixels"));
+        }
+
+        let samples = info.color_type.samples();
+        let stride = (width + 7) / 8;
+        let mut bits = vec![0; stride * height];
+        for y in 0..height {
+            for x in 0..width {
+                let pixel = &buf[(y * width + x) * samples..][..samples];
+                let (luma, alpha) = match *pixel {
+                    [l] => (l as u32, 255),
+                    [l, a] => (l as u32, a as u32),
+                    [r, g, b] => (luma(r, g, b), 255),
+                    [r, g, b, a] => (luma(r, g, b), a as u32),
+                    _ => unreachable!("PNG pixels have 1 to 4 samples"),
+                };
+
+                if alpha >= 128 && luma < 128 {
+                    bits[y * stride + x / 8] |= 1 << (x % 8);
+                }
+            }
+        }
+
+        Ok(Image {
+            width: width as u32,
+            height: height as u32,
+            bits,
+        })
+    }
+
+    /// Encodes the image as an icon frame, compressing it if that makes it smaller.
+    pub fn encode(&self) -> Vec<u8> {
+        let compressed = heatshrink::compress(&self.bits);
+
+        // Same criteria as the firmware's asset compiler.
+        if compressed.len() + 4 < self.bits.len() {
+            let len = compressed.len() as u16;
+            let mut frame = vec![0x01, 0x00];
+            frame.extend_from_slice(&len.to_le_bytes());
+            frame.extend_from_slice(&compressed);
+            frame
+        } else {
+            let mut frame = vec![0x00];
+            frame.extend_from_slice(&self.bits);
+            frame
+        }
+    }
+}
+
+fn luma(r: u8, g: u8, b: u8) -> u32 {
+    (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::Image;
+    use crate::heatshrink;
+
+    fn icons_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../flipperzero/examples/icons")
+    }
+
+    #[test]
+    fn uncompressed_icon() {
+        let image = Image::load_png(&icons_dir().join("rustacean-10x10.png")).unwrap();
+        assert_eq!((image.width, image.height), (10, 10));
+
+        let expected = std::fs::read(icons_dir().join("rustacean-10x10.icon")).unwrap();
+        assert_eq!(image.encode(), expected);
+    }
+
+    #[test]
+    fn compressed_icon() {
+        let image = Image::load_png(&icons_dir().join("rustacean-48x32.png")).unwrap();
+        assert_eq!((image.width, image.height), (48, 32));
+
+        let frame = image.encode();
+        assert_eq!(frame[..2], [0x01, 0x00]);
+        let len = u16::from_le_bytes([frame[2], frame[3]]) as usize;
+        assert_eq!(frame.len(), len + 4);
+        assert_eq!(heatshrink::decompress(&frame[4..]), image.bits);
+
+        // The reference icon was produced with the `heatshrink` tool, which drops the
+        // trailing zeros of this image, so only the decompressed prefix is compared.
+        let reference = std::fs::read(icons_dir().join("rustacean-48x32.icon")).unwrap();
+        let reference = heatshrink::decompress(&reference[4..]);
+        assert_eq!(image.bits[..reference.len()], reference[..]);
+        assert!(image.bits[reference.len()..].iter().all(|&b| b == 0));
+    }
+}
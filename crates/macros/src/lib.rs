@@ -0,0 +1,204 @@
+//! Procedural macros for the `flipperzero` crate.
+//!
+//! Use the re-exports in the `flipperzero` crate rather than depending on this crate
+//! directly.
+
+use std::path::{Path, PathBuf};
+
+use proc_macro::TokenStream;
+use proc_macro2::Literal;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input, LitInt, LitStr, Token,
+};
+
+mod heatshrink;
+mod icon;
+
+use icon::Image;
+
+/// Arguments of `include_animation!`.
+struct AnimationArgs {
+    path: LitStr,
+    frame_rate: Option<LitInt>,
+}
+
+impl Parse for AnimationArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+        let frame_rate = if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        Ok(AnimationArgs { path, frame_rate })
+    }
+}
+
+/// Resolves a path relative to the crate being compiled.
+fn resolve(path: &LitStr) -> PathBuf {
+    let root = std::env::var_os("CARGO_MANIFEST_DIR").unwrap_or_default();
+    Path::new(&root).join(path.value())
+}
+
+/// Generates a `&'static Icon` from encoded frames.
+///
+/// `sources` are included with `include_bytes!` so that the icon is rebuilt when they
+/// change.
+fn icon_tokens(
+    width: u32,
+    height: u32,
+    frame_rate: u8,
+    frames: &[Vec<u8>],
+    sources: &[PathBuf],
+) -> proc_macro2::TokenStream {
+    let width = width as u8;
+    let height = height as u8;
+    let frame_count = frames.len();
+    let frame_count_u8 = frame_count as u8;
+    let frames = frames.iter().map(|frame| Literal::byte_string(frame));
+    let sources = sources
+        .iter()
+        .map(|source| source.to_string_lossy().into_owned());
+
+    quote! {{
+        #( const _: &[u8] = include_bytes!(#sources); )*
+
+        static FRAMES: ::flipperzero::gui::icon::__IconFrames<#frame_count> =
+            ::flipperzero::gui::icon::__IconFrames([#( #frames.as_ptr() ),*]);
+        static ICON: ::flipperzero::gui::icon::Icon = unsafe {
+            ::flipperzero::gui::icon::Icon::from_raw_parts(
+                #width,
+                #height,
+                #frame_count_u8,
+                #frame_rate,
+                FRAMES.as_ptr(),
+            )
+        };
+
+        &ICON
+    }}
+}
+
+/// Includes a PNG image as a `&'static Icon`.
+///
+/// The path is relative to the crate's `Cargo.toml`. The image is converted into the
+/// firmware's icon format when the crate is compiled: opaque pixels darker than 50%
+/// grey are drawn, and everything else is left blank.
+#[proc_macro]
+pub fn include_icon(input: TokenStream) -> TokenStream {
+    let path = parse_macro_input!(input as LitStr);
+    let file = resolve(&path);
+
+    match Image::load_png(&file) {
+        Ok(image) => icon_tokens(image.width, image.height, 0, &[image.encode()], &[file]).into(),
+        Err(e) => syn::Error::new(path.span(), e).to_compile_error().into(),
+    }
+}
+
+/// Includes a directory of PNG frames as an animated `&'static Icon`.
+///
+/// Frames are ordered by file name, and must all have the same size. The frame rate
+/// (in frames per second) is either given as the second argument, or read from a
+/// `frame_rate` file in the directory, matching the firmware's asset layout.
+#[proc_macro]
+pub fn include_animation(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as AnimationArgs);
+
+    match load_animation(&args) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => syn::Error::new(args.path.span(), e)
+            .to_compile_error()
+            .into(),
+    }
+}
+
+fn load_animation(args: &AnimationArgs) -> Result<proc_macro2::TokenStream, String> {
+    let dir = resolve(&args.path);
+    let error = |e: &dyn std::fmt::Display| format!("{}: {}", dir.display(), e);
+
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .map_err(|e| error(&e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().map_or(false, |ext| ext == "png"))
+        .collect();
+    files.sort();
+    if files.is_empty() {
+        return Err(error(&"no PNG frames found"));
+    }
+    if files.len() > 255 {
+        return Err(error(&"animations can have at most 255 frames"));
+    }
+
+    let frame_rate = match &args.frame_rate {
+        Some(frame_rate) => frame_rate.base10_parse::<u8>().map_err(|e| e.to_string())?,
+        None => {
+            let frame_rate_file = dir.join("frame_rate");
+            std::fs::read_to_string(&frame_rate_file)
+                .map_err(|e| format!("{}: {}", frame_rate_file.display(), e))?
+                .trim()
+                .parse::<u8>()
+                .map_err(|e| format!("{}: {}", frame_rate_file.display(), e))?
+        }
+    };
+
+    let mut size = None;
+    let mut frames = Vec::with_capacity(files.len());
+    for file in &files {
+        let image = Image::load_png(file)?;
+        let frame_size = (image.width, image.height);
+        if *size.get_or_insert(frame_size) != frame_size {
+            return Err(format!(
+                "{}: frame is {}x{}, but the first frame is {}x{}",
+                file.display(),
+                frame_size.0,
+                frame_size.1,
+                size.unwrap().0,
+                size.unwrap().1,
+            ));
+        }
+        frames.push(image.encode());
+    }
+
+    let (width, height) = size.unwrap();
+    Ok(icon_tokens(width, height, frame_rate, &frames, &files))
+}
+
+/// Produces the data for the `icon` field of the application manifest.
+///
+/// PNG images (relative to the crate's `Cargo.toml`) are converted, and must be 10x10
+/// pixels. Any other file is assumed to already be an encoded icon, and is included
+/// as-is relative to the current file.
+#[doc(hidden)]
+#[proc_macro]
+pub fn manifest_icon(input: TokenStream) -> TokenStream {
+    let path = parse_macro_input!(input as LitStr);
+    if !path.value().ends_with(".png") {
+        return quote!(include_bytes!(#path)).into();
+    }
+
+    let image = match Image::load_png(&resolve(&path)) {
+        Ok(image) => image,
+        Err(e) => return syn::Error::new(path.span(), e).to_compile_error().into(),
+    };
+    if (image.width, image.height) != (10, 10) {
+        let message = format!(
+            "application icons must be 10x10 pixels, but this image is {}x{}",
+            image.width, image.height
+        );
+        return syn::Error::new(path.span(), message)
+            .to_compile_error()
+            .into();
+    }
+
+    let data = Literal::byte_string(&image.encode());
+    let source = resolve(&path).to_string_lossy().into_owned();
+
+    quote!({
+        const _: &[u8] = include_bytes!(#source);
+        #data
+    })
+    .into()
+}
@@ -0,0 +1,187 @@
+//! Launch arguments.
+//!
+//! Applications are launched with an optional argument string (for example a file
+//! path, when opened from the archive). The entry point defined with
+//! [`entry!`](crate::entry!) can receive it as a raw pointer, as an `Option<&CStr>`,
+//! as [`Args`], or as any type implementing [`FromArgs`].
+//!
+//! The SDK doesn't pass arguments to applications launched with `run-fap` yet.
+
+use core::ffi::{c_char, CStr};
+use core::fmt;
+use core::str::{self, FromStr};
+
+use flipperzero_args::Words;
+
+/// Argument parsing error kinds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArgsError {
+    /// A required argument is missing.
+    Missing,
+    /// An argument could not be parsed.
+    Invalid,
+    /// There are more arguments than expected.
+    Unexpected,
+    /// An argument is not valid UTF-8.
+    Utf8,
+}
+
+impl ArgsError {
+    /// Describes the error.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Missing => "Missing argument",
+            Self::Invalid => "Invalid argument",
+            Self::Unexpected => "Unexpected argument",
+            Self::Utf8 => "Argument is not valid UTF-8",
+        }
+    }
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for ArgsError {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+/// Launch arguments, split into words.
+///
+/// Words are separated by whitespace. A word can contain whitespace by surrounding it
+/// with double quotes (`"like this"`). See [`Words`] for the exact rules.
+#[derive(Debug, Clone)]
+pub struct Args<'a> {
+    raw: *mut u8,
+    words: Words<'a>,
+}
+
+impl<'a> Args<'a> {
+    /// Wraps the argument pointer passed to the application.
+    ///
+    /// # Safety
+    ///
+    /// `raw` must either be NULL, or point to a NULL-terminated string that is valid
+    /// and unchanged for `'a`.
+    pub unsafe fn from_raw(raw: *mut u8) -> Self {
+        let args = if raw.is_null() {
+            &[]
+        } else {
+            CStr::from_ptr(raw as *const c_char).to_bytes()
+        };
+
+        Args {
+            raw,
+            words: Words::new(args),
+        }
+    }
+
+    /// Obtain the raw argument pointer, which may be NULL.
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.raw
+    }
+
+    /// Returns the complete argument string, if any.
+    pub fn as_c_str(&self) -> Option<&'a CStr> {
+        if self.raw.is_null() {
+            None
+        } else {
+            Some(unsafe { CStr::from_ptr(self.raw as *const c_char) })
+        }
+    }
+
+    /// Returns `true` if there are no more words.
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Returns the next word, or `None` if there are no more.
+    pub fn next_str(&mut self) -> Result<Option<&'a str>, ArgsError> {
+        self.words
+            .next()
+            .map(|word| str::from_utf8(word).map_err(|_| ArgsError::Utf8))
+            .transpose()
+    }
+
+    /// Parses the next word, which must be present.
+    pub fn required<T: FromStr>(&mut self) -> Result<T, ArgsError> {
+        self.optional()?.ok_or(ArgsError::Missing)
+    }
+
+    /// Parses the next word, if any.
+    pub fn optional<T: FromStr>(&mut self) -> Result<Option<T>, ArgsError> {
+        self.next_str()?
+            .map(|word| word.parse().map_err(|_| ArgsError::Invalid))
+            .transpose()
+    }
+
+    /// Checks that all words have been consumed.
+    pub fn finish(self) -> Result<(), ArgsError> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(ArgsError::Unexpected)
+        }
+    }
+}
+
+/// Types that can be parsed from the launch arguments.
+///
+/// Implement this for a type to use it as the argument of the entry point. If parsing
+/// fails, the error is printed and the application exits with a non-zero exit code.
+///
+/// # Examples
+///
+/// ```
+/// struct Options {
+///     path: &'static str,
+///     repeat: Option<u32>,
+/// }
+///
+/// impl FromArgs for Options {
+///     fn from_args(mut args: Args<'static>) -> Result<Self, ArgsError> {
+///         let options = Options {
+///             path: args.next_str()?.ok_or(ArgsError::Missing)?,
+///             repeat: args.optional()?,
+///         };
+///         args.finish()?;
+///         Ok(options)
+///     }
+/// }
+///
+/// fn main(options: Options) -> i32 {
+///     // ...
+/// }
+///
+/// entry!(main);
+/// ```
+pub trait FromArgs: Sized {
+    /// Parses the launch arguments.
+    fn from_args(args: Args<'static>) -> Result<Self, ArgsError>;
+}
+
+impl FromArgs for *mut u8 {
+    fn from_args(args: Args<'static>) -> Result<Self, ArgsError> {
+        Ok(args.as_ptr())
+    }
+}
+
+impl FromArgs for Option<&'static CStr> {
+    fn from_args(args: Args<'static>) -> Result<Self, ArgsError> {
+        Ok(args.as_c_str())
+    }
+}
+
+impl FromArgs for Args<'static> {
+    fn from_args(args: Args<'static>) -> Result<Self, ArgsError> {
+        Ok(args)
+    }
+}
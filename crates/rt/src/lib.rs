@@ -5,8 +5,12 @@
 
 #![no_std]
 
+use flipperzero_sys as sys;
+
+pub mod args;
 pub mod manifest;
 pub mod panic_handler;
+pub mod plugin;
 
 /// The C entry point.
 /// This just delegates to the user's Rust entry point.
@@ -16,11 +20,30 @@ pub unsafe extern "C" fn _start(args: *mut u8) -> i32 {
         fn main(args: *mut u8) -> i32;
     }
 
-    main(args)
+    unsafe extern "C" fn call_main(args: *mut u8) -> i32 {
+        main(args)
+    }
+
+    // Allows the panic handler to exit the application.
+    panic_handler::exit::call_main(call_main, args)
 }
 
 /// Define the entry point.
-/// Must have the following signature: `fn(*mut u8) -> i32`.
+///
+/// The entry point takes either no argument, or an argument implementing
+/// [`FromArgs`](args::FromArgs) such as `*mut u8`, `Option<&CStr>` or
+/// [`Args`](args::Args). It returns a [`Termination`] value: an `i32` exit code, `()`,
+/// or a `Result` whose error is printed before exiting with a non-zero exit code.
+///
+/// # Examples
+///
+/// ```
+/// fn main(args: Option<&CStr>) -> Result<(), Error> {
+///     // ...
+/// }
+///
+/// entry!(main);
+/// ```
 #[macro_export]
 macro_rules! entry {
     ($path:path) => {
@@ -28,10 +51,85 @@ macro_rules! entry {
         // lld seems not to automatically rename `.rel.text.main` properly.
         #[export_name = "main"]
         pub unsafe fn __main(args: *mut u8) -> i32 {
-            // type check the entry function
-            let f: fn(*mut u8) -> i32 = $path;
+            $crate::_call_main($path, args)
+        }
+    }
+}
+
+/// Functions that can be used as the entry point.
+///
+/// `Args` is `()` for functions without arguments, or `(A,)` for functions taking an
+/// argument `A`.
+pub trait Main<Args> {
+    /// Parses the arguments, calls the function and returns its exit code.
+    fn call(self, args: args::Args<'static>) -> i32;
+}
 
-            f(args)
+impl<F, R> Main<()> for F
+where
+    F: FnOnce() -> R,
+    R: Termination,
+{
+    fn call(self, _args: args::Args<'static>) -> i32 {
+        self().report()
+    }
+}
+
+impl<F, A, R> Main<(A,)> for F
+where
+    F: FnOnce(A) -> R,
+    A: args::FromArgs,
+    R: Termination,
+{
+    fn call(self, args: args::Args<'static>) -> i32 {
+        A::from_args(args).map(self).report()
+    }
+}
+
+#[doc(hidden)]
+pub unsafe fn _call_main<F: Main<Args>, Args>(f: F, args: *mut u8) -> i32 {
+    f.call(args::Args::from_raw(args))
+}
+
+/// Values that can be returned from the entry point.
+pub trait Termination {
+    /// Returns the exit code, printing any error.
+    fn report(self) -> i32;
+}
+
+impl Termination for () {
+    fn report(self) -> i32 {
+        0
+    }
+}
+
+impl Termination for i32 {
+    fn report(self) -> i32 {
+        self
+    }
+}
+
+impl<T: Termination, E: ufmt::uDisplay> Termination for Result<T, E> {
+    fn report(self) -> i32 {
+        match self {
+            Ok(value) => value.report(),
+            Err(error) => {
+                let _ = ufmt::uwrite!(Stdout, "Error: {}\r\n", error);
+                unsafe { sys::furi_thread_stdout_flush() };
+                1
+            }
         }
     }
 }
+
+/// Writes to the thread's stdout.
+struct Stdout;
+
+impl ufmt::uWrite for Stdout {
+    type Error = core::convert::Infallible;
+
+    fn write_str(&mut self, s: &str) -> Result<(), Self::Error> {
+        unsafe { sys::furi_thread_stdout_write(s.as_ptr() as *const core::ffi::c_char, s.len()) };
+        Ok(())
+    }
+}
@@ -1,15 +1,175 @@
 //! Flipper Zero Manifest.
 
 use core::ffi::c_char;
+use core::fmt;
 
 use flipperzero_sys as sys;
 
+#[doc(hidden)]
+pub use flipperzero_macros::manifest_icon as __manifest_icon;
+
 const MANIFEST_MAGIC: u32 = 0x52474448;
 const HARDWARE_TARGET: u16 = 7;
 const DEFAULT_STACK_SIZE: u16 = 2048; // 2 KiB
 
+/// Smallest stack size accepted by [`manifest!`].
+pub const MIN_STACK_SIZE: u16 = 1024; // 1 KiB
+
+/// Longest application name that fits in the manifest, excluding the NULL terminator.
+pub const MAX_NAME_LEN: usize = 31;
+
+/// Invalid manifest fields, as reported at compile time by [`manifest!`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ManifestError {
+    /// The name is longer than [`MAX_NAME_LEN`].
+    NameTooLong,
+    /// The stack size is below [`MIN_STACK_SIZE`].
+    StackTooSmall,
+    /// The icon is longer than 32 bytes.
+    IconTooLong,
+    /// The uncompressed icon doesn't contain 10x10 image data.
+    IconSize,
+    /// The compressed icon length doesn't match its header.
+    IconLength,
+    /// The icon is neither uncompressed nor compressed.
+    IconFormat,
+    /// `has_icon` is set, but no icon was given.
+    MissingIcon,
+    /// An icon was given, but `has_icon` is not set.
+    UnexpectedIcon,
+    /// The manifest version isn't defined by the firmware.
+    UnsupportedVersion,
+}
+
+impl ManifestError {
+    /// Describes the error.
+    pub const fn description(&self) -> &'static str {
+        match self {
+            Self::NameTooLong => "application name must be at most 31 bytes",
+            Self::StackTooSmall => "stack size must be at least 1024 bytes",
+            Self::IconTooLong => "icon must be at most 32 bytes",
+            Self::IconSize => "uncompressed icon must contain 20 bytes of 10x10 image data",
+            Self::IconLength => "compressed icon length does not match its header",
+            Self::IconFormat => "icon data is not a valid icon",
+            Self::MissingIcon => "`has_icon` is set, but no icon was given",
+            Self::UnexpectedIcon => "an icon was given, but `has_icon` is not set",
+            Self::UnsupportedVersion => "unsupported manifest version",
+        }
+    }
+
+    /// Fails compilation with the description of the error, when called in a constant.
+    #[track_caller]
+    pub const fn panic(self) -> ! {
+        // `panic!` only accepts string literals in constants.
+        match self {
+            Self::NameTooLong => panic!("application name must be at most 31 bytes"),
+            Self::StackTooSmall => panic!("stack size must be at least 1024 bytes"),
+            Self::IconTooLong => panic!("icon must be at most 32 bytes"),
+            Self::IconSize => {
+                panic!("uncompressed icon must contain 20 bytes of 10x10 image data")
+            }
+            Self::IconLength => panic!("compressed icon length does not match its header"),
+            Self::IconFormat => panic!("icon data is not a valid icon"),
+            Self::MissingIcon => panic!("`has_icon` is set, but no icon was given"),
+            Self::UnexpectedIcon => panic!("an icon was given, but `has_icon` is not set"),
+            Self::UnsupportedVersion => panic!("unsupported manifest version"),
+        }
+    }
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for ManifestError {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+/// Checks that an application name fits in the manifest.
+pub const fn check_name(name: &str) -> Result<(), ManifestError> {
+    if name.len() > MAX_NAME_LEN {
+        return Err(ManifestError::NameTooLong);
+    }
+    Ok(())
+}
+
+/// Checks the stack size of an application, or `0` for plugins.
+pub const fn check_stack_size(stack_size: u16) -> Result<(), ManifestError> {
+    // Plugins are identified by a stack size of 0.
+    if stack_size != 0 && stack_size < MIN_STACK_SIZE {
+        return Err(ManifestError::StackTooSmall);
+    }
+    Ok(())
+}
+
+/// Checks that `icon` is an encoded 10x10 icon (see `docs/icons.md`).
+pub const fn check_icon(icon: &[u8]) -> Result<(), ManifestError> {
+    if icon.len() > 32 {
+        return Err(ManifestError::IconTooLong);
+    }
+    match icon {
+        // Uncompressed: 10 rows of 2 bytes.
+        [0, data @ ..] if data.len() != 20 => Err(ManifestError::IconSize),
+        [0, ..] => Ok(()),
+        [1, 0, low, high, data @ ..] => {
+            if data.len() != u16::from_le_bytes([*low, *high]) as usize {
+                Err(ManifestError::IconLength)
+            } else {
+                Ok(())
+            }
+        }
+        _ => Err(ManifestError::IconFormat),
+    }
+}
+
+/// Checks that `has_icon` matches whether an icon was set.
+///
+/// A blank icon is all zeros, so whether an icon was set can't be told from its data.
+pub const fn check_has_icon(has_icon: bool, icon_set: bool) -> Result<(), ManifestError> {
+    match (has_icon, icon_set) {
+        (true, false) => Err(ManifestError::MissingIcon),
+        (false, true) => Err(ManifestError::UnexpectedIcon),
+        _ => Ok(()),
+    }
+}
+
+/// Checks that the firmware defines the manifest version.
+pub const fn check_version(version: u32) -> Result<(), ManifestError> {
+    match version {
+        1 => Ok(()),
+        _ => Err(ManifestError::UnsupportedVersion),
+    }
+}
+
 /// Define application manifest.
 /// 
+/// Fields:
+/// 
+/// - `name`: Application name, at most [`MAX_NAME_LEN`] bytes.
+/// - `stack_size`: Stack size in bytes, at least [`MIN_STACK_SIZE`].
+/// - `app_version`: Application version.
+/// - `has_icon`: Must be `true` if, and only if, an `icon` is given.
+/// - `icon`: Either a 10x10 PNG image relative to the crate's `Cargo.toml`, which is
+///   converted at compile time, or an already encoded `.icon` file relative to the
+///   current file.
+/// - `api_version`: Firmware API version to declare, see [`api_version`]. Defaults to
+///   the version of the SDK this crate was built against.
+/// - `hardware_target_id`: Hardware target to declare. Defaults to `7` (Flipper Zero).
+/// - `plugin`: Marks the application as a plugin, see [`plugin!`](crate::plugin!).
+/// 
+/// The manifest format version can be selected by starting with `version = N`.
+/// Only version `1` is currently defined by the firmware, and is the default.
+/// 
+/// Invalid manifests are rejected at compile time.
+/// 
 /// # Examples
 /// 
 /// ```
@@ -18,29 +178,63 @@ const DEFAULT_STACK_SIZE: u16 = 2048; // 2 KiB
 ///     stack_size = 1024,
 ///     app_version = 1,
 ///     has_icon = true,
-///     icon: "app.icon",
+///     icon = "app.png",
 /// )
 /// ```
 #[macro_export]
 macro_rules! manifest {
+    (version = $version:tt $(, $($field:ident = $value:expr),* )? $(,)?) => {
+        $crate::_manifest!($crate::_manifest_type!($version); $($($field = $value),*)?);
+    };
     ($($field:ident = $value:expr),* $(,)?) => {
+        $crate::_manifest!($crate::manifest::ApplicationManifestV1; $($field = $value),*);
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _manifest {
+    ($type:ty; $($field:ident = $value:expr),*) => {
         #[no_mangle]
         #[link_section = ".fapmeta"]
-        static FAP_MANIFEST: $crate::manifest::ApplicationManifestV1 = $crate::manifest::ApplicationManifestV1 {
-            $( $field: $crate::_manifest_field!($field = $value), )*
-            .. $crate::manifest::ApplicationManifestV1::default()
+        static FAP_MANIFEST: $type = {
+            let manifest = <$type>::default();
+            let icon_set = false;
+            $(
+                let manifest = manifest.$field($crate::_manifest_value!($field = $value));
+                let icon_set = icon_set || $crate::_manifest_is_icon!($field);
+            )*
+            manifest.validate(icon_set)
         };
     };
 }
 
 #[macro_export]
 #[doc(hidden)]
-macro_rules! _manifest_field {
-    (stack_size = $value:expr) => { $value };
-    (app_version = $value:expr) => { $value };
-    (name = $value:expr) => { $crate::manifest::_padded($value.as_bytes()) };
-    (has_icon = $value:expr) => { $value as core::ffi::c_char };
-    (icon = $value:expr) => { $crate::manifest::_padded(include_bytes!($value)) };
+macro_rules! _manifest_type {
+    (1) => { $crate::manifest::ApplicationManifestV1 };
+    ($version:tt) => {
+        compile_error!(concat!("unsupported manifest version: ", stringify!($version)))
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _manifest_value {
+    (icon = $value:expr) => { $crate::manifest::__manifest_icon!($value) };
+    ($field:ident = $value:expr) => { $value };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _manifest_is_icon {
+    (icon) => { true };
+    ($field:ident) => { false };
+}
+
+/// Packs a firmware API version, as used by the `api_version` manifest field.
+pub const fn api_version(major: u16, minor: u16) -> u32 {
+    ((major as u32) << 16) | minor as u32
 }
 
 #[repr(C, packed)]
@@ -79,6 +273,91 @@ impl ApplicationManifestV1 {
             icon: [0; 32],
         }
     }
+
+    /// Sets the application name.
+    ///
+    /// Panics if the name is longer than [`MAX_NAME_LEN`].
+    pub const fn name(mut self, name: &str) -> Self {
+        expect_valid(check_name(name));
+        self.name = _padded(name.as_bytes());
+        self
+    }
+
+    /// Sets the stack size in bytes.
+    pub const fn stack_size(mut self, stack_size: u16) -> Self {
+        self.stack_size = stack_size;
+        self
+    }
+
+    /// Marks the application as a plugin, which is loaded by a host application
+    /// instead of being run on its own.
+    pub const fn plugin(mut self, plugin: bool) -> Self {
+        if plugin {
+            self.stack_size = 0;
+        }
+        self
+    }
+
+    /// Sets the application version.
+    pub const fn app_version(mut self, app_version: u32) -> Self {
+        self.app_version = app_version;
+        self
+    }
+
+    /// Sets whether the manifest contains an icon.
+    pub const fn has_icon(mut self, has_icon: bool) -> Self {
+        self.has_icon = has_icon as c_char;
+        self
+    }
+
+    /// Sets the encoded 10x10 icon (see `docs/icons.md`).
+    ///
+    /// Panics if the data is not a valid icon.
+    pub const fn icon(mut self, icon: &[u8]) -> Self {
+        expect_valid(check_icon(icon));
+
+        let mut data = [0; 32];
+        let mut i = 0;
+        while i < icon.len() {
+            data[i] = icon[i];
+            i += 1;
+        }
+        self.icon = data;
+        self
+    }
+
+    /// Overrides the firmware API version, see [`api_version`].
+    pub const fn api_version(mut self, api_version: u32) -> Self {
+        self.base.api_version = api_version;
+        self
+    }
+
+    /// Overrides the hardware target ID.
+    pub const fn hardware_target_id(mut self, hardware_target_id: u16) -> Self {
+        self.base.hardware_target_id = hardware_target_id;
+        self
+    }
+
+    /// Checks the consistency of the manifest, where `icon_set` is whether
+    /// [`icon`](Self::icon) was called.
+    ///
+    /// Panics if the stack size of an application that isn't a plugin is below
+    /// [`MIN_STACK_SIZE`], if `has_icon` doesn't match `icon_set`, or if the manifest
+    /// version isn't supported.
+    pub const fn validate(self, icon_set: bool) -> Self {
+        expect_valid(check_version(self.base.manifest_version));
+        expect_valid(check_stack_size(self.stack_size));
+        expect_valid(check_has_icon(self.has_icon != 0, icon_set));
+        self
+    }
+}
+
+/// Panics if a manifest check failed.
+#[track_caller]
+const fn expect_valid(result: Result<(), ManifestError>) {
+    if let Err(err) = result {
+        err.panic();
+    }
 }
 
 /// Pads an array with NULL bytes.
@@ -96,3 +375,58 @@ pub const fn _padded<const SIZE: usize>(value: &[u8]) -> [u8; SIZE] {
 
     array
 }
+
+// The library isn't built for the host, so the checks are tested at compile time.
+const _: () = {
+    assert!(matches!(check_name("MyApp"), Ok(())));
+    assert!(matches!(check_name("Thirty-one bytes long, at most."), Ok(())));
+    assert!(matches!(
+        check_name("Thirty-two bytes long, too long."),
+        Err(ManifestError::NameTooLong)
+    ));
+
+    assert!(matches!(check_stack_size(0), Ok(())));
+    assert!(matches!(check_stack_size(MIN_STACK_SIZE), Ok(())));
+    assert!(matches!(
+        check_stack_size(MIN_STACK_SIZE - 1),
+        Err(ManifestError::StackTooSmall)
+    ));
+
+    assert!(matches!(check_icon(&[0; 21]), Ok(())));
+    assert!(matches!(check_icon(&[1, 0, 2, 0, 0xff, 0x00]), Ok(())));
+    assert!(matches!(check_icon(&[0; 20]), Err(ManifestError::IconSize)));
+    assert!(matches!(check_icon(&[0; 33]), Err(ManifestError::IconTooLong)));
+    assert!(matches!(
+        check_icon(&[1, 0, 3, 0, 0xff, 0x00]),
+        Err(ManifestError::IconLength)
+    ));
+    assert!(matches!(check_icon(&[2; 21]), Err(ManifestError::IconFormat)));
+    assert!(matches!(check_icon(&[]), Err(ManifestError::IconFormat)));
+
+    assert!(matches!(check_has_icon(false, false), Ok(())));
+    assert!(matches!(check_has_icon(true, true), Ok(())));
+    assert!(matches!(
+        check_has_icon(true, false),
+        Err(ManifestError::MissingIcon)
+    ));
+    assert!(matches!(
+        check_has_icon(false, true),
+        Err(ManifestError::UnexpectedIcon)
+    ));
+    // A blank icon is still an icon.
+    let blank = ApplicationManifestV1::default()
+        .has_icon(true)
+        .icon(&[0; 21])
+        .validate(true);
+    assert!(blank.has_icon != 0);
+
+    assert!(matches!(check_version(1), Ok(())));
+    assert!(matches!(
+        check_version(2),
+        Err(ManifestError::UnsupportedVersion)
+    ));
+    assert!(matches!(
+        check_version(ApplicationManifestV1::default().base.manifest_version),
+        Ok(())
+    ));
+};
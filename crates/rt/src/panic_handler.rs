@@ -1,17 +1,96 @@
 //! Panic handler for Furi applications.
-//! This will print the panic info to stdout and then trigger a crash.
+//!
+//! The panic message is always printed to stdout. What happens next is chosen with
+//! [`set_panic_behavior`]:
+//!
+//! - [`PanicBehavior::Crash`] (the default) triggers a crash, which reboots the device.
+//! - [`PanicBehavior::Dialog`] shows the message in a dialog, then abandons the
+//!   application.
+//! - [`PanicBehavior::LogFile`] appends the message to a file, then abandons the
+//!   application.
+//!
+//! # Limitations
+//!
+//! Panics don't unwind. Abandoning the application returns from its entry point
+//! without running any destructor, so this is not a clean exit:
+//!
+//! - Records opened by the application are never closed.
+//! - Memory, files and other resources that are only released by a destructor leak.
+//! - A view port that is still attached to the GUI will crash the device once the
+//!   application is unloaded.
+//!
+//! Only choose [`PanicBehavior::Dialog`] or [`PanicBehavior::LogFile`] if the
+//! application can't panic while a view port is attached, and if leaking its
+//! resources until the device reboots is acceptable. Panics on threads other than
+//! the application's main thread always crash.
 
-use core::ffi::c_char;
+use core::ffi::{c_char, c_void, CStr};
+use core::fmt::{self, Write};
 use core::panic::PanicInfo;
-use core::str;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU8, Ordering};
 
 use flipperzero_sys as sys;
 use sys::c_string;
 
+/// Exit code of an application that exited because of a panic.
+pub const PANIC_EXIT_CODE: i32 = -1;
+
+/// Size of the buffer the panic message is rendered into. Longer messages are
+/// truncated.
+const MESSAGE_BUFFER_SIZE: usize = 256;
+
+const BEHAVIOR_CRASH: u8 = 0;
+const BEHAVIOR_DIALOG: u8 = 1;
+const BEHAVIOR_LOG_FILE: u8 = 2;
+
+static BEHAVIOR: AtomicU8 = AtomicU8::new(BEHAVIOR_CRASH);
+static LOG_PATH: AtomicPtr<c_char> = AtomicPtr::new(ptr::null_mut());
+static PANICKING: AtomicBool = AtomicBool::new(false);
+
+/// What to do after a panic message has been printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanicBehavior {
+    /// Crash the device.
+    Crash,
+    /// Show the message in an error dialog, then return from the entry point without
+    /// running destructors.
+    Dialog,
+    /// Append the message to the given file, then return from the entry point without
+    /// running destructors.
+    ///
+    /// The parent directory must exist.
+    LogFile(&'static CStr),
+}
+
+/// Sets what to do when the application panics.
+///
+/// See the [module documentation](self) for what is leaked when the application
+/// doesn't crash.
+pub fn set_panic_behavior(behavior: PanicBehavior) {
+    let behavior = match behavior {
+        PanicBehavior::Crash => BEHAVIOR_CRASH,
+        PanicBehavior::Dialog => BEHAVIOR_DIALOG,
+        PanicBehavior::LogFile(path) => {
+            LOG_PATH.store(path.as_ptr() as *mut c_char, Ordering::Release);
+            BEHAVIOR_LOG_FILE
+        }
+    };
+    BEHAVIOR.store(behavior, Ordering::Release);
+}
+
 #[panic_handler]
 pub fn panic(panic_info: &PanicInfo<'_>) -> ! {
-    // Format: "thread: 'App Name' paniced at 'panic!', panic.rs:5"
-    // Note: Don't use `format!` as it pulls in 10 KiB+ of formatting code.
+    // A panic while handling a panic can only crash.
+    if PANICKING.swap(true, Ordering::AcqRel) {
+        sys::crash!("Rust panic while panicking");
+    }
+
+    // Format: "thread: 'App Name' panicked at 'panic!', panic.rs:5:1"
+    // Note: Only `core::fmt` is used for rendering; `format!` would require an allocator.
+    let mut message = MessageBuffer::new();
+    let _ = write!(message, "{}", panic_info);
+
     unsafe {
         let thread_id = sys::furi_thread_get_current_id();
         let thread_name = if !thread_id.is_null() {
@@ -20,23 +99,192 @@ pub fn panic(panic_info: &PanicInfo<'_>) -> ! {
             c_string!("unknown")
         };
 
-        sys::__wrap_printf(c_string!("\x1b[0;31mthread: '%s' paniced"), thread_name);
+        sys::__wrap_printf(
+            c_string!("\x1b[0;31mthread: '%s' %s\x1b[0m\r\n"),
+            thread_name,
+            message.as_c_str().as_ptr(),
+        );
+        sys::furi_thread_stdout_flush();
+        sys::furi_thread_yield(); // Allow console to flush
 
-        if let Some(s) = panic_info.payload().downcast_ref::<&str>() {
-            sys::__wrap_printf(c_string!(" at '%*s'"), s.len(), s.as_ptr() as *const c_char);
+        match BEHAVIOR.load(Ordering::Acquire) {
+            BEHAVIOR_DIALOG => show_dialog(message.as_c_str()),
+            BEHAVIOR_LOG_FILE => {
+                let path = LOG_PATH.load(Ordering::Acquire);
+                append_to_file(path, thread_name, message.as_c_str());
+            }
+            _ => sys::crash!("Rust panic"),
         }
 
-        if let Some(location) = panic_info.location() {
-            let file = location.file();
-            let line = location.line();
+        exit::exit(PANIC_EXIT_CODE)
+    }
+}
+
+/// Shows a blocking error dialog.
+unsafe fn show_dialog(message: &CStr) {
+    let dialogs = sys::furi_record_open(c_string!("dialogs")) as *mut sys::DialogsApp;
+
+    let dialog = sys::dialog_message_alloc();
+    sys::dialog_message_set_header(
+        dialog,
+        c_string!("Rust panic"),
+        64,
+        0,
+        sys::Align_AlignCenter,
+        sys::Align_AlignTop,
+    );
+    sys::dialog_message_set_text(
+        dialog,
+        message.as_ptr(),
+        64,
+        12,
+        sys::Align_AlignCenter,
+        sys::Align_AlignTop,
+    );
+    sys::dialog_message_set_buttons(dialog, ptr::null(), c_string!("Exit"), ptr::null());
+    sys::dialog_message_show(dialogs, dialog);
+    sys::dialog_message_free(dialog);
 
-            sys::__wrap_printf(c_string!(", %*s:%u"), file.len(), file.as_ptr() as *const c_char, line);
+    sys::furi_record_close(c_string!("dialogs"));
+}
+
+/// Appends a line with the panic message to the file at `path`.
+unsafe fn append_to_file(path: *const c_char, thread_name: *const c_char, message: &CStr) {
+    if path.is_null() {
+        return;
+    }
+
+    let storage = sys::furi_record_open(c_string!("storage")) as *mut sys::Storage;
+    let file = sys::storage_file_alloc(storage);
+
+    if sys::storage_file_open(
+        file,
+        path,
+        sys::FS_AccessMode_FSAM_WRITE,
+        sys::FS_OpenMode_FSOM_OPEN_APPEND,
+    ) {
+        let thread_name = CStr::from_ptr(thread_name).to_bytes();
+        let message = message.to_bytes();
+        let parts: [&[u8]; 5] = [b"thread: '", thread_name, b"' ", message, b"\n"];
+        for data in parts {
+            sys::storage_file_write(file, data.as_ptr() as *const c_void, data.len() as u16);
         }
+    }
 
-        sys::__wrap_printf(c_string!("\x1b[0m\r\n"));
-        sys::furi_thread_stdout_flush();
-        sys::furi_thread_yield(); // Allow console to flush
+    sys::storage_file_close(file);
+    sys::storage_file_free(file);
+    sys::furi_record_close(c_string!("storage"));
+}
+
+/// Fixed-size, NULL-terminated buffer that silently truncates what is written to it.
+struct MessageBuffer {
+    data: [u8; MESSAGE_BUFFER_SIZE],
+    len: usize,
+}
+
+impl MessageBuffer {
+    fn new() -> Self {
+        Self {
+            data: [0; MESSAGE_BUFFER_SIZE],
+            len: 0,
+        }
+    }
+
+    fn as_c_str(&self) -> &CStr {
+        // SAFETY: The last byte is never written, and `write_str` skips NULL bytes.
+        unsafe { CStr::from_bytes_with_nul_unchecked(&self.data[..=self.len]) }
+    }
+}
+
+impl fmt::Write for MessageBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars().filter(|&c| c != '\0') {
+            let mut encoded = [0; 4];
+            let encoded = c.encode_utf8(&mut encoded).as_bytes();
+
+            // Keep room for the NULL terminator, and don't split characters.
+            if self.len + encoded.len() >= self.data.len() {
+                break;
+            }
+            self.data[self.len..self.len + encoded.len()].copy_from_slice(encoded);
+            self.len += encoded.len();
+        }
+
+        Ok(())
+    }
+}
+
+/// Exiting the application from a panic.
+///
+/// [`call_main`](exit::call_main) saves the callee-saved registers and stack pointer
+/// before calling the application's entry point. [`exit`](exit::exit) restores them,
+/// which returns from `call_main` as if the entry point had returned.
+pub(crate) mod exit {
+    use core::ffi::c_void;
+    use core::ptr;
+    use core::sync::atomic::{AtomicPtr, Ordering};
+
+    use flipperzero_sys as sys;
+
+    /// Entry point of the application.
+    pub(crate) type Main = unsafe extern "C" fn(args: *mut u8) -> i32;
+
+    /// Registers r4-r11 and sp, followed by the floating point registers d8-d15.
+    type Context = [u32; 9 + 2 * 8];
+
+    static mut CONTEXT: Context = [0; 9 + 2 * 8];
+    static MAIN_THREAD: AtomicPtr<c_void> = AtomicPtr::new(ptr::null_mut());
+
+    core::arch::global_asm!(
+        ".section .text",
+        ".syntax unified",
+        ".thumb",
+        // The FPU isn't enabled for module-level assembly.
+        ".fpu fpv4-sp-d16",
+        // i32 __flipperzero_rt_call(Main main, u8 *args, Context *context)
+        ".global __flipperzero_rt_call",
+        ".type __flipperzero_rt_call, %function",
+        ".thumb_func",
+        "__flipperzero_rt_call:",
+        "    push {{r4, lr}}",
+        "    mov r12, sp",
+        "    stmia r2!, {{r4-r11, r12}}",
+        "    vstmia r2!, {{d8-d15}}",
+        "    mov r3, r0",
+        "    mov r0, r1",
+        "    blx r3",
+        "    pop {{r4, pc}}",
+        // void __flipperzero_rt_return(Context *context, i32 code)
+        ".global __flipperzero_rt_return",
+        ".type __flipperzero_rt_return, %function",
+        ".thumb_func",
+        "__flipperzero_rt_return:",
+        "    ldmia r0!, {{r4-r11, r12}}",
+        "    vldmia r0!, {{d8-d15}}",
+        "    mov sp, r12",
+        "    mov r0, r1",
+        "    pop {{r4, pc}}",
+    );
+
+    extern "C" {
+        fn __flipperzero_rt_call(main: Main, args: *mut u8, context: *mut Context) -> i32;
+        fn __flipperzero_rt_return(context: *const Context, code: i32) -> !;
+    }
+
+    /// Calls the application's entry point, so that [`exit`] can return from it.
+    pub(crate) unsafe fn call_main(main: Main, args: *mut u8) -> i32 {
+        MAIN_THREAD.store(sys::furi_thread_get_current_id(), Ordering::Release);
+        __flipperzero_rt_call(main, args, ptr::addr_of_mut!(CONTEXT))
+    }
+
+    /// Returns `code` from [`call_main`], or crashes if not on the application's main
+    /// thread.
+    pub(crate) unsafe fn exit(code: i32) -> ! {
+        let main_thread = MAIN_THREAD.load(Ordering::Acquire);
+        if main_thread.is_null() || main_thread != sys::furi_thread_get_current_id() {
+            sys::crash!("Rust panic");
+        }
 
-        sys::crash!("Rust panic")
+        __flipperzero_rt_return(ptr::addr_of!(CONTEXT), code)
     }
 }
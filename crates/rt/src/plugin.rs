@@ -0,0 +1,84 @@
+//! Flipper Zero application plugins.
+//!
+//! A plugin is a FAP that is not run on its own, but loaded by a host application
+//! (for example with `flipperzero::plugin::PluginManager`). It exports a single
+//! entry point, usually a `#[repr(C)]` struct of function pointers, together with the
+//! application ID and API version it implements.
+
+use core::ffi::{c_char, c_void};
+
+use flipperzero_sys as sys;
+
+/// Define a plugin's entry point.
+///
+/// The crate must also be marked as a plugin in its manifest, and must not define an
+/// [`entry!`](crate::entry!) point.
+///
+/// # Examples
+///
+/// ```
+/// #[repr(C)]
+/// pub struct Decoder {
+///     pub name: &'static str,
+///     pub decode: extern "C" fn(data: *const u8, len: usize) -> bool,
+/// }
+///
+/// extern "C" fn decode(data: *const u8, len: usize) -> bool {
+///     // ...
+/// }
+///
+/// static DECODER: Decoder = Decoder { name: "My decoder", decode };
+///
+/// manifest!(name = "My decoder", plugin = true);
+/// plugin!(app_id = "my_app_decoder", api_version = 1, entry_point = &DECODER);
+/// ```
+#[macro_export]
+macro_rules! plugin {
+    (app_id = $app_id:literal, api_version = $api_version:expr, entry_point = $entry_point:expr $(,)?) => {
+        static __PLUGIN_DESCRIPTOR: $crate::plugin::PluginDescriptor =
+            $crate::plugin::PluginDescriptor::new(
+                concat!($app_id, "\0").as_ptr() as *const core::ffi::c_char,
+                $api_version,
+                $entry_point,
+            );
+
+        // The firmware calls the plugin's entry point to obtain its descriptor.
+        #[export_name = "main"]
+        pub unsafe fn __main(_args: *mut u8) -> i32 {
+            __PLUGIN_DESCRIPTOR.as_ptr() as i32
+        }
+    };
+}
+
+// Plugin entry points return a pointer through the `i32` return value of `_start`.
+const _: () = assert!(core::mem::size_of::<usize>() == core::mem::size_of::<i32>());
+
+/// Descriptor returned by a plugin's entry point.
+#[repr(transparent)]
+pub struct PluginDescriptor(sys::FlipperAppPluginDescriptor);
+
+// SAFETY: The descriptor only points to immutable static data.
+unsafe impl Sync for PluginDescriptor {}
+
+impl PluginDescriptor {
+    /// Creates a descriptor for an entry point implementing the API `api_version` of
+    /// the host application `app_id`.
+    ///
+    /// `app_id` must point to a NULL-terminated string.
+    pub const fn new<T: Sync>(
+        app_id: *const c_char,
+        api_version: u32,
+        entry_point: &'static T,
+    ) -> Self {
+        PluginDescriptor(sys::FlipperAppPluginDescriptor {
+            appid: app_id,
+            ep_api_version: api_version,
+            entry_point: entry_point as *const T as *const c_void,
+        })
+    }
+
+    /// Obtain raw descriptor.
+    pub const fn as_ptr(&self) -> *const sys::FlipperAppPluginDescriptor {
+        &self.0
+    }
+}
@@ -38,7 +38,7 @@ macro_rules! crash {
         unsafe {
             // Crash message is passed via r12
             let msg = $crate::c_string!($msg);
-            core::arch::asm!("", in("r12") msg, options(nomem, nostack));
+            core::arch::asm!("", in("r12") msg, options(nostack));
 
             $crate::__furi_crash();
             core::hint::unreachable_unchecked();
@@ -1,3 +1,5 @@
+use std::mem;
+
 use proc_macro::TokenStream;
 use proc_macro2::Span;
 use quote::{quote, ToTokens};
@@ -78,16 +80,23 @@ fn tests_runner_impl(args: TokenStream) -> parse::Result<TokenStream> {
     let test_suites = test_suites
         .elems
         .into_iter()
-        .map(|attr| {
+        .map(|mut attr| {
+            // Attributes such as `#[cfg(feature = "alloc")]` apply to the suite's entries.
+            let cfgs = match &mut attr {
+                Expr::Path(path) => mem::take(&mut path.attrs),
+                _ => vec![],
+            };
+
             let mut module = String::new();
             for token in attr.to_token_stream() {
                 module.push_str(&token.to_string());
             }
             let module = module.trim_start_matches("crate::");
 
+            let list = quote!(#attr::__test_list().map(|(name, test_fn)| (#module, name, test_fn)));
             (
-                quote!(#attr::__test_list().len()),
-                quote!(#attr::__test_list().map(|(name, test_fn)| (#module, name, test_fn))),
+                quote!(#(#cfgs)* let ret = ret + #attr::__test_list().len();),
+                quote!(#(#cfgs)* let ret = ret.chain(#list);),
             )
         })
         .collect::<Vec<_>>();
@@ -120,13 +129,13 @@ fn tests_runner_impl(args: TokenStream) -> parse::Result<TokenStream> {
 
             const fn test_count() -> usize {
                 let ret = 0;
-                #( let ret = ret + #test_counts; )*
+                #( #test_counts )*
                 ret
             }
 
             fn test_list() -> impl Iterator<Item = (&'static str, &'static str, ::flipperzero_test::TestFn)> {
                 let ret = ::core::iter::empty();
-                #( let ret = ret.chain(#test_lists); )*
+                #( #test_lists )*
                 ret
             }
 
@@ -0,0 +1,130 @@
+//! Sub-GHz RAW file converter.
+//!
+//! Converts `.sub` RAW captures to and from CSV and VCD, for analysis in other tools.
+
+use std::path::{Path, PathBuf};
+use std::{fs, process};
+
+use clap::{Parser, Subcommand};
+use flipperzero_tools::subghz::{self, RawFile};
+
+const DEFAULT_PRESET: &str = "FuriHalSubGhzPresetOok650Async";
+
+/// Flipper Zero Sub-GHz RAW file converter
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    /// Commands
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Convert a RAW file to CSV (`level,duration_us`)
+    ToCsv {
+        /// RAW `.sub` file
+        input: PathBuf,
+        /// Output file (defaults to stdout)
+        output: Option<PathBuf>,
+    },
+    /// Convert a RAW file to VCD
+    ToVcd {
+        /// RAW `.sub` file
+        input: PathBuf,
+        /// Output file (defaults to stdout)
+        output: Option<PathBuf>,
+    },
+    /// Convert CSV (`level,duration_us`) to a RAW file
+    FromCsv {
+        /// CSV file
+        input: PathBuf,
+        /// RAW `.sub` file
+        output: PathBuf,
+        #[command(flatten)]
+        settings: RadioSettings,
+    },
+    /// Convert VCD to a RAW file
+    FromVcd {
+        /// VCD file
+        input: PathBuf,
+        /// RAW `.sub` file
+        output: PathBuf,
+        #[command(flatten)]
+        settings: RadioSettings,
+    },
+}
+
+/// Radio settings saved in the RAW file.
+#[derive(clap::Args)]
+struct RadioSettings {
+    /// Frequency in Hz
+    #[arg(short, long, default_value_t = 433_920_000)]
+    frequency: u32,
+    /// Preset name
+    #[arg(short, long, default_value = DEFAULT_PRESET)]
+    preset: String,
+}
+
+impl RadioSettings {
+    fn raw_file(self, samples: Vec<subghz::Sample>) -> RawFile {
+        RawFile {
+            frequency: self.frequency,
+            preset: self.preset,
+            custom_preset_data: None,
+            samples,
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|err| format!("{}: {err}", path.display()))
+}
+
+fn write(path: Option<&Path>, contents: &str) -> Result<(), String> {
+    match path {
+        Some(path) => fs::write(path, contents).map_err(|err| format!("{}: {err}", path.display())),
+        None => {
+            print!("{contents}");
+            Ok(())
+        }
+    }
+}
+
+fn run(command: Commands) -> Result<(), String> {
+    match command {
+        Commands::ToCsv { input, output } => {
+            let raw = RawFile::parse(&read(&input)?).map_err(|err| err.to_string())?;
+            write(output.as_deref(), &subghz::to_csv(&raw.samples))
+        }
+        Commands::ToVcd { input, output } => {
+            let raw = RawFile::parse(&read(&input)?).map_err(|err| err.to_string())?;
+            write(output.as_deref(), &subghz::to_vcd(&raw.samples))
+        }
+        Commands::FromCsv {
+            input,
+            output,
+            settings,
+        } => {
+            let samples = subghz::from_csv(&read(&input)?).map_err(|err| err.to_string())?;
+            write(Some(&output), &settings.raw_file(samples).to_string())
+        }
+        Commands::FromVcd {
+            input,
+            output,
+            settings,
+        } => {
+            let samples = subghz::from_vcd(&read(&input)?).map_err(|err| err.to_string())?;
+            write(Some(&output), &settings.raw_file(samples).to_string())
+        }
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    if let Err(err) = run(cli.command) {
+        eprintln!("ERROR: {err}");
+        process::exit(1);
+    }
+}
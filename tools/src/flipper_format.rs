@@ -0,0 +1,409 @@
+//! Host-side Flipper Format parser and writer.
+//!
+//! Flipper Format is the `Key: value` text format used for `.sub`, `.ir`, `.nfc` and
+//! `.rfid` key files. This is a pure-Rust implementation of the format, so that files
+//! can be created and validated off-device.
+//!
+//! See: https://github.com/flipperdevices/flipperzero-firmware/blob/dev/lib/flipper_format/flipper_format.h
+
+use std::fmt::{self, Display};
+use std::path::Path;
+use std::{error, fs, io};
+
+const FILETYPE_KEY: &str = "Filetype";
+const VERSION_KEY: &str = "Version";
+
+/// Flipper Format error.
+#[derive(Debug)]
+pub enum Error {
+    /// The file could not be read.
+    Io(io::Error),
+    /// A line is not a comment or a `Key: value` pair.
+    Syntax { line: usize, text: String },
+    /// The key does not exist.
+    MissingKey(String),
+    /// The value could not be parsed as the requested type.
+    InvalidValue { key: String, value: String },
+    /// The file header does not match what was expected.
+    Header { filetype: String, version: u32 },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{err}"),
+            Error::Syntax { line, text } => {
+                write!(f, "line {line}: expected `Key: value`, found {text:?}")
+            }
+            Error::MissingKey(key) => write!(f, "missing key {key:?}"),
+            Error::InvalidValue { key, value } => {
+                write!(f, "invalid value for key {key:?}: {value:?}")
+            }
+            Error::Header { filetype, version } => {
+                write!(f, "unexpected header: {filetype:?} version {version}")
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        Error::Io(value)
+    }
+}
+
+/// A single line of a Flipper Format file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Line {
+    /// A comment, without the leading `#`.
+    Comment(String),
+    /// A key and its (space separated) values.
+    Value { key: String, value: String },
+}
+
+/// An in-memory Flipper Format file.
+///
+/// Keys may be repeated (for example, each signal in an `.ir` file starts with a `name`
+/// key), so lines are kept in file order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FlipperFormat {
+    lines: Vec<Line>,
+}
+
+impl FlipperFormat {
+    /// Create an empty [`FlipperFormat`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a [`FlipperFormat`] with a `Filetype` and `Version` header.
+    pub fn with_header(filetype: &str, version: u32) -> Self {
+        let mut ff = Self::new();
+        ff.push(FILETYPE_KEY, filetype);
+        ff.push(VERSION_KEY, version.to_string());
+
+        ff
+    }
+
+    /// Parse a file on the local file system.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parse Flipper Format text.
+    ///
+    /// This follows the device parser: blank lines are ignored, lines starting with `#`
+    /// are comments and every other line must contain a `:` separating key and value.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut lines = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if let Some(comment) = line.strip_prefix('#') {
+                lines.push(Line::Comment(comment.trim_start().to_string()));
+                continue;
+            }
+
+            match line.split_once(':') {
+                Some((key, value)) if !key.is_empty() => lines.push(Line::Value {
+                    key: key.to_string(),
+                    value: value.trim().to_string(),
+                }),
+                _ => {
+                    return Err(Error::Syntax {
+                        line: n + 1,
+                        text: line.to_string(),
+                    })
+                }
+            }
+        }
+
+        Ok(Self { lines })
+    }
+
+    /// Lines of the file, in order.
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// Iterate over `(key, value)` pairs, skipping comments.
+    pub fn values(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Value { key, value } => Some((key.as_str(), value.as_str())),
+            Line::Comment(_) => None,
+        })
+    }
+
+    /// Get the raw value of the first occurrence of `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// Get the raw values of every occurrence of `key`.
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.values()
+            .filter(move |(k, _)| *k == key)
+            .map(|(_, v)| v)
+    }
+
+    /// Read the `Filetype` and `Version` header.
+    pub fn header(&self) -> Result<(&str, u32), Error> {
+        let filetype = self.get_str(FILETYPE_KEY)?;
+        let version = self.get_u32(VERSION_KEY)?;
+
+        Ok((filetype, version))
+    }
+
+    /// Check that the header matches the expected file type and version.
+    pub fn check_header(&self, filetype: &str, version: u32) -> Result<(), Error> {
+        let (actual_filetype, actual_version) = self.header()?;
+        if actual_filetype != filetype || actual_version != version {
+            return Err(Error::Header {
+                filetype: actual_filetype.to_string(),
+                version: actual_version,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Get the string value of `key`.
+    pub fn get_str(&self, key: &str) -> Result<&str, Error> {
+        self.get(key)
+            .ok_or_else(|| Error::MissingKey(key.to_string()))
+    }
+
+    /// Get the array of values of `key`, parsing each with `parse`.
+    fn get_array<T>(&self, key: &str, parse: impl Fn(&str) -> Option<T>) -> Result<Vec<T>, Error> {
+        let value = self.get_str(key)?;
+        value
+            .split_whitespace()
+            .map(|v| {
+                parse(v).ok_or_else(|| Error::InvalidValue {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// Get a single value of `key`, parsing it with `parse`.
+    fn get_single<T>(&self, key: &str, parse: impl Fn(&str) -> Option<T>) -> Result<T, Error> {
+        let mut values = self.get_array(key, parse)?;
+        if values.len() != 1 {
+            return Err(Error::InvalidValue {
+                key: key.to_string(),
+                value: self.get_str(key)?.to_string(),
+            });
+        }
+
+        Ok(values.remove(0))
+    }
+
+    /// Get the unsigned integer values of `key`.
+    pub fn get_u32_array(&self, key: &str) -> Result<Vec<u32>, Error> {
+        self.get_array(key, |v| v.parse().ok())
+    }
+
+    /// Get the unsigned integer value of `key`.
+    pub fn get_u32(&self, key: &str) -> Result<u32, Error> {
+        self.get_single(key, |v| v.parse().ok())
+    }
+
+    /// Get the signed integer values of `key`.
+    pub fn get_i32_array(&self, key: &str) -> Result<Vec<i32>, Error> {
+        self.get_array(key, |v| v.parse().ok())
+    }
+
+    /// Get the signed integer value of `key`.
+    pub fn get_i32(&self, key: &str) -> Result<i32, Error> {
+        self.get_single(key, |v| v.parse().ok())
+    }
+
+    /// Get the float values of `key`.
+    pub fn get_f32_array(&self, key: &str) -> Result<Vec<f32>, Error> {
+        self.get_array(key, |v| v.parse().ok())
+    }
+
+    /// Get the float value of `key`.
+    pub fn get_f32(&self, key: &str) -> Result<f32, Error> {
+        self.get_single(key, |v| v.parse().ok())
+    }
+
+    /// Get the boolean values of `key`.
+    pub fn get_bool_array(&self, key: &str) -> Result<Vec<bool>, Error> {
+        self.get_array(key, parse_bool)
+    }
+
+    /// Get the boolean value of `key`.
+    pub fn get_bool(&self, key: &str) -> Result<bool, Error> {
+        self.get_single(key, parse_bool)
+    }
+
+    /// Get the hex-encoded bytes of `key`.
+    pub fn get_hex(&self, key: &str) -> Result<Vec<u8>, Error> {
+        self.get_array(key, parse_hex)
+    }
+
+    /// Append a comment line.
+    pub fn push_comment(&mut self, comment: impl Into<String>) {
+        self.lines.push(Line::Comment(comment.into()));
+    }
+
+    /// Append a key with a raw value.
+    pub fn push(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.lines.push(Line::Value {
+            key: key.into(),
+            value: value.into(),
+        });
+    }
+
+    /// Append a key with unsigned integer values.
+    pub fn push_u32(&mut self, key: impl Into<String>, values: &[u32]) {
+        self.push(key, join(values.iter().map(|v| v.to_string())));
+    }
+
+    /// Append a key with signed integer values.
+    pub fn push_i32(&mut self, key: impl Into<String>, values: &[i32]) {
+        self.push(key, join(values.iter().map(|v| v.to_string())));
+    }
+
+    /// Append a key with float values.
+    ///
+    /// Floats are written with six decimal places, like the device.
+    pub fn push_f32(&mut self, key: impl Into<String>, values: &[f32]) {
+        self.push(key, join(values.iter().map(|v| format!("{v:.6}"))));
+    }
+
+    /// Append a key with boolean values.
+    pub fn push_bool(&mut self, key: impl Into<String>, values: &[bool]) {
+        self.push(key, join(values.iter().map(|v| v.to_string())));
+    }
+
+    /// Append a key with hex-encoded bytes.
+    pub fn push_hex(&mut self, key: impl Into<String>, values: &[u8]) {
+        self.push(key, join(values.iter().map(|v| format!("{v:02X}"))));
+    }
+}
+
+impl Display for FlipperFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            match line {
+                Line::Comment(comment) => writeln!(f, "# {comment}")?,
+                Line::Value { key, value } => writeln!(f, "{key}: {value}")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn join(values: impl Iterator<Item = String>) -> String {
+    values.collect::<Vec<_>>().join(" ")
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_hex(value: &str) -> Option<u8> {
+    if value.len() != 2 {
+        return None;
+    }
+
+    u8::from_str_radix(value, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUB_FILE: &str = "\
+Filetype: Flipper SubGhz Key File
+Version: 1
+# Generated by a test
+Frequency: 433920000
+Preset: FuriHalSubGhzPresetOok650Async
+Protocol: Princeton
+Bit: 24
+Key: 00 00 00 00 00 95 D5 D4
+TE: 400
+";
+
+    #[test]
+    fn parse_sub_file() {
+        let ff = FlipperFormat::parse(SUB_FILE).unwrap();
+
+        assert_eq!(ff.header().unwrap(), ("Flipper SubGhz Key File", 1));
+        ff.check_header("Flipper SubGhz Key File", 1).unwrap();
+        assert_eq!(ff.get_u32("Frequency").unwrap(), 433920000);
+        assert_eq!(ff.get_str("Protocol").unwrap(), "Princeton");
+        assert_eq!(
+            ff.get_hex("Key").unwrap(),
+            vec![0, 0, 0, 0, 0, 0x95, 0xD5, 0xD4]
+        );
+        assert!(matches!(ff.get_str("Repeat"), Err(Error::MissingKey(_))));
+    }
+
+    #[test]
+    fn round_trip() {
+        let ff = FlipperFormat::parse(SUB_FILE).unwrap();
+
+        assert_eq!(ff.to_string(), SUB_FILE);
+        assert_eq!(FlipperFormat::parse(&ff.to_string()).unwrap(), ff);
+    }
+
+    #[test]
+    fn typed_values() {
+        let mut ff = FlipperFormat::with_header("Test File", 2);
+        ff.push_u32("Counts", &[1, 2, 3]);
+        ff.push_i32("Offset", &[-42]);
+        ff.push_f32("Gain", &[1.5]);
+        ff.push_bool("Enabled", &[true, false]);
+        ff.push_hex("Data", &[0xDE, 0xAD]);
+
+        let text = ff.to_string();
+        assert!(text.contains("Gain: 1.500000\n"));
+        assert!(text.contains("Data: DE AD\n"));
+
+        let ff = FlipperFormat::parse(&text).unwrap();
+        assert_eq!(ff.get_u32_array("Counts").unwrap(), vec![1, 2, 3]);
+        assert_eq!(ff.get_i32("Offset").unwrap(), -42);
+        assert_eq!(ff.get_f32("Gain").unwrap(), 1.5);
+        assert_eq!(ff.get_bool_array("Enabled").unwrap(), vec![true, false]);
+        assert_eq!(ff.get_hex("Data").unwrap(), vec![0xDE, 0xAD]);
+    }
+
+    #[test]
+    fn invalid_input() {
+        assert!(matches!(
+            FlipperFormat::parse("Filetype: Test\nnot a key value pair\n"),
+            Err(Error::Syntax { line: 2, .. })
+        ));
+
+        let ff = FlipperFormat::parse("Key: 0G\nCount: 1 2\n").unwrap();
+        assert!(matches!(ff.get_hex("Key"), Err(Error::InvalidValue { .. })));
+        assert!(matches!(
+            ff.get_u32("Count"),
+            Err(Error::InvalidValue { .. })
+        ));
+    }
+}
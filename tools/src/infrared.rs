@@ -0,0 +1,287 @@
+//! Host-side infrared remote (`.ir`) files.
+//!
+//! This mirrors `flipperzero::infrared::remote` on the device, so that remotes can be
+//! created and validated off-device.
+
+use std::fmt::{self, Display};
+use std::path::Path;
+use std::{error, fs};
+
+use crate::flipper_format::{self, FlipperFormat};
+
+pub const FILETYPE: &str = "IR signals file";
+pub const VERSION: u32 = 1;
+
+/// Names of the protocols supported by the firmware.
+pub const PROTOCOLS: &[&str] = &[
+    "NEC",
+    "NECext",
+    "NEC42",
+    "NEC42ext",
+    "Samsung32",
+    "RC6",
+    "RC5",
+    "RC5X",
+    "SIRC",
+    "SIRC15",
+    "SIRC20",
+    "Kaseikyo",
+];
+
+/// Remote file error.
+#[derive(Debug)]
+pub enum Error {
+    /// The file is not a valid Flipper Format file, or a value is missing.
+    Format(flipper_format::Error),
+    /// A button has an unknown signal type or protocol.
+    InvalidSignal { name: String, reason: String },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Format(err) => write!(f, "{err}"),
+            Error::InvalidSignal { name, reason } => {
+                write!(f, "invalid signal {name:?}: {reason}")
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Format(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<flipper_format::Error> for Error {
+    fn from(value: flipper_format::Error) -> Self {
+        Error::Format(value)
+    }
+}
+
+/// The signal sent by a [`Button`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Signal {
+    /// A message of a known protocol.
+    Parsed {
+        protocol: String,
+        address: u32,
+        command: u32,
+    },
+    /// Alternating mark and space durations in microseconds, starting with a mark.
+    Raw {
+        frequency: u32,
+        duty_cycle: f32,
+        data: Vec<u32>,
+    },
+}
+
+/// A named button of a [`Remote`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Button {
+    pub name: String,
+    pub signal: Signal,
+}
+
+impl Button {
+    /// Parse a button from its lines (starting with `name`).
+    fn parse(ff: &FlipperFormat) -> Result<Self, Error> {
+        let name = ff.get_str("name")?.to_string();
+        let invalid = |reason: &str| Error::InvalidSignal {
+            name: name.clone(),
+            reason: reason.to_string(),
+        };
+
+        let signal = match ff.get_str("type")? {
+            "parsed" => {
+                let protocol = ff.get_str("protocol")?;
+                if !PROTOCOLS.contains(&protocol) {
+                    return Err(invalid(&format!("unknown protocol {protocol:?}")));
+                }
+
+                Signal::Parsed {
+                    protocol: protocol.to_string(),
+                    address: le_u32(&ff.get_hex("address")?).ok_or_else(|| invalid("address"))?,
+                    command: le_u32(&ff.get_hex("command")?).ok_or_else(|| invalid("command"))?,
+                }
+            }
+            "raw" => Signal::Raw {
+                frequency: ff.get_u32("frequency")?,
+                duty_cycle: ff.get_f32("duty_cycle")?,
+                data: ff.get_u32_array("data")?,
+            },
+            other => return Err(invalid(&format!("unknown type {other:?}"))),
+        };
+
+        Ok(Button { name, signal })
+    }
+
+    fn write(&self, ff: &mut FlipperFormat) {
+        ff.push_comment("");
+        ff.push("name", &self.name);
+        match &self.signal {
+            Signal::Parsed {
+                protocol,
+                address,
+                command,
+            } => {
+                ff.push("type", "parsed");
+                ff.push("protocol", protocol);
+                ff.push_hex("address", &address.to_le_bytes());
+                ff.push_hex("command", &command.to_le_bytes());
+            }
+            Signal::Raw {
+                frequency,
+                duty_cycle,
+                data,
+            } => {
+                ff.push("type", "raw");
+                ff.push_u32("frequency", &[*frequency]);
+                ff.push_f32("duty_cycle", &[*duty_cycle]);
+                ff.push_u32("data", data);
+            }
+        }
+    }
+}
+
+fn le_u32(bytes: &[u8]) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// An infrared remote: a list of named buttons.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Remote {
+    pub buttons: Vec<Button>,
+}
+
+impl Remote {
+    /// Parse a `.ir` file on the local file system.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::parse(&fs::read_to_string(path).map_err(flipper_format::Error::Io)?)
+    }
+
+    /// Parse the text of a `.ir` file.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        Self::from_flipper_format(&FlipperFormat::parse(text)?)
+    }
+
+    /// Read a remote from a parsed Flipper Format file.
+    pub fn from_flipper_format(ff: &FlipperFormat) -> Result<Self, Error> {
+        ff.check_header(FILETYPE, VERSION)?;
+
+        // Each button starts with a `name` key.
+        let mut buttons = Vec::new();
+        let mut button: Option<FlipperFormat> = None;
+        for (key, value) in ff.values() {
+            if key == "name" {
+                if let Some(lines) = button.take() {
+                    buttons.push(Button::parse(&lines)?);
+                }
+                button = Some(FlipperFormat::new());
+            }
+            if let Some(lines) = &mut button {
+                lines.push(key, value);
+            }
+        }
+        if let Some(lines) = button {
+            buttons.push(Button::parse(&lines)?);
+        }
+
+        Ok(Remote { buttons })
+    }
+
+    /// Convert the remote to a Flipper Format file, formatted like the device.
+    pub fn to_flipper_format(&self) -> FlipperFormat {
+        let mut ff = FlipperFormat::with_header(FILETYPE, VERSION);
+        for button in &self.buttons {
+            button.write(&mut ff);
+        }
+
+        ff
+    }
+
+    /// Get the first button with the given name.
+    pub fn button(&self, name: &str) -> Option<&Button> {
+        self.buttons.iter().find(|button| button.name == name)
+    }
+}
+
+impl Display for Remote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.to_flipper_format().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REMOTE: &str = include_str!("../../fixtures/infrared/remote.ir");
+    const UNKNOWN_PROTOCOL: &str = include_str!("../../fixtures/infrared/unknown_protocol.ir");
+
+    fn fixture_remote() -> Remote {
+        Remote {
+            buttons: vec![
+                Button {
+                    name: "Power".to_string(),
+                    signal: Signal::Parsed {
+                        protocol: "NEC".to_string(),
+                        address: 0x07,
+                        command: 0x02,
+                    },
+                },
+                Button {
+                    name: "Vol_up".to_string(),
+                    signal: Signal::Parsed {
+                        protocol: "Samsung32".to_string(),
+                        address: 0x0E,
+                        command: 0x0C,
+                    },
+                },
+                Button {
+                    name: "Mute".to_string(),
+                    signal: Signal::Raw {
+                        frequency: 38000,
+                        duty_cycle: 0.33,
+                        data: vec![9024, 4512, 579, 552, 579, 1683, 579, 552, 579, 40000],
+                    },
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn read_fixture() {
+        let remote = Remote::parse(REMOTE).unwrap();
+
+        assert_eq!(remote, fixture_remote());
+        assert_eq!(remote.button("Power"), Some(&fixture_remote().buttons[0]));
+        assert_eq!(remote.button("Missing"), None);
+    }
+
+    #[test]
+    fn write_matches_fixture() {
+        assert_eq!(fixture_remote().to_string(), REMOTE);
+    }
+
+    #[test]
+    fn unknown_protocol_is_rejected() {
+        assert!(matches!(
+            Remote::parse(UNKNOWN_PROTOCOL),
+            Err(Error::InvalidSignal { name, .. }) if name == "Power"
+        ));
+    }
+
+    #[test]
+    fn wrong_filetype_is_rejected() {
+        let text = REMOTE.replace(FILETYPE, "IR library file");
+        assert!(matches!(
+            Remote::parse(&text),
+            Err(Error::Format(flipper_format::Error::Header { .. }))
+        ));
+    }
+}
@@ -1,2 +1,9 @@
+pub mod flipper_format;
+pub mod infrared;
+pub mod rfid;
 pub mod storage;
+pub mod subghz;
 pub mod serial;
+
+/// The key codes, keyboard layouts and DuckyScript parser of `flipperzero::usb::hid`.
+pub use flipperzero_hid as hid;
@@ -0,0 +1,251 @@
+//! Host-side RFID key (`.rfid`) files.
+//!
+//! This mirrors `flipperzero::rfid::dict` on the device, so that keys can be created and
+//! validated off-device.
+
+use std::fmt::{self, Display};
+use std::path::Path;
+use std::{error, fs};
+
+use crate::flipper_format::{self, FlipperFormat};
+
+pub const FILETYPE: &str = "Flipper RFID key";
+pub const VERSION: u32 = 1;
+
+/// Names of the protocols supported by the firmware, and the size of their data in
+/// bytes.
+pub const PROTOCOLS: &[(&str, usize)] = &[
+    ("EM4100", 5),
+    ("H10301", 3),
+    ("Idteck", 8),
+    ("Indala26", 4),
+    ("IoProxXSF", 4),
+    ("AWID", 9),
+    ("FDX-A", 5),
+    ("FDX-B", 11),
+    ("HIDProx", 6),
+    ("HIDExt", 12),
+    ("Pyramid", 4),
+    ("Viking", 4),
+    ("Jablotron", 5),
+    ("Paradox", 6),
+    ("PAC/Stanley", 4),
+    ("Keri", 4),
+    ("Gallagher", 8),
+];
+
+/// Get the data size of a protocol, or `None` if the protocol is unknown.
+pub fn data_size(protocol: &str) -> Option<usize> {
+    PROTOCOLS
+        .iter()
+        .find(|(name, _)| *name == protocol)
+        .map(|&(_, size)| size)
+}
+
+/// Key file error.
+#[derive(Debug)]
+pub enum Error {
+    /// The file is not a valid Flipper Format file, or a value is missing.
+    Format(flipper_format::Error),
+    /// The protocol is not supported by the firmware.
+    UnknownProtocol(String),
+    /// The data size doesn't match the protocol.
+    InvalidDataSize {
+        protocol: String,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Format(err) => write!(f, "{err}"),
+            Error::UnknownProtocol(protocol) => write!(f, "unknown protocol {protocol:?}"),
+            Error::InvalidDataSize {
+                protocol,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{protocol} data is {expected} bytes long, found {actual} bytes"
+            ),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Format(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<flipper_format::Error> for Error {
+    fn from(value: flipper_format::Error) -> Self {
+        Error::Format(value)
+    }
+}
+
+/// An RFID key: the data of a card of a given protocol.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Key {
+    protocol: String,
+    data: Vec<u8>,
+}
+
+impl Key {
+    /// Create a key, checking that the data size matches the protocol.
+    pub fn new(protocol: impl Into<String>, data: Vec<u8>) -> Result<Self, Error> {
+        let protocol = protocol.into();
+        let expected =
+            data_size(&protocol).ok_or_else(|| Error::UnknownProtocol(protocol.clone()))?;
+        if data.len() != expected {
+            return Err(Error::InvalidDataSize {
+                protocol,
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        Ok(Key { protocol, data })
+    }
+
+    /// Parse a `.rfid` file on the local file system.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::parse(&fs::read_to_string(path).map_err(flipper_format::Error::Io)?)
+    }
+
+    /// Parse the text of a `.rfid` file.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        Self::from_flipper_format(&FlipperFormat::parse(text)?)
+    }
+
+    /// Read a key from a parsed Flipper Format file.
+    pub fn from_flipper_format(ff: &FlipperFormat) -> Result<Self, Error> {
+        ff.check_header(FILETYPE, VERSION)?;
+        Self::new(ff.get_str("Key type")?, ff.get_hex("Data")?)
+    }
+
+    /// Convert the key to a Flipper Format file, formatted like the device.
+    pub fn to_flipper_format(&self) -> FlipperFormat {
+        let mut ff = FlipperFormat::with_header(FILETYPE, VERSION);
+        ff.push("Key type", &self.protocol);
+        ff.push_hex("Data", &self.data);
+        ff
+    }
+
+    /// The name of the key's protocol.
+    pub fn protocol(&self) -> &str {
+        &self.protocol
+    }
+
+    /// The key's data.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.to_flipper_format().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A key file written by the device.
+    const EM4100: &str = include_str!("../../fixtures/rfid/em4100.rfid");
+
+    #[test]
+    fn protocol_data_sizes() {
+        for &(protocol, size) in PROTOCOLS {
+            assert_eq!(data_size(protocol), Some(size));
+            assert!(Key::new(protocol, vec![0; size]).is_ok(), "{protocol}");
+            for actual in [size - 1, size + 1] {
+                assert!(
+                    matches!(
+                        Key::new(protocol, vec![0; actual]),
+                        Err(Error::InvalidDataSize { protocol: p, expected, actual: a })
+                            if p == protocol && expected == size && a == actual
+                    ),
+                    "{protocol} with {actual} bytes"
+                );
+            }
+        }
+
+        // Names are matched exactly, as the firmware does.
+        assert_eq!(data_size("em4100"), None);
+        assert_eq!(data_size("FDX"), None);
+        assert!(matches!(
+            Key::new("EM4200", vec![0; 5]),
+            Err(Error::UnknownProtocol(protocol)) if protocol == "EM4200"
+        ));
+    }
+
+    #[test]
+    fn data_size_mismatch_in_file() {
+        let text = EM4100.replace("DC 69 66 0F 12", "DC 69 66 0F 12 34");
+        let err = Key::parse(&text).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "EM4100 data is 5 bytes long, found 6 bytes"
+        );
+
+        // The size is checked against the protocol named in the file.
+        let text = EM4100.replace("EM4100", "H10301");
+        assert!(matches!(
+            Key::parse(&text),
+            Err(Error::InvalidDataSize {
+                expected: 3,
+                actual: 5,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn device_render_format() {
+        // Data is written as upper case, zero-padded hex bytes separated by spaces.
+        let key = Key::new("PAC/Stanley", vec![0x0A, 0x00, 0xBC, 0x01]).unwrap();
+        assert_eq!(
+            key.to_string(),
+            "Filetype: Flipper RFID key\n\
+             Version: 1\n\
+             Key type: PAC/Stanley\n\
+             Data: 0A 00 BC 01\n"
+        );
+        assert_eq!(Key::parse(&key.to_string()).unwrap(), key);
+
+        // Files written by the device round-trip unchanged.
+        let key = Key::parse(EM4100).unwrap();
+        assert_eq!(key.protocol(), "EM4100");
+        assert_eq!(key.data(), &[0xDC, 0x69, 0x66, 0x0F, 0x12]);
+        assert_eq!(key.to_string(), EM4100);
+    }
+
+    #[test]
+    fn invalid_files() {
+        let text = EM4100.replace("Flipper RFID key", "Flipper NFC device");
+        assert!(matches!(
+            Key::parse(&text),
+            Err(Error::Format(flipper_format::Error::Header { .. }))
+        ));
+
+        let text = EM4100.replace("Data: DC 69 66 0F 12\n", "");
+        assert!(matches!(
+            Key::parse(&text),
+            Err(Error::Format(flipper_format::Error::MissingKey(key))) if key == "Data"
+        ));
+
+        let text = EM4100.replace("0F", "0G");
+        assert!(matches!(
+            Key::parse(&text),
+            Err(Error::Format(flipper_format::Error::InvalidValue { .. }))
+        ));
+    }
+}
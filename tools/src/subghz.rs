@@ -0,0 +1,511 @@
+//! Host-side Sub-GHz RAW (`.sub`) files.
+//!
+//! RAW files hold a captured signal as the durations of alternating levels, so that it
+//! can be analyzed off-device. Captures can be converted to and from CSV and VCD (Value
+//! Change Dump, as read by logic analyzer software such as PulseView or GTKWave).
+//!
+//! This mirrors `flipperzero::subghz::raw` on the device.
+
+use std::fmt::{self, Display, Write};
+use std::path::Path;
+use std::{error, fs};
+
+use crate::flipper_format::{self, FlipperFormat};
+
+pub const RAW_FILETYPE: &str = "Flipper SubGhz RAW File";
+pub const VERSION: u32 = 1;
+pub const RAW_PROTOCOL: &str = "RAW";
+
+/// Preset name of custom presets, whose registers are saved in the file.
+pub const PRESET_CUSTOM: &str = "FuriHalSubGhzPresetCustom";
+
+/// Number of samples per `RAW_Data` line, as written by the device.
+pub const SAMPLES_PER_LINE: usize = 512;
+
+const CSV_HEADER: &str = "level,duration_us";
+const VCD_ID: &str = "!";
+
+/// RAW file error.
+#[derive(Debug)]
+pub enum Error {
+    /// The file is not a valid Flipper Format file, or a value is missing.
+    Format(flipper_format::Error),
+    /// The file holds a decoded key rather than a raw signal.
+    NotRaw { protocol: String },
+    /// A sample has a duration of zero.
+    ZeroDuration,
+    /// A CSV line is not a `level,duration_us` pair.
+    Csv { line: usize, text: String },
+    /// A VCD file is not a single 1-bit signal with increasing timestamps.
+    Vcd { line: usize, text: String },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Format(err) => write!(f, "{err}"),
+            Error::NotRaw { protocol } => write!(f, "not a RAW file: protocol {protocol:?}"),
+            Error::ZeroDuration => write!(f, "sample with a duration of zero"),
+            Error::Csv { line, text } => {
+                write!(
+                    f,
+                    "CSV line {line}: expected `level,duration_us`, found {text:?}"
+                )
+            }
+            Error::Vcd { line, text } => write!(f, "VCD line {line}: unexpected {text:?}"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Format(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<flipper_format::Error> for Error {
+    fn from(value: flipper_format::Error) -> Self {
+        Error::Format(value)
+    }
+}
+
+/// A level of the signal and how long it lasts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sample {
+    pub level: bool,
+    pub duration_us: u32,
+}
+
+impl Sample {
+    /// Parse a `RAW_Data` value: positive for high, negative for low.
+    fn from_raw(value: i32) -> Result<Self, Error> {
+        if value == 0 {
+            return Err(Error::ZeroDuration);
+        }
+
+        Ok(Sample {
+            level: value > 0,
+            duration_us: value.unsigned_abs(),
+        })
+    }
+
+    /// Convert to a `RAW_Data` value.
+    fn to_raw(self) -> i32 {
+        let duration = self.duration_us.min(i32::MAX as u32) as i32;
+        if self.level {
+            duration
+        } else {
+            -duration
+        }
+    }
+}
+
+/// A captured signal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawFile {
+    /// Frequency in Hz.
+    pub frequency: u32,
+    /// Preset name (e.g. `FuriHalSubGhzPresetOok650Async`).
+    pub preset: String,
+    /// Register data of a [custom preset](PRESET_CUSTOM).
+    pub custom_preset_data: Option<Vec<u8>>,
+    pub samples: Vec<Sample>,
+}
+
+impl RawFile {
+    /// Parse a `.sub` RAW file on the local file system.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::parse(&fs::read_to_string(path).map_err(flipper_format::Error::Io)?)
+    }
+
+    /// Parse the text of a `.sub` RAW file.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        Self::from_flipper_format(&FlipperFormat::parse(text)?)
+    }
+
+    /// Read a capture from a parsed Flipper Format file.
+    pub fn from_flipper_format(ff: &FlipperFormat) -> Result<Self, Error> {
+        ff.check_header(RAW_FILETYPE, VERSION)?;
+
+        let protocol = ff.get_str("Protocol")?;
+        if protocol != RAW_PROTOCOL {
+            return Err(Error::NotRaw {
+                protocol: protocol.to_string(),
+            });
+        }
+
+        let preset = ff.get_str("Preset")?.to_string();
+        let custom_preset_data = if preset == PRESET_CUSTOM {
+            Some(ff.get_hex("Custom_preset_data")?)
+        } else {
+            None
+        };
+
+        // The samples are split over any number of lines.
+        let mut samples = Vec::new();
+        for value in ff.get_all("RAW_Data") {
+            for raw in value.split_whitespace() {
+                let raw = raw
+                    .parse()
+                    .map_err(|_| flipper_format::Error::InvalidValue {
+                        key: "RAW_Data".to_string(),
+                        value: value.to_string(),
+                    })?;
+                samples.push(Sample::from_raw(raw)?);
+            }
+        }
+
+        Ok(RawFile {
+            frequency: ff.get_u32("Frequency")?,
+            preset,
+            custom_preset_data,
+            samples,
+        })
+    }
+
+    /// Convert the capture to a Flipper Format file, formatted like the device.
+    pub fn to_flipper_format(&self) -> FlipperFormat {
+        let mut ff = FlipperFormat::with_header(RAW_FILETYPE, VERSION);
+        ff.push_u32("Frequency", &[self.frequency]);
+        ff.push("Preset", &self.preset);
+        if let Some(data) = &self.custom_preset_data {
+            ff.push("Custom_preset_module", "CC1101");
+            ff.push_hex("Custom_preset_data", data);
+        }
+        ff.push("Protocol", RAW_PROTOCOL);
+        for chunk in self.samples.chunks(SAMPLES_PER_LINE) {
+            let values: Vec<i32> = chunk.iter().map(|sample| sample.to_raw()).collect();
+            ff.push_i32("RAW_Data", &values);
+        }
+
+        ff
+    }
+
+    /// Total duration of the capture, in microseconds.
+    pub fn duration_us(&self) -> u64 {
+        self.samples
+            .iter()
+            .map(|sample| u64::from(sample.duration_us))
+            .sum()
+    }
+}
+
+impl Display for RawFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.to_flipper_format().fmt(f)
+    }
+}
+
+/// Convert samples to CSV, with a `level,duration_us` header and one sample per line.
+pub fn to_csv(samples: &[Sample]) -> String {
+    let mut csv = format!("{CSV_HEADER}\n");
+    for sample in samples {
+        writeln!(csv, "{},{}", u8::from(sample.level), sample.duration_us).unwrap();
+    }
+
+    csv
+}
+
+/// Parse samples from CSV written by [`to_csv`]. The header is optional.
+pub fn from_csv(text: &str) -> Result<Vec<Sample>, Error> {
+    let mut samples = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (n == 0 && line == CSV_HEADER) {
+            continue;
+        }
+
+        let invalid = || Error::Csv {
+            line: n + 1,
+            text: line.to_string(),
+        };
+        let (level, duration) = line.split_once(',').ok_or_else(invalid)?;
+        let level = match level.trim() {
+            "0" => false,
+            "1" => true,
+            _ => return Err(invalid()),
+        };
+        let duration_us = duration.trim().parse().map_err(|_| invalid())?;
+        if duration_us == 0 {
+            return Err(Error::ZeroDuration);
+        }
+
+        samples.push(Sample { level, duration_us });
+    }
+
+    Ok(samples)
+}
+
+/// Convert samples to a VCD file with a single 1-bit `rf` signal, in microseconds.
+///
+/// Consecutive samples with the same level are merged.
+pub fn to_vcd(samples: &[Sample]) -> String {
+    let mut vcd = String::new();
+    vcd.push_str("$timescale 1us $end\n");
+    vcd.push_str("$scope module subghz $end\n");
+    writeln!(vcd, "$var wire 1 {VCD_ID} rf $end").unwrap();
+    vcd.push_str("$upscope $end\n");
+    vcd.push_str("$enddefinitions $end\n");
+
+    let mut time = 0u64;
+    let mut level = None;
+    for sample in samples {
+        if level != Some(sample.level) {
+            writeln!(vcd, "#{time}").unwrap();
+            writeln!(vcd, "{}{VCD_ID}", u8::from(sample.level)).unwrap();
+            level = Some(sample.level);
+        }
+        time += u64::from(sample.duration_us);
+    }
+    // Mark the end of the last level.
+    writeln!(vcd, "#{time}").unwrap();
+
+    vcd
+}
+
+/// Parse samples from a VCD file.
+///
+/// The first 1-bit signal is read; other signals are ignored. Timestamps are converted
+/// to microseconds using the file's timescale. The last level lasts until the last
+/// timestamp.
+pub fn from_vcd(text: &str) -> Result<Vec<Sample>, Error> {
+    // Nanoseconds per timestamp unit.
+    let mut timescale_ns = 1000u64;
+    let mut id: Option<String> = None;
+    let mut definitions = true;
+
+    let mut samples = Vec::new();
+    let mut time_ns = 0u64;
+    // The current level and when it started.
+    let mut current: Option<(bool, u64)> = None;
+
+    let mut lines = text.lines().enumerate().peekable();
+    while let Some((n, line)) = lines.next() {
+        let invalid = || Error::Vcd {
+            line: n + 1,
+            text: line.to_string(),
+        };
+        let line = line.trim();
+
+        if definitions {
+            let mut tokens: Vec<&str> = line.split_whitespace().collect();
+            // Declarations can span lines until their `$end`.
+            while !tokens.is_empty() && !tokens.contains(&"$end") {
+                match lines.next() {
+                    Some((_, next)) => tokens.extend(next.split_whitespace()),
+                    None => return Err(invalid()),
+                }
+            }
+
+            match tokens.first() {
+                Some(&"$timescale") => {
+                    let scale: String = tokens[1..tokens.len() - 1].concat();
+                    timescale_ns = parse_timescale(&scale).ok_or_else(invalid)?;
+                }
+                // $var <type> <width> <id> <name> $end
+                Some(&"$var") if id.is_none() && tokens.get(2) == Some(&"1") => {
+                    id = tokens.get(3).map(|id| id.to_string());
+                }
+                Some(&"$enddefinitions") => definitions = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        let id = id.as_deref().ok_or_else(invalid)?;
+        let mut tokens = line.split_whitespace();
+        while let Some(token) = tokens.next() {
+            if token.starts_with(['b', 'B', 'r', 'R']) {
+                // Vector and real values are followed by their signal's id.
+                tokens.next();
+            } else if let Some(time) = token.strip_prefix('#') {
+                let time: u64 = time.parse().map_err(|_| invalid())?;
+                let time = time * timescale_ns;
+                if time < time_ns {
+                    return Err(invalid());
+                }
+                time_ns = time;
+            } else if token.starts_with('$') {
+                // `$dumpvars`, `$end`, ...
+            } else if let Some(value) = token.strip_suffix(id) {
+                let level = match value {
+                    "1" => true,
+                    "0" | "x" | "X" | "z" | "Z" => false,
+                    _ => continue,
+                };
+                match current {
+                    Some((current_level, _)) if current_level == level => {}
+                    Some((current_level, start)) => {
+                        // A level that changes again at the same time is dropped.
+                        if time_ns > start {
+                            push_vcd_sample(&mut samples, current_level, time_ns - start)?;
+                        }
+                        current = Some((level, time_ns));
+                    }
+                    None => current = Some((level, time_ns)),
+                }
+            }
+        }
+    }
+
+    if let Some((level, start)) = current {
+        if time_ns > start {
+            push_vcd_sample(&mut samples, level, time_ns - start)?;
+        }
+    }
+
+    Ok(samples)
+}
+
+fn push_vcd_sample(samples: &mut Vec<Sample>, level: bool, duration_ns: u64) -> Result<(), Error> {
+    let duration_us = u32::try_from(duration_ns / 1000).unwrap_or(u32::MAX);
+    if duration_us == 0 {
+        return Err(Error::ZeroDuration);
+    }
+
+    samples.push(Sample { level, duration_us });
+    Ok(())
+}
+
+/// Parse a VCD timescale (e.g. `1us`, `10 ns`) into nanoseconds.
+fn parse_timescale(scale: &str) -> Option<u64> {
+    let unit_start = scale.find(|c: char| !c.is_ascii_digit())?;
+    let (count, unit) = scale.split_at(unit_start);
+    let count: u64 = count.parse().ok()?;
+    let unit_ns = match unit {
+        "ns" => 1,
+        "us" => 1_000,
+        "ms" => 1_000_000,
+        "s" => 1_000_000_000,
+        _ => return None,
+    };
+
+    Some(count * unit_ns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RAW: &str = include_str!("../../fixtures/subghz/raw.sub");
+    const CSV: &str = include_str!("../../fixtures/subghz/raw.csv");
+    const VCD: &str = include_str!("../../fixtures/subghz/raw.vcd");
+
+    fn fixture_samples() -> Vec<Sample> {
+        [
+            (true, 29262),
+            (false, 362),
+            (true, 1403),
+            (false, 1030),
+            (true, 397),
+            (false, 1043),
+            (true, 1386),
+            (false, 12024),
+        ]
+        .into_iter()
+        .map(|(level, duration_us)| Sample { level, duration_us })
+        .collect()
+    }
+
+    #[test]
+    fn read_fixture() {
+        let raw = RawFile::parse(RAW).unwrap();
+
+        assert_eq!(raw.frequency, 433_920_000);
+        assert_eq!(raw.preset, "FuriHalSubGhzPresetOok650Async");
+        assert_eq!(raw.custom_preset_data, None);
+        assert_eq!(raw.samples, fixture_samples());
+        assert_eq!(raw.duration_us(), 46907);
+    }
+
+    #[test]
+    fn write_matches_fixture() {
+        let raw = RawFile {
+            frequency: 433_920_000,
+            preset: "FuriHalSubGhzPresetOok650Async".to_string(),
+            custom_preset_data: None,
+            samples: fixture_samples(),
+        };
+
+        assert_eq!(raw.to_string(), RAW);
+    }
+
+    #[test]
+    fn lines_are_split() {
+        let raw = RawFile {
+            frequency: 433_920_000,
+            preset: PRESET_CUSTOM.to_string(),
+            custom_preset_data: Some(vec![0x02, 0x0D, 0x00, 0x00]),
+            samples: vec![
+                Sample {
+                    level: true,
+                    duration_us: 100
+                };
+                SAMPLES_PER_LINE + 1
+            ],
+        };
+
+        let ff = raw.to_flipper_format();
+        assert_eq!(ff.get_all("RAW_Data").count(), 2);
+        assert_eq!(ff.get_str("Custom_preset_module").unwrap(), "CC1101");
+        assert_eq!(RawFile::from_flipper_format(&ff).unwrap(), raw);
+    }
+
+    #[test]
+    fn key_files_are_rejected() {
+        let text = RAW.replace("Protocol: RAW", "Protocol: Princeton");
+        assert!(matches!(
+            RawFile::parse(&text),
+            Err(Error::NotRaw { protocol }) if protocol == "Princeton"
+        ));
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        assert_eq!(to_csv(&fixture_samples()), CSV);
+        assert_eq!(from_csv(CSV).unwrap(), fixture_samples());
+        assert!(matches!(
+            from_csv("level,duration_us\n2,100\n"),
+            Err(Error::Csv { line: 2, .. })
+        ));
+        assert!(matches!(from_csv("1,0\n"), Err(Error::ZeroDuration)));
+    }
+
+    #[test]
+    fn vcd_round_trip() {
+        assert_eq!(to_vcd(&fixture_samples()), VCD);
+        assert_eq!(from_vcd(VCD).unwrap(), fixture_samples());
+    }
+
+    #[test]
+    fn vcd_from_other_tools() {
+        // Nanosecond timescale, multi-line declarations, an extra signal and repeated
+        // values.
+        let vcd = "$date today $end\n\
+                   $timescale\n  100 ns\n$end\n\
+                   $scope module top $end\n\
+                   $var wire 8 # bus $end\n\
+                   $var wire 1 % rf $end\n\
+                   $upscope $end\n\
+                   $enddefinitions $end\n\
+                   $dumpvars\n0% b0 # $end\n\
+                   #0 1%\n#50 1% b1 #\n#100 0%\n#130\n";
+
+        assert_eq!(
+            from_vcd(vcd).unwrap(),
+            vec![
+                Sample {
+                    level: true,
+                    duration_us: 10
+                },
+                Sample {
+                    level: false,
+                    duration_us: 3
+                },
+            ]
+        );
+    }
+}